@@ -0,0 +1,39 @@
+//! Tokens per second of the lexer on small, medium, and pathological inputs. Run with `cargo bench`.
+//! Each input is lexed directly with [Lexer] and through a [TokenStream], the two token sources a parser can read from
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use lox::{lexer::Lexer, token_stream::TokenStream};
+use std::hint::black_box;
+
+fn lexers(c: &mut Criterion) {
+    let long_string = format!("var s = \"{}\";", "a".repeat(1 << 20));
+    let long_identifiers = "abcdefghijklmnopqrstuvwxyz_".repeat(1 << 12) + ";";
+
+    let inputs = [
+        ("small", include_str!("../example.lox").to_owned()),
+        ("medium", include_str!("fixtures/medium.lox").to_owned()),
+        ("comments", include_str!("fixtures/comments.lox").to_owned()),
+        ("long string", long_string),
+        ("long identifier", long_identifiers),
+    ];
+
+    for (name, source) in &inputs {
+        let mut group = c.benchmark_group(*name);
+        // the tokens and errors of one run
+        group.throughput(Throughput::Elements(Lexer::new(source).count() as u64));
+
+        group.bench_with_input(BenchmarkId::from_parameter("Lexer"), source, |b, source| {
+            b.iter(|| Lexer::new(black_box(source)).count())
+        });
+        group.bench_with_input(
+            BenchmarkId::from_parameter("TokenStream"),
+            source,
+            |b, source| b.iter(|| TokenStream::new(Lexer::new(black_box(source))).count()),
+        );
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, lexers);
+criterion_main!(benches);
@@ -1,73 +1,2179 @@
-use super::token::Token;
-use std::fmt::Display;
+use super::{
+    interner::Interner,
+    parser::{ParseError, ParseErrorKind, MAX_NESTING_DEPTH},
+    precedence::{self, Associativity},
+    token::{Span, Token, TokenKind},
+};
+use alloc::{borrow::ToOwned, boxed::Box, format, string::String, vec::Vec};
+use core::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
 
+/// A parsed lox source file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program<'a> {
+    statements: Vec<Statement<'a>>,
+    /// resolves the [Token::symbol] of every identifier and keyword in `statements`
+    interner: Interner<'a>,
+}
+impl<'a> Program<'a> {
+    pub const fn new(statements: Vec<Statement<'a>>, interner: Interner<'a>) -> Self {
+        Self {
+            statements,
+            interner,
+        }
+    }
+    pub fn statements(&self) -> &[Statement<'a>] {
+        &self.statements
+    }
+    pub const fn interner(&self) -> &Interner<'a> {
+        &self.interner
+    }
+    pub fn into_statements(self) -> Vec<Statement<'a>> {
+        self.statements
+    }
+    pub fn into_parts(self) -> (Vec<Statement<'a>>, Interner<'a>) {
+        (self.statements, self.interner)
+    }
+    /// Whether every statement is [Statement::eq_ignoring_spans] to the one in the same place in `other`.
+    /// The interners are not compared
+    pub fn eq_ignoring_spans(&self, other: &Program) -> bool {
+        self.statements.len() == other.statements.len()
+            && self
+                .statements
+                .iter()
+                .zip(&other.statements)
+                .all(|(statement, other)| statement.eq_ignoring_spans(other))
+    }
+}
+
+/// Every variant's `span` covers the whole statement, from its first token through its `;` or closing `}`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement<'a> {
+    Expression {
+        expression: Box<Expression<'a>>,
+        span: Span,
+    },
+    Print {
+        expression: Box<Expression<'a>>,
+        span: Span,
+    },
+    Var {
+        name: Token<'a>,
+        initializer: Option<Box<Expression<'a>>>,
+        span: Span,
+    },
+    Block {
+        statements: Vec<Statement<'a>>,
+        span: Span,
+    },
+    If {
+        condition: Box<Expression<'a>>,
+        then_branch: Box<Statement<'a>>,
+        else_branch: Option<Box<Statement<'a>>>,
+        span: Span,
+    },
+    While {
+        condition: Box<Expression<'a>>,
+        body: Box<Statement<'a>>,
+        span: Span,
+    },
+    For {
+        /// a [Statement::Var] or [Statement::Expression]
+        initializer: Option<Box<Statement<'a>>>,
+        condition: Option<Box<Expression<'a>>>,
+        increment: Option<Box<Expression<'a>>>,
+        body: Box<Statement<'a>>,
+        span: Span,
+    },
+    /// Started by a keyword claimed by the host with [crate::dialect::Dialect::with_statement_keyword], which is also responsible for running it.
+    /// Unless the host registers a [crate::parser::CustomStatementParser], the syntax is the keyword,
+    /// zero or more comma separated expressions, then either `;` or a block whose statements become `statements`
+    Custom {
+        keyword: Token<'a>,
+        expressions: Vec<Expression<'a>>,
+        statements: Vec<Statement<'a>>,
+        span: Span,
+    },
+}
+/// Builders for tools that make trees without source text.
+/// Each node's span covers its children and tokens; keywords and punctuation that are not passed in are not included
+impl<'a> Statement<'a> {
+    pub fn expression(expression: Expression<'a>) -> Self {
+        Statement::Expression {
+            span: expression.span(),
+            expression: Box::new(expression),
+        }
+    }
+    pub fn print(expression: Expression<'a>) -> Self {
+        Statement::Print {
+            span: expression.span(),
+            expression: Box::new(expression),
+        }
+    }
+    pub fn var(name: Token<'a>, initializer: Option<Expression<'a>>) -> Self {
+        Statement::Var {
+            span: initializer.as_ref().map_or(name.span(), |initializer| {
+                name.span().to(initializer.span())
+            }),
+            name,
+            initializer: initializer.map(Box::new),
+        }
+    }
+    pub fn block(statements: Vec<Statement<'a>>) -> Self {
+        Statement::Block {
+            span: spans_of(statements.iter().map(Statement::span)),
+            statements,
+        }
+    }
+    pub fn if_else(
+        condition: Expression<'a>,
+        then_branch: Statement<'a>,
+        else_branch: Option<Statement<'a>>,
+    ) -> Self {
+        let span = condition.span().to(else_branch
+            .as_ref()
+            .map_or(then_branch.span(), Statement::span));
+        Statement::If {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: else_branch.map(Box::new),
+            span,
+        }
+    }
+    pub fn while_loop(condition: Expression<'a>, body: Statement<'a>) -> Self {
+        Statement::While {
+            span: condition.span().to(body.span()),
+            condition: Box::new(condition),
+            body: Box::new(body),
+        }
+    }
+    pub fn for_loop(
+        initializer: Option<Statement<'a>>,
+        condition: Option<Expression<'a>>,
+        increment: Option<Expression<'a>>,
+        body: Statement<'a>,
+    ) -> Self {
+        let span = spans_of(
+            initializer
+                .iter()
+                .map(Statement::span)
+                .chain(condition.iter().chain(&increment).map(Expression::span))
+                .chain([body.span()]),
+        );
+        Statement::For {
+            initializer: initializer.map(Box::new),
+            condition: condition.map(Box::new),
+            increment: increment.map(Box::new),
+            body: Box::new(body),
+            span,
+        }
+    }
+    pub fn custom(
+        keyword: Token<'a>,
+        expressions: Vec<Expression<'a>>,
+        statements: Vec<Statement<'a>>,
+    ) -> Self {
+        let span = spans_of(
+            [keyword.span()]
+                .into_iter()
+                .chain(expressions.iter().map(Expression::span))
+                .chain(statements.iter().map(Statement::span)),
+        );
+        Statement::Custom {
+            keyword,
+            expressions,
+            statements,
+            span,
+        }
+    }
+}
+impl Statement<'_> {
+    /// Where the whole statement is in the source
+    pub const fn span(&self) -> Span {
+        match self {
+            Statement::Expression { span, .. }
+            | Statement::Print { span, .. }
+            | Statement::Var { span, .. }
+            | Statement::Block { span, .. }
+            | Statement::If { span, .. }
+            | Statement::While { span, .. }
+            | Statement::For { span, .. }
+            | Statement::Custom { span, .. } => *span,
+        }
+    }
+}
+
+/// Every variant's `span` covers the whole expression, including the parentheses of a [Expression::Grouping] or [Expression::Call]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression<'a> {
+    Assign {
+        name: Token<'a>,
+        value: Box<Expression<'a>>,
+        span: Span,
+    },
     Binary {
         left_operand: Box<Expression<'a>>,
         operator: Token<'a>,
         right_operand: Box<Expression<'a>>,
+        span: Span,
     },
     Unary {
         operator: Token<'a>,
         right_operand: Box<Expression<'a>>,
+        span: Span,
+    },
+    /// `callee(arguments)`
+    Call {
+        callee: Box<Expression<'a>>,
+        /// the `)` ending the arguments, where errors about the call are reported
+        closing_parenthesis: Token<'a>,
+        arguments: Vec<Expression<'a>>,
+        span: Span,
+    },
+    /// `object.name`
+    Get {
+        object: Box<Expression<'a>>,
+        name: Token<'a>,
+        span: Span,
+    },
+    /// `and` and `or`, which only evaluate their right operand when needed
+    Logical {
+        left_operand: Box<Expression<'a>>,
+        operator: Token<'a>,
+        right_operand: Box<Expression<'a>>,
+        span: Span,
     },
-    Grouping(Box<Expression<'a>>),
+    Grouping {
+        expression: Box<Expression<'a>>,
+        span: Span,
+    },
+    /// The span is the token's
     Literal(Token<'a>),
+    /// The span is the token's
+    Variable(Token<'a>),
+}
+/// Builders for tools that make trees without source text. Each node's span covers its children and tokens
+impl<'a> Expression<'a> {
+    pub fn assign(name: Token<'a>, value: Expression<'a>) -> Self {
+        Expression::Assign {
+            span: name.span().to(value.span()),
+            name,
+            value: Box::new(value),
+        }
+    }
+    pub fn binary(
+        left_operand: Expression<'a>,
+        operator: Token<'a>,
+        right_operand: Expression<'a>,
+    ) -> Self {
+        Expression::Binary {
+            span: left_operand.span().to(right_operand.span()),
+            left_operand: Box::new(left_operand),
+            operator,
+            right_operand: Box::new(right_operand),
+        }
+    }
+    pub fn unary(operator: Token<'a>, right_operand: Expression<'a>) -> Self {
+        Expression::Unary {
+            span: operator.span().to(right_operand.span()),
+            operator,
+            right_operand: Box::new(right_operand),
+        }
+    }
+    pub fn call(
+        callee: Expression<'a>,
+        arguments: Vec<Expression<'a>>,
+        closing_parenthesis: Token<'a>,
+    ) -> Self {
+        Expression::Call {
+            span: callee.span().to(closing_parenthesis.span()),
+            callee: Box::new(callee),
+            closing_parenthesis,
+            arguments,
+        }
+    }
+    pub fn get(object: Expression<'a>, name: Token<'a>) -> Self {
+        Expression::Get {
+            span: object.span().to(name.span()),
+            object: Box::new(object),
+            name,
+        }
+    }
+    pub fn logical(
+        left_operand: Expression<'a>,
+        operator: Token<'a>,
+        right_operand: Expression<'a>,
+    ) -> Self {
+        Expression::Logical {
+            span: left_operand.span().to(right_operand.span()),
+            left_operand: Box::new(left_operand),
+            operator,
+            right_operand: Box::new(right_operand),
+        }
+    }
+    /// The span is the inner expression's since the parentheses have no tokens
+    pub fn grouping(expression: Expression<'a>) -> Self {
+        Expression::Grouping {
+            span: expression.span(),
+            expression: Box::new(expression),
+        }
+    }
+}
+impl<'a> Expression<'a> {
+    /// Where the whole expression is in the source
+    pub const fn span(&self) -> Span {
+        match self {
+            Expression::Literal(token) | Expression::Variable(token) => token.span(),
+            Expression::Assign { span, .. }
+            | Expression::Binary { span, .. }
+            | Expression::Unary { span, .. }
+            | Expression::Call { span, .. }
+            | Expression::Get { span, .. }
+            | Expression::Logical { span, .. }
+            | Expression::Grouping { span, .. } => *span,
+        }
+    }
+    /// The first token of the expression, not counting the `(` of a [Expression::Grouping], which is not kept
+    pub fn first_token(&self) -> &Token<'a> {
+        match self {
+            Expression::Binary { left_operand, .. } | Expression::Logical { left_operand, .. } => {
+                left_operand.first_token()
+            }
+            Expression::Call { callee, .. } => callee.first_token(),
+            Expression::Get { object, .. } => object.first_token(),
+            Expression::Grouping { expression, .. } => expression.first_token(),
+            Expression::Unary {
+                operator: token, ..
+            }
+            | Expression::Assign { name: token, .. }
+            | Expression::Literal(token)
+            | Expression::Variable(token) => token,
+        }
+    }
+}
+/// Comparison and hashing that only look at the kind and lexeme of tokens, so trees parsed from
+/// differently laid out sources, or lexed by different backends, compare equal.
+/// Consistent with each other: trees that are [Self::eq_ignoring_spans] have the same [Self::hash_ignoring_spans]
+impl Expression<'_> {
+    pub fn eq_ignoring_spans(&self, other: &Expression) -> bool {
+        match (self, other) {
+            (
+                Expression::Assign { name, value, .. },
+                Expression::Assign {
+                    name: other_name,
+                    value: other_value,
+                    ..
+                },
+            ) => tokens_eq_ignoring_spans(name, other_name) && value.eq_ignoring_spans(other_value),
+            (
+                Expression::Binary {
+                    left_operand,
+                    operator,
+                    right_operand,
+                    ..
+                },
+                Expression::Binary {
+                    left_operand: other_left_operand,
+                    operator: other_operator,
+                    right_operand: other_right_operand,
+                    ..
+                },
+            )
+            | (
+                Expression::Logical {
+                    left_operand,
+                    operator,
+                    right_operand,
+                    ..
+                },
+                Expression::Logical {
+                    left_operand: other_left_operand,
+                    operator: other_operator,
+                    right_operand: other_right_operand,
+                    ..
+                },
+            ) => {
+                tokens_eq_ignoring_spans(operator, other_operator)
+                    && left_operand.eq_ignoring_spans(other_left_operand)
+                    && right_operand.eq_ignoring_spans(other_right_operand)
+            }
+            (
+                Expression::Unary {
+                    operator,
+                    right_operand,
+                    ..
+                },
+                Expression::Unary {
+                    operator: other_operator,
+                    right_operand: other_right_operand,
+                    ..
+                },
+            ) => {
+                tokens_eq_ignoring_spans(operator, other_operator)
+                    && right_operand.eq_ignoring_spans(other_right_operand)
+            }
+            (
+                Expression::Call {
+                    callee, arguments, ..
+                },
+                Expression::Call {
+                    callee: other_callee,
+                    arguments: other_arguments,
+                    ..
+                },
+            ) => {
+                callee.eq_ignoring_spans(other_callee)
+                    && arguments.len() == other_arguments.len()
+                    && arguments
+                        .iter()
+                        .zip(other_arguments)
+                        .all(|(argument, other_argument)| {
+                            argument.eq_ignoring_spans(other_argument)
+                        })
+            }
+            (
+                Expression::Get { object, name, .. },
+                Expression::Get {
+                    object: other_object,
+                    name: other_name,
+                    ..
+                },
+            ) => {
+                tokens_eq_ignoring_spans(name, other_name) && object.eq_ignoring_spans(other_object)
+            }
+            (
+                Expression::Grouping { expression, .. },
+                Expression::Grouping {
+                    expression: other_expression,
+                    ..
+                },
+            ) => expression.eq_ignoring_spans(other_expression),
+            (Expression::Literal(token), Expression::Literal(other_token))
+            | (Expression::Variable(token), Expression::Variable(other_token)) => {
+                tokens_eq_ignoring_spans(token, other_token)
+            }
+            _ => false,
+        }
+    }
+    pub fn hash_ignoring_spans<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+
+        match self {
+            Expression::Assign { name, value, .. } => {
+                hash_token_ignoring_spans(name, state);
+                value.hash_ignoring_spans(state);
+            }
+            Expression::Binary {
+                left_operand,
+                operator,
+                right_operand,
+                ..
+            }
+            | Expression::Logical {
+                left_operand,
+                operator,
+                right_operand,
+                ..
+            } => {
+                left_operand.hash_ignoring_spans(state);
+                hash_token_ignoring_spans(operator, state);
+                right_operand.hash_ignoring_spans(state);
+            }
+            Expression::Unary {
+                operator,
+                right_operand,
+                ..
+            } => {
+                hash_token_ignoring_spans(operator, state);
+                right_operand.hash_ignoring_spans(state);
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                callee.hash_ignoring_spans(state);
+                arguments.len().hash(state);
+                for argument in arguments {
+                    argument.hash_ignoring_spans(state);
+                }
+            }
+            Expression::Get { object, name, .. } => {
+                object.hash_ignoring_spans(state);
+                hash_token_ignoring_spans(name, state);
+            }
+            Expression::Grouping { expression, .. } => expression.hash_ignoring_spans(state),
+            Expression::Literal(token) | Expression::Variable(token) => {
+                hash_token_ignoring_spans(token, state)
+            }
+        }
+    }
+}
+/// Comparison and hashing that only look at the kind and lexeme of tokens, like [Expression::eq_ignoring_spans]
+impl Statement<'_> {
+    pub fn eq_ignoring_spans(&self, other: &Statement) -> bool {
+        let expressions_eq = |expression: &Option<Box<Expression>>,
+                              other: &Option<Box<Expression>>| {
+            match (expression, other) {
+                (Some(expression), Some(other)) => expression.eq_ignoring_spans(other),
+                (expression, other) => expression.is_none() && other.is_none(),
+            }
+        };
+        let statements_eq = |statements: &[Statement], other: &[Statement]| {
+            statements.len() == other.len()
+                && statements
+                    .iter()
+                    .zip(other)
+                    .all(|(statement, other)| statement.eq_ignoring_spans(other))
+        };
+        let optional_statements_eq =
+            |statement: &Option<Box<Statement>>, other: &Option<Box<Statement>>| match (
+                statement, other,
+            ) {
+                (Some(statement), Some(other)) => statement.eq_ignoring_spans(other),
+                (statement, other) => statement.is_none() && other.is_none(),
+            };
+
+        match (self, other) {
+            (
+                Statement::Expression { expression, .. },
+                Statement::Expression {
+                    expression: other_expression,
+                    ..
+                },
+            )
+            | (
+                Statement::Print { expression, .. },
+                Statement::Print {
+                    expression: other_expression,
+                    ..
+                },
+            ) => expression.eq_ignoring_spans(other_expression),
+            (
+                Statement::Var {
+                    name, initializer, ..
+                },
+                Statement::Var {
+                    name: other_name,
+                    initializer: other_initializer,
+                    ..
+                },
+            ) => {
+                tokens_eq_ignoring_spans(name, other_name)
+                    && expressions_eq(initializer, other_initializer)
+            }
+            (
+                Statement::Block { statements, .. },
+                Statement::Block {
+                    statements: other_statements,
+                    ..
+                },
+            ) => statements_eq(statements, other_statements),
+            (
+                Statement::If {
+                    condition,
+                    then_branch,
+                    else_branch,
+                    ..
+                },
+                Statement::If {
+                    condition: other_condition,
+                    then_branch: other_then_branch,
+                    else_branch: other_else_branch,
+                    ..
+                },
+            ) => {
+                condition.eq_ignoring_spans(other_condition)
+                    && then_branch.eq_ignoring_spans(other_then_branch)
+                    && optional_statements_eq(else_branch, other_else_branch)
+            }
+            (
+                Statement::While {
+                    condition, body, ..
+                },
+                Statement::While {
+                    condition: other_condition,
+                    body: other_body,
+                    ..
+                },
+            ) => condition.eq_ignoring_spans(other_condition) && body.eq_ignoring_spans(other_body),
+            (
+                Statement::For {
+                    initializer,
+                    condition,
+                    increment,
+                    body,
+                    ..
+                },
+                Statement::For {
+                    initializer: other_initializer,
+                    condition: other_condition,
+                    increment: other_increment,
+                    body: other_body,
+                    ..
+                },
+            ) => {
+                optional_statements_eq(initializer, other_initializer)
+                    && expressions_eq(condition, other_condition)
+                    && expressions_eq(increment, other_increment)
+                    && body.eq_ignoring_spans(other_body)
+            }
+            (
+                Statement::Custom {
+                    keyword,
+                    expressions,
+                    statements,
+                    ..
+                },
+                Statement::Custom {
+                    keyword: other_keyword,
+                    expressions: other_expressions,
+                    statements: other_statements,
+                    ..
+                },
+            ) => {
+                tokens_eq_ignoring_spans(keyword, other_keyword)
+                    && expressions.len() == other_expressions.len()
+                    && expressions
+                        .iter()
+                        .zip(other_expressions)
+                        .all(|(expression, other)| expression.eq_ignoring_spans(other))
+                    && statements_eq(statements, other_statements)
+            }
+            _ => false,
+        }
+    }
+    pub fn hash_ignoring_spans<H: Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+
+        let hash_expression = |expression: &Option<Box<Expression>>, state: &mut H| {
+            expression.is_some().hash(state);
+            if let Some(expression) = expression {
+                expression.hash_ignoring_spans(state);
+            }
+        };
+        let hash_statement = |statement: &Option<Box<Statement>>, state: &mut H| {
+            statement.is_some().hash(state);
+            if let Some(statement) = statement {
+                statement.hash_ignoring_spans(state);
+            }
+        };
+        let hash_statements = |statements: &[Statement], state: &mut H| {
+            statements.len().hash(state);
+            for statement in statements {
+                statement.hash_ignoring_spans(state);
+            }
+        };
+
+        match self {
+            Statement::Expression { expression, .. } | Statement::Print { expression, .. } => {
+                expression.hash_ignoring_spans(state)
+            }
+            Statement::Var {
+                name, initializer, ..
+            } => {
+                hash_token_ignoring_spans(name, state);
+                hash_expression(initializer, state);
+            }
+            Statement::Block { statements, .. } => hash_statements(statements, state),
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                condition.hash_ignoring_spans(state);
+                then_branch.hash_ignoring_spans(state);
+                hash_statement(else_branch, state);
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                condition.hash_ignoring_spans(state);
+                body.hash_ignoring_spans(state);
+            }
+            Statement::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                hash_statement(initializer, state);
+                hash_expression(condition, state);
+                hash_expression(increment, state);
+                body.hash_ignoring_spans(state);
+            }
+            Statement::Custom {
+                keyword,
+                expressions,
+                statements,
+                ..
+            } => {
+                hash_token_ignoring_spans(keyword, state);
+                expressions.len().hash(state);
+                for expression in expressions {
+                    expression.hash_ignoring_spans(state);
+                }
+                hash_statements(statements, state);
+            }
+        }
+    }
+}
+
+fn tokens_eq_ignoring_spans(token: &Token, other: &Token) -> bool {
+    token.kind() == other.kind() && token.lexeme() == other.lexeme()
 }
+fn hash_token_ignoring_spans<H: Hasher>(token: &Token, state: &mut H) {
+    token.kind().hash(state);
+    token.lexeme().hash(state);
+}
+
+/// Like [Expression::to_s_expression] with [Parentheses::Preserve], but string literals are printed without their quotes
 impl Display for Expression<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        fn parenthesizes(name: &str, expressions: &[&Expression]) -> String {
-            let mut output = String::new();
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.s_expression(Parentheses::Preserve, false))
+    }
+}
+/// Like [Statement::to_s_expression] with [Parentheses::Preserve], but string literals are printed without their quotes
+impl Display for Statement<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.s_expression(Parentheses::Preserve, false))
+    }
+}
+/// One statement per line
+impl Display for Program<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (index, statement) in self.statements.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", statement)?;
+        }
+        Ok(())
+    }
+}
+
+impl Statement<'_> {
+    /// Prints the statement as an s-expression like [Display], with `parentheses` applied to every expression in it.
+    /// Missing parts of a `for` are printed as `_`, and string literals keep their quotes
+    pub fn to_s_expression(&self, parentheses: Parentheses) -> String {
+        self.s_expression(parentheses, true)
+    }
+    /// [Self::to_s_expression], with string literals quoted only when `quote_strings` is set
+    fn s_expression(&self, parentheses: Parentheses, quote_strings: bool) -> String {
+        let mut output = String::from("(");
+        let mut push = |part: &str| {
+            if output.len() > 1 {
+                output.push(' ');
+            }
+            output.push_str(part);
+        };
+
+        match self {
+            Statement::Expression { expression, .. } => {
+                push(";");
+                push(&expression.s_expression(parentheses, quote_strings));
+            }
+            Statement::Print { expression, .. } => {
+                push("print");
+                push(&expression.s_expression(parentheses, quote_strings));
+            }
+            Statement::Var {
+                name, initializer, ..
+            } => {
+                push("var");
+                push(name.lexeme());
+                if let Some(initializer) = initializer {
+                    push("=");
+                    push(&initializer.s_expression(parentheses, quote_strings));
+                }
+            }
+            Statement::Block { statements, .. } => {
+                push("block");
+                for statement in statements {
+                    push(&statement.s_expression(parentheses, quote_strings));
+                }
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                push(if else_branch.is_some() {
+                    "if-else"
+                } else {
+                    "if"
+                });
+                push(&condition.s_expression(parentheses, quote_strings));
+                push(&then_branch.s_expression(parentheses, quote_strings));
+                if let Some(else_branch) = else_branch {
+                    push(&else_branch.s_expression(parentheses, quote_strings));
+                }
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                push("while");
+                push(&condition.s_expression(parentheses, quote_strings));
+                push(&body.s_expression(parentheses, quote_strings));
+            }
+            Statement::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                push("for");
+                push(&initializer.as_ref().map_or_else(
+                    || "_".to_owned(),
+                    |initializer| initializer.s_expression(parentheses, quote_strings),
+                ));
+                for expression in [condition, increment] {
+                    push(&expression.as_ref().map_or_else(
+                        || "_".to_owned(),
+                        |expression| expression.s_expression(parentheses, quote_strings),
+                    ));
+                }
+                push(&body.s_expression(parentheses, quote_strings));
+            }
+            Statement::Custom {
+                keyword,
+                expressions,
+                statements,
+                ..
+            } => {
+                push(keyword.lexeme());
+                for expression in expressions {
+                    push(&expression.s_expression(parentheses, quote_strings));
+                }
+                for statement in statements {
+                    push(&statement.s_expression(parentheses, quote_strings));
+                }
+            }
+        }
+
+        output.push(')');
+        output
+    }
+}
 
-            output.push('(');
-            output.push_str(name);
+/// The smallest span covering every span in `spans`. Empty at 0 when there are none
+fn spans_of(spans: impl IntoIterator<Item = Span>) -> Span {
+    spans
+        .into_iter()
+        .reduce(|covered, span| covered.to(span))
+        .unwrap_or_default()
+}
+
+/// How printers treat parentheses from the original source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Parentheses {
+    /// Print every [Expression::Grouping], keeping the user's grouping
+    #[default]
+    Preserve,
+    /// Ignore [Expression::Grouping] and print only the parentheses required by [precedence](crate::precedence)
+    Minimal,
+}
 
+/// Which side of its parent operator an operand is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandPosition {
+    Left,
+    /// Also the position of a unary operator's operand
+    Right,
+}
+
+impl Expression<'_> {
+    /// Prints the expression as an s-expression like [Display] but with control over `(group ...)` nodes.
+    /// With [Parentheses::Minimal] no group nodes are printed because s-expressions are already unambiguous.
+    /// String literals keep their quotes, unlike in [Display], so the output can be read back with [Self::from_s_expression]
+    pub fn to_s_expression(&self, parentheses: Parentheses) -> String {
+        self.s_expression(parentheses, true)
+    }
+    /// [Self::to_s_expression], with string literals quoted only when `quote_strings` is set
+    fn s_expression(&self, parentheses: Parentheses, quote_strings: bool) -> String {
+        let parenthesizes = |name: &str, expressions: &[&Expression]| {
+            let mut output = format!("({}", name);
             for expression in expressions {
                 output.push(' ');
-                output.push_str(&expression.to_string());
+                output.push_str(&expression.s_expression(parentheses, quote_strings));
             }
             output.push(')');
-
             output
-        }
+        };
 
-        let output = match self {
+        match self {
+            Expression::Assign { name, value, .. } => format!(
+                "(= {} {})",
+                name.lexeme(),
+                value.s_expression(parentheses, quote_strings)
+            ),
             Expression::Binary {
                 left_operand,
                 operator,
                 right_operand,
+                ..
+            }
+            | Expression::Logical {
+                left_operand,
+                operator,
+                right_operand,
+                ..
             } => parenthesizes(operator.lexeme(), &[left_operand, right_operand]),
             Expression::Unary {
                 operator,
                 right_operand,
+                ..
             } => parenthesizes(operator.lexeme(), &[right_operand]),
-            Expression::Grouping(expression) => parenthesizes("group", &[expression]),
-            Expression::Literal(literal) => literal.lexeme().to_owned(),
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                let mut expressions = Vec::from([&**callee]);
+                expressions.extend(arguments);
+                parenthesizes("call", &expressions)
+            }
+            Expression::Get { object, name, .. } => format!(
+                "(. {} {})",
+                object.s_expression(parentheses, quote_strings),
+                name.lexeme()
+            ),
+            Expression::Grouping { expression, .. } => match parentheses {
+                Parentheses::Preserve => parenthesizes("group", &[expression]),
+                Parentheses::Minimal => expression.s_expression(parentheses, quote_strings),
+            },
+            Expression::Literal(literal)
+                if quote_strings && literal.kind() == TokenKind::String =>
+            {
+                format!("\"{}\"", literal.lexeme())
+            }
+            Expression::Literal(literal) | Expression::Variable(literal) => {
+                literal.lexeme().to_owned()
+            }
+        }
+    }
+
+    /// Prints the expression back as lox source, keeping the user's parentheses. See [Self::to_source_with]
+    pub fn to_source(&self) -> String {
+        self.to_source_with(Parentheses::Preserve)
+    }
+    /// Prints the expression back as lox source
+    pub fn to_source_with(&self, parentheses: Parentheses) -> String {
+        let operand_source = |operand: &Expression, parent, position| {
+            let operand = match parentheses {
+                Parentheses::Preserve => operand,
+                Parentheses::Minimal => operand.without_outer_groupings(),
+            };
+            let source = operand.to_source_with(parentheses);
+
+            if parentheses == Parentheses::Minimal && operand.needs_parentheses(parent, position) {
+                format!("({})", source)
+            } else {
+                source
+            }
+        };
+
+        match self {
+            Expression::Assign { name, value, .. } => {
+                let parent = (precedence::ASSIGNMENT, Associativity::Right);
+                format!(
+                    "{} = {}",
+                    name.lexeme(),
+                    operand_source(value, parent, OperandPosition::Right)
+                )
+            }
+            Expression::Binary {
+                left_operand,
+                operator,
+                right_operand,
+                ..
+            }
+            | Expression::Logical {
+                left_operand,
+                operator,
+                right_operand,
+                ..
+            } => {
+                let parent = precedence::precedence(operator.kind())
+                    .expect("binary and logical expressions have binary operators");
+                format!(
+                    "{} {} {}",
+                    operand_source(left_operand, parent, OperandPosition::Left),
+                    operator.lexeme(),
+                    operand_source(right_operand, parent, OperandPosition::Right)
+                )
+            }
+            Expression::Unary {
+                operator,
+                right_operand,
+                ..
+            } => {
+                let parent = (precedence::UNARY, Associativity::Right);
+                format!(
+                    "{}{}",
+                    operator.lexeme(),
+                    operand_source(right_operand, parent, OperandPosition::Right)
+                )
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                let parent = (precedence::CALL, Associativity::Left);
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| {
+                        operand_source(
+                            argument,
+                            (precedence::ASSIGNMENT, Associativity::Right),
+                            OperandPosition::Right,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "{}({})",
+                    operand_source(callee, parent, OperandPosition::Left),
+                    arguments.join(", ")
+                )
+            }
+            Expression::Get { object, name, .. } => {
+                let parent = (precedence::CALL, Associativity::Left);
+                format!(
+                    "{}.{}",
+                    operand_source(object, parent, OperandPosition::Left),
+                    name.lexeme()
+                )
+            }
+            Expression::Grouping { expression, .. } => match parentheses {
+                Parentheses::Preserve => format!("({})", expression.to_source_with(parentheses)),
+                Parentheses::Minimal => expression.to_source_with(parentheses),
+            },
+            Expression::Literal(literal) if literal.kind() == TokenKind::String => {
+                format!("\"{}\"", literal.lexeme())
+            }
+            Expression::Literal(literal) | Expression::Variable(literal) => {
+                literal.lexeme().to_owned()
+            }
+        }
+    }
+
+    /// Whether this expression must be parenthesized to be the operand at `position` of an operator with the given
+    /// precedence and associativity (see [precedence::precedence]) without changing the meaning of the tree
+    pub fn needs_parentheses(
+        &self,
+        (parent_level, parent_associativity): (u8, Associativity),
+        position: OperandPosition,
+    ) -> bool {
+        let level = match self.without_outer_groupings() {
+            Expression::Binary { operator, .. } | Expression::Logical { operator, .. } => {
+                match precedence::precedence(operator.kind()) {
+                    Some((level, _)) => level,
+                    None => return false,
+                }
+            }
+            Expression::Assign { .. } => precedence::ASSIGNMENT,
+            Expression::Unary { .. } => precedence::UNARY,
+            Expression::Call { .. } | Expression::Get { .. } => precedence::CALL,
+            Expression::Grouping { .. } | Expression::Literal(_) | Expression::Variable(_) => {
+                return false
+            }
+        };
+
+        match level.cmp(&parent_level) {
+            core::cmp::Ordering::Less => true,
+            core::cmp::Ordering::Greater => false,
+            core::cmp::Ordering::Equal => matches!(
+                (parent_associativity, position),
+                (Associativity::Left, OperandPosition::Right)
+                    | (Associativity::Right, OperandPosition::Left)
+            ),
+        }
+    }
+
+    /// Returns the first expression inside any number of [Expression::Grouping]s
+    pub fn without_outer_groupings(&self) -> &Self {
+        let mut expression = self;
+        while let Expression::Grouping {
+            expression: inner_expression,
+            ..
+        } = expression
+        {
+            expression = inner_expression;
+        }
+        expression
+    }
+}
+
+impl<'a> Expression<'a> {
+    /// Reads an expression printed by [Self::to_s_expression], such as `(* (- 123) (group 45.67))`.
+    /// The atoms are lexed as lox tokens, so spans and line numbers are positions in `source`
+    /// # Error
+    /// A [ParseErrorKind::ExpectedExpression] where a node cannot start or is missing an operand,
+    /// a [ParseErrorKind::MissingRightParenthesis] where a node has too many operands,
+    /// a [ParseErrorKind::TooDeeplyNested] where nodes are nested deeper than [MAX_NESTING_DEPTH], or a lexer error
+    pub fn from_s_expression(source: &'a str) -> Result<Self, ParseError<'a>> {
+        let mut tokens = crate::lexer::Lexer::new(source).peekable();
+        let expression = read_s_expression(&mut tokens, 0)?;
+
+        match tokens.next() {
+            Some(Ok(token)) if token.is_end_of_file() => Ok(expression),
+            Some(Ok(token)) => Err(ParseError::new(
+                ParseErrorKind::MissingRightParenthesis,
+                token,
+            )),
+            Some(Err(error)) => Err(error.into()),
+            None => Ok(expression),
+        }
+    }
+}
+type SExpressionTokens<'a> = core::iter::Peekable<crate::lexer::Lexer<'a>>;
+fn next_s_expression_token<'a>(
+    tokens: &mut SExpressionTokens<'a>,
+) -> Result<Token<'a>, ParseError<'a>> {
+    match tokens.next() {
+        Some(Ok(token)) => Ok(token),
+        Some(Err(error)) => Err(error.into()),
+        None => Err(ParseError::new(
+            ParseErrorKind::ExpectedExpression,
+            Token::end_of_file(0),
+        )),
+    }
+}
+/// Reads a node `depth` nodes below the root
+fn read_s_expression<'a>(
+    tokens: &mut SExpressionTokens<'a>,
+    depth: usize,
+) -> Result<Expression<'a>, ParseError<'a>> {
+    let token = next_s_expression_token(tokens)?;
+
+    match token.kind() {
+        TokenKind::Number
+        | TokenKind::String
+        | TokenKind::True
+        | TokenKind::False
+        | TokenKind::Nil => return Ok(Expression::Literal(token)),
+        TokenKind::Identifier => return Ok(Expression::Variable(token)),
+        TokenKind::LeftParentheses if depth >= MAX_NESTING_DEPTH => {
+            return Err(ParseError::new(ParseErrorKind::TooDeeplyNested, token))
+        }
+        TokenKind::LeftParentheses => {}
+        _ => return Err(ParseError::new(ParseErrorKind::ExpectedExpression, token)),
+    }
+
+    let head = next_s_expression_token(tokens)?;
+
+    // each operand with its first token, to report extra operands at
+    let mut operands = Vec::new();
+    let closing_parenthesis = loop {
+        match tokens.peek().cloned() {
+            Some(Ok(token)) if token.kind() == TokenKind::RightParentheses => {
+                tokens.next();
+                break token;
+            }
+            Some(Ok(token)) if token.is_end_of_file() => {
+                return Err(ParseError::new(
+                    ParseErrorKind::MissingRightParenthesis,
+                    token,
+                ))
+            }
+            Some(Ok(token)) => operands.push((token, read_s_expression(tokens, depth + 1)?)),
+            _ => {
+                next_s_expression_token(tokens)?;
+            }
+        }
+    };
+
+    let operand_count = operands.len();
+    let mut operands = operands.into_iter();
+    let mut operand = || {
+        operands
+            .next()
+            .map(|(_, operand)| operand)
+            .ok_or(ParseError::new(
+                ParseErrorKind::ExpectedExpression,
+                closing_parenthesis,
+            ))
+    };
+
+    let expression = match head.kind() {
+        TokenKind::Identifier if head.lexeme() == "group" => Expression::grouping(operand()?),
+        TokenKind::Identifier if head.lexeme() == "call" => {
+            let callee = operand()?;
+            let arguments = operands.map(|(_, argument)| argument).collect();
+            return Ok(Expression::call(callee, arguments, closing_parenthesis));
+        }
+        TokenKind::Equal => {
+            let Expression::Variable(name) = operand()? else {
+                return Err(ParseError::new(
+                    ParseErrorKind::InvalidAssignmentTarget,
+                    head,
+                ));
+            };
+            Expression::assign(name, operand()?)
+        }
+        TokenKind::Dot => {
+            let object = operand()?;
+            let Expression::Variable(name) = operand()? else {
+                return Err(ParseError::new(ParseErrorKind::ExpectedPropertyName, head));
+            };
+            Expression::get(object, name)
+        }
+        TokenKind::And | TokenKind::Or => {
+            let left_operand = operand()?;
+            Expression::logical(left_operand, head, operand()?)
+        }
+        kind if kind.is_any(TokenKind::UNARY_OPERATORS) && operand_count == 1 => {
+            Expression::unary(head, operand()?)
+        }
+        kind if precedence::precedence(kind).is_some() => {
+            let left_operand = operand()?;
+            Expression::binary(left_operand, head, operand()?)
+        }
+        _ => return Err(ParseError::new(ParseErrorKind::ExpectedExpression, head)),
+    };
+
+    match operands.next() {
+        Some((extra, _)) => Err(ParseError::new(
+            ParseErrorKind::MissingRightParenthesis,
+            extra,
+        )),
+        None => Ok(expression),
+    }
+}
+
+/// Version of the format produced by [to_canonical_string] and [program_to_canonical_string].
+/// Bumped whenever the output for any existing [Expression] or [Statement] changes
+pub const CANONICAL_FORMAT_VERSION: u32 = 1;
+
+/// Renders `expression` as a stable, indented tree for snapshot tests.
+///
+/// # Format guarantees (version [CANONICAL_FORMAT_VERSION])
+/// - The first line is the header `lox-ast v{CANONICAL_FORMAT_VERSION}`
+/// - Every node is on its own line, indented two spaces per level of depth, followed by its children in source order
+/// - A node line is the variant name, then any token kinds and lexemes. Lexemes are quoted and escaped like [str]'s [core::fmt::Debug]
+/// - A missing optional child of a [Statement::For] is the line `None`
+/// - Line numbers and other positional information are never included
+/// - The output always ends with a newline
+pub fn to_canonical_string(expression: &Expression) -> String {
+    let mut output = format!("lox-ast v{}\n", CANONICAL_FORMAT_VERSION);
+    write_canonical_expression(&mut output, expression, 0);
+    output
+}
+/// Renders every statement of `program` in the format of [to_canonical_string], one after the other at depth zero
+pub fn program_to_canonical_string(program: &Program) -> String {
+    let mut output = format!("lox-ast v{}\n", CANONICAL_FORMAT_VERSION);
+    for statement in program.statements() {
+        write_canonical_statement(&mut output, statement, 0);
+    }
+    output
+}
+
+fn write_canonical_indent(output: &mut String, depth: usize) {
+    for _ in 0..depth {
+        output.push_str("  ");
+    }
+}
+fn write_canonical_none(output: &mut String, depth: usize) {
+    write_canonical_indent(output, depth);
+    output.push_str("None\n");
+}
+fn write_canonical_statement(output: &mut String, statement: &Statement, depth: usize) {
+    write_canonical_indent(output, depth);
+
+    match statement {
+        Statement::Expression { expression, .. } => {
+            output.push_str("Expression\n");
+            write_canonical_expression(output, expression, depth + 1);
+        }
+        Statement::Print { expression, .. } => {
+            output.push_str("Print\n");
+            write_canonical_expression(output, expression, depth + 1);
+        }
+        Statement::Var {
+            name, initializer, ..
+        } => {
+            output.push_str(&format!("Var {:?}\n", name.lexeme()));
+            if let Some(initializer) = initializer {
+                write_canonical_expression(output, initializer, depth + 1);
+            }
+        }
+        Statement::Block { statements, .. } => {
+            output.push_str("Block\n");
+            for statement in statements {
+                write_canonical_statement(output, statement, depth + 1);
+            }
+        }
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            output.push_str("If\n");
+            write_canonical_expression(output, condition, depth + 1);
+            write_canonical_statement(output, then_branch, depth + 1);
+            if let Some(else_branch) = else_branch {
+                write_canonical_statement(output, else_branch, depth + 1);
+            }
+        }
+        Statement::While {
+            condition, body, ..
+        } => {
+            output.push_str("While\n");
+            write_canonical_expression(output, condition, depth + 1);
+            write_canonical_statement(output, body, depth + 1);
+        }
+        Statement::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            output.push_str("For\n");
+            match initializer {
+                Some(initializer) => write_canonical_statement(output, initializer, depth + 1),
+                None => write_canonical_none(output, depth + 1),
+            }
+            for expression in [condition, increment] {
+                match expression {
+                    Some(expression) => write_canonical_expression(output, expression, depth + 1),
+                    None => write_canonical_none(output, depth + 1),
+                }
+            }
+            write_canonical_statement(output, body, depth + 1);
+        }
+        Statement::Custom {
+            keyword,
+            expressions,
+            statements,
+            ..
+        } => {
+            output.push_str(&format!("Custom {:?}\n", keyword.lexeme()));
+            for expression in expressions {
+                write_canonical_expression(output, expression, depth + 1);
+            }
+            for statement in statements {
+                write_canonical_statement(output, statement, depth + 1);
+            }
+        }
+    }
+}
+fn write_canonical_expression(output: &mut String, expression: &Expression, depth: usize) {
+    write_canonical_indent(output, depth);
+
+    match expression {
+        Expression::Assign { name, value, .. } => {
+            output.push_str(&format!("Assign {:?}\n", name.lexeme()));
+            write_canonical_expression(output, value, depth + 1);
+        }
+        Expression::Binary {
+            left_operand,
+            operator,
+            right_operand,
+            ..
+        } => {
+            output.push_str(&format!("Binary {:?}\n", operator.lexeme()));
+            write_canonical_expression(output, left_operand, depth + 1);
+            write_canonical_expression(output, right_operand, depth + 1);
+        }
+        Expression::Logical {
+            left_operand,
+            operator,
+            right_operand,
+            ..
+        } => {
+            output.push_str(&format!("Logical {:?}\n", operator.lexeme()));
+            write_canonical_expression(output, left_operand, depth + 1);
+            write_canonical_expression(output, right_operand, depth + 1);
+        }
+        Expression::Unary {
+            operator,
+            right_operand,
+            ..
+        } => {
+            output.push_str(&format!("Unary {:?}\n", operator.lexeme()));
+            write_canonical_expression(output, right_operand, depth + 1);
+        }
+        Expression::Call {
+            callee, arguments, ..
+        } => {
+            output.push_str("Call\n");
+            write_canonical_expression(output, callee, depth + 1);
+            for argument in arguments {
+                write_canonical_expression(output, argument, depth + 1);
+            }
+        }
+        Expression::Get { object, name, .. } => {
+            output.push_str(&format!("Get {:?}\n", name.lexeme()));
+            write_canonical_expression(output, object, depth + 1);
+        }
+        Expression::Grouping { expression, .. } => {
+            output.push_str("Grouping\n");
+            write_canonical_expression(output, expression, depth + 1);
+        }
+        Expression::Literal(literal) => {
+            output.push_str(&format!(
+                "Literal {:?} {:?}\n",
+                literal.kind(),
+                literal.lexeme()
+            ));
+        }
+        Expression::Variable(name) => {
+            output.push_str(&format!("Variable {:?}\n", name.lexeme()));
+        }
+    }
+}
+
+/// Reads the output of [to_canonical_string] back into an expression.
+/// Lexemes are borrowed from `input`, every token is on line 0 with an empty span, and identifiers and keywords are interned
+/// into a fresh [Interner] the same way the parser does.
+/// # Error
+/// When `input` is not in the format of version [CANONICAL_FORMAT_VERSION], or has a lexeme that [core::fmt::Debug] escaped
+/// (such as a string containing a newline) since it cannot be borrowed unescaped,
+/// or has a node deeper than [MAX_CANONICAL_DEPTH]
+pub fn from_canonical_string(input: &str) -> Result<Expression<'_>, CanonicalFormatError> {
+    let mut reader = CanonicalReader::new(input)?;
+    let expression = reader.read_expression(0)?;
+    reader.expect_end()?;
+    Ok(expression)
+}
+/// Reads the output of [program_to_canonical_string] back into a program. See [from_canonical_string].
+/// [Statement::Custom] cannot be read back because the format does not record the id of its keyword
+pub fn program_from_canonical_string(input: &str) -> Result<Program<'_>, CanonicalFormatError> {
+    let mut reader = CanonicalReader::new(input)?;
+
+    let mut statements = Vec::new();
+    while reader.lines.peek().is_some() {
+        statements.push(reader.read_statement(0)?);
+    }
+
+    Ok(Program::new(statements, reader.interner))
+}
+
+/// How many levels of indentation [from_canonical_string] and [program_from_canonical_string] read before giving up,
+/// so deep input cannot overflow the stack. Each nested rule of the parser adds at most two levels,
+/// so every program it accepts can be read back
+pub const MAX_CANONICAL_DEPTH: usize = 2 * MAX_NESTING_DEPTH;
+
+/// A line of input that [from_canonical_string] or [program_from_canonical_string] could not read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalFormatError {
+    line_number: usize,
+    message: &'static str,
+}
+impl CanonicalFormatError {
+    pub const fn line_number(&self) -> usize {
+        self.line_number
+    }
+    pub const fn message(&self) -> &'static str {
+        self.message
+    }
+}
+impl Display for CanonicalFormatError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Invalid canonical AST on line {}: {}",
+            self.line_number, self.message
+        )
+    }
+}
+impl core::error::Error for CanonicalFormatError {}
+
+/// One node line of the canonical format
+struct CanonicalLine<'a> {
+    line_number: usize,
+    depth: usize,
+    name: &'a str,
+    /// everything after the name
+    arguments: &'a str,
+}
+
+struct CanonicalReader<'a> {
+    lines: core::iter::Peekable<alloc::vec::IntoIter<CanonicalLine<'a>>>,
+    /// line number of the last line read, for errors at the end of the input
+    line_number: usize,
+    interner: Interner<'a>,
+}
+impl<'a> CanonicalReader<'a> {
+    fn new(input: &'a str) -> Result<Self, CanonicalFormatError> {
+        let mut lines = input.lines().enumerate();
+
+        let header = format!("lox-ast v{}", CANONICAL_FORMAT_VERSION);
+        if lines.next().map(|(_, line)| line) != Some(header.as_str()) {
+            return Err(CanonicalFormatError {
+                line_number: 1,
+                message: "expected the header of a supported version",
+            });
+        }
+
+        let mut node_lines = Vec::new();
+        for (index, line) in lines {
+            let content = line.trim_start_matches(' ');
+            let indent = line.len() - content.len();
+            let error = |message| CanonicalFormatError {
+                line_number: index + 1,
+                message,
+            };
+            if indent % 2 != 0 {
+                return Err(error("indentation must be a multiple of two spaces"));
+            }
+            if content.is_empty() {
+                return Err(error("expected a node"));
+            }
+            if indent / 2 > MAX_CANONICAL_DEPTH {
+                return Err(error("node is nested too deeply"));
+            }
+
+            let (name, arguments) = content.split_once(' ').unwrap_or((content, ""));
+            node_lines.push(CanonicalLine {
+                line_number: index + 1,
+                depth: indent / 2,
+                name,
+                arguments,
+            });
+        }
+
+        Ok(Self {
+            lines: node_lines.into_iter().peekable(),
+            line_number: 1,
+            interner: Interner::new(),
+        })
+    }
+
+    fn error(&self, message: &'static str) -> CanonicalFormatError {
+        CanonicalFormatError {
+            line_number: self.line_number,
+            message,
+        }
+    }
+    fn expect_end(&mut self) -> Result<(), CanonicalFormatError> {
+        match self.lines.next() {
+            Some(line) => {
+                self.line_number = line.line_number;
+                Err(self.error("expected the end of the input"))
+            }
+            None => Ok(()),
+        }
+    }
+    /// Returns the next line if it is at `depth`
+    fn next_line(&mut self, depth: usize) -> Result<CanonicalLine<'a>, CanonicalFormatError> {
+        let Some(line) = self.lines.next() else {
+            return Err(self.error("expected another node"));
+        };
+        self.line_number = line.line_number;
+
+        if line.depth != depth {
+            return Err(self.error("node is indented wrong"));
+        }
+        Ok(line)
+    }
+    /// Whether the next line is a child of a node at `depth - 1`
+    fn has_child(&mut self, depth: usize) -> bool {
+        self.lines.peek().is_some_and(|line| line.depth == depth)
+    }
+    fn next_is_none(&mut self, depth: usize) -> bool {
+        self.lines
+            .next_if(|line| line.depth == depth && line.name == "None" && line.arguments.is_empty())
+            .inspect(|line| self.line_number = line.line_number)
+            .is_some()
+    }
+    fn next_is_expression(&mut self, depth: usize) -> bool {
+        const NAMES: &[&str] = &[
+            "Assign", "Binary", "Unary", "Call", "Get", "Logical", "Grouping", "Literal",
+            "Variable",
+        ];
+        self.lines
+            .peek()
+            .is_some_and(|line| line.depth == depth && NAMES.contains(&line.name))
+    }
+    fn expect_no_more_children(&mut self, depth: usize) -> Result<(), CanonicalFormatError> {
+        if self.has_child(depth) {
+            self.next_line(depth)?;
+            return Err(self.error("node has too many children"));
+        }
+        Ok(())
+    }
+
+    /// The lexeme in `"..."` at the start of `arguments` and what follows it
+    fn quoted(&self, arguments: &'a str) -> Result<(&'a str, &'a str), CanonicalFormatError> {
+        let (lexeme, rest) = arguments
+            .strip_prefix('"')
+            .and_then(|arguments| arguments.split_once('"'))
+            .ok_or_else(|| self.error("expected a quoted lexeme"))?;
+
+        if lexeme.contains('\\') {
+            return Err(self.error("escaped lexemes cannot be borrowed from the input"));
+        }
+        Ok((lexeme, rest))
+    }
+    /// The only argument of `line`, a quoted lexeme
+    fn lexeme(&self, line: &CanonicalLine<'a>) -> Result<&'a str, CanonicalFormatError> {
+        match self.quoted(line.arguments)? {
+            (lexeme, "") => Ok(lexeme),
+            _ => Err(self.error("unexpected text after the lexeme")),
+        }
+    }
+    fn name_token(&mut self, line: &CanonicalLine<'a>) -> Result<Token<'a>, CanonicalFormatError> {
+        let name = self.lexeme(line)?;
+        if TokenKind::parse_keyword(name) != TokenKind::Identifier {
+            return Err(self.error("expected an identifier"));
+        }
+        Ok(Token::new(TokenKind::Identifier, name, 0).with_symbol(self.interner.intern(name)))
+    }
+    /// Lexes the operator of `line`, whose kind must pass `is_expected`
+    fn operator_token(
+        &mut self,
+        line: &CanonicalLine<'a>,
+        is_expected: impl Fn(TokenKind) -> bool,
+    ) -> Result<Token<'a>, CanonicalFormatError> {
+        let lexeme = self.lexeme(line)?;
+
+        let kind = match crate::lexer::Lexer::new(lexeme).next() {
+            Some(Ok(token)) if token.lexeme() == lexeme => token.kind(),
+            _ => return Err(self.error("expected an operator")),
+        };
+        if !is_expected(kind) {
+            return Err(self.error("operator does not belong to this node"));
+        }
+
+        let token = Token::new(kind, lexeme, 0);
+        Ok(if kind.is_keyword() {
+            token.with_symbol(self.interner.intern(lexeme))
+        } else {
+            token
+        })
+    }
+    fn literal_token(
+        &mut self,
+        line: &CanonicalLine<'a>,
+    ) -> Result<Token<'a>, CanonicalFormatError> {
+        let (kind, quoted) = line
+            .arguments
+            .split_once(' ')
+            .ok_or_else(|| self.error("expected a token kind and a lexeme"))?;
+        let lexeme = match self.quoted(quoted)? {
+            (lexeme, "") => lexeme,
+            _ => return Err(self.error("unexpected text after the lexeme")),
+        };
+
+        let token = match kind {
+            "String" => Token::new(TokenKind::String, lexeme, 0),
+            "True" | "False" | "Nil" => {
+                let parsed_kind = TokenKind::parse_keyword(lexeme);
+                if format!("{:?}", parsed_kind) != kind {
+                    return Err(self.error("literal kind does not match its lexeme"));
+                }
+                Token::new(parsed_kind, lexeme, 0).with_symbol(self.interner.intern(lexeme))
+            }
+            "Number" => match crate::lexer::Lexer::new(lexeme).next() {
+                Some(Ok(token))
+                    if token.kind() == TokenKind::Number && token.lexeme() == lexeme =>
+                {
+                    Token::new(TokenKind::Number, lexeme, 0)
+                        .with_number_value(token.number_value().unwrap_or_default())
+                }
+                _ => return Err(self.error("expected a number")),
+            },
+            _ => {
+                let id = kind
+                    .strip_prefix("Custom(")
+                    .and_then(|kind| kind.strip_suffix(')'))
+                    .and_then(|id| id.parse().ok())
+                    .ok_or_else(|| self.error("unknown literal kind"))?;
+                Token::new(TokenKind::Custom(id), lexeme, 0)
+            }
+        };
+
+        Ok(token)
+    }
+
+    fn read_statement(&mut self, depth: usize) -> Result<Statement<'a>, CanonicalFormatError> {
+        let line = self.next_line(depth)?;
+        let child = depth + 1;
+
+        let statement = match line.name {
+            "Expression" | "Print" if line.arguments.is_empty() => {
+                let expression = self.read_expression(child)?;
+                match line.name {
+                    "Expression" => Statement::expression(expression),
+                    _ => Statement::print(expression),
+                }
+            }
+            "Var" => {
+                let name = self.name_token(&line)?;
+                let initializer = if self.has_child(child) {
+                    Some(self.read_expression(child)?)
+                } else {
+                    None
+                };
+                Statement::var(name, initializer)
+            }
+            "Block" if line.arguments.is_empty() => {
+                let mut statements = Vec::new();
+                while self.has_child(child) {
+                    statements.push(self.read_statement(child)?);
+                }
+                Statement::block(statements)
+            }
+            "If" if line.arguments.is_empty() => {
+                let condition = self.read_expression(child)?;
+                let then_branch = self.read_statement(child)?;
+                let else_branch = if self.has_child(child) {
+                    Some(self.read_statement(child)?)
+                } else {
+                    None
+                };
+                Statement::if_else(condition, then_branch, else_branch)
+            }
+            "While" if line.arguments.is_empty() => {
+                let condition = self.read_expression(child)?;
+                let body = self.read_statement(child)?;
+                Statement::while_loop(condition, body)
+            }
+            "For" if line.arguments.is_empty() => {
+                let initializer = if self.next_is_none(child) {
+                    None
+                } else {
+                    Some(self.read_statement(child)?)
+                };
+                let condition = if self.next_is_none(child) {
+                    None
+                } else {
+                    Some(self.read_expression(child)?)
+                };
+                let increment = if self.next_is_none(child) {
+                    None
+                } else {
+                    Some(self.read_expression(child)?)
+                };
+                let body = self.read_statement(child)?;
+                Statement::for_loop(initializer, condition, increment, body)
+            }
+            "Custom" => {
+                return Err(self.error("custom statements do not record their keyword's id"))
+            }
+            _ => return Err(self.error("expected a statement")),
+        };
+
+        self.expect_no_more_children(child)?;
+        Ok(statement)
+    }
+
+    fn read_expression(&mut self, depth: usize) -> Result<Expression<'a>, CanonicalFormatError> {
+        let line = self.next_line(depth)?;
+        let child = depth + 1;
+
+        let expression = match line.name {
+            "Assign" => {
+                let name = self.name_token(&line)?;
+                Expression::assign(name, self.read_expression(child)?)
+            }
+            "Binary" => {
+                let operator = self.operator_token(&line, |kind| {
+                    !matches!(kind, TokenKind::Equal | TokenKind::And | TokenKind::Or)
+                        && precedence::precedence(kind).is_some()
+                })?;
+                let left_operand = self.read_expression(child)?;
+                Expression::binary(left_operand, operator, self.read_expression(child)?)
+            }
+            "Logical" => {
+                let operator = self
+                    .operator_token(&line, |kind| matches!(kind, TokenKind::And | TokenKind::Or))?;
+                let left_operand = self.read_expression(child)?;
+                Expression::logical(left_operand, operator, self.read_expression(child)?)
+            }
+            "Unary" => {
+                let operator =
+                    self.operator_token(&line, |kind| kind.is_any(TokenKind::UNARY_OPERATORS))?;
+                Expression::unary(operator, self.read_expression(child)?)
+            }
+            "Call" if line.arguments.is_empty() => {
+                let callee = self.read_expression(child)?;
+                let mut arguments = Vec::new();
+                while self.next_is_expression(child) {
+                    arguments.push(self.read_expression(child)?);
+                }
+                let closing_parenthesis = Token::new(TokenKind::RightParentheses, ")", 0);
+                Expression::call(callee, arguments, closing_parenthesis)
+            }
+            "Get" => {
+                let name = self.name_token(&line)?;
+                Expression::get(self.read_expression(child)?, name)
+            }
+            "Grouping" if line.arguments.is_empty() => {
+                Expression::grouping(self.read_expression(child)?)
+            }
+            "Literal" => Expression::Literal(self.literal_token(&line)?),
+            "Variable" => Expression::Variable(self.name_token(&line)?),
+            _ => return Err(self.error("expected an expression")),
         };
 
-        write!(f, "{}", output)
+        self.expect_no_more_children(child)?;
+        Ok(expression)
     }
 }
 
 #[test]
 fn ast_print() {
-    use crate::token::TokenKind;
+    use alloc::string::ToString;
 
-    const EXPECTED: &'static str = "(* (- 123) (group 45.67))";
+    const EXPECTED: &str = "(* (- 123) (group 45.67))";
 
     let expression = Expression::Binary {
         left_operand: Box::new(Expression::Unary {
             operator: Token::new(TokenKind::Minus, "-", 0),
             right_operand: Box::new(Expression::Literal(Token::new(TokenKind::Number, "123", 0))),
+            span: Span::default(),
         }),
         operator: Token::new(TokenKind::Star, "*", 0),
-        right_operand: Box::new(Expression::Grouping(Box::new(Expression::Literal(
-            Token::new(TokenKind::Number, "45.67", 0),
-        )))),
+        right_operand: Box::new(Expression::Grouping {
+            expression: Box::new(Expression::Literal(Token::new(
+                TokenKind::Number,
+                "45.67",
+                0,
+            ))),
+            span: Span::default(),
+        }),
+        span: Span::default(),
     };
 
     let output = expression.to_string();
 
     assert_eq!(output, EXPECTED);
 }
+
+#[test]
+fn canonical_string() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const EXPECTED: &str = "\
+lox-ast v1
+Binary \"==\"
+  Binary \"*\"
+    Unary \"-\"
+      Literal Number \"123\"
+    Grouping
+      Binary \"+\"
+        Literal Number \"45.67\"
+        Literal String \"a b\"
+  Literal True \"true\"
+";
+
+    let mut parser = Parser::from(Lexer::new("-123 * (45.67 + \"a b\") == true"));
+    let expression = parser.expression_rule().unwrap();
+
+    assert_eq!(to_canonical_string(&expression), EXPECTED);
+}
+
+#[test]
+fn minimal_parentheses() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    let cases = [
+        (
+            "((1 + 2)) * (3 * 4) - (5 - 6)",
+            "(1 + 2) * (3 * 4) - (5 - 6)",
+        ),
+        ("(1 * 2) + (3) == ((true))", "1 * 2 + 3 == true"),
+        ("-(-(1)) - (-2)", "--1 - -2"),
+        ("!(1 < 2)", "!(1 < 2)"),
+        ("(1 - 2) - 3", "1 - 2 - 3"),
+    ];
+
+    for (source, expected) in cases {
+        let expression = Parser::from(Lexer::new(source)).expression_rule().unwrap();
+
+        let minimal = expression.to_source_with(Parentheses::Minimal);
+        assert_eq!(minimal, expected);
+        assert_eq!(expression.to_source(), source);
+
+        let reparsed = Parser::from(Lexer::new(&minimal))
+            .expression_rule()
+            .unwrap();
+        assert_eq!(
+            reparsed.to_s_expression(Parentheses::Minimal),
+            expression.to_s_expression(Parentheses::Minimal)
+        );
+    }
+}
+
+#[test]
+fn canonical_program() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const EXPECTED: &str = "\
+lox-ast v1
+Var \"i\"
+For
+  None
+  Logical \"and\"
+    Binary \"<\"
+      Variable \"i\"
+      Literal Number \"5\"
+    Literal True \"true\"
+  Assign \"i\"
+    Binary \"+\"
+      Variable \"i\"
+      Literal Number \"1\"
+  Block
+    If
+      Variable \"i\"
+      Print
+        Variable \"i\"
+      Expression
+        Assign \"i\"
+          Literal Nil \"nil\"
+";
+
+    let program = Parser::from(Lexer::new(
+        "var i; for (; i < 5 and true; i = i + 1) { if (i) print i; else i = nil; }",
+    ))
+    .parse()
+    .unwrap();
+
+    assert_eq!(program_to_canonical_string(&program), EXPECTED);
+
+    let [Statement::Var { name, .. }, ..] = program.statements() else {
+        panic!("expected a variable declaration");
+    };
+    assert_eq!(
+        program.interner().resolve(name.symbol().unwrap()),
+        Some("i")
+    );
+}
+
+#[test]
+fn statement_print() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use alloc::string::ToString;
+
+    const SOURCE: &str = "\
+var a;
+var b = (1 + 2) * 3;
+print a;
+a = b or nil;
+{ var c = a; print c; }
+if (a) print 1; else if (b) print 2;
+if (a) {}
+while (a and b) a = !a;
+for (var i = 0; i < 3; i = i + 1) print i;
+for (;;) {}";
+
+    const EXPECTED: &str = "\
+(var a)
+(var b = (* (group (+ 1 2)) 3))
+(print a)
+(; (= a (or b nil)))
+(block (var c = a) (print c))
+(if-else a (print 1) (if b (print 2)))
+(if a (block))
+(while (and a b) (; (= a (! a))))
+(for (var i = 0) (< i 3) (= i (+ i 1)) (print i))
+(for _ _ _ (block))";
+
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+
+    assert_eq!(program.to_string(), EXPECTED);
+    assert_eq!(
+        program.statements()[1].to_s_expression(Parentheses::Minimal),
+        "(var b = (* (+ 1 2) 3))"
+    );
+}
+
+#[test]
+fn node_spans() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const SOURCE: &str = "var a = -(1 + 2);\nif (a) { print f(a).b; } else a = 3;";
+
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+    let text = |span: Span| &SOURCE[span.range()];
+
+    let [declaration, if_statement] = program.statements() else {
+        panic!("expected two statements");
+    };
+    assert_eq!(text(declaration.span()), "var a = -(1 + 2);");
+    assert_eq!(text(if_statement.span()), &SOURCE[18..]);
+
+    let Statement::Var {
+        initializer: Some(initializer),
+        ..
+    } = declaration
+    else {
+        panic!("expected an initialized variable");
+    };
+    assert_eq!(text(initializer.span()), "-(1 + 2)");
+
+    let Statement::If {
+        then_branch,
+        else_branch: Some(else_branch),
+        ..
+    } = if_statement
+    else {
+        panic!("expected an if-else");
+    };
+    assert_eq!(text(then_branch.span()), "{ print f(a).b; }");
+    let Statement::Block { statements, .. } = &**then_branch else {
+        panic!("expected a block");
+    };
+    let Statement::Print { expression, .. } = &statements[0] else {
+        panic!("expected a print");
+    };
+    assert_eq!(text(expression.span()), "f(a).b");
+    assert_eq!(text(else_branch.span()), "a = 3;");
+}
+
+#[test]
+fn read_canonical_string() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use alloc::string::ToString;
+
+    const SOURCE: &str = "\
+var total = 0;
+for (var i = 0; i < 10; i = i + 1) { if (!(i == 3) and ready) total = total + f(i).value; }
+for (;;) print \"done\";
+while (false) nil;";
+
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+    let canonical = program_to_canonical_string(&program);
+
+    let read = program_from_canonical_string(&canonical).unwrap();
+    assert_eq!(program_to_canonical_string(&read), canonical);
+    assert_eq!(read.to_string(), program.to_string());
+    assert!(read.interner().get("total").is_some());
+
+    let expression = Expression::binary(
+        Expression::Literal(Token::new(TokenKind::Number, "1", 0).with_number_value(1.0)),
+        Token::new(TokenKind::Plus, "+", 0),
+        Expression::grouping(Expression::unary(
+            Token::new(TokenKind::Minus, "-", 0),
+            Expression::Variable(Token::new(TokenKind::Identifier, "x", 0)),
+        )),
+    );
+    let canonical = to_canonical_string(&expression);
+    assert_eq!(
+        to_canonical_string(&from_canonical_string(&canonical).unwrap()),
+        canonical
+    );
+
+    let errors = [
+        ("lox-ast v2\nPrint\n", 1),
+        ("lox-ast v1\nPrint\n    Literal Nil \"nil\"\n", 3),
+        ("lox-ast v1\nBinary \"=\"\n", 2),
+        ("lox-ast v1\nVar \"x\"\n  Literal String \"a\\nb\"\n", 3),
+        ("lox-ast v1\nIf\n  Literal True \"true\"\n", 3),
+    ];
+    for (input, line_number) in errors {
+        let error = program_from_canonical_string(input).unwrap_err();
+        assert_eq!(error.line_number(), line_number, "{}", input);
+    }
+
+    let depth = crate::parser::MAX_NESTING_DEPTH;
+    let source = format!("print {}1{};", "(".repeat(depth), ")".repeat(depth));
+    let program = Parser::from(Lexer::new(&source)).parse().unwrap();
+    let canonical = program_to_canonical_string(&program);
+    assert!(program_from_canonical_string(&canonical).is_ok());
+
+    // the header is line 1, so the first line past the limit is MAX_CANONICAL_DEPTH + 3
+    let mut deep = String::from("lox-ast v1\n");
+    for depth in 0..1_000 {
+        deep += &format!("{}Grouping\n", "  ".repeat(depth));
+    }
+    let error = from_canonical_string(&deep).unwrap_err();
+    assert_eq!(error.line_number(), MAX_CANONICAL_DEPTH + 3);
+}
+
+#[test]
+fn s_expression_round_trip() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use alloc::string::ToString;
+
+    let sources = [
+        "-123 * (45.67 + \"a (b)\") == true",
+        "a = b.c(1, !d) or nil and -(e)",
+        "f()()",
+    ];
+    for source in sources {
+        let expression = Parser::from(Lexer::new(source)).expression_rule().unwrap();
+
+        for parentheses in [Parentheses::Preserve, Parentheses::Minimal] {
+            let printed = expression.to_s_expression(parentheses);
+            let read = Expression::from_s_expression(&printed).unwrap();
+            assert_eq!(read.to_s_expression(parentheses), printed);
+        }
+    }
+    let string = Parser::from(Lexer::new("\"a\" + b"))
+        .expression_rule()
+        .unwrap();
+    assert_eq!(string.to_s_expression(Parentheses::Preserve), "(+ \"a\" b)");
+    assert_eq!(string.to_string(), "(+ a b)");
+
+    let read = Expression::from_s_expression("(* (- 123) (group 45.67))").unwrap();
+    assert_eq!(read.to_source(), "-123 * (45.67)");
+
+    let errors = [
+        ("(+ 1)", ParseErrorKind::ExpectedExpression, ")"),
+        ("(group 1 2)", ParseErrorKind::MissingRightParenthesis, "2"),
+        ("(* 1 2", ParseErrorKind::MissingRightParenthesis, ""),
+        ("(= 1 2)", ParseErrorKind::InvalidAssignmentTarget, "="),
+        ("(print 1)", ParseErrorKind::ExpectedExpression, "print"),
+        ("1 2", ParseErrorKind::MissingRightParenthesis, "2"),
+    ];
+    for (source, kind, lexeme) in errors {
+        let error = Expression::from_s_expression(source).unwrap_err();
+        assert_eq!(
+            (error.kind(), error.token().lexeme()),
+            (&kind, lexeme),
+            "{}",
+            source
+        );
+    }
+
+    let deep = format!("{}1{}", "(group ".repeat(10_000), ")".repeat(10_000));
+    assert_eq!(
+        Expression::from_s_expression(&deep).unwrap_err().kind(),
+        &ParseErrorKind::TooDeeplyNested
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn span_insensitive_comparison() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use std::hash::DefaultHasher;
+
+    let parse = |source| Parser::from(Lexer::new(source)).parse().unwrap();
+    let hash = |statement: &Statement| {
+        let mut hasher = DefaultHasher::new();
+        statement.hash_ignoring_spans(&mut hasher);
+        hasher.finish()
+    };
+
+    let program = parse("for (var i = 0; i < 3; i = i + 1) { print f(i).x; }");
+    let reformatted = parse(
+        "// counting
+for (var i=0;
+     i<3;
+     i=i+1)
+{
+    print f( i ).x;
+}
+",
+    );
+    let different = parse("for (var i = 0; i < 3; i = i + 2) { print f(i).x; }");
+
+    assert_ne!(program.statements(), reformatted.statements());
+    assert!(program.statements()[0].eq_ignoring_spans(&reformatted.statements()[0]));
+    assert_eq!(
+        hash(&program.statements()[0]),
+        hash(&reformatted.statements()[0])
+    );
+    assert!(!program.statements()[0].eq_ignoring_spans(&different.statements()[0]));
+    assert_ne!(
+        hash(&program.statements()[0]),
+        hash(&different.statements()[0])
+    );
+}
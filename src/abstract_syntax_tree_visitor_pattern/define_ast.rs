@@ -0,0 +1,117 @@
+//! [crate::define_ast], which generates the node structs, enum, visitor trait, and dispatch of a visitor-pattern tree
+
+/// Defines a visitor-pattern tree from a list of node kinds, like the book's `GenerateAst`.
+/// Emits the enum, a struct per node with `new` and an accessor per field, a by-reference visitor trait,
+/// and `accept_visitor` on the enum.
+///
+/// The input is the enum, the visitor trait with the name of its visit methods' parameter, then each node as
+/// `Variant(Struct) => visit_method { field: kind Type, ... }`. The kind says how a field is stored:
+/// - `value`: as is. The accessor returns `&Type`
+/// - `boxed`: in a [alloc::boxed::Box]. `new` takes `Type` and the accessor returns `&Type`
+/// - `optional`: in an [Option]. The accessor returns `Option<&Type>`
+/// - `optional_boxed`: in an `Option<Box<Type>>`. `new` takes `Option<Type>` and the accessor returns `Option<&Type>`
+/// - `list`: in a [alloc::vec::Vec]. The accessor returns `&[Type]`
+///
+/// See [crate::abstract_syntax_tree_visitor_pattern::Statement] for a use
+#[macro_export]
+macro_rules! define_ast {
+    (
+        $(#[$enum_meta:meta])*
+        $enum_visibility:vis enum $enum_name:ident<$lifetime:lifetime>;
+        $(#[$visitor_meta:meta])*
+        $visitor_visibility:vis trait $visitor:ident($parameter:ident);
+        $(
+            $(#[$node_meta:meta])*
+            $variant:ident($node:ident) => $visit:ident {
+                $(
+                    $(#[$field_meta:meta])*
+                    $field:ident: $kind:ident $field_type:ty
+                ),* $(,)?
+            }
+        ),* $(,)?
+    ) => {
+        $(#[$visitor_meta])*
+        $visitor_visibility trait $visitor<R> {
+            $(
+                fn $visit(&self, $parameter: &$node) -> R;
+            )*
+        }
+
+        $(#[$enum_meta])*
+        $enum_visibility enum $enum_name<$lifetime> {
+            $(
+                $variant($node<$lifetime>),
+            )*
+        }
+        impl $enum_name<'_> {
+            pub fn accept_visitor<R>(&self, visitor: &impl $visitor<R>) -> R {
+                match self {
+                    $(
+                        $enum_name::$variant($parameter) => visitor.$visit($parameter),
+                    )*
+                }
+            }
+        }
+
+        $(
+            $(#[$node_meta])*
+            $enum_visibility struct $node<$lifetime> {
+                $(
+                    $(#[$field_meta])*
+                    $field: $crate::define_ast_field!(@stored $kind $field_type),
+                )*
+            }
+            impl<$lifetime> $node<$lifetime> {
+                pub fn new($($field: $crate::define_ast_field!(@parameter $kind $field_type)),*) -> Self {
+                    Self {
+                        $(
+                            $field: $crate::define_ast_field!(@store $kind $field),
+                        )*
+                    }
+                }
+                $(
+                    pub fn $field(&self) -> $crate::define_ast_field!(@borrowed $kind $field_type) {
+                        $crate::define_ast_field!(@borrow $kind self.$field)
+                    }
+                )*
+            }
+        )*
+    };
+}
+
+/// How [define_ast] stores, takes, and lends each kind of field
+#[doc(hidden)]
+#[macro_export]
+macro_rules! define_ast_field {
+    (@stored value $field_type:ty) => { $field_type };
+    (@stored boxed $field_type:ty) => { $crate::__alloc::boxed::Box<$field_type> };
+    (@stored optional $field_type:ty) => { ::core::option::Option<$field_type> };
+    (@stored optional_boxed $field_type:ty) => {
+        ::core::option::Option<$crate::__alloc::boxed::Box<$field_type>>
+    };
+    (@stored list $field_type:ty) => { $crate::__alloc::vec::Vec<$field_type> };
+
+    (@parameter value $field_type:ty) => { $field_type };
+    (@parameter boxed $field_type:ty) => { $field_type };
+    (@parameter optional $field_type:ty) => { ::core::option::Option<$field_type> };
+    (@parameter optional_boxed $field_type:ty) => { ::core::option::Option<$field_type> };
+    (@parameter list $field_type:ty) => { $crate::__alloc::vec::Vec<$field_type> };
+
+    (@store value $value:expr) => { $value };
+    (@store boxed $value:expr) => { $crate::__alloc::boxed::Box::new($value) };
+    (@store optional $value:expr) => { $value };
+    (@store optional_boxed $value:expr) => { $value.map($crate::__alloc::boxed::Box::new) };
+    (@store list $value:expr) => { $value };
+
+    (@borrowed value $field_type:ty) => { &$field_type };
+    (@borrowed boxed $field_type:ty) => { &$field_type };
+    (@borrowed optional $field_type:ty) => { ::core::option::Option<&$field_type> };
+    (@borrowed optional_boxed $field_type:ty) => { ::core::option::Option<&$field_type> };
+    (@borrowed list $field_type:ty) => { &[$field_type] };
+
+    (@borrow value $value:expr) => { &$value };
+    (@borrow boxed $value:expr) => { &$value };
+    (@borrow optional $value:expr) => { $value.as_ref() };
+    (@borrow optional_boxed $value:expr) => { $value.as_deref() };
+    (@borrow list $value:expr) => { &$value };
+}
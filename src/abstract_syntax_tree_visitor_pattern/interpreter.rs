@@ -0,0 +1,1307 @@
+//! Evaluates expressions as an [ExpressionVisitor] and runs statements as a [StatementVisitor]
+
+use super::*;
+use crate::excerpt::{SourceExcerpt, WithSource};
+use crate::{
+    interner::{Interner, OwnedInterner, Symbol},
+    messages,
+    token::{Span, TokenKind},
+};
+use alloc::{
+    borrow::ToOwned,
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+};
+
+/// The result of evaluating an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+}
+impl Value {
+    /// `nil` and `false` are falsey, everything else is truthy
+    pub const fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Boolean(false))
+    }
+    /// The name of this value's type, as [ValueDiff] writes it
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+        }
+    }
+    /// Where `other` differs from `self`, taking `self` as the expected value.
+    /// Values are equal when `==` in Lox says so, so `NaN` differs from itself
+    pub fn diff(&self, other: &Value) -> ValueDiff {
+        match (self, other) {
+            (expected, actual) if expected == actual => ValueDiff::Equal,
+            (Value::String(expected), Value::String(actual)) => {
+                let (index, (expected_char, actual_char)) = expected
+                    .chars()
+                    .map(Some)
+                    .chain(core::iter::once(None))
+                    .zip(actual.chars().map(Some).chain(core::iter::once(None)))
+                    .enumerate()
+                    .find(|(_, (expected, actual))| expected != actual)
+                    .unwrap_or((0, (None, None)));
+                ValueDiff::String {
+                    index,
+                    expected: expected_char,
+                    actual: actual_char,
+                }
+            }
+            (Value::Number(expected), Value::Number(actual)) => ValueDiff::Number {
+                expected: *expected,
+                actual: *actual,
+            },
+            (Value::Boolean(expected), Value::Boolean(actual)) => ValueDiff::Boolean {
+                expected: *expected,
+                actual: *actual,
+            },
+            (expected, actual) => ValueDiff::Type {
+                expected: expected.clone(),
+                actual: actual.clone(),
+            },
+        }
+    }
+}
+impl Display for Value {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Boolean(boolean) => write!(f, "{}", boolean),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::String(string) => write!(f, "{}", string),
+        }
+    }
+}
+
+/// Where two [Value]s differ, from [Value::diff]. Displays as a sentence for assertion failures
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueDiff {
+    Equal,
+    /// the values have different types
+    Type {
+        expected: Value,
+        actual: Value,
+    },
+    Boolean {
+        expected: bool,
+        actual: bool,
+    },
+    Number {
+        expected: f64,
+        actual: f64,
+    },
+    /// the strings first differ at the character `index`. [None] where a string ended before it
+    String {
+        index: usize,
+        expected: Option<char>,
+        actual: Option<char>,
+    },
+}
+impl ValueDiff {
+    pub const fn is_equal(&self) -> bool {
+        matches!(self, ValueDiff::Equal)
+    }
+}
+impl Display for ValueDiff {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        /// The value's type and the value as it would be written in Lox, so strings are quoted
+        fn typed(value: &Value) -> String {
+            match value {
+                Value::Nil => "nil".to_owned(),
+                Value::String(string) => alloc::format!("a string ({:?})", string),
+                value => alloc::format!("a {} ({})", value.type_name(), value),
+            }
+        }
+        fn character(character: Option<char>) -> String {
+            character.map_or("the end of the string".to_owned(), |character| {
+                alloc::format!("{:?}", character)
+            })
+        }
+
+        match self {
+            ValueDiff::Equal => write!(f, "values are equal"),
+            ValueDiff::Type { expected, actual } => {
+                write!(f, "expected {}, found {}", typed(expected), typed(actual))
+            }
+            ValueDiff::Boolean { expected, actual } => {
+                write!(f, "expected {}, found {}", expected, actual)
+            }
+            ValueDiff::Number { expected, actual } if expected.is_nan() || actual.is_nan() => {
+                write!(
+                    f,
+                    "expected {}, found {}, and NaN equals nothing",
+                    expected, actual
+                )
+            }
+            ValueDiff::Number { expected, actual } => write!(
+                f,
+                "expected {}, found {}, which is off by {}",
+                expected,
+                actual,
+                actual - expected
+            ),
+            ValueDiff::String {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "strings differ at character {}: expected {}, found {}",
+                index,
+                character(*expected),
+                character(*actual)
+            ),
+        }
+    }
+}
+
+/// Runs a [Custom] statement, registered with [Interpreter::with_custom_statement_handler].
+/// It can use [Interpreter::evaluate] and [Interpreter::execute] on the statement's parts
+pub type CustomStatementHandler = fn(&Interpreter, &Custom) -> Result<(), RuntimeError>;
+
+/// A change to the variables or scopes, or a statement starting or finishing,
+/// reported to the hook set with [Interpreter::with_hook] right after it happens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event<'e> {
+    /// the statement at `span` is about to run
+    Execute { span: Span },
+    /// the statement at `span` finished running, or stopped with an error. Statements inside it finish first
+    Executed { span: Span },
+    /// `name` was created in the innermost scope, or replaced there, with `value`
+    Define { name: &'e str, value: &'e Value },
+    /// the innermost variable called `name` was changed from `old` to `new`
+    Assign {
+        name: &'e str,
+        old: &'e Value,
+        new: &'e Value,
+    },
+    /// a block or loop started running in a new innermost scope
+    EnterScope,
+    /// the innermost scope was dropped
+    ExitScope,
+}
+
+/// Told about each [Event], with the interpreter so it can read the variables or [Interpreter::print]
+pub type Hook = Box<dyn FnMut(&Interpreter, &Event)>;
+
+/// Holds the variables and what `print` printed. They are behind [RefCell]s because visitors only get `&self`.
+/// Variables are keyed by a [Symbol] from `names`, so looking one up compares numbers rather than strings
+pub struct Interpreter {
+    /// the globals, then one per block being run, innermost last
+    scopes: RefCell<Vec<BTreeMap<Symbol, Value>>>,
+    /// the name of every variable, which outlives the sources the names were read from
+    names: RefCell<OwnedInterner>,
+    /// the symbol in `names` for each [Token::symbol] of the program being run, by [Symbol::index]. See [Self::with_symbols_of]
+    program_symbols: RefCell<Vec<Symbol>>,
+    /// what `print` statements printed since the last [Self::take_output]
+    output: RefCell<String>,
+    /// keyed by the id in [TokenKind::CustomKeyword]
+    custom_statement_handlers: BTreeMap<u16, CustomStatementHandler>,
+    /// taken out while it runs, see [Self::notify]
+    hook: RefCell<Option<Hook>>,
+    /// how many statements may run, see [Self::with_step_budget]
+    step_budget: Option<u64>,
+    /// how many statements have run
+    steps: Cell<u64>,
+    /// how many bytes a string or the output may have, see [Self::with_size_limit]
+    size_limit: Option<usize>,
+    /// the loops that are running, outermost first. Only tracked when there is a step budget
+    running_loops: RefCell<Vec<RunningLoop>>,
+    /// how many steps ran in each loop and not in a loop inside it, by the start and end of the loop's span
+    loop_steps: RefCell<BTreeMap<(usize, usize), u64>>,
+}
+
+/// A `while` or `for` loop that is running, for the report when the step budget runs out
+struct RunningLoop {
+    span: Span,
+    keyword: &'static str,
+    /// the line of the first token in the loop
+    line_number: usize,
+    /// the variables its condition and increment use, in order of first use
+    variables: Vec<String>,
+}
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self {
+            scopes: RefCell::new(Vec::from([BTreeMap::new()])),
+            names: RefCell::new(OwnedInterner::new()),
+            program_symbols: RefCell::new(Vec::new()),
+            output: RefCell::new(String::new()),
+            custom_statement_handlers: BTreeMap::new(),
+            hook: RefCell::new(None),
+            step_budget: None,
+            steps: Cell::new(0),
+            size_limit: None,
+            running_loops: RefCell::new(Vec::new()),
+            loop_steps: RefCell::new(BTreeMap::new()),
+        }
+    }
+}
+impl core::fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("scopes", &self.scopes)
+            .field("names", &self.names)
+            .field("output", &self.output)
+            .field("custom_statement_handlers", &self.custom_statement_handlers)
+            .field("step_budget", &self.step_budget)
+            .field("steps", &self.steps)
+            .field("size_limit", &self.size_limit)
+            .finish_non_exhaustive()
+    }
+}
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Runs statements starting with a [TokenKind::CustomKeyword] with id `id` using `handler`.
+    /// Without one they fail with [RuntimeErrorKind::UnsupportedStatement]
+    pub fn with_custom_statement_handler(
+        mut self,
+        id: u16,
+        handler: CustomStatementHandler,
+    ) -> Self {
+        self.custom_statement_handlers.insert(id, handler);
+        self
+    }
+    /// Calls `hook` after each [Event], such as for a trace of the variables as a program runs
+    pub fn with_hook(self, hook: impl FnMut(&Interpreter, &Event) + 'static) -> Self {
+        *self.hook.borrow_mut() = Some(Box::new(hook));
+        self
+    }
+    /// Stops with [RuntimeErrorKind::StepBudgetExhausted] instead of running more than `steps` statements
+    /// since the last [Self::reset_steps], so a runaway loop ends. The error is at the running loop that ran the most statements itself,
+    /// with notes giving how many and the values of the variables in its condition and increment
+    pub fn with_step_budget(mut self, steps: u64) -> Self {
+        self.step_budget = Some(steps);
+        self
+    }
+    /// How many statements have run since the last [Self::reset_steps]
+    pub fn steps(&self) -> u64 {
+        self.steps.get()
+    }
+    /// Starts counting steps from zero, such as before each script or prompt input, so each gets the whole budget
+    pub fn reset_steps(&self) {
+        self.steps.set(0);
+        self.loop_steps.borrow_mut().clear();
+    }
+    /// Stops with [RuntimeErrorKind::SizeLimitExceeded] instead of concatenating a string longer than `bytes`,
+    /// or printing past `bytes` of output since the last [Self::take_output], so a program that doubles a string
+    /// in a loop fails in a few steps instead of using up the memory of the process
+    pub fn with_size_limit(mut self, bytes: usize) -> Self {
+        self.size_limit = Some(bytes);
+        self
+    }
+    /// An error at `token` when `bytes` is over the size limit
+    fn check_size(&self, bytes: usize, token: &Token) -> Result<(), RuntimeError> {
+        match self.size_limit {
+            Some(size_limit) if bytes > size_limit => Err(RuntimeError::new(
+                RuntimeErrorKind::SizeLimitExceeded,
+                token,
+            )),
+            _ => Ok(()),
+        }
+    }
+    /// Runs `run` with the [Token::symbol]s of identifiers naming variables through `interner`,
+    /// which must be the interner of the [Program](crate::abstract_syntax_tree::Program) whose statements `run` executes.
+    /// Each name is looked up once here instead of on every use. Outside of it, variables are found by their names
+    pub fn with_symbols_of<T>(&self, interner: &Interner, run: impl FnOnce() -> T) -> T {
+        let program_symbols = {
+            let mut names = self.names.borrow_mut();
+            interner.strings().map(|name| names.intern(name)).collect()
+        };
+        let outer = self.program_symbols.replace(program_symbols);
+        let result = run();
+        *self.program_symbols.borrow_mut() = outer;
+        result
+    }
+    /// Creates the variable `name` in the innermost scope, or replaces its value there
+    pub fn define(&self, name: &str, value: Value) {
+        let symbol = self.names.borrow_mut().intern(name);
+        self.define_symbol(symbol, name, value);
+    }
+    /// The value of the innermost variable called `name`
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let symbol = self.names.borrow().get(name)?;
+        self.get_symbol(symbol)
+    }
+    /// How many blocks and loops are running, so 0 when the innermost scope is the globals
+    pub fn depth(&self) -> usize {
+        self.scopes.borrow().len() - 1
+    }
+    /// Forgets every variable
+    pub fn clear(&self) {
+        *self.scopes.borrow_mut() = Vec::from([BTreeMap::new()]);
+    }
+    /// The names of every global variable, in order
+    pub fn globals(&self) -> Vec<String> {
+        let names = self.names.borrow();
+        let mut globals = self
+            .scopes
+            .borrow()
+            .first()
+            .into_iter()
+            .flat_map(BTreeMap::keys)
+            .filter_map(|symbol| names.resolve(*symbol))
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        globals.sort();
+        globals
+    }
+    pub fn evaluate(&self, expression: &Expression) -> Result<Value, RuntimeError> {
+        expression.accept_visitor(self)
+    }
+    pub fn execute(&self, statement: &Statement) -> Result<(), RuntimeError> {
+        self.count_step(statement)?;
+        let span = statement.span();
+        self.notify(&Event::Execute { span });
+        let result = statement.accept_visitor(self);
+        self.notify(&Event::Executed { span });
+        result
+    }
+    /// Appends `value` and a newline to the output, like a `print` statement
+    pub fn print(&self, value: &Value) {
+        let mut output = self.output.borrow_mut();
+        output.push_str(&value.to_string());
+        output.push('\n');
+    }
+    /// Returns what has been printed since the last call
+    pub fn take_output(&self) -> String {
+        core::mem::take(&mut self.output.borrow_mut())
+    }
+    /// The symbol in `names` of the variable `name` refers to
+    fn symbol(&self, name: &Token) -> Symbol {
+        name.symbol()
+            .and_then(|symbol| self.program_symbols.borrow().get(symbol.index()).copied())
+            .unwrap_or_else(|| self.names.borrow_mut().intern(name.lexeme()))
+    }
+    /// [Self::define] with the symbol of `name` already looked up
+    fn define_symbol(&self, symbol: Symbol, name: &str, value: Value) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(symbol, value.clone());
+        }
+        self.notify(&Event::Define {
+            name,
+            value: &value,
+        });
+    }
+    fn get_symbol(&self, symbol: Symbol) -> Option<Value> {
+        self.scopes
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&symbol))
+            .cloned()
+    }
+    /// Runs `run` in a new innermost scope, which is dropped afterwards even on error
+    fn scoped<T>(&self, run: impl FnOnce() -> T) -> T {
+        self.scopes.borrow_mut().push(BTreeMap::new());
+        self.notify(&Event::EnterScope);
+        let result = run();
+        self.scopes.borrow_mut().pop();
+        self.notify(&Event::ExitScope);
+        result
+    }
+    /// Counts `statement` against the step budget, if there is one, and the innermost running loop
+    fn count_step(&self, statement: &Statement) -> Result<(), RuntimeError> {
+        let Some(step_budget) = self.step_budget else {
+            return Ok(());
+        };
+        if self.steps.get() >= step_budget {
+            return Err(self.step_budget_error(statement, step_budget));
+        }
+
+        self.steps.set(self.steps.get() + 1);
+        if let Some(running_loop) = self.running_loops.borrow().last() {
+            let span = running_loop.span;
+            *self
+                .loop_steps
+                .borrow_mut()
+                .entry((span.start, span.end))
+                .or_default() += 1;
+        }
+        Ok(())
+    }
+    /// The error at the running loop with the most steps, or at `statement` when no loop is running
+    fn step_budget_error(&self, statement: &Statement, step_budget: u64) -> RuntimeError {
+        let loop_steps = self.loop_steps.borrow();
+        let running_loops = self.running_loops.borrow();
+        let steps_in = |running_loop: &RunningLoop| {
+            let span = running_loop.span;
+            loop_steps
+                .get(&(span.start, span.end))
+                .copied()
+                .unwrap_or_default()
+        };
+        // the last of equals, so the innermost of loops that ran as many
+        let Some(hottest) = running_loops
+            .iter()
+            .max_by_key(|running_loop| steps_in(running_loop))
+        else {
+            let (lexeme, line_number) = first_token(statement)
+                .map_or(("", 0), |token| (token.lexeme(), token.line_number()));
+            return RuntimeError {
+                kind: RuntimeErrorKind::StepBudgetExhausted,
+                lexeme: lexeme.to_owned(),
+                line_number,
+                span: statement.span(),
+                notes: Vec::new(),
+            };
+        };
+
+        let steps = steps_in(hottest).to_string();
+        let mut notes = Vec::from([messages::format_message(
+            messages::LOOP_STEPS_TEMPLATE,
+            &[("steps", &steps), ("budget", &step_budget)],
+        )]);
+        let values = hottest
+            .variables
+            .iter()
+            .filter_map(|name| {
+                let value = match self.get(name)? {
+                    Value::String(string) => alloc::format!("{:?}", string),
+                    value => value.to_string(),
+                };
+                Some(alloc::format!("{} = {}", name, value))
+            })
+            .collect::<Vec<_>>();
+        if !values.is_empty() {
+            notes.push(messages::format_message(
+                messages::LOOP_VARIABLES_TEMPLATE,
+                &[("variables", &values.join(", "))],
+            ));
+        }
+
+        RuntimeError {
+            kind: RuntimeErrorKind::StepBudgetExhausted,
+            lexeme: hottest.keyword.to_owned(),
+            line_number: hottest.line_number,
+            span: hottest.span,
+            notes,
+        }
+    }
+    /// Runs `run` as the loop at `span`, so the steps it takes are counted against it. See [Self::with_step_budget].
+    /// `first_token` is the first token inside it, whose line the loop is reported on
+    fn in_loop<T>(
+        &self,
+        span: Span,
+        keyword: &'static str,
+        first_token: Option<&Token>,
+        condition: Option<&Expression>,
+        increment: Option<&Expression>,
+        run: impl FnOnce() -> T,
+    ) -> T {
+        if self.step_budget.is_none() {
+            return run();
+        }
+
+        let mut variables = Vec::new();
+        for expression in condition.into_iter().chain(increment) {
+            variable_names(expression, &mut variables);
+        }
+        self.running_loops.borrow_mut().push(RunningLoop {
+            span,
+            keyword,
+            line_number: first_token.map_or(0, Token::line_number),
+            variables,
+        });
+        let result = run();
+        self.running_loops.borrow_mut().pop();
+        result
+    }
+    /// Calls the hook with `event`. It is taken out while it runs, so it can use the interpreter,
+    /// but the events of anything it runs are not reported to it
+    fn notify(&self, event: &Event) {
+        let Some(mut hook) = self.hook.borrow_mut().take() else {
+            return;
+        };
+        hook(self, event);
+        *self.hook.borrow_mut() = Some(hook);
+    }
+    fn number_operand(&self, operator: &Token, operand: &Expression) -> Result<f64, RuntimeError> {
+        match self.evaluate(operand)? {
+            Value::Number(number) => Ok(number),
+            _ => Err(RuntimeError::new(
+                RuntimeErrorKind::OperandMustBeNumber,
+                operator,
+            )),
+        }
+    }
+}
+impl ExpressionVisitor<Result<Value, RuntimeError>> for Interpreter {
+    fn visit_binary_expression(&self, expression: &Binary) -> Result<Value, RuntimeError> {
+        let left_operand = self.evaluate(expression.left_operand())?;
+        let right_operand = self.evaluate(expression.right_operand())?;
+        let operator = expression.operator();
+
+        let value = match (operator.kind(), left_operand, right_operand) {
+            (TokenKind::EqualEqual, left, right) => Value::Boolean(left == right),
+            (TokenKind::BangEqual, left, right) => Value::Boolean(left != right),
+            (TokenKind::Plus, Value::Number(left), Value::Number(right)) => {
+                Value::Number(left + right)
+            }
+            (TokenKind::Plus, Value::String(left), Value::String(right)) => {
+                self.check_size(left.len() + right.len(), operator)?;
+                Value::String(left + &right)
+            }
+            (TokenKind::Plus, _, _) => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::OperandsMustBeNumbersOrStrings,
+                    operator,
+                ))
+            }
+            (kind, Value::Number(left), Value::Number(right)) => match kind {
+                TokenKind::Minus => Value::Number(left - right),
+                TokenKind::Star => Value::Number(left * right),
+                TokenKind::Slash => Value::Number(left / right),
+                TokenKind::Greater => Value::Boolean(left > right),
+                TokenKind::GreaterEqual => Value::Boolean(left >= right),
+                TokenKind::Less => Value::Boolean(left < right),
+                TokenKind::LessEqual => Value::Boolean(left <= right),
+                _ => {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorKind::UnsupportedOperator,
+                        operator,
+                    ))
+                }
+            },
+            _ => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::OperandsMustBeNumbers,
+                    operator,
+                ))
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn visit_unary_expression(&self, expression: &Unary) -> Result<Value, RuntimeError> {
+        let operator = expression.operator();
+        match operator.kind() {
+            TokenKind::Bang => Ok(Value::Boolean(
+                !self.evaluate(expression.right_operand())?.is_truthy(),
+            )),
+            TokenKind::Minus => Ok(Value::Number(
+                -self.number_operand(operator, expression.right_operand())?,
+            )),
+            _ => Err(RuntimeError::new(
+                RuntimeErrorKind::UnsupportedOperator,
+                operator,
+            )),
+        }
+    }
+
+    fn visit_grouping_expression(&self, expression: &Grouping) -> Result<Value, RuntimeError> {
+        self.evaluate(expression.inner_expression())
+    }
+
+    fn visit_literal_expression(&self, expression: &Literal) -> Result<Value, RuntimeError> {
+        let token = expression.token();
+        let value = match token.kind() {
+            TokenKind::Nil => Value::Nil,
+            TokenKind::True => Value::Boolean(true),
+            TokenKind::False => Value::Boolean(false),
+            TokenKind::String => Value::String(token.lexeme().to_owned()),
+            TokenKind::Number => match token.number_value().or_else(|| token.lexeme().parse().ok())
+            {
+                Some(number) => Value::Number(number),
+                None => {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorKind::UnsupportedLiteral,
+                        token,
+                    ))
+                }
+            },
+            _ => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorKind::UnsupportedLiteral,
+                    token,
+                ))
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn visit_variable_expression(&self, expression: &Variable) -> Result<Value, RuntimeError> {
+        let name = expression.name();
+        self.get_symbol(self.symbol(name))
+            .ok_or_else(|| RuntimeError::new(RuntimeErrorKind::UndefinedVariable, name))
+    }
+
+    fn visit_assign_expression(&self, expression: &Assign) -> Result<Value, RuntimeError> {
+        let name = expression.name();
+        let value = self.evaluate(expression.value())?;
+        let symbol = self.symbol(name);
+
+        let old = {
+            let mut scopes = self.scopes.borrow_mut();
+            let variable = scopes
+                .iter_mut()
+                .rev()
+                .find_map(|scope| scope.get_mut(&symbol))
+                .ok_or_else(|| RuntimeError::new(RuntimeErrorKind::UndefinedVariable, name))?;
+            core::mem::replace(variable, value.clone())
+        };
+        self.notify(&Event::Assign {
+            name: name.lexeme(),
+            old: &old,
+            new: &value,
+        });
+
+        Ok(value)
+    }
+
+    fn visit_logical_expression(&self, expression: &Logical) -> Result<Value, RuntimeError> {
+        let left_operand = self.evaluate(expression.left_operand())?;
+
+        let short_circuits = match expression.operator().kind() {
+            TokenKind::Or => left_operand.is_truthy(),
+            _ => !left_operand.is_truthy(),
+        };
+        if short_circuits {
+            return Ok(left_operand);
+        }
+
+        self.evaluate(expression.right_operand())
+    }
+
+    fn visit_call_expression(&self, expression: &Call) -> Result<Value, RuntimeError> {
+        self.evaluate(expression.callee())?;
+        for argument in expression.arguments() {
+            self.evaluate(argument)?;
+        }
+
+        // there are no function or class values yet
+        Err(RuntimeError::new(
+            RuntimeErrorKind::NotCallable,
+            expression.closing_parenthesis(),
+        ))
+    }
+
+    fn visit_get_expression(&self, expression: &Get) -> Result<Value, RuntimeError> {
+        self.evaluate(expression.object())?;
+
+        // there are no instances yet
+        Err(RuntimeError::new(
+            RuntimeErrorKind::NotAnInstance,
+            expression.name(),
+        ))
+    }
+}
+
+impl StatementVisitor<Result<(), RuntimeError>> for Interpreter {
+    fn visit_expression_statement(
+        &self,
+        statement: &ExpressionStatement,
+    ) -> Result<(), RuntimeError> {
+        self.evaluate(statement.expression())?;
+        Ok(())
+    }
+
+    fn visit_print_statement(&self, statement: &Print) -> Result<(), RuntimeError> {
+        let value = self.evaluate(statement.expression())?;
+        if self.size_limit.is_some() {
+            let printed = self.output.borrow().len() + value.to_string().len() + 1;
+            self.check_size(printed, expression_first_token(statement.expression()))?;
+        }
+        self.print(&value);
+        Ok(())
+    }
+
+    fn visit_var_statement(&self, statement: &Var) -> Result<(), RuntimeError> {
+        let value = match statement.initializer() {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        let name = statement.name();
+        self.define_symbol(self.symbol(name), name.lexeme(), value);
+        Ok(())
+    }
+
+    fn visit_block_statement(&self, statement: &Block) -> Result<(), RuntimeError> {
+        self.scoped(|| {
+            statement
+                .statements()
+                .iter()
+                .try_for_each(|statement| self.execute(statement))
+        })
+    }
+
+    fn visit_if_statement(&self, statement: &If) -> Result<(), RuntimeError> {
+        if self.evaluate(statement.condition())?.is_truthy() {
+            self.execute(statement.then_branch())
+        } else if let Some(else_branch) = statement.else_branch() {
+            self.execute(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_while_statement(&self, statement: &While) -> Result<(), RuntimeError> {
+        let condition = statement.condition();
+        let first_token = expression_first_token(condition);
+        self.in_loop(
+            *statement.span(),
+            "while",
+            Some(first_token),
+            Some(condition),
+            None,
+            || {
+                while self.evaluate(condition)?.is_truthy() {
+                    self.execute(statement.body())?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn visit_for_statement(&self, statement: &For) -> Result<(), RuntimeError> {
+        let first_token = statement
+            .initializer()
+            .and_then(first_token)
+            .or_else(|| statement.condition().map(expression_first_token))
+            .or_else(|| statement.increment().map(expression_first_token))
+            .or_else(|| first_token(statement.body()));
+
+        // the initializer's variable only exists inside the loop
+        self.scoped(|| {
+            if let Some(initializer) = statement.initializer() {
+                self.execute(initializer)?;
+            }
+
+            self.in_loop(
+                *statement.span(),
+                "for",
+                first_token,
+                statement.condition(),
+                statement.increment(),
+                || {
+                    while statement
+                        .condition()
+                        .map_or(Ok(Value::Boolean(true)), |condition| {
+                            self.evaluate(condition)
+                        })?
+                        .is_truthy()
+                    {
+                        self.execute(statement.body())?;
+                        if let Some(increment) = statement.increment() {
+                            self.evaluate(increment)?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        })
+    }
+
+    fn visit_custom_statement(&self, statement: &Custom) -> Result<(), RuntimeError> {
+        let handler = match statement.keyword().kind() {
+            TokenKind::CustomKeyword(id) => self.custom_statement_handlers.get(&id),
+            _ => None,
+        };
+
+        match handler {
+            Some(handler) => handler(self, statement),
+            None => Err(RuntimeError::new(
+                RuntimeErrorKind::UnsupportedStatement,
+                statement.keyword(),
+            )),
+        }
+    }
+}
+
+/// The first token of `expression`, not counting the `(` of a [Grouping], which is not kept
+fn expression_first_token<'e>(expression: &'e Expression) -> &'e Token<'e> {
+    match expression {
+        Expression::Binary(binary) => expression_first_token(binary.left_operand()),
+        Expression::Logical(logical) => expression_first_token(logical.left_operand()),
+        Expression::Call(call) => expression_first_token(call.callee()),
+        Expression::Get(get) => expression_first_token(get.object()),
+        Expression::Grouping(grouping) => expression_first_token(grouping.inner_expression()),
+        Expression::Unary(unary) => unary.operator(),
+        Expression::Assign(assign) => assign.name(),
+        Expression::Literal(literal) => literal.token(),
+        Expression::Variable(variable) => variable.name(),
+    }
+}
+
+/// The first token kept in `statement`. Keywords other than a [Custom] one are not kept, so [None] for an empty block
+fn first_token<'s>(statement: &'s Statement) -> Option<&'s Token<'s>> {
+    match statement {
+        Statement::Expression(statement) => Some(expression_first_token(statement.expression())),
+        Statement::Print(statement) => Some(expression_first_token(statement.expression())),
+        Statement::Var(statement) => Some(statement.name()),
+        Statement::Block(statement) => statement.statements().iter().find_map(first_token),
+        Statement::If(statement) => Some(expression_first_token(statement.condition())),
+        Statement::While(statement) => Some(expression_first_token(statement.condition())),
+        Statement::For(statement) => statement
+            .initializer()
+            .and_then(first_token)
+            .or_else(|| statement.condition().map(expression_first_token))
+            .or_else(|| statement.increment().map(expression_first_token))
+            .or_else(|| first_token(statement.body())),
+        Statement::Custom(statement) => Some(statement.keyword()),
+    }
+}
+
+/// Appends each variable `expression` reads or assigns to `names`, unless it is already there
+fn variable_names(expression: &Expression, names: &mut Vec<String>) {
+    let mut add = |name: &Token| {
+        if !names.iter().any(|added| added == name.lexeme()) {
+            names.push(name.lexeme().to_owned());
+        }
+    };
+    match expression {
+        Expression::Variable(variable) => add(variable.name()),
+        Expression::Assign(assign) => {
+            add(assign.name());
+            variable_names(assign.value(), names);
+        }
+        Expression::Binary(binary) => {
+            variable_names(binary.left_operand(), names);
+            variable_names(binary.right_operand(), names);
+        }
+        Expression::Logical(logical) => {
+            variable_names(logical.left_operand(), names);
+            variable_names(logical.right_operand(), names);
+        }
+        Expression::Unary(unary) => variable_names(unary.right_operand(), names),
+        Expression::Grouping(grouping) => variable_names(grouping.inner_expression(), names),
+        Expression::Call(call) => {
+            variable_names(call.callee(), names);
+            for argument in call.arguments() {
+                variable_names(argument, names);
+            }
+        }
+        Expression::Get(get) => variable_names(get.object(), names),
+        Expression::Literal(_) => {}
+    }
+}
+
+/// An error found while evaluating. Owns the text of the token it is at,
+/// since the visited nodes only lend their tokens for the duration of each visit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    kind: RuntimeErrorKind,
+    lexeme: String,
+    line_number: usize,
+    span: Span,
+    /// more about what went wrong, such as the loop variables of [RuntimeErrorKind::StepBudgetExhausted]
+    notes: Vec<String>,
+}
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind, token: &Token) -> Self {
+        Self {
+            kind,
+            lexeme: token.lexeme().to_owned(),
+            line_number: token.line_number(),
+            span: token.span(),
+            notes: Vec::new(),
+        }
+    }
+    pub const fn kind(&self) -> RuntimeErrorKind {
+        self.kind
+    }
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+    pub const fn line_number(&self) -> usize {
+        self.line_number
+    }
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+    /// Displays the error followed by the line of `source` it is on, marking the token it was found at
+    pub fn with_source<'s>(&'s self, source: &'s str) -> WithSource<'s, Self> {
+        WithSource::new(self, SourceExcerpt::new(source, self.span))
+    }
+}
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let output = messages::format_message(
+            messages::RUNTIME_ERROR_TEMPLATE,
+            &[
+                ("lexeme", &self.lexeme),
+                ("line", &self.line_number),
+                ("message", &messages::message(self.kind.code())),
+            ],
+        );
+
+        write!(f, "{}", output)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    OperandMustBeNumber,
+    OperandsMustBeNumbers,
+    OperandsMustBeNumbersOrStrings,
+    UndefinedVariable,
+    NotCallable,
+    NotAnInstance,
+    /// An operator token the parser never puts in that position, in a hand-built tree
+    UnsupportedOperator,
+    /// A host-defined literal, or a literal token the parser never produces, in a hand-built tree
+    UnsupportedLiteral,
+    /// A [Custom] statement without a [CustomStatementHandler]
+    UnsupportedStatement,
+    /// More statements than [Interpreter::with_step_budget] allows
+    StepBudgetExhausted,
+    /// A string or output larger than [Interpreter::with_size_limit] allows
+    SizeLimitExceeded,
+}
+impl RuntimeErrorKind {
+    /// Stable identifier used to look up this error's message in [crate::messages]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            RuntimeErrorKind::OperandMustBeNumber => "R0001",
+            RuntimeErrorKind::OperandsMustBeNumbers => "R0002",
+            RuntimeErrorKind::OperandsMustBeNumbersOrStrings => "R0003",
+            RuntimeErrorKind::UndefinedVariable => "R0004",
+            RuntimeErrorKind::NotCallable => "R0005",
+            RuntimeErrorKind::UnsupportedOperator => "R0006",
+            RuntimeErrorKind::UnsupportedLiteral => "R0007",
+            RuntimeErrorKind::NotAnInstance => "R0008",
+            RuntimeErrorKind::UnsupportedStatement => "R0009",
+            RuntimeErrorKind::StepBudgetExhausted => "R0010",
+            RuntimeErrorKind::SizeLimitExceeded => "R0011",
+        }
+    }
+}
+
+#[test]
+fn evaluate_expressions() {
+    use alloc::format;
+
+    let token = |kind, lexeme| Token::new(kind, lexeme, 1);
+    let literal = |kind, lexeme| Expression::Literal(Literal::new(token(kind, lexeme)));
+    let number = |lexeme| literal(TokenKind::Number, lexeme);
+    let variable = |name| Expression::Variable(Variable::new(token(TokenKind::Identifier, name)));
+
+    let interpreter = Interpreter::new();
+    interpreter.define("a", Value::Number(2.0));
+
+    // -a * (1 + 3)
+    let product = Expression::Binary(Binary::new(
+        Expression::Unary(Unary::new(token(TokenKind::Minus, "-"), variable("a"))),
+        token(TokenKind::Star, "*"),
+        Expression::Grouping(Grouping::new(Expression::Binary(Binary::new(
+            number("1"),
+            token(TokenKind::Plus, "+"),
+            number("3"),
+        )))),
+    ));
+    assert_eq!(interpreter.evaluate(&product), Ok(Value::Number(-8.0)));
+
+    // a = "lo" + "x"
+    let assign = Expression::Assign(Assign::new(
+        token(TokenKind::Identifier, "a"),
+        Expression::Binary(Binary::new(
+            literal(TokenKind::String, "lo"),
+            token(TokenKind::Plus, "+"),
+            literal(TokenKind::String, "x"),
+        )),
+    ));
+    assert_eq!(interpreter.evaluate(&assign).unwrap().to_string(), "lox");
+    assert_eq!(interpreter.get("a"), Some(Value::String("lox".to_string())));
+
+    // nil or b, where b is undefined, evaluates b. false and b does not
+    let logical = |operator, lexeme, left| {
+        Expression::Logical(Logical::new(left, token(operator, lexeme), variable("b")))
+    };
+    let or = logical(TokenKind::Or, "or", literal(TokenKind::Nil, "nil"));
+    let error = interpreter.evaluate(&or).unwrap_err();
+    assert_eq!(error.kind(), RuntimeErrorKind::UndefinedVariable);
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Runtime error at b on line 1: {}",
+            messages::message("R0004")
+        )
+    );
+    let and = logical(TokenKind::And, "and", literal(TokenKind::False, "false"));
+    assert_eq!(interpreter.evaluate(&and), Ok(Value::Boolean(false)));
+
+    // -a, where a is a string
+    let negate = Expression::Unary(Unary::new(token(TokenKind::Minus, "-"), variable("a")));
+    assert_eq!(
+        interpreter.evaluate(&negate).unwrap_err().kind(),
+        RuntimeErrorKind::OperandMustBeNumber
+    );
+}
+
+#[test]
+fn execute_statements() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const SOURCE: &str = "var a = 1; { var a = 2; print a; } print a; for (var i = 0; i < 3; i = i + 1) if (i != 1) a = a + i; else print \"one\"; while (a > 0) a = a - 2; print a;";
+    let statements = Parser::from(Lexer::new(SOURCE))
+        .parse_into_visitor_pattern()
+        .unwrap();
+
+    let interpreter = Interpreter::new();
+    for statement in &statements {
+        interpreter.execute(statement).unwrap();
+    }
+
+    assert_eq!(interpreter.take_output(), "2\n1\none\n-1\n");
+    assert_eq!(interpreter.take_output(), "");
+    assert_eq!(interpreter.globals(), ["a"]);
+}
+
+#[test]
+fn symbols_from_several_programs() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    let interpreter = Interpreter::new();
+    // each program numbers its names in the order they first appear
+    for source in ["var b = 1; var a = 2;", "a = a + b; print a;", "print c;"] {
+        let (statements, interner) = Parser::from(Lexer::new(source))
+            .parse()
+            .unwrap()
+            .into_parts();
+        let result = interpreter.with_symbols_of(&interner, || {
+            statements
+                .into_iter()
+                .try_for_each(|statement| interpreter.execute(&statement.into()))
+        });
+
+        if source == "print c;" {
+            assert_eq!(result.unwrap_err().lexeme(), "c");
+        } else {
+            result.unwrap();
+        }
+    }
+
+    assert_eq!(interpreter.take_output(), "3\n");
+    assert_eq!(interpreter.get("a"), Some(Value::Number(3.0)));
+    assert_eq!(interpreter.get("c"), None);
+    assert_eq!(interpreter.globals(), ["a", "b"]);
+}
+
+#[test]
+fn custom_statement_handlers() {
+    use crate::{dialect::Dialect, lexer::Lexer, parser::Parser};
+
+    const REPEAT: u16 = 0;
+    const UNHANDLED: u16 = 1;
+
+    /// `repeat n { ... }` runs its block `n` times
+    fn run_repeat(interpreter: &Interpreter, statement: &Custom) -> Result<(), RuntimeError> {
+        let Value::Number(count) = interpreter.evaluate(&statement.expressions()[0])? else {
+            return Err(RuntimeError::new(
+                RuntimeErrorKind::OperandMustBeNumber,
+                statement.keyword(),
+            ));
+        };
+        for _ in 0..count as usize {
+            for inner in statement.statements() {
+                interpreter.execute(inner)?;
+            }
+        }
+        Ok(())
+    }
+
+    let dialect = Dialect::new("scripting")
+        .with_statement_keyword("repeat", REPEAT)
+        .with_statement_keyword("skip", UNHANDLED);
+    let statements = Parser::from(Lexer::with_config(
+        "repeat 2 { print 1; } skip;",
+        dialect.lexer_config(),
+    ))
+    .parse_into_visitor_pattern()
+    .unwrap();
+
+    let interpreter = Interpreter::new().with_custom_statement_handler(REPEAT, run_repeat);
+    interpreter.execute(&statements[0]).unwrap();
+    assert_eq!(interpreter.take_output(), "1\n1\n");
+
+    let error = interpreter.execute(&statements[1]).unwrap_err();
+    assert_eq!(error.kind(), RuntimeErrorKind::UnsupportedStatement);
+    assert_eq!(error.lexeme(), "skip");
+}
+
+#[test]
+fn environment_events() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use alloc::{format, rc::Rc};
+
+    let statements = Parser::from(Lexer::new("var a = 1; { var a = 2; a = 3; } a = 4;"))
+        .parse_into_visitor_pattern()
+        .unwrap();
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let recorded = Rc::clone(&events);
+    let interpreter = Interpreter::new().with_hook(move |interpreter, event| {
+        if let Event::Execute { span } | Event::Executed { span } = event {
+            recorded.borrow_mut().push(format!("{:?}", span.range()));
+            return;
+        }
+        recorded
+            .borrow_mut()
+            .push(format!("{} {:?}", interpreter.depth(), event));
+        // what the hook runs is not reported to it
+        interpreter.define("seen", Value::Boolean(true));
+    });
+    for statement in &statements {
+        interpreter.execute(statement).unwrap();
+    }
+
+    assert_eq!(
+        *events.borrow(),
+        [
+            "0..10",
+            "0 Define { name: \"a\", value: Number(1.0) }",
+            "0..10",
+            "11..32",
+            "1 EnterScope",
+            "13..23",
+            "1 Define { name: \"a\", value: Number(2.0) }",
+            "13..23",
+            "24..30",
+            "1 Assign { name: \"a\", old: Number(2.0), new: Number(3.0) }",
+            "24..30",
+            "0 ExitScope",
+            "11..32",
+            "33..39",
+            "0 Assign { name: \"a\", old: Number(1.0), new: Number(4.0) }",
+            "33..39",
+        ]
+    );
+    assert_eq!(interpreter.get("seen"), Some(Value::Boolean(true)));
+}
+
+#[test]
+fn step_budget() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    let run = |source: &str, step_budget: u64| {
+        let statements = Parser::from(Lexer::new(source))
+            .parse_into_visitor_pattern()
+            .unwrap();
+        let interpreter = Interpreter::new().with_step_budget(step_budget);
+        let result = statements
+            .iter()
+            .try_for_each(|statement| interpreter.execute(statement));
+        (result, interpreter.steps())
+    };
+
+    const RUNAWAY: &str = "var n = 0; var label = \"x\";
+for (var i = 0; i < 10; i = i - 1) {
+  n = n + 1;
+  var k = 0;
+  while (k < 1 and label != \"\") k = k + 1;
+}";
+    let (result, steps) = run(RUNAWAY, 40);
+    let error = result.unwrap_err();
+    assert_eq!(steps, 40);
+    assert_eq!(error.kind(), RuntimeErrorKind::StepBudgetExhausted);
+    assert_eq!(error.lexeme(), "for");
+    assert_eq!(error.line_number(), 2);
+    assert_eq!(&RUNAWAY[error.span().range()], &RUNAWAY[28..]);
+    // the while loop only ever runs one statement at a time, so the for loop ran the most
+    assert_eq!(
+        error.notes(),
+        [
+            "this loop ran 29 of the 40 steps",
+            "its variables when it stopped: i = -7",
+        ]
+    );
+
+    let (result, _) = run("var label = \"a\"; while (label != \"\") label = label;", 5);
+    assert_eq!(
+        result.unwrap_err().notes(),
+        [
+            "this loop ran 3 of the 5 steps",
+            "its variables when it stopped: label = \"a\"",
+        ]
+    );
+
+    // outside of a loop, the error is at the statement that would have gone over
+    let (result, steps) = run("print 1; print 2;", 1);
+    let error = result.unwrap_err();
+    assert_eq!((error.lexeme(), steps), ("2", 1));
+    assert!(error.notes().is_empty());
+
+    assert_eq!(run("print 1; print 2;", 2), (Ok(()), 2));
+
+    // each run gets the whole budget once the steps are reset
+    let interpreter = Interpreter::new().with_step_budget(3);
+    for _ in 0..2 {
+        interpreter.reset_steps();
+        let statements = Parser::from(Lexer::new("print 1; print 2;"))
+            .parse_into_visitor_pattern()
+            .unwrap();
+        for statement in &statements {
+            interpreter.execute(statement).unwrap();
+        }
+        assert_eq!(interpreter.steps(), 2);
+    }
+    assert_eq!(interpreter.take_output(), "1\n2\n1\n2\n");
+}
+
+#[test]
+fn value_diffs() {
+    use alloc::format;
+
+    let string = |string: &str| Value::String(string.to_owned());
+    let described = |expected: Value, actual: Value| format!("{}", expected.diff(&actual));
+
+    assert!(Value::Number(1.0).diff(&Value::Number(1.0)).is_equal());
+    assert!(string("lox").diff(&string("lox")).is_equal());
+    assert_eq!(
+        string("lox").diff(&string("lo")),
+        ValueDiff::String {
+            index: 2,
+            expected: Some('x'),
+            actual: None,
+        }
+    );
+    assert_eq!(
+        described(string("héllo"), string("hélp")),
+        "strings differ at character 3: expected 'l', found 'p'"
+    );
+    assert_eq!(
+        described(string("lo"), string("lox")),
+        "strings differ at character 2: expected the end of the string, found 'x'"
+    );
+    assert_eq!(
+        described(Value::Number(0.3), Value::Number(0.5)),
+        "expected 0.3, found 0.5, which is off by 0.2"
+    );
+    assert_eq!(
+        described(Value::Number(f64::NAN), Value::Number(f64::NAN)),
+        "expected NaN, found NaN, and NaN equals nothing"
+    );
+    assert_eq!(
+        described(Value::Boolean(true), Value::Boolean(false)),
+        "expected true, found false"
+    );
+    assert_eq!(
+        described(Value::Number(1.0), string("1")),
+        "expected a number (1), found a string (\"1\")"
+    );
+    assert_eq!(
+        described(Value::Nil, Value::Boolean(false)),
+        "expected nil, found a boolean (false)"
+    );
+}
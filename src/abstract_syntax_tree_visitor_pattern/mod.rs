@@ -1,15 +1,55 @@
-//! A re-implementation of [super::abstract_syntax_tree] that uses the Visitor design pattern
+//! A re-implementation of [super::abstract_syntax_tree] that uses the Visitor design pattern.
+//! Trees parsed into [super::abstract_syntax_tree] convert into it with [From], which keeps the span of each statement
+//! but drops those of expressions
 
+mod define_ast;
+pub mod interpreter;
 pub mod printer;
 
-use crate::token::Token;
-use std::ops::Deref;
+use crate::{
+    abstract_syntax_tree as enum_ast,
+    token::{Span, Token},
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::ops::Deref;
 
 pub trait ExpressionVisitor<R> {
     fn visit_binary_expression(&self, expression: &Binary) -> R;
     fn visit_unary_expression(&self, expression: &Unary) -> R;
     fn visit_grouping_expression(&self, expression: &Grouping) -> R;
     fn visit_literal_expression(&self, expression: &Literal) -> R;
+    fn visit_variable_expression(&self, expression: &Variable) -> R;
+    fn visit_assign_expression(&self, expression: &Assign) -> R;
+    fn visit_logical_expression(&self, expression: &Logical) -> R;
+    fn visit_call_expression(&self, expression: &Call) -> R;
+    fn visit_get_expression(&self, expression: &Get) -> R;
+}
+
+/// An [ExpressionVisitor] that can change the nodes it visits and its own state
+pub trait ExpressionVisitorMut<R> {
+    fn visit_binary_expression_mut(&mut self, expression: &mut Binary) -> R;
+    fn visit_unary_expression_mut(&mut self, expression: &mut Unary) -> R;
+    fn visit_grouping_expression_mut(&mut self, expression: &mut Grouping) -> R;
+    fn visit_literal_expression_mut(&mut self, expression: &mut Literal) -> R;
+    fn visit_variable_expression_mut(&mut self, expression: &mut Variable) -> R;
+    fn visit_assign_expression_mut(&mut self, expression: &mut Assign) -> R;
+    fn visit_logical_expression_mut(&mut self, expression: &mut Logical) -> R;
+    fn visit_call_expression_mut(&mut self, expression: &mut Call) -> R;
+    fn visit_get_expression_mut(&mut self, expression: &mut Get) -> R;
+}
+
+/// Consumes each node and returns the expression that replaces it, for passes like desugaring or constant folding.
+/// Use [Expression::transform] on the children to rewrite them too
+pub trait Transformer<'a> {
+    fn transform_binary_expression(&mut self, expression: Binary<'a>) -> Expression<'a>;
+    fn transform_unary_expression(&mut self, expression: Unary<'a>) -> Expression<'a>;
+    fn transform_grouping_expression(&mut self, expression: Grouping<'a>) -> Expression<'a>;
+    fn transform_literal_expression(&mut self, expression: Literal<'a>) -> Expression<'a>;
+    fn transform_variable_expression(&mut self, expression: Variable<'a>) -> Expression<'a>;
+    fn transform_assign_expression(&mut self, expression: Assign<'a>) -> Expression<'a>;
+    fn transform_logical_expression(&mut self, expression: Logical<'a>) -> Expression<'a>;
+    fn transform_call_expression(&mut self, expression: Call<'a>) -> Expression<'a>;
+    fn transform_get_expression(&mut self, expression: Get<'a>) -> Expression<'a>;
 }
 
 pub enum Expression<'a> {
@@ -17,14 +57,50 @@ pub enum Expression<'a> {
     Unary(Unary<'a>),
     Grouping(Grouping<'a>),
     Literal(Literal<'a>),
+    Variable(Variable<'a>),
+    Assign(Assign<'a>),
+    Logical(Logical<'a>),
+    Call(Call<'a>),
+    Get(Get<'a>),
 }
-impl Expression<'_> {
+impl<'a> Expression<'a> {
     pub fn accept_visitor<R>(&self, visitor: &impl ExpressionVisitor<R>) -> R {
         match self {
             Expression::Binary(binary) => visitor.visit_binary_expression(binary),
             Expression::Unary(unary) => visitor.visit_unary_expression(unary),
             Expression::Grouping(grouping) => visitor.visit_grouping_expression(grouping),
             Expression::Literal(literal) => visitor.visit_literal_expression(literal),
+            Expression::Variable(variable) => visitor.visit_variable_expression(variable),
+            Expression::Assign(assign) => visitor.visit_assign_expression(assign),
+            Expression::Logical(logical) => visitor.visit_logical_expression(logical),
+            Expression::Call(call) => visitor.visit_call_expression(call),
+            Expression::Get(get) => visitor.visit_get_expression(get),
+        }
+    }
+    pub fn accept_visitor_mut<R>(&mut self, visitor: &mut impl ExpressionVisitorMut<R>) -> R {
+        match self {
+            Expression::Binary(binary) => visitor.visit_binary_expression_mut(binary),
+            Expression::Unary(unary) => visitor.visit_unary_expression_mut(unary),
+            Expression::Grouping(grouping) => visitor.visit_grouping_expression_mut(grouping),
+            Expression::Literal(literal) => visitor.visit_literal_expression_mut(literal),
+            Expression::Variable(variable) => visitor.visit_variable_expression_mut(variable),
+            Expression::Assign(assign) => visitor.visit_assign_expression_mut(assign),
+            Expression::Logical(logical) => visitor.visit_logical_expression_mut(logical),
+            Expression::Call(call) => visitor.visit_call_expression_mut(call),
+            Expression::Get(get) => visitor.visit_get_expression_mut(get),
+        }
+    }
+    pub fn transform(self, transformer: &mut impl Transformer<'a>) -> Expression<'a> {
+        match self {
+            Expression::Binary(binary) => transformer.transform_binary_expression(binary),
+            Expression::Unary(unary) => transformer.transform_unary_expression(unary),
+            Expression::Grouping(grouping) => transformer.transform_grouping_expression(grouping),
+            Expression::Literal(literal) => transformer.transform_literal_expression(literal),
+            Expression::Variable(variable) => transformer.transform_variable_expression(variable),
+            Expression::Assign(assign) => transformer.transform_assign_expression(assign),
+            Expression::Logical(logical) => transformer.transform_logical_expression(logical),
+            Expression::Call(call) => transformer.transform_call_expression(call),
+            Expression::Get(get) => transformer.transform_get_expression(get),
         }
     }
 }
@@ -34,7 +110,18 @@ pub struct Binary<'a> {
     operator: Token<'a>,
     right_operand: Box<Expression<'a>>,
 }
-impl Binary<'_> {
+impl<'a> Binary<'a> {
+    pub fn new(
+        left_operand: Expression<'a>,
+        operator: Token<'a>,
+        right_operand: Expression<'a>,
+    ) -> Self {
+        Self {
+            left_operand: Box::new(left_operand),
+            operator,
+            right_operand: Box::new(right_operand),
+        }
+    }
     pub fn left_operand(&self) -> &Expression<'_> {
         self.left_operand.deref()
     }
@@ -44,31 +131,554 @@ impl Binary<'_> {
     pub fn right_operand(&self) -> &Expression<'_> {
         self.right_operand.deref()
     }
+    pub fn left_operand_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.left_operand
+    }
+    pub fn right_operand_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.right_operand
+    }
+    /// The left operand, operator, and right operand
+    pub fn into_parts(self) -> (Expression<'a>, Token<'a>, Expression<'a>) {
+        (*self.left_operand, self.operator, *self.right_operand)
+    }
 }
 
 pub struct Unary<'a> {
     operator: Token<'a>,
     right_operand: Box<Expression<'a>>,
 }
-impl Unary<'_> {
+impl<'a> Unary<'a> {
+    pub fn new(operator: Token<'a>, right_operand: Expression<'a>) -> Self {
+        Self {
+            operator,
+            right_operand: Box::new(right_operand),
+        }
+    }
     pub fn operator(&self) -> &Token<'_> {
         &self.operator
     }
     pub fn right_operand(&self) -> &Expression<'_> {
         self.right_operand.deref()
     }
+    pub fn right_operand_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.right_operand
+    }
+    /// The operator and operand
+    pub fn into_parts(self) -> (Token<'a>, Expression<'a>) {
+        (self.operator, *self.right_operand)
+    }
 }
 
 pub struct Grouping<'a>(Box<Expression<'a>>);
-impl Grouping<'_> {
+impl<'a> Grouping<'a> {
+    pub fn new(inner_expression: Expression<'a>) -> Self {
+        Self(Box::new(inner_expression))
+    }
     pub fn inner_expression(&self) -> &Expression<'_> {
         self.0.deref()
     }
+    pub fn inner_expression_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.0
+    }
+    pub fn into_inner_expression(self) -> Expression<'a> {
+        *self.0
+    }
 }
 
 pub struct Literal<'a>(Token<'a>);
-impl Literal<'_> {
+impl<'a> Literal<'a> {
+    pub const fn new(token: Token<'a>) -> Self {
+        Self(token)
+    }
     pub fn token(&self) -> &Token<'_> {
         &self.0
     }
+    pub fn token_mut(&mut self) -> &mut Token<'a> {
+        &mut self.0
+    }
+    pub const fn into_token(self) -> Token<'a> {
+        self.0
+    }
+}
+
+pub struct Variable<'a>(Token<'a>);
+impl<'a> Variable<'a> {
+    pub const fn new(name: Token<'a>) -> Self {
+        Self(name)
+    }
+    pub fn name(&self) -> &Token<'_> {
+        &self.0
+    }
+    pub fn name_mut(&mut self) -> &mut Token<'a> {
+        &mut self.0
+    }
+    pub const fn into_name(self) -> Token<'a> {
+        self.0
+    }
+}
+
+pub struct Assign<'a> {
+    name: Token<'a>,
+    value: Box<Expression<'a>>,
+}
+impl<'a> Assign<'a> {
+    pub fn new(name: Token<'a>, value: Expression<'a>) -> Self {
+        Self {
+            name,
+            value: Box::new(value),
+        }
+    }
+    pub fn name(&self) -> &Token<'_> {
+        &self.name
+    }
+    pub fn value(&self) -> &Expression<'_> {
+        self.value.deref()
+    }
+    pub fn value_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.value
+    }
+    /// The name and value
+    pub fn into_parts(self) -> (Token<'a>, Expression<'a>) {
+        (self.name, *self.value)
+    }
+}
+
+/// `and` and `or`, which only evaluate their right operand when needed
+pub struct Logical<'a> {
+    left_operand: Box<Expression<'a>>,
+    operator: Token<'a>,
+    right_operand: Box<Expression<'a>>,
+}
+impl<'a> Logical<'a> {
+    pub fn new(
+        left_operand: Expression<'a>,
+        operator: Token<'a>,
+        right_operand: Expression<'a>,
+    ) -> Self {
+        Self {
+            left_operand: Box::new(left_operand),
+            operator,
+            right_operand: Box::new(right_operand),
+        }
+    }
+    pub fn left_operand(&self) -> &Expression<'_> {
+        self.left_operand.deref()
+    }
+    pub fn operator(&self) -> &Token<'_> {
+        &self.operator
+    }
+    pub fn right_operand(&self) -> &Expression<'_> {
+        self.right_operand.deref()
+    }
+    pub fn left_operand_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.left_operand
+    }
+    pub fn right_operand_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.right_operand
+    }
+    /// The left operand, operator, and right operand
+    pub fn into_parts(self) -> (Expression<'a>, Token<'a>, Expression<'a>) {
+        (*self.left_operand, self.operator, *self.right_operand)
+    }
+}
+
+/// `callee(arguments)`
+pub struct Call<'a> {
+    callee: Box<Expression<'a>>,
+    /// the `)` ending the arguments, where errors about the call are reported
+    closing_parenthesis: Token<'a>,
+    arguments: Vec<Expression<'a>>,
+}
+impl<'a> Call<'a> {
+    pub fn new(
+        callee: Expression<'a>,
+        closing_parenthesis: Token<'a>,
+        arguments: Vec<Expression<'a>>,
+    ) -> Self {
+        Self {
+            callee: Box::new(callee),
+            closing_parenthesis,
+            arguments,
+        }
+    }
+    pub fn callee(&self) -> &Expression<'_> {
+        self.callee.deref()
+    }
+    pub fn closing_parenthesis(&self) -> &Token<'_> {
+        &self.closing_parenthesis
+    }
+    pub fn arguments(&self) -> &[Expression<'a>] {
+        &self.arguments
+    }
+    pub fn callee_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.callee
+    }
+    pub fn arguments_mut(&mut self) -> &mut Vec<Expression<'a>> {
+        &mut self.arguments
+    }
+    /// The callee, closing parenthesis, and arguments
+    pub fn into_parts(self) -> (Expression<'a>, Token<'a>, Vec<Expression<'a>>) {
+        (*self.callee, self.closing_parenthesis, self.arguments)
+    }
+}
+
+/// `object.name`
+pub struct Get<'a> {
+    object: Box<Expression<'a>>,
+    name: Token<'a>,
+}
+impl<'a> Get<'a> {
+    pub fn new(object: Expression<'a>, name: Token<'a>) -> Self {
+        Self {
+            object: Box::new(object),
+            name,
+        }
+    }
+    pub fn object(&self) -> &Expression<'_> {
+        self.object.deref()
+    }
+    pub fn name(&self) -> &Token<'_> {
+        &self.name
+    }
+    pub fn object_mut(&mut self) -> &mut Expression<'a> {
+        &mut self.object
+    }
+    /// The object and name
+    pub fn into_parts(self) -> (Expression<'a>, Token<'a>) {
+        (*self.object, self.name)
+    }
+}
+
+impl<'a> From<enum_ast::Expression<'a>> for Expression<'a> {
+    fn from(expression: enum_ast::Expression<'a>) -> Self {
+        match expression {
+            enum_ast::Expression::Assign { name, value, .. } => {
+                Expression::Assign(Assign::new(name, (*value).into()))
+            }
+            enum_ast::Expression::Binary {
+                left_operand,
+                operator,
+                right_operand,
+                ..
+            } => Expression::Binary(Binary::new(
+                (*left_operand).into(),
+                operator,
+                (*right_operand).into(),
+            )),
+            enum_ast::Expression::Unary {
+                operator,
+                right_operand,
+                ..
+            } => Expression::Unary(Unary::new(operator, (*right_operand).into())),
+            enum_ast::Expression::Call {
+                callee,
+                closing_parenthesis,
+                arguments,
+                ..
+            } => Expression::Call(Call::new(
+                (*callee).into(),
+                closing_parenthesis,
+                arguments.into_iter().map(Into::into).collect(),
+            )),
+            enum_ast::Expression::Get { object, name, .. } => {
+                Expression::Get(Get::new((*object).into(), name))
+            }
+            enum_ast::Expression::Logical {
+                left_operand,
+                operator,
+                right_operand,
+                ..
+            } => Expression::Logical(Logical::new(
+                (*left_operand).into(),
+                operator,
+                (*right_operand).into(),
+            )),
+            enum_ast::Expression::Grouping { expression, .. } => {
+                Expression::Grouping(Grouping::new((*expression).into()))
+            }
+            enum_ast::Expression::Literal(token) => Expression::Literal(Literal::new(token)),
+            enum_ast::Expression::Variable(name) => Expression::Variable(Variable::new(name)),
+        }
+    }
+}
+
+crate::define_ast! {
+    pub enum Statement<'a>;
+    pub trait StatementVisitor(statement);
+
+    Expression(ExpressionStatement) => visit_expression_statement {
+        expression: value Expression<'a>,
+        span: value Span,
+    },
+    Print(Print) => visit_print_statement {
+        expression: value Expression<'a>,
+        span: value Span,
+    },
+    Var(Var) => visit_var_statement {
+        name: value Token<'a>,
+        initializer: optional Expression<'a>,
+        span: value Span,
+    },
+    Block(Block) => visit_block_statement {
+        statements: list Statement<'a>,
+        span: value Span,
+    },
+    If(If) => visit_if_statement {
+        condition: value Expression<'a>,
+        then_branch: boxed Statement<'a>,
+        else_branch: optional_boxed Statement<'a>,
+        span: value Span,
+    },
+    While(While) => visit_while_statement {
+        condition: value Expression<'a>,
+        body: boxed Statement<'a>,
+        span: value Span,
+    },
+    For(For) => visit_for_statement {
+        /// a [Statement::Var] or [Statement::Expression]
+        initializer: optional_boxed Statement<'a>,
+        condition: optional Expression<'a>,
+        increment: optional Expression<'a>,
+        body: boxed Statement<'a>,
+        span: value Span,
+    },
+    /// A statement started by a keyword claimed by a [crate::dialect::Dialect]. See [enum_ast::Statement::Custom]
+    Custom(Custom) => visit_custom_statement {
+        keyword: value Token<'a>,
+        expressions: list Expression<'a>,
+        statements: list Statement<'a>,
+        span: value Span,
+    },
+}
+
+impl Statement<'_> {
+    /// Where the statement is in the source it was parsed from
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Expression(statement) => *statement.span(),
+            Statement::Print(statement) => *statement.span(),
+            Statement::Var(statement) => *statement.span(),
+            Statement::Block(statement) => *statement.span(),
+            Statement::If(statement) => *statement.span(),
+            Statement::While(statement) => *statement.span(),
+            Statement::For(statement) => *statement.span(),
+            Statement::Custom(statement) => *statement.span(),
+        }
+    }
+}
+
+impl<'a> From<enum_ast::Statement<'a>> for Statement<'a> {
+    fn from(statement: enum_ast::Statement<'a>) -> Self {
+        let statements = |statements: Vec<enum_ast::Statement<'a>>| {
+            statements.into_iter().map(Into::into).collect::<Vec<_>>()
+        };
+
+        match statement {
+            enum_ast::Statement::Expression { expression, span } => {
+                Statement::Expression(ExpressionStatement::new((*expression).into(), span))
+            }
+            enum_ast::Statement::Print { expression, span } => {
+                Statement::Print(Print::new((*expression).into(), span))
+            }
+            enum_ast::Statement::Var {
+                name,
+                initializer,
+                span,
+            } => Statement::Var(Var::new(
+                name,
+                initializer.map(|initializer| (*initializer).into()),
+                span,
+            )),
+            enum_ast::Statement::Block {
+                statements: body,
+                span,
+            } => Statement::Block(Block::new(statements(body), span)),
+            enum_ast::Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                span,
+            } => Statement::If(If::new(
+                (*condition).into(),
+                (*then_branch).into(),
+                else_branch.map(|else_branch| (*else_branch).into()),
+                span,
+            )),
+            enum_ast::Statement::While {
+                condition,
+                body,
+                span,
+            } => Statement::While(While::new((*condition).into(), (*body).into(), span)),
+            enum_ast::Statement::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                span,
+            } => Statement::For(For::new(
+                initializer.map(|initializer| (*initializer).into()),
+                condition.map(|condition| (*condition).into()),
+                increment.map(|increment| (*increment).into()),
+                (*body).into(),
+                span,
+            )),
+            enum_ast::Statement::Custom {
+                keyword,
+                expressions,
+                statements: body,
+                span,
+            } => Statement::Custom(Custom::new(
+                keyword,
+                expressions.into_iter().map(Into::into).collect(),
+                statements(body),
+                span,
+            )),
+        }
+    }
+}
+
+#[test]
+fn mutable_visitors() {
+    use crate::token::TokenKind;
+    use printer::AbstractSyntaxTreePrinter;
+
+    /// Replaces every literal with `x` and counts them
+    struct Placeholder(usize);
+    impl ExpressionVisitorMut<()> for Placeholder {
+        fn visit_binary_expression_mut(&mut self, expression: &mut Binary) {
+            expression.left_operand_mut().accept_visitor_mut(self);
+            expression.right_operand_mut().accept_visitor_mut(self);
+        }
+        fn visit_unary_expression_mut(&mut self, expression: &mut Unary) {
+            expression.right_operand_mut().accept_visitor_mut(self);
+        }
+        fn visit_grouping_expression_mut(&mut self, expression: &mut Grouping) {
+            expression.inner_expression_mut().accept_visitor_mut(self);
+        }
+        fn visit_literal_expression_mut(&mut self, expression: &mut Literal) {
+            *expression.token_mut() = Token::new(TokenKind::Identifier, "x", 0);
+            self.0 += 1;
+        }
+        fn visit_variable_expression_mut(&mut self, _expression: &mut Variable) {}
+        fn visit_assign_expression_mut(&mut self, expression: &mut Assign) {
+            expression.value_mut().accept_visitor_mut(self);
+        }
+        fn visit_logical_expression_mut(&mut self, expression: &mut Logical) {
+            expression.left_operand_mut().accept_visitor_mut(self);
+            expression.right_operand_mut().accept_visitor_mut(self);
+        }
+        fn visit_call_expression_mut(&mut self, expression: &mut Call) {
+            expression.callee_mut().accept_visitor_mut(self);
+            for argument in expression.arguments_mut() {
+                argument.accept_visitor_mut(self);
+            }
+        }
+        fn visit_get_expression_mut(&mut self, expression: &mut Get) {
+            expression.object_mut().accept_visitor_mut(self);
+        }
+    }
+
+    /// Removes redundant parentheses
+    struct Ungroup;
+    impl<'a> Transformer<'a> for Ungroup {
+        fn transform_binary_expression(&mut self, expression: Binary<'a>) -> Expression<'a> {
+            let (left_operand, operator, right_operand) = expression.into_parts();
+            Expression::Binary(Binary::new(
+                left_operand.transform(self),
+                operator,
+                right_operand.transform(self),
+            ))
+        }
+        fn transform_unary_expression(&mut self, expression: Unary<'a>) -> Expression<'a> {
+            let (operator, right_operand) = expression.into_parts();
+            Expression::Unary(Unary::new(operator, right_operand.transform(self)))
+        }
+        fn transform_grouping_expression(&mut self, expression: Grouping<'a>) -> Expression<'a> {
+            expression.into_inner_expression().transform(self)
+        }
+        fn transform_literal_expression(&mut self, expression: Literal<'a>) -> Expression<'a> {
+            Expression::Literal(expression)
+        }
+        fn transform_variable_expression(&mut self, expression: Variable<'a>) -> Expression<'a> {
+            Expression::Variable(expression)
+        }
+        fn transform_assign_expression(&mut self, expression: Assign<'a>) -> Expression<'a> {
+            let (name, value) = expression.into_parts();
+            Expression::Assign(Assign::new(name, value.transform(self)))
+        }
+        fn transform_logical_expression(&mut self, expression: Logical<'a>) -> Expression<'a> {
+            let (left_operand, operator, right_operand) = expression.into_parts();
+            Expression::Logical(Logical::new(
+                left_operand.transform(self),
+                operator,
+                right_operand.transform(self),
+            ))
+        }
+        fn transform_call_expression(&mut self, expression: Call<'a>) -> Expression<'a> {
+            let (callee, closing_parenthesis, arguments) = expression.into_parts();
+            let arguments = arguments
+                .into_iter()
+                .map(|argument| argument.transform(self))
+                .collect();
+            Expression::Call(Call::new(
+                callee.transform(self),
+                closing_parenthesis,
+                arguments,
+            ))
+        }
+        fn transform_get_expression(&mut self, expression: Get<'a>) -> Expression<'a> {
+            let (object, name) = expression.into_parts();
+            Expression::Get(Get::new(object.transform(self), name))
+        }
+    }
+
+    let number =
+        |lexeme| Expression::Literal(Literal::new(Token::new(TokenKind::Number, lexeme, 0)));
+    let expression = Expression::Binary(Binary::new(
+        Expression::Unary(Unary::new(
+            Token::new(TokenKind::Minus, "-", 0),
+            number("1"),
+        )),
+        Token::new(TokenKind::Star, "*", 0),
+        Expression::Grouping(Grouping::new(Expression::Grouping(Grouping::new(number(
+            "2",
+        ))))),
+    ));
+
+    let mut expression = expression.transform(&mut Ungroup);
+    assert_eq!(AbstractSyntaxTreePrinter.print(&expression), "(* (- 1) 2)");
+
+    let mut placeholder = Placeholder(0);
+    expression.accept_visitor_mut(&mut placeholder);
+    assert_eq!(placeholder.0, 2);
+    assert_eq!(AbstractSyntaxTreePrinter.print(&expression), "(* (- x) x)");
+}
+
+#[test]
+fn from_parsed_tree() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use alloc::string::ToString;
+    use interpreter::{Interpreter, Value};
+    use printer::AbstractSyntaxTreePrinter;
+
+    const SOURCE: &str = "var a = 1; for (;a < 3;) { a = a + 1; } print f(a.b, -(2));";
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+    let expected = program.to_string();
+
+    let statements = Parser::from(Lexer::new(SOURCE))
+        .parse_into_visitor_pattern()
+        .unwrap();
+    let printed = statements
+        .iter()
+        .map(|statement| AbstractSyntaxTreePrinter.print_statement(statement))
+        .collect::<Vec<_>>();
+    assert_eq!(printed.join("\n"), expected);
+
+    let interpreter = Interpreter::new();
+    interpreter.define("a", Value::Number(1.0));
+    let Statement::For(for_statement) = &statements[1] else {
+        panic!("expected a for statement");
+    };
+    let condition = for_statement.condition().unwrap();
+    assert_eq!(interpreter.evaluate(condition), Ok(Value::Boolean(true)));
 }
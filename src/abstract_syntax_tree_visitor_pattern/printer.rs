@@ -1,10 +1,14 @@
 use super::*;
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
 
 pub struct AbstractSyntaxTreePrinter;
 impl AbstractSyntaxTreePrinter {
     pub fn print(&self, expression: &Expression) -> String {
         expression.accept_visitor(self)
     }
+    pub fn print_statement(&self, statement: &Statement) -> String {
+        statement.accept_visitor(self)
+    }
 }
 impl ExpressionVisitor<String> for AbstractSyntaxTreePrinter {
     fn visit_binary_expression(&self, expression: &Binary) -> String {
@@ -28,17 +32,141 @@ impl ExpressionVisitor<String> for AbstractSyntaxTreePrinter {
     fn visit_literal_expression(&self, expression: &Literal) -> String {
         expression.token().lexeme().to_owned()
     }
+
+    fn visit_variable_expression(&self, expression: &Variable) -> String {
+        expression.name().lexeme().to_owned()
+    }
+
+    fn visit_assign_expression(&self, expression: &Assign) -> String {
+        parenthesizes(
+            &format!("= {}", expression.name().lexeme()),
+            &[expression.value()],
+        )
+    }
+
+    fn visit_logical_expression(&self, expression: &Logical) -> String {
+        parenthesizes(
+            expression.operator().lexeme(),
+            &[expression.left_operand(), expression.right_operand()],
+        )
+    }
+
+    fn visit_call_expression(&self, expression: &Call) -> String {
+        let expressions = core::iter::once(expression.callee())
+            .chain(expression.arguments())
+            .collect::<Vec<_>>();
+        parenthesizes("call", &expressions)
+    }
+
+    fn visit_get_expression(&self, expression: &Get) -> String {
+        format!(
+            "(. {} {})",
+            self.print(expression.object()),
+            expression.name().lexeme()
+        )
+    }
+}
+/// Prints statements in the same s-expression form as [crate::abstract_syntax_tree::Statement::to_s_expression]
+impl StatementVisitor<String> for AbstractSyntaxTreePrinter {
+    fn visit_expression_statement(&self, statement: &ExpressionStatement) -> String {
+        parenthesizes(";", &[statement.expression()])
+    }
+
+    fn visit_print_statement(&self, statement: &Print) -> String {
+        parenthesizes("print", &[statement.expression()])
+    }
+
+    fn visit_var_statement(&self, statement: &Var) -> String {
+        match statement.initializer() {
+            Some(initializer) => parenthesizes(
+                &format!("var {} =", statement.name().lexeme()),
+                &[initializer],
+            ),
+            None => format!("(var {})", statement.name().lexeme()),
+        }
+    }
+
+    fn visit_block_statement(&self, statement: &Block) -> String {
+        let parts = statement
+            .statements()
+            .iter()
+            .map(|statement| self.print_statement(statement))
+            .collect::<Vec<_>>();
+        parenthesize_parts("block", &parts)
+    }
+
+    fn visit_if_statement(&self, statement: &If) -> String {
+        let mut parts = Vec::from([
+            self.print(statement.condition()),
+            self.print_statement(statement.then_branch()),
+        ]);
+        match statement.else_branch() {
+            Some(else_branch) => {
+                parts.push(self.print_statement(else_branch));
+                parenthesize_parts("if-else", &parts)
+            }
+            None => parenthesize_parts("if", &parts),
+        }
+    }
+
+    fn visit_while_statement(&self, statement: &While) -> String {
+        let parts = [
+            self.print(statement.condition()),
+            self.print_statement(statement.body()),
+        ];
+        parenthesize_parts("while", &parts)
+    }
+
+    fn visit_for_statement(&self, statement: &For) -> String {
+        let missing = || "_".to_owned();
+        let parts = [
+            statement
+                .initializer()
+                .map_or_else(missing, |initializer| self.print_statement(initializer)),
+            statement
+                .condition()
+                .map_or_else(missing, |condition| self.print(condition)),
+            statement
+                .increment()
+                .map_or_else(missing, |increment| self.print(increment)),
+            self.print_statement(statement.body()),
+        ];
+        parenthesize_parts("for", &parts)
+    }
+
+    fn visit_custom_statement(&self, statement: &Custom) -> String {
+        let parts = statement
+            .expressions()
+            .iter()
+            .map(|expression| self.print(expression))
+            .chain(
+                statement
+                    .statements()
+                    .iter()
+                    .map(|statement| self.print_statement(statement)),
+            )
+            .collect::<Vec<_>>();
+        parenthesize_parts(statement.keyword().lexeme(), &parts)
+    }
 }
 
 fn parenthesizes(name: &str, expressions: &[&Expression]) -> String {
+    let parts = expressions
+        .iter()
+        .map(|expression| AbstractSyntaxTreePrinter.print(expression))
+        .collect::<Vec<_>>();
+    parenthesize_parts(name, &parts)
+}
+
+fn parenthesize_parts(name: &str, parts: &[String]) -> String {
     let mut output = String::new();
 
     output.push('(');
     output.push_str(name);
 
-    for expression in expressions {
+    for part in parts {
         output.push(' ');
-        output.push_str(&AbstractSyntaxTreePrinter.print(expression));
+        output.push_str(part);
     }
 
     output.push(')');
@@ -50,7 +178,7 @@ fn parenthesizes(name: &str, expressions: &[&Expression]) -> String {
 fn ast_print() {
     use crate::token::TokenKind;
 
-    const EXPECTED: &'static str = "(* (- 123) (group 45.67))";
+    const EXPECTED: &str = "(* (- 123) (group 45.67))";
 
     let expression = Expression::Binary(Binary {
         left_operand: Box::new(Expression::Unary(Unary {
@@ -71,3 +199,62 @@ fn ast_print() {
 
     assert_eq!(output, EXPECTED);
 }
+
+#[test]
+fn statement_print() {
+    use crate::token::{Span, TokenKind};
+
+    let token = |kind, lexeme| Token::new(kind, lexeme, 0);
+    let variable =
+        |lexeme| Expression::Variable(Variable::new(token(TokenKind::Identifier, lexeme)));
+
+    let span = Span::default();
+    let statement = Statement::For(For::new(
+        Some(Statement::Var(Var::new(
+            token(TokenKind::Identifier, "i"),
+            Some(Expression::Literal(Literal::new(token(
+                TokenKind::Number,
+                "0",
+            )))),
+            span,
+        ))),
+        Some(Expression::Logical(Logical::new(
+            variable("a"),
+            token(TokenKind::Or, "or"),
+            variable("b"),
+        ))),
+        None,
+        Statement::Block(Block::new(
+            Vec::from([
+                Statement::Expression(ExpressionStatement::new(
+                    Expression::Assign(Assign::new(
+                        token(TokenKind::Identifier, "a"),
+                        Expression::Call(Call::new(
+                            variable("f"),
+                            token(TokenKind::RightParentheses, ")"),
+                            Vec::from([variable("i"), variable("b")]),
+                        )),
+                    )),
+                    span,
+                )),
+                Statement::If(If::new(
+                    variable("a"),
+                    Statement::Print(Print::new(variable("a"), span)),
+                    Some(Statement::Var(Var::new(
+                        token(TokenKind::Identifier, "c"),
+                        None,
+                        span,
+                    ))),
+                    span,
+                )),
+            ]),
+            span,
+        )),
+        span,
+    ));
+
+    assert_eq!(
+        AbstractSyntaxTreePrinter.print_statement(&statement),
+        "(for (var i = 0) (or a b) _ (block (; (= a (call f i b))) (if-else a (print a) (var c))))"
+    );
+}
@@ -0,0 +1,186 @@
+//! Bundles everything needed to reproduce an internal error (a panic) so users can attach it to a bug report.
+//! Enabled by [install], which chains a panic hook that writes the bundle to [std::env::temp_dir] and prints its path
+
+use crate::{abstract_syntax_tree::program_to_canonical_string, lexer::Lexer, parser::Parser};
+use std::{
+    backtrace::Backtrace,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The source currently being processed and a description of the options it is processed with
+static CONTEXT: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// Installs a panic hook that writes a [BugReport] for the source last passed to [set_source].
+/// `options` describes how the interpreter was invoked and is included in the bundle
+pub fn install(options: impl Into<String>) {
+    *lock_context() = Some((String::new(), options.into()));
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        // the lexer or parser panicked again while the bundle was written, which [unless_it_panics] records
+        if std::thread::current().name() == Some(HELPER_THREAD_NAME) {
+            return;
+        }
+        default_hook(panic_info);
+
+        let Some((source, options)) = lock_context().clone() else {
+            return;
+        };
+
+        let report = BugReport {
+            source,
+            options,
+            panic_message: panic_info.to_string(),
+            backtrace: Backtrace::force_capture().to_string(),
+        };
+
+        match report.write() {
+            Ok(path) => eprintln!("A bug report bundle was written to {}", path.display()),
+            Err(error) => eprintln!("Failed to write a bug report bundle: {}", error),
+        }
+    }));
+}
+
+/// Records `source` as the input to include if a panic happens. Does nothing unless [install] was called
+pub fn set_source(source: &str) {
+    if let Some((current_source, _)) = lock_context().as_mut() {
+        current_source.clear();
+        current_source.push_str(source);
+    }
+}
+
+fn lock_context() -> std::sync::MutexGuard<'static, Option<(String, String)>> {
+    CONTEXT.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BugReport {
+    source: String,
+    options: String,
+    panic_message: String,
+    backtrace: String,
+}
+impl BugReport {
+    pub fn new(source: &str, options: &str, panic_message: &str, backtrace: &str) -> Self {
+        Self {
+            source: source.to_owned(),
+            options: options.to_owned(),
+            panic_message: panic_message.to_owned(),
+            backtrace: backtrace.to_owned(),
+        }
+    }
+
+    /// Writes the bundle to a new directory in [std::env::temp_dir] and returns its path
+    pub fn write(&self) -> Result<PathBuf, io::Error> {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        let directory =
+            std::env::temp_dir().join(format!("lox-bug-report-{}-{}", std::process::id(), seconds));
+
+        self.write_to(&directory)?;
+
+        Ok(directory)
+    }
+
+    /// Writes one file per part of the bundle into `directory`, creating it if needed
+    pub fn write_to(&self, directory: &Path) -> Result<(), io::Error> {
+        fs::create_dir_all(directory)?;
+
+        fs::write(directory.join("source.lox"), &self.source)?;
+        fs::write(directory.join("options.txt"), &self.options)?;
+        fs::write(
+            directory.join("version.txt"),
+            format!(
+                "{} {}\n{} {}\n",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ),
+        )?;
+        fs::write(directory.join("panic.txt"), &self.panic_message)?;
+        fs::write(directory.join("backtrace.txt"), &self.backtrace)?;
+
+        // last, since the panic being reported may well have been in the lexer or parser
+        fs::write(
+            directory.join("tokens.txt"),
+            unless_it_panics(|| self.tokens()),
+        )?;
+        fs::write(
+            directory.join("ast.txt"),
+            unless_it_panics(|| self.abstract_syntax_tree()),
+        )?;
+
+        Ok(())
+    }
+
+    fn tokens(&self) -> String {
+        let mut output = String::new();
+
+        for possible_token in Lexer::new(&self.source) {
+            let _ = match possible_token {
+                Ok(token) => writeln!(output, "{}", token),
+                Err(error) => writeln!(output, "{}", error),
+            };
+        }
+
+        output
+    }
+
+    fn abstract_syntax_tree(&self) -> String {
+        match Parser::from(Lexer::new(&self.source)).parse() {
+            Ok(program) => program_to_canonical_string(&program),
+            Err(errors) => errors.iter().map(|error| format!("{}\n", error)).collect(),
+        }
+    }
+}
+
+/// The name of the thread [unless_it_panics] runs in, so the hook from [install] ignores its panics
+const HELPER_THREAD_NAME: &str = "lox-bug-report";
+
+/// The output of `part`, or a note that it is unavailable when `part` panics.
+/// [std::panic::catch_unwind] cannot be used since a panic inside a panic hook aborts,
+/// so `part` runs in its own thread instead
+fn unless_it_panics(part: impl FnOnce() -> String + Send) -> String {
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name(HELPER_THREAD_NAME.to_owned())
+            .spawn_scoped(scope, part)
+            .ok()
+            .and_then(|helper| helper.join().ok())
+    })
+    .unwrap_or_else(|| String::from("unavailable, it panicked while being computed\n"))
+}
+
+#[test]
+fn bug_report_bundle() {
+    let directory =
+        std::env::temp_dir().join(format!("lox-bug-report-test-{}", std::process::id()));
+
+    BugReport::new("1 + 2;", "lox script.lox", "panicked", "backtrace")
+        .write_to(&directory)
+        .unwrap();
+
+    let read = |name: &str| fs::read_to_string(directory.join(name)).unwrap();
+
+    assert_eq!(read("source.lox"), "1 + 2;");
+    assert!(read("tokens.txt").starts_with("1 Number 1\n1 Plus +\n"));
+    assert!(read("ast.txt").starts_with("lox-ast v1\nExpression\n  Binary \"+\"\n"));
+    assert_eq!(read("options.txt"), "lox script.lox");
+    assert!(read("version.txt").starts_with(concat!("lox ", env!("CARGO_PKG_VERSION"), "\n")));
+    assert_eq!(read("panic.txt"), "panicked");
+    assert_eq!(read("backtrace.txt"), "backtrace");
+
+    fs::remove_dir_all(directory).unwrap();
+
+    assert_eq!(
+        unless_it_panics(|| panic!("again")),
+        "unavailable, it panicked while being computed\n"
+    );
+}
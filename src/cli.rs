@@ -0,0 +1,852 @@
+//! The `lox` command line as data, so its usage text, shell completions, and man page are generated from one definition
+
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Write as _;
+
+/// A `--long` flag, optionally followed by a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandLineOption {
+    long: &'static str,
+    /// the letter of the `-s` spelling, if there is one
+    short: Option<char>,
+    /// shown as `<value_name>` after the flag; [None] for a flag without a value
+    value_name: Option<&'static str>,
+    help: &'static str,
+}
+impl CommandLineOption {
+    pub const fn new(
+        long: &'static str,
+        value_name: Option<&'static str>,
+        help: &'static str,
+    ) -> Self {
+        Self {
+            long,
+            short: None,
+            value_name,
+            help,
+        }
+    }
+    pub const fn short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+    pub const fn long(&self) -> &'static str {
+        self.long
+    }
+    pub const fn get_short(&self) -> Option<char> {
+        self.short
+    }
+    pub const fn value_name(&self) -> Option<&'static str> {
+        self.value_name
+    }
+    pub const fn help(&self) -> &'static str {
+        self.help
+    }
+}
+
+/// A word such as `explain` that selects a mode instead of a script, with the argument it takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subcommand {
+    name: &'static str,
+    argument: Option<&'static str>,
+    /// whether the argument may be given more than once
+    repeated: bool,
+    /// the values shells offer for the argument. Empty when any value is accepted
+    argument_values: &'static [&'static str],
+    help: &'static str,
+}
+impl Subcommand {
+    pub const fn new(
+        name: &'static str,
+        argument: Option<&'static str>,
+        argument_values: &'static [&'static str],
+        help: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            argument,
+            repeated: false,
+            argument_values,
+            help,
+        }
+    }
+    /// Accepts one or more arguments instead of exactly one
+    pub const fn repeated(mut self) -> Self {
+        self.repeated = true;
+        self
+    }
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+    pub const fn argument(&self) -> Option<&'static str> {
+        self.argument
+    }
+    pub const fn is_repeated(&self) -> bool {
+        self.repeated
+    }
+    pub const fn argument_values(&self) -> &'static [&'static str] {
+        self.argument_values
+    }
+    pub const fn help(&self) -> &'static str {
+        self.help
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandLine {
+    name: &'static str,
+    about: &'static str,
+    /// the positional arguments used when no subcommand is given. There may be any number of them
+    positional: &'static str,
+    options: &'static [CommandLineOption],
+    subcommands: &'static [Subcommand],
+    /// environment variables and what they do
+    environment: &'static [(&'static str, &'static str)],
+}
+
+/// The shells [CommandLine::completions] can generate a script for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+impl Shell {
+    pub const NAMES: &'static [&'static str] = &["bash", "zsh", "fish"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// The definition of the `lox` binary
+pub const LOX: CommandLine = CommandLine {
+    name: "lox",
+    about: "Run, lex, or parse lox scripts, or evaluate code at an interactive prompt when no script is given. Scripts given without a command are run in order, like run. A script of - is read from stdin",
+    positional: "script",
+    options: &[
+        CommandLineOption::new("help", None, "Print this usage text"),
+        CommandLineOption::new(
+            "eval",
+            Some("code"),
+            "Run code given on the command line instead of a script",
+        )
+        .short('e'),
+        CommandLineOption::new(
+            "tokens",
+            None,
+            "Print the tokens of the script before anything else",
+        ),
+        CommandLineOption::new(
+            "ast",
+            None,
+            "Print the syntax tree of the script before running it, and again after --optimize",
+        ),
+        CommandLineOption::new(
+            "no-color",
+            None,
+            "Print errors and warnings without color. Also set by NO_COLOR",
+        ),
+        CommandLineOption::new(
+            "format",
+            Some("format"),
+            "How errors and warnings are printed: text, or json for one object per line",
+        ),
+        CommandLineOption::new(
+            "explain",
+            None,
+            "Print the extended explanation of each error after it",
+        ),
+        CommandLineOption::new(
+            "warn-precision-loss",
+            None,
+            "Warn about integer literals that a 64-bit float cannot hold exactly",
+        ),
+        CommandLineOption::new(
+            "allow",
+            Some("warning"),
+            "Stop reporting a warning, such as unused-variable or loop-invariant-expression. Can be repeated",
+        ),
+        CommandLineOption::new(
+            "deny-warnings",
+            None,
+            "Fail instead of running a script that has warnings",
+        ),
+        CommandLineOption::new(
+            "max-errors",
+            Some("count"),
+            "How many errors to print before only counting the rest. 20 by default, 0 for no limit",
+        ),
+        CommandLineOption::new(
+            "optimize",
+            None,
+            "Replace variables that are never assigned and expressions of constants with their values, remove unreachable code, and warn about each removal",
+        ),
+        CommandLineOption::new(
+            "teach",
+            None,
+            "Print each variable definition, assignment, and scope entry and exit as the program runs",
+        ),
+        CommandLineOption::new(
+            "trace-timing",
+            None,
+            "After each script, print how many times each statement ran and how long it took to stderr",
+        ),
+        CommandLineOption::new(
+            "trace-timing-sort",
+            Some("order"),
+            "How --trace-timing lists statements: time, hits, or line. time by default",
+        ),
+        CommandLineOption::new(
+            "trace-timing-folded",
+            Some("path"),
+            "Write the time spent in each chain of nested statements of the last script to path, in the folded format flamegraph tools read. Implies --trace-timing",
+        ),
+        CommandLineOption::new(
+            "max-steps",
+            Some("count"),
+            "Stop with an error after count statements have run, reporting the loop that ran the most of them and its variables",
+        ),
+        CommandLineOption::new(
+            "spec",
+            Some("directory"),
+            "The cases grade runs each submission against: each name.out is what it must print after running name.lox",
+        ),
+        CommandLineOption::new(
+            "report",
+            Some("format"),
+            "How grade writes its scores: csv or json. csv by default",
+        ),
+        CommandLineOption::new(
+            "reference",
+            Some("path"),
+            "The interpreter compare runs each script with, such as jlox or clox, given the script's path as its argument",
+        ),
+        CommandLineOption::new(
+            "bug-report",
+            None,
+            "Write a bug report bundle if the interpreter crashes",
+        ),
+        CommandLineOption::new("prompt", Some("text"), "The prompt shown before each input"),
+        CommandLineOption::new(
+            "continuation-prompt",
+            Some("text"),
+            "The prompt shown before each line that continues an incomplete input",
+        ),
+        CommandLineOption::new(
+            "banner",
+            Some("text"),
+            "The text shown when the prompt starts",
+        ),
+        CommandLineOption::new("no-banner", None, "Start the prompt without a banner"),
+        CommandLineOption::new(
+            "check",
+            None,
+            "With fmt, exit with status 1 instead of rewriting scripts that are not formatted",
+        ),
+    ],
+    subcommands: &[
+        Subcommand::new(
+            "run",
+            Some("script"),
+            &[],
+            "Run scripts in order, sharing their global variables",
+        )
+        .repeated(),
+        Subcommand::new("tokenize", Some("script"), &[], "Print the tokens of a script"),
+        Subcommand::new("parse", Some("script"), &[], "Print the syntax tree of a script"),
+        Subcommand::new(
+            "watch",
+            Some("script"),
+            &[],
+            "Run a script, then again each time it changes",
+        ),
+        Subcommand::new(
+            "explain",
+            Some("code"),
+            &[],
+            "Print the extended explanation of an error code such as P0003",
+        ),
+        Subcommand::new(
+            "completions",
+            Some("shell"),
+            Shell::NAMES,
+            "Print a completion script for bash, zsh, or fish",
+        ),
+        Subcommand::new(
+            "fmt",
+            Some("script"),
+            &[],
+            "Rewrite scripts in the canonical layout",
+        )
+        .repeated(),
+        Subcommand::new(
+            "grade",
+            Some("submissions"),
+            &[],
+            "Run each script in the submissions directory against the cases of --spec and print a score report",
+        ),
+        Subcommand::new(
+            "compare",
+            Some("script"),
+            &[],
+            "Run scripts here and with --reference, and report where their output and exit codes differ",
+        )
+        .repeated(),
+        Subcommand::new("man", None, &[], "Print this manual page in roff format"),
+    ],
+    environment: &[
+        ("LOX_PROMPT", "Default for --prompt"),
+        (
+            "LOX_CONTINUATION_PROMPT",
+            "Default for --continuation-prompt",
+        ),
+        (
+            "LOX_BANNER",
+            "Default for --banner. 'none' hides the banner",
+        ),
+        (
+            "LOX_CONFIG",
+            "Path of the prompt config file. Defaults to ~/.loxrc",
+        ),
+        ("LOX_BUG_REPORT", "When set, acts like --bug-report"),
+        (
+            "LOX_ROUND_TRIP_CHECK",
+            "When set, panics after each script whose printed syntax tree does not parse back to itself, writing a minimized reproduction. On by default in debug builds; 0 turns it off",
+        ),
+        ("NO_COLOR", "When set, acts like --no-color"),
+    ],
+};
+
+impl CommandLine {
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+    pub const fn options(&self) -> &'static [CommandLineOption] {
+        self.options
+    }
+    pub const fn subcommands(&self) -> &'static [Subcommand] {
+        self.subcommands
+    }
+
+    /// Matches `args`, without the program name, against the options and subcommands.
+    /// Options may come anywhere, and an option's value may follow it or be joined to its `--long` spelling with `=`.
+    /// # Error
+    /// The first argument that does not fit
+    pub fn parse_arguments<S: AsRef<str>>(&self, args: &[S]) -> Result<Arguments, ArgumentError> {
+        let mut arguments = Arguments::default();
+        let mut args = args.iter().map(AsRef::as_ref);
+
+        while let Some(arg) = args.next() {
+            let mut letters = arg.chars();
+            if let (Some('-'), Some(letter @ 'a'..='z'), None) =
+                (letters.next(), letters.next(), letters.next())
+            {
+                let Some(option) = self
+                    .options
+                    .iter()
+                    .find(|option| option.short == Some(letter))
+                else {
+                    return Err(ArgumentError::UnknownOption(arg.into()));
+                };
+                arguments.push_option(option, None, &mut args)?;
+                continue;
+            }
+
+            let Some(flag) = arg.strip_prefix("--") else {
+                match self
+                    .subcommands
+                    .iter()
+                    .find(|subcommand| subcommand.name == arg)
+                {
+                    Some(subcommand)
+                        if arguments.subcommand.is_none() && arguments.arguments.is_empty() =>
+                    {
+                        arguments.subcommand = Some(*subcommand)
+                    }
+                    _ if arguments.arguments.is_empty()
+                        || arguments
+                            .subcommand
+                            .is_none_or(|subcommand| subcommand.repeated) =>
+                    {
+                        arguments.arguments.push(arg.into())
+                    }
+                    _ => return Err(ArgumentError::UnexpectedArgument(arg.into())),
+                }
+                continue;
+            };
+
+            let (long, joined_value) = match flag.split_once('=') {
+                Some((long, value)) => (long, Some(value)),
+                None => (flag, None),
+            };
+            let Some(option) = self.options.iter().find(|option| option.long == long) else {
+                return Err(ArgumentError::UnknownOption(arg.into()));
+            };
+            arguments.push_option(option, joined_value, &mut args)?;
+        }
+
+        match (arguments.subcommand, arguments.arguments.first()) {
+            (Some(subcommand), None) if subcommand.argument.is_some() => {
+                Err(ArgumentError::MissingArgument(subcommand.name))
+            }
+            (Some(subcommand), Some(argument)) if subcommand.argument.is_none() => {
+                Err(ArgumentError::UnexpectedArgument(argument.clone()))
+            }
+            _ => Ok(arguments),
+        }
+    }
+
+    /// The text printed for invalid arguments
+    pub fn usage(&self) -> String {
+        let mut output = format!("Usage: {} [options] [{}...]\n", self.name, self.positional);
+
+        for subcommand in self.subcommands {
+            let _ = write!(output, "       {} {}", self.name, subcommand.name);
+            if let Some(argument) = subcommand.argument {
+                let _ = write!(output, " <{}>", argument);
+            }
+            if subcommand.repeated {
+                output.push_str("...");
+            }
+            output.push('\n');
+        }
+
+        let width = self
+            .options
+            .iter()
+            .map(|option| option_synopsis(option).len() + 2)
+            .max()
+            .unwrap_or(0);
+
+        output.push_str("\nOptions:\n");
+        for option in self.options {
+            let _ = writeln!(
+                output,
+                "  {:<width$}{}",
+                option_synopsis(option),
+                option.help
+            );
+        }
+
+        output
+    }
+
+    pub fn completions(&self, shell: Shell) -> String {
+        match shell {
+            Shell::Bash => self.bash_completions(),
+            Shell::Zsh => self.zsh_completions(),
+            Shell::Fish => self.fish_completions(),
+        }
+    }
+
+    fn bash_completions(&self) -> String {
+        let flags = self
+            .options
+            .iter()
+            .flat_map(|option| {
+                let short = option.short.map(|short| format!("-{}", short));
+                short.into_iter().chain([format!("--{}", option.long)])
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let subcommands = self
+            .subcommands
+            .iter()
+            .map(|subcommand| subcommand.name)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut output = format!(
+            "_{name}() {{\n    local current=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    local previous=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n\n    case \"$previous\" in\n",
+            name = self.name
+        );
+        for option in self
+            .options
+            .iter()
+            .filter(|option| option.value_name.is_some())
+        {
+            let _ = match option.short {
+                Some(short) => writeln!(output, "        -{}|--{}) return ;;", short, option.long),
+                None => writeln!(output, "        --{}) return ;;", option.long),
+            };
+        }
+        for subcommand in self
+            .subcommands
+            .iter()
+            .filter(|subcommand| subcommand.argument.is_some())
+        {
+            let _ = match subcommand.argument_values {
+                [] if subcommand.argument == Some(self.positional) => writeln!(
+                    output,
+                    "        {}) COMPREPLY=($(compgen -f -- \"$current\")); return ;;",
+                    subcommand.name
+                ),
+                [] => writeln!(output, "        {}) return ;;", subcommand.name),
+                values => writeln!(
+                    output,
+                    "        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$current\")); return ;;",
+                    subcommand.name,
+                    values.join(" ")
+                ),
+            };
+        }
+        let _ = write!(
+            output,
+            "    esac\n\n    if [[ \"$current\" == -* ]]; then\n        COMPREPLY=($(compgen -W \"{flags}\" -- \"$current\"))\n    else\n        COMPREPLY=($(compgen -W \"{subcommands}\" -- \"$current\") $(compgen -f -- \"$current\"))\n    fi\n}}\ncomplete -F _{name} {name}\n",
+            name = self.name
+        );
+
+        output
+    }
+
+    fn zsh_completions(&self) -> String {
+        let mut output = format!(
+            "#compdef {name}\n\n_{name}() {{\n    local state\n    _arguments \\\n",
+            name = self.name
+        );
+
+        for option in self.options {
+            let value = option
+                .value_name
+                .map_or(String::new(), |value_name| format!(":{}: ", value_name));
+            let _ = match option.short {
+                Some(short) => writeln!(
+                    output,
+                    "        '(-{short} --{long})'{{-{short},--{long}}}'[{}]{}' \\",
+                    zsh_escape(option.help),
+                    value,
+                    short = short,
+                    long = option.long
+                ),
+                None => writeln!(
+                    output,
+                    "        '--{}[{}]{}' \\",
+                    option.long,
+                    zsh_escape(option.help),
+                    value
+                ),
+            };
+        }
+        output.push_str("        '1: :->first' \\\n        '2: :->second'\n\n    case $state in\n        first)\n            _alternative \\\n                'commands:command:((");
+        let subcommands = self
+            .subcommands
+            .iter()
+            .map(|subcommand| format!("{}\\:\"{}\"", subcommand.name, zsh_escape(subcommand.help)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        output.push_str(&subcommands);
+        let _ = write!(
+            output,
+            "))' \\\n                '{positional}s:{positional}:_files'\n            ;;\n        second)\n            case $words[2] in\n",
+            positional = self.positional
+        );
+        for subcommand in self.subcommands {
+            let _ = match subcommand.argument_values {
+                [] if subcommand.argument == Some(self.positional) => {
+                    writeln!(output, "                {}) _files ;;", subcommand.name)
+                }
+                [] => Ok(()),
+                values => writeln!(
+                    output,
+                    "                {}) _values {} {} ;;",
+                    subcommand.name,
+                    subcommand.argument.unwrap_or(subcommand.name),
+                    values.join(" ")
+                ),
+            };
+        }
+        let _ = write!(
+            output,
+            "            esac\n            ;;\n    esac\n}}\n\n_{name} \"$@\"\n",
+            name = self.name
+        );
+
+        output
+    }
+
+    fn fish_completions(&self) -> String {
+        let mut output = String::new();
+
+        for option in self.options {
+            let requires_value = if option.value_name.is_some() {
+                " -x"
+            } else {
+                ""
+            };
+            let short = option
+                .short
+                .map_or(String::new(), |short| format!(" -s {}", short));
+            let _ = writeln!(
+                output,
+                "complete -c {}{} -l {}{} -d '{}'",
+                self.name,
+                short,
+                option.long,
+                requires_value,
+                fish_escape(option.help)
+            );
+        }
+        for subcommand in self.subcommands {
+            let _ = writeln!(
+                output,
+                "complete -c {} -n __fish_use_subcommand -a {} -d '{}'",
+                self.name,
+                subcommand.name,
+                fish_escape(subcommand.help)
+            );
+            if !subcommand.argument_values.is_empty() {
+                let _ = writeln!(
+                    output,
+                    "complete -c {} -n '__fish_seen_subcommand_from {}' -x -a '{}'",
+                    self.name,
+                    subcommand.name,
+                    subcommand.argument_values.join(" ")
+                );
+            }
+        }
+
+        output
+    }
+
+    /// A man page in roff format, for `man -l`
+    pub fn man_page(&self) -> String {
+        let mut output = format!(
+            ".TH {} 1 \"\" \"{} {}\"\n.SH NAME\n{} \\- {}\n.SH SYNOPSIS\n.B {}\n[\\fIoptions\\fR] [\\fI{}\\fR...]\n",
+            self.name.to_uppercase(),
+            self.name,
+            env!("CARGO_PKG_VERSION"),
+            self.name,
+            roff_escape(self.about),
+            self.name,
+            self.positional
+        );
+
+        for subcommand in self.subcommands {
+            let _ = write!(output, ".br\n.B {} {}\n", self.name, subcommand.name);
+            if let Some(argument) = subcommand.argument {
+                let repeated = if subcommand.repeated { "..." } else { "" };
+                let _ = writeln!(output, "\\fI{}\\fR{}", argument, repeated);
+            }
+        }
+
+        output.push_str(".SH OPTIONS\n");
+        for option in self.options {
+            output.push_str(".TP\n");
+            if let Some(short) = option.short {
+                let _ = write!(output, "\\fB\\-{}\\fR, ", short);
+            }
+            let _ = write!(output, "\\fB\\-\\-{}\\fR", roff_escape(option.long));
+            if let Some(value_name) = option.value_name {
+                let _ = write!(output, " \\fI{}\\fR", value_name);
+            }
+            let _ = writeln!(output, "\n{}", roff_escape(option.help));
+        }
+
+        output.push_str(".SH COMMANDS\n");
+        for subcommand in self.subcommands {
+            let _ = write!(output, ".TP\n\\fB{}\\fR", subcommand.name);
+            if let Some(argument) = subcommand.argument {
+                let _ = write!(output, " \\fI{}\\fR", argument);
+            }
+            let _ = writeln!(output, "\n{}", roff_escape(subcommand.help));
+        }
+
+        output.push_str(".SH ENVIRONMENT\n");
+        for (variable, help) in self.environment {
+            let _ = writeln!(output, ".TP\n\\fB{}\\fR\n{}", variable, roff_escape(help));
+        }
+
+        output
+    }
+}
+
+/// The command line after [CommandLine::parse_arguments]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Arguments {
+    subcommand: Option<Subcommand>,
+    /// the subcommand's arguments, or the positional arguments when there is no subcommand
+    arguments: Vec<String>,
+    flags: Vec<&'static str>,
+    /// each option given with a value, in order
+    values: Vec<(&'static str, String)>,
+}
+impl Arguments {
+    /// Records `option`, taking its value from `joined_value` or else the next of `args`
+    fn push_option<'s>(
+        &mut self,
+        option: &CommandLineOption,
+        joined_value: Option<&'s str>,
+        args: &mut impl Iterator<Item = &'s str>,
+    ) -> Result<(), ArgumentError> {
+        match (option.value_name, joined_value) {
+            (None, None) => self.flags.push(option.long),
+            (None, Some(_)) => return Err(ArgumentError::UnexpectedValue(option.long)),
+            (Some(_), value) => {
+                let value = value
+                    .or_else(|| args.next())
+                    .ok_or(ArgumentError::MissingValue(option.long))?;
+                self.values.push((option.long, value.into()));
+            }
+        }
+        Ok(())
+    }
+    pub fn subcommand(&self) -> Option<&'static str> {
+        self.subcommand.map(|subcommand| subcommand.name)
+    }
+    /// The first of [Self::arguments]
+    pub fn argument(&self) -> Option<&str> {
+        self.arguments.first().map(String::as_str)
+    }
+    pub fn arguments(&self) -> &[String] {
+        &self.arguments
+    }
+    /// True when the flag `--long` was given
+    pub fn flag(&self, long: &str) -> bool {
+        self.flags.contains(&long)
+    }
+    /// The value of the last `--long` given
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(option, _)| *option == long)
+            .map(|(_, value)| value.as_str())
+    }
+    /// The value of each `--long` given, in order
+    pub fn values<'s>(&'s self, long: &'s str) -> impl Iterator<Item = &'s str> + 's {
+        self.values
+            .iter()
+            .filter(move |(option, _)| *option == long)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Why [CommandLine::parse_arguments] rejected the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgumentError {
+    UnknownOption(String),
+    /// the long name of an option that takes a value but was given none
+    MissingValue(&'static str),
+    /// the long name of a flag that was given a value with `=`
+    UnexpectedValue(&'static str),
+    /// the name of a subcommand that was given no argument
+    MissingArgument(&'static str),
+    UnexpectedArgument(String),
+}
+impl core::fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ArgumentError::UnknownOption(option) => write!(f, "Unknown option {}", option),
+            ArgumentError::MissingValue(long) => write!(f, "--{} expects a value", long),
+            ArgumentError::UnexpectedValue(long) => write!(f, "--{} does not take a value", long),
+            ArgumentError::MissingArgument(name) => write!(f, "{} expects an argument", name),
+            ArgumentError::UnexpectedArgument(argument) => {
+                write!(f, "Unexpected argument {}", argument)
+            }
+        }
+    }
+}
+
+fn option_synopsis(option: &CommandLineOption) -> String {
+    let short = option
+        .short
+        .map_or(String::new(), |short| format!("-{}, ", short));
+    match option.value_name {
+        Some(value_name) => format!("{}--{} <{}>", short, option.long, value_name),
+        None => format!("{}--{}", short, option.long),
+    }
+}
+
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+fn roff_escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+#[test]
+fn generated_cli_documents() {
+    let usage = LOX.usage();
+    assert!(usage.starts_with("Usage: lox [options] [script...]\n       lox run <script>...\n"));
+    assert!(
+        usage.contains("\n  --prompt <text>               The prompt shown before each input\n")
+    );
+
+    let bash = LOX.completions(Shell::Bash);
+    assert!(bash.contains("        completions) COMPREPLY=($(compgen -W \"bash zsh fish\" -- \"$current\")); return ;;\n"));
+    assert!(bash.ends_with("complete -F _lox lox\n"));
+
+    assert!(LOX.completions(Shell::Zsh).starts_with("#compdef lox\n"));
+    assert!(LOX
+        .completions(Shell::Fish)
+        .contains("complete -c lox -l prompt -x -d 'The prompt shown before each input'\n"));
+
+    let man_page = LOX.man_page();
+    assert!(man_page.starts_with(".TH LOX 1"));
+    assert!(man_page.contains(".TP\n\\fB\\-\\-banner\\fR \\fItext\\fR\n"));
+    assert_eq!(Shell::from_name("powershell"), None);
+}
+
+#[test]
+fn parse_arguments() {
+    let arguments = LOX
+        .parse_arguments(&[
+            "--ast",
+            "run",
+            "--prompt=> ",
+            "example.lox",
+            "--prompt",
+            "$ ",
+        ])
+        .unwrap();
+    assert_eq!(arguments.subcommand(), Some("run"));
+    assert_eq!(arguments.argument(), Some("example.lox"));
+    assert!(arguments.flag("ast"));
+    assert!(!arguments.flag("tokens"));
+    assert_eq!(arguments.value("prompt"), Some("$ "));
+    assert_eq!(arguments.values("prompt").collect::<Vec<_>>(), ["> ", "$ "]);
+
+    let script = LOX.parse_arguments(&["example.lox", "--no-color"]).unwrap();
+    assert_eq!(script.subcommand(), None);
+    assert_eq!(script.argument(), Some("example.lox"));
+    let scripts = LOX.parse_arguments(&["a.lox", "b.lox"]).unwrap();
+    assert_eq!(scripts.arguments(), ["a.lox", "b.lox"]);
+    let run = LOX.parse_arguments(&["run", "a.lox", "b.lox"]).unwrap();
+    assert_eq!(run.arguments(), ["a.lox", "b.lox"]);
+    assert_eq!(LOX.parse_arguments::<&str>(&[]), Ok(Arguments::default()));
+
+    let eval = LOX.parse_arguments(&["-e", "print 1;", "-"]).unwrap();
+    assert_eq!(eval.value("eval"), Some("print 1;"));
+    assert_eq!(eval.argument(), Some("-"));
+
+    let error = |args: &[&str]| LOX.parse_arguments(args).unwrap_err();
+    assert_eq!(
+        error(&["--colour"]),
+        ArgumentError::UnknownOption("--colour".into())
+    );
+    assert_eq!(error(&["-x"]), ArgumentError::UnknownOption("-x".into()));
+    assert_eq!(error(&["-e"]), ArgumentError::MissingValue("eval"));
+    assert_eq!(error(&["--prompt"]), ArgumentError::MissingValue("prompt"));
+    assert_eq!(error(&["--ast=yes"]), ArgumentError::UnexpectedValue("ast"));
+    assert_eq!(error(&["parse"]), ArgumentError::MissingArgument("parse"));
+    assert_eq!(
+        error(&["man", "lox"]),
+        ArgumentError::UnexpectedArgument("lox".into())
+    );
+    assert_eq!(
+        error(&["parse", "a.lox", "b.lox"]),
+        ArgumentError::UnexpectedArgument("b.lox".into())
+    );
+}
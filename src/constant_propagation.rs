@@ -0,0 +1,316 @@
+//! Replaces reads of `var` bindings that are never assigned with their values, and expressions made only of constants
+//! with the value they evaluate to, so `var k = 10; print k * 2;` becomes `var k = 10; print 20;`.
+//! There is no resolver yet, so [Constants::find] tracks block scopes itself, like [crate::lint] does.
+//! A name assigned anywhere in the program is never treated as constant
+
+use crate::{
+    abstract_syntax_tree::{Expression, Program, Statement},
+    abstract_syntax_tree_visitor_pattern::interpreter::{Interpreter, Value},
+    token::{Span, Token, TokenKind},
+    walk::{self, Fold, Walk},
+};
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// The value of an expression that [Constants::propagate] replaces with a literal
+#[derive(Debug, Clone, PartialEq)]
+struct Constant {
+    value: Value,
+    /// the lexeme of the literal, without the sign of a negative number
+    lexeme: String,
+    /// the line of the first token of the replaced expression
+    line_number: usize,
+}
+impl Constant {
+    /// A negative number becomes `-` applied to its magnitude, since a literal has no sign
+    fn to_expression(&self, span: Span) -> Expression<'_> {
+        let token = |kind: TokenKind| {
+            let lexeme = kind.canonical_lexeme().unwrap_or(&self.lexeme);
+            Token::new(kind, lexeme, self.line_number).with_span(span)
+        };
+
+        match self.value {
+            Value::Nil => Expression::Literal(token(TokenKind::Nil)),
+            Value::Boolean(true) => Expression::Literal(token(TokenKind::True)),
+            Value::Boolean(false) => Expression::Literal(token(TokenKind::False)),
+            Value::String(_) => Expression::Literal(token(TokenKind::String)),
+            Value::Number(number) if number.is_sign_negative() => Expression::Unary {
+                operator: token(TokenKind::Minus),
+                right_operand: Box::new(Expression::Literal(
+                    token(TokenKind::Number).with_number_value(-number),
+                )),
+                span,
+            },
+            Value::Number(number) => {
+                Expression::Literal(token(TokenKind::Number).with_number_value(number))
+            }
+        }
+    }
+}
+
+/// The expressions of a [Program] that always evaluate to the same value, found by [Self::find].
+/// It owns the lexemes of the literals that replace them, so the program [Self::propagate] returns borrows it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Constants {
+    /// keyed by the start and end of the expression's span
+    values: BTreeMap<(usize, usize), Constant>,
+}
+impl Constants {
+    /// The outermost expressions of `program` that are constant and not already a literal.
+    /// Constants that cannot be written as a literal, such as the result of dividing by zero, are left to run
+    pub fn find(program: &Program) -> Self {
+        let mut assignments = Assignments(Vec::new());
+        assignments.walk_program(program);
+
+        let mut finder = Finder {
+            assigned: assignments.0,
+            scopes: Vec::from([Vec::new()]),
+            values: BTreeMap::new(),
+        };
+        finder.walk_program(program);
+
+        Self {
+            values: finder.values,
+        }
+    }
+    /// How many expressions [Self::propagate] replaces
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    /// The value the expression at `span` always evaluates to, if it is one [Self::propagate] replaces
+    pub fn value(&self, span: Span) -> Option<&Value> {
+        self.values
+            .get(&(span.start, span.end))
+            .map(|constant| &constant.value)
+    }
+    /// `program` with each expression [Self::find] found in it replaced by its value.
+    /// The declarations of the variables are kept, since later scripts or prompt inputs may read them
+    pub fn propagate<'c>(&'c self, program: Program<'c>) -> Program<'c> {
+        Propagate(self).fold_program(program)
+    }
+}
+
+/// Collects the name of every assigned variable
+struct Assignments<'a>(Vec<&'a str>);
+impl<'a> Walk<'a> for Assignments<'a> {
+    fn walk_expression(&mut self, expression: &Expression<'a>) {
+        if let Expression::Assign { name, .. } = expression {
+            self.0.push(name.lexeme());
+        }
+        walk::walk_expression(self, expression);
+    }
+}
+
+struct Finder<'a> {
+    /// every name that is assigned somewhere
+    assigned: Vec<&'a str>,
+    /// the variables of each scope, the top level first, with their value when it is constant
+    scopes: Vec<Vec<(&'a str, Option<Value>)>>,
+    values: BTreeMap<(usize, usize), Constant>,
+}
+impl<'a> Finder<'a> {
+    fn declare(&mut self, name: &'a str, value: Option<Value>) {
+        let value = value.filter(|_| !self.assigned.contains(&name));
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name, value));
+        }
+    }
+    /// The value of the innermost variable called `name`, when it is constant
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(declared, _)| *declared == name)
+            .and_then(|(_, value)| value.as_ref())
+    }
+    /// What `expression` evaluates to, when it only applies operators to built-in literals and constant variables.
+    /// It is evaluated by an [Interpreter] so the result is the same as at runtime. [None] when that fails
+    fn value(&self, expression: &Expression<'a>) -> Option<Value> {
+        let interpreter = Interpreter::new();
+        if !self.define_reads(expression, &interpreter) {
+            return None;
+        }
+        interpreter.evaluate(&expression.clone().into()).ok()
+    }
+    /// Defines each variable `expression` reads in `interpreter`. False when it is not made only of constants
+    fn define_reads(&self, expression: &Expression<'a>, interpreter: &Interpreter) -> bool {
+        match expression {
+            Expression::Literal(token) => matches!(
+                token.kind(),
+                TokenKind::Number
+                    | TokenKind::String
+                    | TokenKind::True
+                    | TokenKind::False
+                    | TokenKind::Nil
+            ),
+            Expression::Variable(name) => match self.lookup(name.lexeme()) {
+                Some(value) => {
+                    interpreter.define(name.lexeme(), value.clone());
+                    true
+                }
+                None => false,
+            },
+            Expression::Unary { right_operand, .. } => {
+                self.define_reads(right_operand, interpreter)
+            }
+            Expression::Binary {
+                left_operand,
+                right_operand,
+                ..
+            }
+            | Expression::Logical {
+                left_operand,
+                right_operand,
+                ..
+            } => {
+                self.define_reads(left_operand, interpreter)
+                    && self.define_reads(right_operand, interpreter)
+            }
+            Expression::Grouping { expression, .. } => self.define_reads(expression, interpreter),
+            Expression::Assign { .. } | Expression::Call { .. } | Expression::Get { .. } => false,
+        }
+    }
+}
+impl<'a> Walk<'a> for Finder<'a> {
+    fn walk_statement(&mut self, statement: &Statement<'a>) {
+        match statement {
+            Statement::Var {
+                name, initializer, ..
+            } => {
+                let value = match initializer {
+                    Some(initializer) => {
+                        self.walk_expression(initializer);
+                        self.value(initializer)
+                    }
+                    None => Some(Value::Nil),
+                };
+                self.declare(name.lexeme(), value);
+            }
+            Statement::Block { .. } | Statement::For { .. } => {
+                self.scopes.push(Vec::new());
+                walk::walk_statement(self, statement);
+                self.scopes.pop();
+            }
+            // a handler may run its parts in any order, so nothing in them is replaced,
+            // and the variables it declares in the enclosing scope are not constant
+            Statement::Custom { statements, .. } => {
+                for statement in statements {
+                    if let Statement::Var { name, .. } = statement {
+                        self.declare(name.lexeme(), None);
+                    }
+                }
+            }
+            _ => walk::walk_statement(self, statement),
+        }
+    }
+    fn walk_expression(&mut self, expression: &Expression<'a>) {
+        let constant = match expression {
+            Expression::Literal(_) => None,
+            expression => self
+                .value(expression)
+                .filter(|value| !matches!(value, Value::Number(number) if !number.is_finite())),
+        };
+        let Some(value) = constant else {
+            return walk::walk_expression(self, expression);
+        };
+
+        let lexeme = match &value {
+            Value::Number(number) => number.abs().to_string(),
+            Value::String(string) => string.clone(),
+            value => value.to_string(),
+        };
+        let span = expression.span();
+        self.values.insert(
+            (span.start, span.end),
+            Constant {
+                value,
+                lexeme,
+                line_number: expression.first_token().line_number(),
+            },
+        );
+    }
+}
+
+struct Propagate<'c>(&'c Constants);
+impl<'c> Fold<'c> for Propagate<'c> {
+    fn fold_expression(&mut self, expression: Expression<'c>) -> Expression<'c> {
+        let span = expression.span();
+        match self.0.values.get(&(span.start, span.end)) {
+            Some(constant) => constant.to_expression(span),
+            None => walk::fold_expression(self, expression),
+        }
+    }
+}
+
+#[test]
+fn constant_propagation() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const SOURCE: &str = "var k = 10; print k * 2;
+var name = \"lo\" + \"x\"; var unset; print name + \"!\"; print unset == nil;
+var n = 1; n = n + 1; print n * k;
+{ var k = -k; print k - 0.5; { var k = \"inner\"; print k; } print (k); }
+for (var i = 0; i < k; i = i + 1) print i / 0;
+print k / 0; print -\"text\";";
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+    let before = program.to_string();
+
+    let constants = Constants::find(&program);
+    let after = constants.propagate(program).to_string();
+    assert_eq!(
+        before.lines().zip(after.lines()).collect::<Vec<_>>(),
+        [
+            ("(var k = 10)", "(var k = 10)"),
+            ("(print (* k 2))", "(print 20)"),
+            ("(var name = (+ lo x))", "(var name = lox)"),
+            ("(var unset)", "(var unset)"),
+            ("(print (+ name !))", "(print lox!)"),
+            ("(print (== unset nil))", "(print true)"),
+            ("(var n = 1)", "(var n = 1)"),
+            ("(; (= n (+ n 1)))", "(; (= n (+ n 1)))"),
+            ("(print (* n k))", "(print (* n 10))"),
+            (
+                "(block (var k = (- k)) (print (- k 0.5)) (block (var k = inner) (print k)) (print (group k)))",
+                "(block (var k = (- 10)) (print (- 10.5)) (block (var k = inner) (print inner)) (print (- 10)))"
+            ),
+            (
+                "(for (var i = 0) (< i k) (= i (+ i 1)) (print (/ i 0)))",
+                "(for (var i = 0) (< i 10) (= i (+ i 1)) (print (/ i 0)))"
+            ),
+            ("(print (/ k 0))", "(print (/ 10 0))"),
+            ("(print (- text))", "(print (- text))"),
+        ]
+    );
+    assert_eq!(
+        constants.value(Span::new(18, 23)),
+        Some(&Value::Number(20.0))
+    );
+
+    // the propagated program prints what the original does
+    let run = |source: &str| {
+        let program = Parser::from(Lexer::new(source)).parse().unwrap();
+        let constants = Constants::find(&program);
+        let (statements, interner) = constants.propagate(program).into_parts();
+        let interpreter = Interpreter::new();
+        interpreter
+            .with_symbols_of(&interner, || {
+                statements
+                    .into_iter()
+                    .try_for_each(|statement| interpreter.execute(&statement.into()))
+            })
+            .unwrap();
+        interpreter.take_output()
+    };
+    assert_eq!(
+        run("var k = 10; print k * 2; { var k = -k; print k - 0.5; print (k); }"),
+        "20\n-10.5\n-10\n"
+    );
+}
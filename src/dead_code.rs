@@ -0,0 +1,202 @@
+//! Removes statements that can never run: the branch of an `if` whose condition is a constant,
+//! and `while` and `for` loops whose condition is constant falsey. Each removal is reported as a [DeadCode] warning
+
+use crate::{
+    abstract_syntax_tree::{Expression, Program, Statement},
+    excerpt::{SourceExcerpt, WithSource},
+    messages,
+    token::{Span, TokenKind},
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt::Display;
+
+/// Code removed by [eliminate_dead_code]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeadCode {
+    span: Span,
+}
+impl DeadCode {
+    /// Stable identifier used to look up this warning's message in [crate::messages]
+    pub const fn code(&self) -> &'static str {
+        "W0002"
+    }
+    /// The removed statement
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+    /// Displays the warning followed by the line of `source` the removed code starts on
+    pub fn with_source<'s>(&'s self, source: &'s str) -> WithSource<'s, Self> {
+        WithSource::new(self, SourceExcerpt::new(source, self.span))
+    }
+}
+impl Display for DeadCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", messages::message(self.code()))
+    }
+}
+
+/// Returns `program` without its dead code, and where each removed statement was
+pub fn eliminate_dead_code(program: Program<'_>) -> (Program<'_>, Vec<DeadCode>) {
+    let interner = program.interner().clone();
+    let mut removed = Vec::new();
+    let statements = eliminate_in_statements(program.into_statements(), &mut removed);
+
+    (Program::new(statements, interner), removed)
+}
+
+/// Whether `expression` always evaluates to a truthy (`Some(true)`) or falsey (`Some(false)`) value.
+/// Only the built-in literals, possibly parenthesized, are constant
+fn constant_truthiness(expression: &Expression) -> Option<bool> {
+    match expression {
+        Expression::Grouping { expression, .. } => constant_truthiness(expression),
+        Expression::Literal(token) => match token.kind() {
+            TokenKind::True | TokenKind::Number | TokenKind::String => Some(true),
+            TokenKind::False | TokenKind::Nil => Some(false),
+            // the value of a host-defined literal is not known here
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn eliminate_in_statements<'a>(
+    statements: Vec<Statement<'a>>,
+    removed: &mut Vec<DeadCode>,
+) -> Vec<Statement<'a>> {
+    statements
+        .into_iter()
+        .filter_map(|statement| eliminate(statement, removed))
+        .collect()
+}
+
+/// Like [eliminate], but a statement that is removed entirely becomes an empty block, since its parent needs one
+fn eliminate_boxed<'a>(
+    mut statement: Box<Statement<'a>>,
+    removed: &mut Vec<DeadCode>,
+) -> Box<Statement<'a>> {
+    let span = statement.span();
+    *statement = eliminate(*statement, removed).unwrap_or(Statement::Block {
+        statements: Vec::new(),
+        span,
+    });
+    statement
+}
+
+/// [None] when the whole statement is dead
+fn eliminate<'a>(statement: Statement<'a>, removed: &mut Vec<DeadCode>) -> Option<Statement<'a>> {
+    let statement = match statement {
+        Statement::Block { statements, span } => Statement::Block {
+            statements: eliminate_in_statements(statements, removed),
+            span,
+        },
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            span,
+        } => match constant_truthiness(&condition) {
+            Some(true) => {
+                removed.extend(else_branch.map(|else_branch| DeadCode {
+                    span: else_branch.span(),
+                }));
+                return eliminate(*then_branch, removed);
+            }
+            Some(false) => {
+                removed.push(DeadCode {
+                    span: then_branch.span(),
+                });
+                return else_branch.and_then(|else_branch| eliminate(*else_branch, removed));
+            }
+            None => Statement::If {
+                condition,
+                then_branch: eliminate_boxed(then_branch, removed),
+                else_branch: else_branch.map(|else_branch| eliminate_boxed(else_branch, removed)),
+                span,
+            },
+        },
+        Statement::While {
+            condition,
+            body,
+            span,
+        } => match constant_truthiness(&condition) {
+            Some(false) => {
+                removed.push(DeadCode { span });
+                return None;
+            }
+            _ => Statement::While {
+                condition,
+                body: eliminate_boxed(body, removed),
+                span,
+            },
+        },
+        Statement::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            span,
+        } => match condition.as_deref().and_then(constant_truthiness) {
+            // the initializer still runs once
+            Some(false) => {
+                removed.push(DeadCode { span: body.span() });
+                return initializer.map(|initializer| Statement::Block {
+                    span: initializer.span(),
+                    statements: Vec::from([*initializer]),
+                });
+            }
+            _ => Statement::For {
+                initializer,
+                condition,
+                increment,
+                body: eliminate_boxed(body, removed),
+                span,
+            },
+        },
+        Statement::Custom {
+            keyword,
+            expressions,
+            statements,
+            span,
+        } => Statement::Custom {
+            keyword,
+            expressions,
+            statements: eliminate_in_statements(statements, removed),
+            span,
+        },
+        statement => statement,
+    };
+
+    Some(statement)
+}
+
+#[test]
+fn dead_code_elimination() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use alloc::string::ToString;
+
+    const SOURCE: &str = "if ((false)) print 1; else print 2;\nif (nil) print 3;\n{ while (false) print 4; print 5; }\nif (0) print 6; else print 7;\nfor (var i = 0; false; i = i + 1) print i;\nif (a) while (nil) a; else print 8;";
+
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+    let (program, removed) = eliminate_dead_code(program);
+
+    assert_eq!(
+        program.to_string(),
+        "(print 2)\n(block (print 5))\n(print 6)\n(block (var i = 0))\n(if-else a (block) (print 8))"
+    );
+
+    let removed = removed
+        .iter()
+        .map(|dead_code| &SOURCE[dead_code.span().range()])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        removed,
+        [
+            "print 1;",
+            "print 3;",
+            "while (false) print 4;",
+            "print 7;",
+            "print i;",
+            "while (nil) a;"
+        ]
+    );
+}
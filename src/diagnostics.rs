@@ -0,0 +1,781 @@
+//! The [Diagnostic] every stage's errors and warnings convert to, and [Diagnostics], which collects them for reporting
+//! so that a flood of errors from one mistake stays readable. [Diagnostic::render] shows one with the source it is
+//! about, and [to_json] gives each one as machine-readable JSON
+
+use crate::{
+    abstract_syntax_tree_visitor_pattern::interpreter::RuntimeError,
+    dead_code::DeadCode,
+    excerpt::floor_char_boundary,
+    lexer::{LexerError, LexerWarning},
+    line_index::{LineIndex, Utf16Range},
+    lint::Lint,
+    messages,
+    parser::{ParseError, ParseErrorKind},
+    token::Span,
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Display, Write as _};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How many diagnostics [Diagnostics] reports unless told otherwise
+pub const DEFAULT_MAX_DIAGNOSTICS: usize = 20;
+
+/// An error or warning that can be reported through [Diagnostics] and turned into a [Diagnostic]
+pub trait ToDiagnostic {
+    /// The code looked up in [crate::messages] and [crate::explain]
+    fn code(&self) -> &'static str;
+    fn span(&self) -> Span;
+    /// The source text the diagnostic is about
+    fn lexeme(&self) -> &str;
+    /// What is wrong, without where, in the current locale
+    fn message(&self) -> String;
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+    /// Other places in the source that explain this one. None by default
+    fn labels(&self) -> Vec<Label> {
+        Vec::new()
+    }
+    /// Lines of advice shown after the diagnostic. None by default
+    fn notes(&self) -> Vec<String> {
+        Vec::new()
+    }
+    fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            severity: self.severity(),
+            code: self.code(),
+            message: self.message(),
+            span: self.span(),
+            labels: self.labels(),
+            notes: self.notes(),
+            range: None,
+        }
+    }
+}
+
+/// An error or warning from any stage, holding everything needed to show it but not the source,
+/// so the command line, the prompt, JSON output, and editor integrations all show the same thing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: &'static str,
+    message: String,
+    span: Span,
+    labels: Vec<Label>,
+    notes: Vec<String>,
+    /// the span as an editor counts it, once [Self::locate] is called
+    range: Option<Utf16Range>,
+}
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: String, span: Span) -> Self {
+        Self {
+            severity,
+            code,
+            message,
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            range: None,
+        }
+    }
+    /// Records the [Utf16Range] of the span and of each label's span in the source `index` is of,
+    /// so a language server can send them on as is
+    pub fn locate(mut self, index: &LineIndex) -> Self {
+        self.range = Some(index.range(self.span));
+        for label in &mut self.labels {
+            label.range = Some(index.range(label.span));
+        }
+        self
+    }
+    pub fn label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+    pub fn note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+    pub const fn severity(&self) -> Severity {
+        self.severity
+    }
+    /// The code looked up in [crate::messages] and [crate::explain]
+    pub const fn code(&self) -> &'static str {
+        self.code
+    }
+    /// What is wrong, without where
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+    /// [None] until [Self::locate] is called
+    pub const fn range(&self) -> Option<Utf16Range> {
+        self.range
+    }
+    /// Displays the diagnostic with the lines of `source` it and its labels are on, underlined. See [Rendered]
+    pub fn render<'s>(&'s self, source: &'s str, path: Option<&'s str>) -> Rendered<'s> {
+        Rendered {
+            diagnostic: self,
+            source,
+            path,
+        }
+    }
+}
+/// `error[P0003]: Missing closing parenthesis`
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {}",
+            self.severity.name(),
+            self.code,
+            self.message
+        )
+    }
+}
+impl From<LexerError<'_>> for Diagnostic {
+    fn from(value: LexerError<'_>) -> Self {
+        value.to_diagnostic()
+    }
+}
+impl From<LexerWarning<'_>> for Diagnostic {
+    fn from(value: LexerWarning<'_>) -> Self {
+        value.to_diagnostic()
+    }
+}
+impl From<ParseError<'_>> for Diagnostic {
+    fn from(value: ParseError<'_>) -> Self {
+        value.to_diagnostic()
+    }
+}
+impl From<RuntimeError> for Diagnostic {
+    fn from(value: RuntimeError) -> Self {
+        value.to_diagnostic()
+    }
+}
+impl From<DeadCode> for Diagnostic {
+    fn from(value: DeadCode) -> Self {
+        value.to_diagnostic()
+    }
+}
+impl From<Lint<'_>> for Diagnostic {
+    fn from(value: Lint<'_>) -> Self {
+        value.to_diagnostic()
+    }
+}
+
+/// A secondary place in the source that a [Diagnostic] points at, with what it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    span: Span,
+    message: String,
+    /// set by [Diagnostic::locate]
+    range: Option<Utf16Range>,
+}
+impl Label {
+    pub const fn new(span: Span, message: String) -> Self {
+        Self {
+            span,
+            message,
+            range: None,
+        }
+    }
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    /// [None] until [Diagnostic::locate] is called on the diagnostic it is in
+    pub const fn range(&self) -> Option<Utf16Range> {
+        self.range
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+impl Severity {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+impl ToDiagnostic for LexerError<'_> {
+    fn code(&self) -> &'static str {
+        self.kind().code()
+    }
+    fn span(&self) -> Span {
+        self.span()
+    }
+    fn lexeme(&self) -> &str {
+        self.token().lexeme()
+    }
+    fn message(&self) -> String {
+        self.kind().to_string()
+    }
+}
+impl ToDiagnostic for LexerWarning<'_> {
+    fn code(&self) -> &'static str {
+        self.kind().code()
+    }
+    fn span(&self) -> Span {
+        self.span()
+    }
+    fn lexeme(&self) -> &str {
+        self.token().lexeme()
+    }
+    fn message(&self) -> String {
+        self.kind().to_string()
+    }
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+impl ToDiagnostic for ParseError<'_> {
+    fn code(&self) -> &'static str {
+        self.kind().code()
+    }
+    fn span(&self) -> Span {
+        self.span()
+    }
+    fn lexeme(&self) -> &str {
+        self.token().lexeme()
+    }
+    fn message(&self) -> String {
+        match self.kind() {
+            ParseErrorKind::LexerError(lexer_error) => lexer_error.message(),
+            kind => kind.to_string(),
+        }
+    }
+    /// The unclosed `(` or `{`, if any
+    fn labels(&self) -> Vec<Label> {
+        let kind = match self.kind() {
+            ParseErrorKind::UnexpectedEof { expected } => expected,
+            kind => kind,
+        };
+        let lexeme = match kind {
+            ParseErrorKind::MissingRightBrace => "{",
+            _ => "(",
+        };
+
+        self.opening()
+            .map(|opening| {
+                Label::new(
+                    opening,
+                    messages::format_message(
+                        messages::OPENED_HERE_TEMPLATE,
+                        &[("lexeme", &lexeme)],
+                    ),
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+}
+impl ToDiagnostic for RuntimeError {
+    fn code(&self) -> &'static str {
+        self.kind().code()
+    }
+    fn span(&self) -> Span {
+        self.span()
+    }
+    fn lexeme(&self) -> &str {
+        self.lexeme()
+    }
+    fn message(&self) -> String {
+        messages::message(self.code())
+    }
+    fn notes(&self) -> Vec<String> {
+        self.notes().to_vec()
+    }
+}
+impl ToDiagnostic for DeadCode {
+    fn code(&self) -> &'static str {
+        self.code()
+    }
+    fn span(&self) -> Span {
+        self.span()
+    }
+    /// Empty, since the removed code is not kept
+    fn lexeme(&self) -> &str {
+        ""
+    }
+    fn message(&self) -> String {
+        messages::message(self.code())
+    }
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+impl ToDiagnostic for Lint<'_> {
+    fn code(&self) -> &'static str {
+        self.code()
+    }
+    fn span(&self) -> Span {
+        self.span()
+    }
+    fn lexeme(&self) -> &str {
+        self.name().lexeme()
+    }
+    fn message(&self) -> String {
+        messages::message(self.code())
+    }
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+    /// The declaration shadowed, if any
+    fn labels(&self) -> Vec<Label> {
+        self.shadowed()
+            .map(|shadowed| {
+                Label::new(
+                    shadowed,
+                    messages::format_message(
+                        messages::SHADOWED_HERE_TEMPLATE,
+                        &[("lexeme", &self.name().lexeme())],
+                    ),
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// A [Diagnostic] shown like a compiler would: the diagnostic, where it is, then each line its span and labels
+/// are on with the span underlined by `^` and each label by `-` followed by its message, then its notes.
+/// A span over several lines is underlined on its first and last line. For example
+/// ```text
+/// error[P0003]: Missing closing parenthesis
+///  --> a.lox:2:4
+///   |
+/// 1 | print (1 +
+///   |       - '(' opened here
+/// 2 |   2;
+///   |    ^
+/// ```
+pub struct Rendered<'s> {
+    diagnostic: &'s Diagnostic,
+    source: &'s str,
+    path: Option<&'s str>,
+}
+/// Where one underline goes
+struct Underline<'s> {
+    /// 1-based
+    line_number: usize,
+    /// byte offsets into the source
+    line_start: usize,
+    start: usize,
+    end: usize,
+    is_primary: bool,
+    message: Option<&'s str>,
+}
+impl<'s> Rendered<'s> {
+    /// The underlines for `span`, with `message` after the last one
+    fn underline(
+        &self,
+        underlines: &mut Vec<Underline<'s>>,
+        span: Span,
+        is_primary: bool,
+        message: Option<&'s str>,
+    ) {
+        let source = self.source;
+        let start = floor_char_boundary(source, span.start);
+        let end = floor_char_boundary(source, span.end).max(start);
+        let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_number = source[..line_start].matches('\n').count() + 1;
+
+        let last_line_start = source[..end].rfind('\n').map_or(0, |index| index + 1);
+        // a span ending just after a line break ends with that line
+        if last_line_start <= start || last_line_start == end {
+            underlines.push(Underline {
+                line_number,
+                line_start,
+                start,
+                end: end.min(line_end(source, start)),
+                is_primary,
+                message,
+            });
+            return;
+        }
+
+        underlines.push(Underline {
+            line_number,
+            line_start,
+            start,
+            end: line_end(source, start),
+            is_primary,
+            message: None,
+        });
+        underlines.push(Underline {
+            line_number: source[..last_line_start].matches('\n').count() + 1,
+            line_start: last_line_start,
+            start: last_line_start,
+            end,
+            is_primary,
+            message,
+        });
+    }
+}
+impl Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = self.source;
+        let diagnostic = self.diagnostic;
+
+        let mut underlines = Vec::new();
+        self.underline(&mut underlines, diagnostic.span, true, None);
+        for label in &diagnostic.labels {
+            self.underline(&mut underlines, label.span, false, Some(&label.message));
+        }
+        let mut lines = underlines
+            .iter()
+            .map(|underline| (underline.line_number, underline.line_start))
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines.dedup();
+
+        let width = lines
+            .last()
+            .map_or(1, |(line_number, _)| line_number.to_string().len());
+        let gutter = "";
+
+        write!(f, "{}", diagnostic)?;
+        let (line, column) = position(source, diagnostic.span.start);
+        match self.path {
+            Some(path) => write!(f, "\n{gutter:width$}--> {}:{}:{}", path, line, column)?,
+            None => write!(f, "\n{gutter:width$}--> {}:{}", line, column)?,
+        }
+        write!(f, "\n{gutter:width$} |")?;
+
+        let mut previous_line_number = None;
+        for (line_number, line_start) in lines {
+            if previous_line_number.is_some_and(|previous| line_number > previous + 1) {
+                write!(f, "\n...")?;
+            }
+            previous_line_number = Some(line_number);
+
+            let text = &source[line_start..line_end(source, line_start)];
+            write!(f, "\n{line_number:>width$} | {}", text)?;
+
+            for underline in underlines
+                .iter()
+                .filter(|underline| underline.line_number == line_number)
+            {
+                write!(f, "\n{gutter:width$} | ")?;
+                // tabs are kept so the underline lines up however wide they are shown
+                for grapheme in source[line_start..underline.start].graphemes(true) {
+                    f.write_char(if grapheme == "\t" { '\t' } else { ' ' })?;
+                }
+                let marker = if underline.is_primary { '^' } else { '-' };
+                let length = source[underline.start..underline.end]
+                    .graphemes(true)
+                    .count()
+                    .max(1);
+                for _ in 0..length {
+                    f.write_char(marker)?;
+                }
+                if let Some(message) = underline.message {
+                    write!(f, " {}", message)?;
+                }
+            }
+        }
+
+        for note in &diagnostic.notes {
+            write!(f, "\n{gutter:width$} = note: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// The byte offset of the end of the line `offset` is on, before any `\r\n` or `\n`
+fn line_end(source: &str, offset: usize) -> usize {
+    let end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |index| offset + index);
+    if source[..end].ends_with('\r') {
+        end - 1
+    } else {
+        end
+    }
+}
+
+/// `diagnostic` as one line of JSON for editors and CI tools: its severity, code, message, the path of the script
+/// (`null` for none), and the range of its span as 1-based lines and grapheme columns, with the end exclusive.
+/// For example `{"severity":"error","code":"L0002","message":"Unrecognized token","file":"a.lox","range":{"start":{"line":1,"column":3},"end":{"line":1,"column":4}}}`
+pub fn to_json(diagnostic: &Diagnostic, source: &str, path: Option<&str>) -> String {
+    let span = diagnostic.span();
+    let (start_line, start_column) = position(source, span.start);
+    let (end_line, end_column) = position(source, span.end);
+
+    format!(
+        "{{\"severity\":\"{}\",\"code\":\"{}\",\"message\":{},\"file\":{},\"range\":{{\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}}}}}",
+        diagnostic.severity().name(),
+        diagnostic.code(),
+        json_string(diagnostic.message()),
+        path.map_or("null".into(), json_string),
+        start_line,
+        start_column,
+        end_line,
+        end_column,
+    )
+}
+
+/// The 1-based line and grapheme column of byte `offset` in `source`
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let offset = floor_char_boundary(source, offset);
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+
+    (
+        source[..line_start].matches('\n').count() + 1,
+        source[line_start..offset].graphemes(true).count() + 1,
+    )
+}
+
+pub(crate) fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+    for character in text.chars() {
+        let _ = match character {
+            '"' => write!(output, "\\\""),
+            '\\' => write!(output, "\\\\"),
+            '\n' => write!(output, "\\n"),
+            '\r' => write!(output, "\\r"),
+            '\t' => write!(output, "\\t"),
+            character if character.is_control() => write!(output, "\\u{:04x}", character as u32),
+            character => write!(output, "{}", character),
+        };
+    }
+    output.push('"');
+    output
+}
+
+/// Keeps the first `max_diagnostics` diagnostics pushed to it and counts the rest.
+/// A diagnostic with the same code and lexeme as the one pushed just before it, starting at most one character
+/// after that one ends, is dropped, so text like `@ @ @ @` is reported once but the same mistake on two lines is not
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostics<T> {
+    reported: Vec<T>,
+    /// how many were pushed after `reported` was full
+    omitted: usize,
+    max_diagnostics: usize,
+    /// code, lexeme, and end of the last diagnostic pushed
+    last: Option<(&'static str, String, usize)>,
+}
+impl<T: ToDiagnostic> Diagnostics<T> {
+    pub const fn new() -> Self {
+        Self {
+            reported: Vec::new(),
+            omitted: 0,
+            max_diagnostics: DEFAULT_MAX_DIAGNOSTICS,
+            last: None,
+        }
+    }
+    /// Defaults to [DEFAULT_MAX_DIAGNOSTICS]
+    pub fn max_diagnostics(mut self, max_diagnostics: usize) -> Self {
+        self.max_diagnostics = max_diagnostics;
+        self
+    }
+    pub fn push(&mut self, diagnostic: T) {
+        let key = (diagnostic.code(), diagnostic.lexeme());
+        let span = diagnostic.span();
+        let is_repeat = self.last.as_ref().is_some_and(|(code, lexeme, end)| {
+            (*code, lexeme.as_str()) == key && span.start <= end + 1
+        });
+        self.last = Some((key.0, key.1.into(), span.end));
+        if is_repeat {
+            return;
+        }
+
+        if self.reported.len() < self.max_diagnostics {
+            self.reported.push(diagnostic);
+        } else {
+            self.omitted += 1;
+        }
+    }
+    /// The diagnostics to report, in the order they were pushed
+    pub fn reported(&self) -> &[T] {
+        &self.reported
+    }
+    pub const fn omitted(&self) -> usize {
+        self.omitted
+    }
+    pub fn is_empty(&self) -> bool {
+        self.reported.is_empty()
+    }
+    /// The line to print after [Self::reported] when some diagnostics were omitted
+    pub fn summary(&self) -> Option<String> {
+        (self.omitted > 0).then(|| {
+            messages::format_message(
+                messages::DIAGNOSTICS_OMITTED_TEMPLATE,
+                &[("count", &self.omitted)],
+            )
+        })
+    }
+    /// The last line to print when any errors were pushed, saying how many stopped the input from running.
+    /// Omitted ones are counted, but not ones dropped as repeats
+    pub fn aborting_summary(&self) -> Option<String> {
+        match self.reported.len() + self.omitted {
+            0 => None,
+            1 => Some(messages::message(messages::ABORTING_AFTER_ONE_ERROR)),
+            count => Some(messages::format_message(
+                messages::ABORTING_TEMPLATE,
+                &[("count", &count)],
+            )),
+        }
+    }
+}
+impl<T: ToDiagnostic> Default for Diagnostics<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T: ToDiagnostic> Extend<T> for Diagnostics<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for diagnostic in iter {
+            self.push(diagnostic);
+        }
+    }
+}
+
+#[test]
+fn diagnostic_flood() {
+    use crate::lexer::Lexer;
+
+    let (_, errors) = Lexer::new("@ @ @ é @ é").lex_all();
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.extend(errors);
+    let lexemes = diagnostics
+        .reported()
+        .iter()
+        .map(|error| error.token().lexeme())
+        .collect::<Vec<_>>();
+    assert_eq!(lexemes, ["@", "é", "@", "é"]);
+    let (_, errors) = Lexer::new("@\n\n@").lex_all();
+    let mut separate_lines = Diagnostics::new();
+    separate_lines.extend(errors);
+    assert_eq!(separate_lines.reported().len(), 2);
+    assert_eq!(diagnostics.summary(), None);
+    assert_eq!(
+        diagnostics.aborting_summary().unwrap(),
+        "aborting due to 4 previous errors"
+    );
+
+    let source = "1 @ 2 é ".repeat(60);
+    let (_, errors) = Lexer::new(&source).lex_all();
+    let mut diagnostics = Diagnostics::new().max_diagnostics(100);
+    diagnostics.extend(errors);
+    assert_eq!(diagnostics.reported().len(), 100);
+    assert_eq!(diagnostics.summary().unwrap(), "20 more errors omitted");
+    assert_eq!(
+        diagnostics.aborting_summary().unwrap(),
+        "aborting due to 120 previous errors"
+    );
+
+    let (_, errors) = Lexer::new("@").lex_all();
+    let mut diagnostics = Diagnostics::new();
+    assert_eq!(diagnostics.aborting_summary(), None);
+    diagnostics.extend(errors);
+    assert_eq!(
+        diagnostics.aborting_summary().unwrap(),
+        "aborting due to the previous error"
+    );
+}
+
+#[test]
+fn json_diagnostics() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    let (_, errors) = Lexer::new("var a;\n  é @").lex_all();
+    assert_eq!(
+        to_json(
+            &errors[0].to_diagnostic(),
+            "var a;\n  é @",
+            Some("dir/\"a\".lox")
+        ),
+        "{\"severity\":\"error\",\"code\":\"L0002\",\"message\":\"Unrecognized token\",\"file\":\"dir/\\\"a\\\".lox\",\"range\":{\"start\":{\"line\":2,\"column\":3},\"end\":{\"line\":2,\"column\":4}}}"
+    );
+
+    let errors = Parser::from(Lexer::new("print (1; @")).parse().unwrap_err();
+    assert_eq!(errors[1].message(), "Unrecognized token");
+    let json = to_json(&errors[0].to_diagnostic(), "print (1; @", None);
+    assert!(json
+        .contains("\"code\":\"P0003\",\"message\":\"Missing closing parenthesis\",\"file\":null,"));
+    assert_eq!(json_string("tab\tbell\u{7}"), "\"tab\\tbell\\u0007\"");
+}
+
+#[test]
+fn unified_diagnostic() {
+    use crate::{
+        abstract_syntax_tree_visitor_pattern::interpreter::RuntimeErrorKind, lexer::Lexer,
+    };
+
+    let (_, errors) = Lexer::new("1 @").lex_all();
+    let diagnostic = Diagnostic::from(errors[0].clone());
+    assert_eq!(diagnostic.to_string(), "error[L0002]: Unrecognized token");
+    assert_eq!(diagnostic.span(), Span::new(2, 3));
+    assert_eq!(
+        diagnostic.render("1 @", None).to_string(),
+        "error[L0002]: Unrecognized token\n --> 1:3\n  |\n1 | 1 @\n  |   ^"
+    );
+
+    let token = Lexer::new("-x").next().unwrap().unwrap();
+    let diagnostic = Diagnostic::from(RuntimeError::new(
+        RuntimeErrorKind::OperandMustBeNumber,
+        &token,
+    ))
+    .label(Label::new(Span::new(1, 2), "this is not a number".into()))
+    .note("convert it first".into());
+    assert_eq!(diagnostic.severity(), Severity::Error);
+    assert_eq!(diagnostic.labels()[0].message(), "this is not a number");
+    assert_eq!(diagnostic.notes(), ["convert it first"]);
+
+    assert_eq!(diagnostic.range(), None);
+    let diagnostic = diagnostic.locate(&LineIndex::new("-x"));
+    assert_eq!(diagnostic.range().unwrap().end().character(), 1);
+    assert_eq!(
+        diagnostic.labels()[0].range().unwrap().start().character(),
+        1
+    );
+}
+
+#[test]
+fn rendered_diagnostic() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const SOURCE: &str = "print (1 +\n\t2;\n";
+    let errors = Parser::from(Lexer::new(SOURCE)).parse().unwrap_err();
+    assert_eq!(
+        errors[0]
+            .to_diagnostic()
+            .render(SOURCE, Some("a.lox"))
+            .to_string(),
+        "error[P0003]: Missing closing parenthesis\n --> a.lox:2:3\n  |\n1 | print (1 +\n  |       - '(' opened here\n2 | \t2;\n  | \t ^"
+    );
+
+    let source = "var a = 1;\n\n\n{\n  a;\n}";
+    let diagnostic = Diagnostic::new(
+        Severity::Warning,
+        "W0001",
+        "unused".into(),
+        Span::new(13, 21),
+    )
+    .label(Label::new(Span::new(4, 5), "declared here".into()))
+    .note("remove it".into());
+    assert_eq!(
+        diagnostic.render(source, None).to_string(),
+        "warning[W0001]: unused\n --> 4:1\n  |\n1 | var a = 1;\n  |     - declared here\n...\n4 | {\n  | ^\n...\n6 | }\n  | ^\n  = note: remove it"
+    );
+}
@@ -0,0 +1,125 @@
+//! Named variants of lox for teaching, such as one that spells `fun` as `function` or `var` as `let`.
+//! A [Dialect] configures the lexer through [LexerConfig::dialect], so the rest of the pipeline only sees standard [TokenKind]s,
+//! plus the [TokenKind::CustomKeyword]s of any statements the host adds
+
+use crate::{
+    lexer::LexerConfig,
+    token::{KeywordTable, TokenKind},
+};
+use alloc::{string::String, vec::Vec};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dialect {
+    name: String,
+    keywords: KeywordTable,
+    strict: bool,
+}
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::new("lox")
+    }
+}
+impl Dialect {
+    /// Standard lox: the keywords in [crate::token::KEYWORDS] and no strictness
+    pub fn standard() -> Self {
+        Self::default()
+    }
+    /// A dialect that starts out like [Self::standard]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            keywords: KeywordTable::default(),
+            strict: false,
+        }
+    }
+    /// Adds `spelling` as another way to write the keyword `kind`
+    /// # Panics
+    /// When `kind` is not a keyword (see [TokenKind::is_keyword])
+    pub fn with_alias(mut self, spelling: &str, kind: TokenKind) -> Self {
+        assert!(kind.is_keyword(), "{:?} is not a keyword", kind);
+        self.keywords.insert(spelling, kind);
+        self
+    }
+    /// Replaces every spelling of the keyword `kind` with `spelling`, so the old spellings lex as identifiers
+    /// # Panics
+    /// When `kind` is not a keyword (see [TokenKind::is_keyword])
+    pub fn with_spelling(mut self, spelling: &str, kind: TokenKind) -> Self {
+        assert!(kind.is_keyword(), "{:?} is not a keyword", kind);
+
+        let old_spellings = self
+            .keywords
+            .spellings(kind)
+            .map(String::from)
+            .collect::<Vec<_>>();
+        for old_spelling in old_spellings {
+            self.keywords.remove(&old_spelling);
+        }
+
+        self.keywords.insert(spelling, kind);
+        self
+    }
+    /// Claims `spelling` as the keyword starting a host-defined [crate::abstract_syntax_tree::Statement::Custom].
+    /// It lexes as [TokenKind::CustomKeyword] with `id`, which also selects the parser's [crate::parser::CustomStatementParser]
+    pub fn with_statement_keyword(mut self, spelling: &str, id: u16) -> Self {
+        self.keywords.insert(spelling, TokenKind::CustomKeyword(id));
+        self
+    }
+    /// See [KeywordTable::case_insensitive]
+    pub fn case_insensitive(self, case_insensitive: bool) -> Self {
+        Self {
+            keywords: self.keywords.case_insensitive(case_insensitive),
+            ..self
+        }
+    }
+    /// See [LexerConfig::strict]
+    pub fn strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub const fn keyword_table(&self) -> &KeywordTable {
+        &self.keywords
+    }
+    pub const fn is_strict(&self) -> bool {
+        self.strict
+    }
+    /// How programs in this dialect write `kind`, for printers and messages.
+    /// The standard spelling is preferred when it is still a keyword
+    pub fn spelling(&self, kind: TokenKind) -> Option<&str> {
+        let standard = kind
+            .canonical_lexeme()
+            .filter(|lexeme| self.keywords.get(lexeme) == Some(kind));
+
+        standard.or_else(|| self.keywords.spellings(kind).next())
+    }
+    pub fn lexer_config(&self) -> LexerConfig {
+        LexerConfig::new().dialect(self)
+    }
+}
+
+#[test]
+fn teaching_dialect() {
+    use crate::{abstract_syntax_tree::Statement, lexer::Lexer, parser::Parser};
+
+    let dialect = Dialect::new("teaching")
+        .with_spelling("let", TokenKind::Var)
+        .with_alias("function", TokenKind::Fun)
+        .with_alias("show", TokenKind::Print);
+
+    assert_eq!(dialect.spelling(TokenKind::Var), Some("let"));
+    assert_eq!(dialect.spelling(TokenKind::Fun), Some("fun"));
+    assert_eq!(dialect.spelling(TokenKind::Identifier), None);
+
+    let program = Parser::from(Lexer::with_config(
+        "let var = 1; show var;",
+        dialect.lexer_config(),
+    ))
+    .parse()
+    .unwrap();
+
+    let [Statement::Var { name, .. }, Statement::Print { .. }] = program.statements() else {
+        panic!("expected a declaration and a print statement");
+    };
+    assert_eq!(name.lexeme(), "var");
+}
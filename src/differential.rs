@@ -0,0 +1,349 @@
+//! Differential testing for `lox compare`: a script is run by this interpreter and by a reference one, such as the book's
+//! jlox or clox, and [compare] reports each way their stdout, stderr, and exit codes differ.
+//! Both run as child processes, since `lox run` writes to the process's own streams
+
+use std::{
+    fmt::Display,
+    io::{self, Read},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long [Run::of] lets a program run before killing it
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often [Run::of] checks whether the program has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// What a program printed and how it exited
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Run {
+    stdout: String,
+    stderr: String,
+    /// [None] when it was killed, by a signal or for running longer than the timeout
+    exit_code: Option<i32>,
+    timed_out: bool,
+}
+impl Run {
+    pub fn new(
+        stdout: impl Into<String>,
+        stderr: impl Into<String>,
+        exit_code: Option<i32>,
+    ) -> Self {
+        Self {
+            stdout: stdout.into(),
+            stderr: stderr.into(),
+            exit_code,
+            timed_out: false,
+        }
+    }
+    /// Runs `command` with no stdin, killing it after `timeout`. Output that is not UTF-8 is read lossily.
+    /// On Unix it runs in a process group of its own and the whole group is killed, so a wrapper script such as
+    /// jlox's does not leave the program it started running. Either way, output is only waited for until `timeout`,
+    /// since anything the command started may keep its stdout and stderr open after it is gone
+    pub fn of(command: &mut Command, timeout: Duration) -> Result<Self, io::Error> {
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(command, 0);
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let started = Instant::now();
+        // read while it runs, so it does not block on a full pipe
+        let stdout = Output::read(child.stdout.take());
+        let stderr = Output::read(child.stderr.take());
+
+        let status = wait(&mut child, started + timeout)?;
+        while !(stdout.is_finished() && stderr.is_finished()) && started.elapsed() < timeout {
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(Self {
+            stdout: stdout.take(),
+            stderr: stderr.take(),
+            exit_code: status.and_then(|status| status.code()),
+            timed_out: status.is_none(),
+        })
+    }
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+    pub const fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+    pub const fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+}
+
+/// A stream of the child read into a buffer by a thread, so what was read can be taken without waiting for it to end
+struct Output {
+    bytes: Arc<Mutex<Vec<u8>>>,
+    reader: thread::JoinHandle<()>,
+}
+impl Output {
+    fn read(stream: Option<impl Read + Send + 'static>) -> Self {
+        let bytes = Arc::new(Mutex::new(Vec::new()));
+        let buffer = Arc::clone(&bytes);
+        let reader = thread::spawn(move || {
+            let Some(mut stream) = stream else {
+                return;
+            };
+            let mut chunk = [0; 4096];
+            while let Ok(length @ 1..) = stream.read(&mut chunk) {
+                if let Ok(mut buffer) = buffer.lock() {
+                    buffer.extend_from_slice(&chunk[..length]);
+                }
+            }
+        });
+        Self { bytes, reader }
+    }
+    fn is_finished(&self) -> bool {
+        self.reader.is_finished()
+    }
+    /// What was read so far. The thread is left to end by itself when the stream closes
+    fn take(&self) -> String {
+        let bytes = self
+            .bytes
+            .lock()
+            .map(|bytes| bytes.clone())
+            .unwrap_or_default();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+/// Waits for `child` to exit, killing it, and on Unix its process group, at `deadline`. [None] when it was killed
+fn wait(child: &mut Child, deadline: Instant) -> Result<Option<ExitStatus>, io::Error> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            #[cfg(unix)]
+            let _ = Command::new("kill")
+                .args(["-s", "KILL", "--", &format!("-{}", child.id())])
+                .status();
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The output streams [Divergence::Output] can be about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+impl Stream {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// One way the [Run] of this interpreter differs from the reference's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// `stream` first differs on the 1-based `line`. [None] where one ended before it
+    Output {
+        stream: Stream,
+        line: usize,
+        ours: Option<String>,
+        reference: Option<String>,
+    },
+    ExitCode {
+        ours: Option<i32>,
+        reference: Option<i32>,
+    },
+    /// one was killed for running longer than the timeout, and the other was not
+    TimedOut { ours: bool, reference: bool },
+}
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn line(line: &Option<String>) -> String {
+            line.as_ref()
+                .map_or("nothing".to_owned(), |line| format!("{:?}", line))
+        }
+        fn exit_code(code: Option<i32>) -> String {
+            code.map_or("no exit code".to_owned(), |code| code.to_string())
+        }
+        fn timed_out(timed_out: bool) -> &'static str {
+            if timed_out {
+                "timed out"
+            } else {
+                "finished"
+            }
+        }
+
+        match self {
+            Divergence::Output {
+                stream,
+                line: number,
+                ours,
+                reference,
+            } => write!(
+                f,
+                "{} differs on line {}: printed {} here and {} by the reference",
+                stream.name(),
+                number,
+                line(ours),
+                line(reference)
+            ),
+            Divergence::ExitCode { ours, reference } => write!(
+                f,
+                "exit code is {} here and {} for the reference",
+                exit_code(*ours),
+                exit_code(*reference)
+            ),
+            Divergence::TimedOut { ours, reference } => write!(
+                f,
+                "{} here and {} for the reference",
+                timed_out(*ours),
+                timed_out(*reference)
+            ),
+        }
+    }
+}
+
+/// Each way `ours` differs from `reference`: whether only one timed out, where stdout first differs, then stderr, then the exit codes.
+/// Empty when they match
+pub fn compare(ours: &Run, reference: &Run) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    if ours.timed_out != reference.timed_out {
+        divergences.push(Divergence::TimedOut {
+            ours: ours.timed_out,
+            reference: reference.timed_out,
+        });
+    }
+    for (stream, ours, reference) in [
+        (Stream::Stdout, &ours.stdout, &reference.stdout),
+        (Stream::Stderr, &ours.stderr, &reference.stderr),
+    ] {
+        divergences.extend(
+            first_different_line(ours, reference).map(|(line, ours, reference)| {
+                Divergence::Output {
+                    stream,
+                    line,
+                    ours,
+                    reference,
+                }
+            }),
+        );
+    }
+    if ours.exit_code != reference.exit_code {
+        divergences.push(Divergence::ExitCode {
+            ours: ours.exit_code,
+            reference: reference.exit_code,
+        });
+    }
+    divergences
+}
+
+/// The 1-based number of the first line where `ours` and `reference` differ, with both lines. [None] when they match.
+/// The lines are without their line breaks, unless those are the only difference
+fn first_different_line(
+    ours: &str,
+    reference: &str,
+) -> Option<(usize, Option<String>, Option<String>)> {
+    if ours == reference {
+        return None;
+    }
+    let mut ours_lines = ours.split_inclusive('\n');
+    let mut reference_lines = reference.split_inclusive('\n');
+    (1..).find_map(|number| {
+        let (ours, reference) = (ours_lines.next(), reference_lines.next());
+        if ours == reference {
+            return None;
+        }
+        let trim = |line: Option<&str>| line.map(|line| line.trim_end_matches('\n').to_owned());
+        match (trim(ours), trim(reference)) {
+            (trimmed_ours, trimmed_reference) if trimmed_ours == trimmed_reference => Some((
+                number,
+                ours.map(str::to_owned),
+                reference.map(str::to_owned),
+            )),
+            (ours, reference) => Some((number, ours, reference)),
+        }
+    })
+}
+
+#[test]
+fn compare_runs() {
+    let ours = Run::new("1\n2\n3\n", "", Some(0));
+    assert!(compare(&ours, &ours.clone()).is_empty());
+
+    let divergences = compare(
+        &ours,
+        &Run::new("1\n2.0\n3\n", "[line 1] Error\n", Some(65)),
+    );
+    assert_eq!(
+        divergences
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        [
+            "stdout differs on line 2: printed \"2\" here and \"2.0\" by the reference",
+            "stderr differs on line 1: printed nothing here and \"[line 1] Error\" by the reference",
+            "exit code is 0 here and 65 for the reference",
+        ]
+    );
+
+    // a missing final line break is a difference
+    assert_eq!(
+        compare(&Run::new("1\n", "", Some(0)), &Run::new("1", "", Some(0))),
+        [Divergence::Output {
+            stream: Stream::Stdout,
+            line: 1,
+            ours: Some("1\n".to_owned()),
+            reference: Some("1".to_owned()),
+        }]
+    );
+
+    let timed_out = Run {
+        timed_out: true,
+        ..Run::default()
+    };
+    assert_eq!(
+        compare(&Run::new("", "", Some(0)), &timed_out)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        [
+            "finished here and timed out for the reference",
+            "exit code is 0 here and no exit code for the reference",
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn run_a_command() {
+    let run = Run::of(
+        Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+        DEFAULT_TIMEOUT,
+    )
+    .unwrap();
+    assert_eq!(run, Run::new("out\n", "err\n", Some(3)));
+
+    // a wrapper script whose child keeps the pipes open is stopped at the timeout too
+    let started = Instant::now();
+    let run = Run::of(
+        Command::new("sh").args(["-c", "echo started; sleep 5; echo finished"]),
+        Duration::from_millis(200),
+    )
+    .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(4));
+    assert!(run.timed_out());
+    assert_eq!(run.exit_code(), None);
+    assert_eq!(run.stdout(), "started\n");
+}
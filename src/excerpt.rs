@@ -0,0 +1,107 @@
+//! Shows the line of source a diagnostic points at, with a `^---` marker under the offending text
+
+use crate::token::Span;
+use core::fmt::Display;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The line containing the start of `span`, prefixed with its line number, above a marker under the spanned text.
+/// A span that continues past the end of its first line is marked up to the end of that line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceExcerpt<'s> {
+    source: &'s str,
+    span: Span,
+}
+impl<'s> SourceExcerpt<'s> {
+    pub const fn new(source: &'s str, span: Span) -> Self {
+        Self { source, span }
+    }
+}
+impl Display for SourceExcerpt<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let source = self.source;
+        let start = floor_char_boundary(source, self.span.start);
+
+        let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |index| start + index);
+        let end = floor_char_boundary(source, self.span.end.clamp(start, line_end));
+
+        let line_number = source[..line_start].matches('\n').count() + 1;
+        let gutter_width = line_number.ilog10() as usize + 1;
+
+        writeln!(
+            f,
+            "{:>gutter_width$} | {}",
+            line_number,
+            source[line_start..line_end].trim_end_matches('\r')
+        )?;
+        write!(f, "{:gutter_width$} | ", "")?;
+        // tabs are kept so the marker lines up however wide the terminal draws them
+        for grapheme in source[line_start..start].graphemes(true) {
+            write!(f, "{}", if grapheme == "\t" { "\t" } else { " " })?;
+        }
+        write!(f, "^")?;
+        for _ in 1..source[start..end].graphemes(true).count() {
+            write!(f, "-")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Displays a diagnostic followed by its [SourceExcerpt]. Made by `with_source` on
+/// [crate::lexer::LexerError] and [crate::parser::ParseError]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WithSource<'s, T> {
+    diagnostic: &'s T,
+    excerpt: SourceExcerpt<'s>,
+}
+impl<'s, T> WithSource<'s, T> {
+    pub const fn new(diagnostic: &'s T, excerpt: SourceExcerpt<'s>) -> Self {
+        Self {
+            diagnostic,
+            excerpt,
+        }
+    }
+}
+impl<T: Display> Display for WithSource<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "{}", self.diagnostic)?;
+        write!(f, "{}", self.excerpt)
+    }
+}
+
+/// `index`, or the closest character boundary before it, so spans from another source cannot split a character
+pub(crate) fn floor_char_boundary(source: &str, index: usize) -> usize {
+    let mut index = index.min(source.len());
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[test]
+fn source_excerpts() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use alloc::string::ToString;
+
+    let source = "var a = 1;\n\tprint \"🍎\" @ a;\n";
+    let error = Lexer::new(source).nth(7).unwrap().unwrap_err();
+    assert_eq!(
+        error.with_source(source).to_string(),
+        "Error lexing @ at line 2, column 12: Unrecognized token\n\
+         2 | \tprint \"🍎\" @ a;\n  \
+           | \t          ^"
+    );
+
+    let source = "print 1 +\n    (2 * three;";
+    let errors = Parser::from(Lexer::new(source)).parse().unwrap_err();
+    assert!(errors[0]
+        .with_source(source)
+        .to_string()
+        .ends_with("\n2 |     (2 * three;\n  |               ^"));
+
+    let excerpt = SourceExcerpt::new("x = \"long\nstring\";", Span::new(4, 17));
+    assert_eq!(excerpt.to_string(), "1 | x = \"long\n  |     ^----");
+}
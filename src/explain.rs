@@ -0,0 +1,313 @@
+//! Extended descriptions of each error code, shown by `lox explain <code>` and `lox --explain`
+
+use crate::messages;
+use alloc::string::String;
+use core::fmt::Display;
+
+pub struct Explanation {
+    code: &'static str,
+    description: &'static str,
+    example: &'static str,
+    fix: &'static str,
+}
+impl Explanation {
+    pub const fn code(&self) -> &'static str {
+        self.code
+    }
+    /// The short message for this code in the current locale (see [messages::message])
+    pub fn title(&self) -> String {
+        messages::message(self.code)
+    }
+    pub const fn description(&self) -> &'static str {
+        self.description
+    }
+    /// Lox source that produces the error
+    pub const fn example(&self) -> &'static str {
+        self.example
+    }
+    /// The example, corrected
+    pub const fn fix(&self) -> &'static str {
+        self.fix
+    }
+}
+impl Display for Explanation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.title())?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.description)?;
+        writeln!(f)?;
+        writeln!(f, "Erroneous code example:")?;
+        writeln!(f)?;
+        writeln!(f, "    {}", self.example)?;
+        writeln!(f)?;
+        writeln!(f, "Fixed:")?;
+        writeln!(f)?;
+        write!(f, "    {}", self.fix)
+    }
+}
+
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "L0001",
+        description: "A string literal was opened with '\"' but the file ended before the closing '\"'. \
+            Strings may span multiple lines, so the missing quote can be far from where the error is reported.",
+        example: "print \"hello;",
+        fix: "print \"hello\";",
+    },
+    Explanation {
+        code: "L0002",
+        description: "The source contains characters that do not start any lox token, such as '@', '#', or non-ASCII symbols outside of strings and comments.",
+        example: "var total = price @ 2;",
+        fix: "var total = price * 2;",
+    },
+    Explanation {
+        code: "L0003",
+        description: "In the strict dialect, a number literal cannot end with a '.' that has no digits after it. Write the fractional digits or remove the '.'. \
+            Outside the strict dialect `1.` lexes as the number 1 followed by a '.'.",
+        example: "var half = 1.;",
+        fix: "var half = 1.0;",
+    },
+    Explanation {
+        code: "L0004",
+        description: "A number literal has an exponent marker 'e' or 'E' (optionally followed by a sign) but no exponent digits.",
+        example: "var small = 1e-;",
+        fix: "var small = 1e-3;",
+    },
+    Explanation {
+        code: "L0005",
+        description: "A '0x' (hexadecimal) or '0b' (binary) prefix must be followed by at least one digit of that base.",
+        example: "var mask = 0x;",
+        fix: "var mask = 0xFF;",
+    },
+    Explanation {
+        code: "L0006",
+        description: "A literal syntax registered by the host program (for example '#rgb(' ... ')') was started but its closing text never appeared. \
+            The example assumes a host that registered '#rgb(' with the terminator ')'.",
+        example: "var orange = #rgb(ff8000;",
+        fix: "var orange = #rgb(ff8000);",
+    },
+    Explanation {
+        code: "P0001",
+        description: "The parser needed an expression (a literal, a parenthesized expression, or an operator applied to one) but found a token that cannot start one.",
+        example: "print 1 + ;",
+        fix: "print 1 + 2;",
+    },
+    Explanation {
+        code: "P0002",
+        description: "A unary operator ('-' or '!') must be followed by the expression it applies to.",
+        example: "print -;",
+        fix: "print -1;",
+    },
+    Explanation {
+        code: "P0003",
+        description: "An opening '(' was not matched by a closing ')' before the expression ended.",
+        example: "print (1 + 2;",
+        fix: "print (1 + 2);",
+    },
+    Explanation {
+        code: "P0004",
+        description: "Parentheses, call arguments, unary operators, assignments, blocks, and the bodies of 'if', 'while', and 'for' \
+            can be nested inside each other at most 128 levels deep. \
+            Deeper code is almost always generated by mistake; simplify them or split them up.",
+        example: "print - - - - - - - - ... - - 1; // 200 '-' in total",
+        fix: "print 1;",
+    },
+    Explanation {
+        code: "P0005",
+        description: "Every expression statement, 'print' statement, and variable declaration must end with a ';'.",
+        example: "print 1 + 2 print 3;",
+        fix: "print 1 + 2; print 3;",
+    },
+    Explanation {
+        code: "P0006",
+        description: "The 'var' keyword must be followed by the name of the variable being declared. Names start with a letter or '_'.",
+        example: "var 2 = 1;",
+        fix: "var two = 1;",
+    },
+    Explanation {
+        code: "P0007",
+        description: "Only a variable can be on the left side of '='. Literals and other expressions cannot be assigned to.",
+        example: "1 + a = 2;",
+        fix: "a = 2 - 1;",
+    },
+    Explanation {
+        code: "P0008",
+        description: "The condition of an 'if' or 'while', and the clauses of a 'for', must be wrapped in parentheses.",
+        example: "if x > 1 print x;",
+        fix: "if (x > 1) print x;",
+    },
+    Explanation {
+        code: "P0009",
+        description: "An opening '{' was not matched by a closing '}' before the end of the file. \
+            Since the file ended, this is reported as part of P0010.",
+        example: "while (true) { print 1;",
+        fix: "while (true) { print 1; }",
+    },
+    Explanation {
+        code: "P0010",
+        description: "The source ended in the middle of a statement or expression. The message after the ':' says what was expected next. \
+            In the REPL, input that ends this way continues on the next line.",
+        example: "print 1 +",
+        fix: "print 1 + 2;",
+    },
+    Explanation {
+        code: "P0011",
+        description: "A function call can pass at most 255 arguments. Group related values into an instance or a list instead.",
+        example: "plot(x0, y0, x1, y1, ...); // 300 arguments in total",
+        fix: "plot(points);",
+    },
+    Explanation {
+        code: "P0012",
+        description: "The arguments of a call must be separated by ',' and closed with ')'.",
+        example: "print max(1, 2;",
+        fix: "print max(1, 2);",
+    },
+    Explanation {
+        code: "P0013",
+        description: "A '.' must be followed by the name of the property or method being accessed. Names start with a letter or '_'.",
+        example: "print point.0;",
+        fix: "print point.x;",
+    },
+    Explanation {
+        code: "R0001",
+        description: "Unary '-' negates numbers only. The value of its operand was a string, boolean, or nil.",
+        example: "print -\"1\";",
+        fix: "print -1;",
+    },
+    Explanation {
+        code: "R0002",
+        description: "'-', '*', '/', '<', '<=', '>', and '>=' need both operands to be numbers. Values are never converted automatically.",
+        example: "print \"2\" * 3;",
+        fix: "print 2 * 3;",
+    },
+    Explanation {
+        code: "R0003",
+        description: "'+' adds two numbers or joins two strings. Mixing a number and a string, or using booleans or nil, is an error.",
+        example: "print \"total: \" + 3;",
+        fix: "print \"total: 3\";",
+    },
+    Explanation {
+        code: "R0004",
+        description: "The variable was read or assigned before any 'var' declared it. Assignment does not declare a new variable.",
+        example: "count = 1;",
+        fix: "var count = 1;",
+    },
+    Explanation {
+        code: "R0005",
+        description: "Only functions and classes can be called, and the value before the '(' was neither.",
+        example: "var name = \"lox\"; name();",
+        fix: "var name = \"lox\"; print name;",
+    },
+    Explanation {
+        code: "R0006",
+        description: "The expression uses an operator token where the parser never puts one, such as '*' as a unary operator. \
+            Only trees built by hand, not parsed from source, can contain it.",
+        example: "Unary::new(Token::new(TokenKind::Star, \"*\", 1), operand)",
+        fix: "Unary::new(Token::new(TokenKind::Minus, \"-\", 1), operand)",
+    },
+    Explanation {
+        code: "R0007",
+        description: "The literal is not a number, string, 'true', 'false', or 'nil', so it has no built-in value. \
+            Host-defined literals of a dialect are evaluated by the host.",
+        example: "Literal::new(Token::new(TokenKind::Identifier, \"x\", 1))",
+        fix: "Literal::new(Token::new(TokenKind::Number, \"1\", 1))",
+    },
+    Explanation {
+        code: "R0008",
+        description: "Only class instances have properties, and the value before the '.' was not one.",
+        example: "var name = \"lox\"; print name.length;",
+        fix: "var name = \"lox\"; print name;",
+    },
+    Explanation {
+        code: "R0009",
+        description: "The statement starts with a keyword a dialect added, and only the program embedding that dialect knows what it does. \
+            The interpreter runs it only when that program registers a handler for the keyword.",
+        example: "repeat 3 { print 1; }",
+        fix: "for (var i = 0; i < 3; i = i + 1) { print 1; }",
+    },
+    Explanation {
+        code: "R0010",
+        description: "The program ran more statements than the step limit set with `--max-steps` allows, and was stopped. \
+            The error points at the loop that ran the most of them, with the values its condition and increment read when it stopped, \
+            which usually show why it never ends.",
+        example: "for (var i = 0; i < 10; i = i - 1) { print i; }",
+        fix: "for (var i = 0; i < 10; i = i + 1) { print i; }",
+    },
+    Explanation {
+        code: "R0011",
+        description: "The program made a string, or printed more output, than the size limit allows, and was stopped. \
+            `lox grade` sets a limit so a submission that keeps doubling a string fails its case instead of using up the grader's memory.",
+        example: "var s = \"x\"; while (true) s = s + s;",
+        fix: "var s = \"x\"; for (var i = 0; i < 10; i = i + 1) s = s + \"x\";",
+    },
+    Explanation {
+        code: "W0001",
+        description: "Numbers are 64-bit floats, which hold every integer up to 2^53 (9007199254740992) but only some above it. \
+            This integer literal is rounded to the nearest one they can hold, so it will not compare equal to the number that was written. \
+            Only reported when precision loss warnings are enabled.",
+        example: "var id = 9007199254740993;",
+        fix: "var id = \"9007199254740993\";",
+    },
+    Explanation {
+        code: "W0002",
+        description: "The condition of this 'if', 'while', or 'for' is constant, so one of its branches or its loop body can never run. \
+            A condition is constant when it is made of literals and variables that are never assigned. \
+            The optimizer removed it. Delete the dead code. \
+            Only reported when optimizing.",
+        example: "var debug = false; if (debug) print \"debug\"; print \"done\";",
+        fix: "print \"done\";",
+    },
+    Explanation {
+        code: "W0003",
+        description: "A variable declared inside a block is never read before the block ends, so its value is never used. \
+            Assigning to it does not count as reading it. Top level variables are not checked, since later code may read them. \
+            Remove the variable, or start its name with '_' if it is meant to be unused.",
+        example: "{ var total = 0; total = 1 + 2; }",
+        fix: "{ var total = 0; total = 1 + 2; print total; }",
+    },
+    Explanation {
+        code: "W0004",
+        description: "A variable declared inside a block has the same name as one already in scope, \
+            so the outer one cannot be reached until the block ends. This is allowed, but assignments meant for the outer variable go to the inner one. \
+            Rename one of them.",
+        example: "var count = 0; { var count = 1; count = count + 1; }",
+        fix: "var count = 0; { var step = 1; count = count + step; }",
+    },
+    Explanation {
+        code: "W0005",
+        description: "The condition of this 'while' or 'for' only reads variables that the loop never assigns, \
+            so it has the same value every time it is checked. Lox has no 'break', so the loop either never runs or never stops. \
+            Assign one of the variables in the loop body or increment.",
+        example: "var i = 0; while (i < 3) print i;",
+        fix: "var i = 0; while (i < 3) { print i; i = i + 1; }",
+    },
+    Explanation {
+        code: "W0006",
+        description: "This expression only reads variables that the loop never assigns, so it computes the same value on every iteration. \
+            Compute it once before the loop and read the result instead.",
+        example: "for (var i = 0; i < 3; i = i + 1) print i * (scale * 2);",
+        fix: "var step = scale * 2; for (var i = 0; i < 3; i = i + 1) print i * step;",
+    },
+];
+
+/// Finds the [Explanation] for an error code such as `P0003`. Case insensitive
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+#[test]
+fn every_message_is_explained() {
+    use alloc::string::ToString;
+
+    for explanation in EXPLANATIONS {
+        assert_ne!(explanation.title(), explanation.code());
+    }
+
+    let explanation = explain("p0003").unwrap();
+    assert!(explanation
+        .to_string()
+        .starts_with("P0003: Missing closing parenthesis\n"));
+    assert!(explain("X9999").is_none());
+}
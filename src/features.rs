@@ -0,0 +1,60 @@
+//! Introspection of what this build of the crate supports, so embedders can adapt to the host interpreter.
+//! Scripts cannot ask yet: a `version()` native returning a map needs callable values and maps,
+//! and [crate::abstract_syntax_tree_visitor_pattern::interpreter::Value] has neither
+
+/// Language extensions beyond the lox described in Crafting Interpreters that this build understands
+pub const EXTENSIONS: &[&str] = &[
+    "multi-line-strings",
+    "radix-number-literals",
+    "exponent-number-literals",
+    "digit-separators",
+    "number-dot-access",
+    "configurable-keywords",
+    "dialects",
+    "custom-literals",
+    "custom-statements",
+    "localized-diagnostics",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features {
+    version: &'static str,
+    backend: &'static str,
+    extensions: &'static [&'static str],
+}
+impl Features {
+    /// The crate version, e.g. `0.1.0`
+    pub const fn version(&self) -> &'static str {
+        self.version
+    }
+    /// The execution backend, `tree-walk` for the
+    /// [crate::abstract_syntax_tree_visitor_pattern::interpreter::Interpreter]
+    pub const fn backend(&self) -> &'static str {
+        self.backend
+    }
+    pub const fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+    pub fn has_extension(&self, extension: &str) -> bool {
+        self.extensions.contains(&extension)
+    }
+}
+
+/// What this build supports
+pub const fn features() -> Features {
+    Features {
+        version: env!("CARGO_PKG_VERSION"),
+        backend: "tree-walk",
+        extensions: EXTENSIONS,
+    }
+}
+
+#[test]
+fn crate_features() {
+    let features = features();
+
+    assert_eq!(features.version(), env!("CARGO_PKG_VERSION"));
+    assert_eq!(features.backend(), "tree-walk");
+    assert!(features.has_extension("radix-number-literals"));
+    assert!(!features.has_extension("classes"));
+}
@@ -0,0 +1,285 @@
+//! Prints lox source back in one canonical layout: one statement per line, blocks indented, comments kept.
+//! Blank lines between statements are kept, but runs of them become one
+
+use crate::{
+    abstract_syntax_tree::Statement,
+    lexer::{Lexer, LexerConfig},
+    parser::{ParseError, Parser},
+    token::{Span, TokenKind},
+};
+use alloc::{format, string::String, vec::Vec};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatterConfig {
+    indent_width: usize,
+}
+impl Default for FormatterConfig {
+    fn default() -> Self {
+        Self { indent_width: 4 }
+    }
+}
+impl FormatterConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// How many spaces each block level is indented by
+    pub fn indent_width(self, indent_width: usize) -> Self {
+        Self { indent_width }
+    }
+    pub const fn get_indent_width(&self) -> usize {
+        self.indent_width
+    }
+}
+
+/// Formats `source` with the default [FormatterConfig]
+pub fn format(source: &str) -> Result<String, Vec<ParseError<'_>>> {
+    format_with(source, FormatterConfig::new())
+}
+
+/// Formats `source`. The output always ends with a newline unless it is empty
+/// # Error
+/// When `source` does not parse
+pub fn format_with(source: &str, config: FormatterConfig) -> Result<String, Vec<ParseError<'_>>> {
+    let program = Parser::from(Lexer::new(source)).parse()?;
+
+    let comments = Lexer::with_config(source, LexerConfig::new().preserve_trivia(true))
+        .filter_map(Result::ok)
+        .filter(|token| token.kind() == TokenKind::Comment)
+        .map(|token| token.span())
+        .collect();
+
+    let mut formatter = Formatter {
+        source,
+        config,
+        comments,
+        next_comment: 0,
+        output: String::new(),
+        previous_end: None,
+    };
+
+    for statement in program.statements() {
+        formatter.write_statement_line(statement, 0);
+    }
+    formatter.write_comments_before(source.len(), 0);
+
+    Ok(formatter.output)
+}
+
+struct Formatter<'s> {
+    source: &'s str,
+    config: FormatterConfig,
+    /// spans of every comment, in source order
+    comments: Vec<Span>,
+    /// index in `comments` of the first one not written yet
+    next_comment: usize,
+    output: String,
+    /// where the last statement or comment written ended in `source`. [None] at the start of a block
+    previous_end: Option<usize>,
+}
+impl Formatter<'_> {
+    fn indent(&self, depth: usize) -> String {
+        " ".repeat(depth * self.config.indent_width)
+    }
+
+    /// Writes a blank line if the source has one between the previous item and `start`
+    fn write_blank_line_before(&mut self, start: usize) {
+        let gap = self
+            .previous_end
+            .and_then(|previous_end| self.source.get(previous_end..start));
+
+        // only whitespace separates items, so two line breaks mean a blank line
+        if gap.is_some_and(|gap| gap.matches('\n').count() > 1) {
+            self.output.push('\n');
+        }
+    }
+
+    /// Writes each comment that starts before `end` on its own line
+    fn write_comments_before(&mut self, end: usize, depth: usize) {
+        while let Some(&comment) = self.comments.get(self.next_comment) {
+            if comment.start >= end {
+                break;
+            }
+            self.next_comment += 1;
+
+            self.write_blank_line_before(comment.start);
+            let indent = self.indent(depth);
+            self.output.push_str(&indent);
+            self.output
+                .push_str(self.source[comment.range()].trim_end());
+            self.output.push('\n');
+            self.previous_end = Some(comment.end);
+        }
+    }
+
+    /// Writes a comment separated from `end` by nothing but spaces after a space, so it stays at the end of the line
+    fn write_trailing_comment(&mut self, end: usize) {
+        let Some(&comment) = self.comments.get(self.next_comment) else {
+            return;
+        };
+        let is_trailing = self
+            .source
+            .get(end..comment.start)
+            .is_some_and(|gap| gap.bytes().all(|byte| byte == b' ' || byte == b'\t'));
+        if !is_trailing {
+            return;
+        }
+        self.next_comment += 1;
+
+        self.output.push(' ');
+        self.output
+            .push_str(self.source[comment.range()].trim_end());
+        self.previous_end = Some(comment.end);
+    }
+
+    /// Writes `statement` on its own lines at `depth`, with the comments before and after it
+    fn write_statement_line(&mut self, statement: &Statement, depth: usize) {
+        let span = statement.span();
+
+        self.write_comments_before(span.start, depth);
+        self.write_blank_line_before(span.start);
+
+        let indent = self.indent(depth);
+        self.output.push_str(&indent);
+        self.write_statement(statement, depth);
+        self.previous_end = Some(span.end);
+        self.write_trailing_comment(span.end);
+        self.output.push('\n');
+    }
+
+    /// Writes `statement` starting at the current position. Lines inside it are indented relative to `depth`
+    fn write_statement(&mut self, statement: &Statement, depth: usize) {
+        match statement {
+            Statement::Expression { expression, .. } => {
+                self.output.push_str(&expression.to_source());
+                self.output.push(';');
+            }
+            Statement::Print { expression, .. } => {
+                self.output
+                    .push_str(&format!("print {};", expression.to_source()));
+            }
+            Statement::Var {
+                name, initializer, ..
+            } => match initializer {
+                Some(initializer) => self.output.push_str(&format!(
+                    "var {} = {};",
+                    name.lexeme(),
+                    initializer.to_source()
+                )),
+                None => self.output.push_str(&format!("var {};", name.lexeme())),
+            },
+            Statement::Block { statements, span } => {
+                self.write_block(statements, *span, depth);
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.output
+                    .push_str(&format!("if ({}) ", condition.to_source()));
+                self.write_statement(then_branch, depth);
+                if let Some(else_branch) = else_branch {
+                    self.output.push_str(" else ");
+                    self.write_statement(else_branch, depth);
+                }
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                self.output
+                    .push_str(&format!("while ({}) ", condition.to_source()));
+                self.write_statement(body, depth);
+            }
+            Statement::For {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                self.output.push_str("for (");
+                match initializer {
+                    Some(initializer) => self.write_statement(initializer, depth),
+                    None => self.output.push(';'),
+                }
+                if let Some(condition) = condition {
+                    self.output.push(' ');
+                    self.output.push_str(&condition.to_source());
+                }
+                self.output.push(';');
+                if let Some(increment) = increment {
+                    self.output.push(' ');
+                    self.output.push_str(&increment.to_source());
+                }
+                self.output.push_str(") ");
+                self.write_statement(body, depth);
+            }
+            Statement::Custom {
+                keyword,
+                expressions,
+                statements,
+                span,
+            } => {
+                self.output.push_str(keyword.lexeme());
+                let expressions = expressions
+                    .iter()
+                    .map(|expression| expression.to_source())
+                    .collect::<Vec<_>>();
+                if !expressions.is_empty() {
+                    self.output.push(' ');
+                    self.output.push_str(&expressions.join(", "));
+                }
+                if self.source[span.range()].trim_end().ends_with('}') {
+                    self.output.push(' ');
+                    self.write_block(statements, *span, depth);
+                } else {
+                    self.output.push(';');
+                }
+            }
+        }
+    }
+
+    /// Writes `{`, each statement indented one level deeper than `depth`, then `}`. `span` ends with the `}`
+    fn write_block(&mut self, statements: &[Statement], span: Span, depth: usize) {
+        let has_comments = self
+            .comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.start < span.end);
+        if statements.is_empty() && !has_comments {
+            self.output.push_str("{}");
+            return;
+        }
+
+        self.output.push('{');
+        self.previous_end = None;
+        self.write_trailing_comment(span.start + 1);
+        self.output.push('\n');
+
+        for statement in statements {
+            self.write_statement_line(statement, depth + 1);
+        }
+        self.write_comments_before(span.end, depth + 1);
+
+        let indent = self.indent(depth);
+        self.output.push_str(&indent);
+        self.output.push('}');
+    }
+}
+
+/// True when [format] would not change `source`. A source that does not parse is not formatted
+pub fn is_formatted(source: &str) -> bool {
+    format(source).is_ok_and(|formatted| formatted == source)
+}
+
+#[test]
+fn format_source() {
+    const SOURCE: &str = "// setup\nvar a=1;var b ;\n\n\n  // loop\nfor(var i=0;i<3;i=i+1){print i; // each\nif(a)print(a);else{a=-a;}}\n{ }\nwhile (a or b) { // spin\n}\nfor (;;) print 1; // done\n";
+    const EXPECTED: &str = "// setup\nvar a = 1;\nvar b;\n\n// loop\nfor (var i = 0; i < 3; i = i + 1) {\n  print i; // each\n  if (a) print (a); else {\n    a = -a;\n  }\n}\n{}\nwhile (a or b) { // spin\n}\nfor (;;) print 1; // done\n";
+
+    let formatted = format_with(SOURCE, FormatterConfig::new().indent_width(2)).unwrap();
+    assert_eq!(formatted, EXPECTED);
+    assert!(is_formatted(&format(EXPECTED).unwrap()));
+    assert!(!is_formatted("print 1 ;"));
+    assert!(format("print ;").is_err());
+}
@@ -0,0 +1,64 @@
+//! Entry points for fuzzers such as cargo-fuzz (see `fuzz/`). Each one returns normally for every input;
+//! a panic or stack overflow is a bug in the crate
+
+use crate::{
+    abstract_syntax_tree::program_to_canonical_string,
+    lexer::{Lexer, LexerConfig},
+    parser::Parser,
+};
+
+/// Lexes `data` with each [LexerConfig] dialect. Input that is not UTF-8 is skipped since [Lexer] takes a [str]
+pub fn lex_never_panics(data: &[u8]) {
+    let Ok(source) = core::str::from_utf8(data) else {
+        return;
+    };
+
+    for config in [
+        LexerConfig::new(),
+        LexerConfig::new().strict(true).preserve_trivia(true),
+    ] {
+        let (tokens, errors) = Lexer::with_config(source, config).lex_all();
+
+        for token in &tokens {
+            let _ = &source[token.span().range()];
+        }
+        for error in &errors {
+            let _ = (error.line_number(), error.column_number());
+        }
+    }
+}
+
+/// Parses `source` as a program and prints it
+pub fn parse_never_panics(source: &str) {
+    if let Ok(program) = Parser::from(Lexer::new(source)).parse() {
+        let _ = program_to_canonical_string(&program);
+    }
+}
+
+#[test]
+fn adversarial_inputs() {
+    use alloc::{format, string::String};
+
+    let deep = [
+        "(".repeat(10_000),
+        "-".repeat(10_000),
+        "//\n".repeat(10_000),
+        "1 + ".repeat(10_000),
+        "{".repeat(10_000),
+        "if (true) ".repeat(10_000),
+        "a = ".repeat(10_000),
+        "f(".repeat(10_000),
+        "a.b".repeat(10_000),
+        format!("print 1{};", " + 1".repeat(10_000)),
+        format!("print a{};", ".b()".repeat(10_000)),
+    ];
+
+    for source in deep.iter().map(String::as_str).chain([
+        "", "\"", "0x", "1e", "é@ü(", "1.", ")", "for (", "var", "(a) = 1;",
+    ]) {
+        lex_never_panics(source.as_bytes());
+        parse_never_panics(source);
+    }
+
+    lex_never_panics(&[0xff, 0xfe, b'(']);
+}
@@ -0,0 +1,335 @@
+//! Grading student submissions for `lox grade`. A [Spec] is a directory of test cases: `name.lox` is run before the
+//! submission, to give it its inputs as variables, and `name.out` is everything the two must print together.
+//! Each submission runs once per case in a new [Interpreter] with a step budget and a size limit, so a loop that never ends,
+//! or one that keeps growing a string or the output, fails that case instead of stopping the grader.
+//! Lox can only `print`, so a submission cannot touch files or the network
+
+use crate::{
+    abstract_syntax_tree_visitor_pattern::{
+        interpreter::{Interpreter, Value},
+        Statement,
+    },
+    diagnostics::{json_string, ToDiagnostic},
+    lexer::{Lexer, LexerConfig},
+    parser::Parser,
+};
+use std::{fmt::Write as _, fs, io, path::Path};
+
+/// How many statements a submission may run for each case when no other budget is given
+pub const DEFAULT_STEP_BUDGET: u64 = 1_000_000;
+/// How many bytes a string a submission makes, or its output for a case, may have. See [Interpreter::with_size_limit]
+pub const SIZE_LIMIT: usize = 1 << 20;
+
+/// One input a submission is run with and what it must print
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    name: String,
+    /// Lox run before the submission, usually `var` declarations of its inputs
+    input: String,
+    expected_output: String,
+}
+impl Case {
+    pub fn new(
+        name: impl Into<String>,
+        input: impl Into<String>,
+        expected_output: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            input: input.into(),
+            expected_output: expected_output.into(),
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The cases every submission is graded against, in order of name
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Spec {
+    cases: Vec<Case>,
+}
+impl Spec {
+    pub fn new(mut cases: Vec<Case>) -> Self {
+        cases.sort_by(|a, b| a.name.cmp(&b.name));
+        Self { cases }
+    }
+    /// A case for each `name.out` in `directory`, with the `name.lox` beside it as its input, or no input when there is none
+    pub fn load(directory: &Path) -> Result<Self, io::Error> {
+        let mut cases = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "out") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let input = match fs::read_to_string(path.with_extension("lox")) {
+                Ok(input) => input,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+                Err(error) => return Err(error),
+            };
+            cases.push(Case::new(name, input, fs::read_to_string(&path)?));
+        }
+        Ok(Self::new(cases))
+    }
+    pub fn cases(&self) -> &[Case] {
+        &self.cases
+    }
+}
+
+/// How a submission did on one [Case]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// it ran, but printed something else. Holds where the output first differs, from [Value::diff]
+    WrongOutput(String),
+    /// the case's input or the submission did not lex or parse, or stopped with a runtime error, such as `R0010`
+    /// when it ran out of steps. Holds the error's code
+    Error(&'static str),
+}
+impl Outcome {
+    pub const fn is_passed(&self) -> bool {
+        matches!(self, Outcome::Passed)
+    }
+    /// `pass`, `fail`, or the error code, as the reports write it
+    pub fn summary(&self) -> &str {
+        match self {
+            Outcome::Passed => "pass",
+            Outcome::WrongOutput(_) => "fail",
+            Outcome::Error(code) => code,
+        }
+    }
+}
+
+/// How one submission did on every case of a [Spec]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grade {
+    submission: String,
+    /// the name of each case and how the submission did on it, in the order of [Spec::cases]
+    outcomes: Vec<(String, Outcome)>,
+}
+impl Grade {
+    pub fn submission(&self) -> &str {
+        &self.submission
+    }
+    pub fn outcomes(&self) -> &[(String, Outcome)] {
+        &self.outcomes
+    }
+    pub fn passed(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| outcome.is_passed())
+            .count()
+    }
+    /// The fraction of cases passed, or 1 when the spec has none
+    pub fn score(&self) -> f64 {
+        match self.outcomes.len() {
+            0 => 1.0,
+            total => self.passed() as f64 / total as f64,
+        }
+    }
+}
+
+/// Runs the `source` of the submission called `submission` against each case of `spec`, allowing it `step_budget` statements
+/// and [SIZE_LIMIT] bytes per string and of output per case
+pub fn grade(
+    spec: &Spec,
+    submission: &str,
+    source: &str,
+    step_budget: u64,
+    lexer_config: &LexerConfig,
+) -> Grade {
+    let outcomes = spec
+        .cases
+        .iter()
+        .map(|case| {
+            let interpreter = Interpreter::new()
+                .with_step_budget(step_budget)
+                .with_size_limit(SIZE_LIMIT);
+            let outcome = match run(&case.input, &interpreter, lexer_config)
+                .and_then(|()| run(source, &interpreter, lexer_config))
+            {
+                Err(code) => Outcome::Error(code),
+                Ok(()) => {
+                    let expected = Value::String(case.expected_output.clone());
+                    let diff = expected.diff(&Value::String(interpreter.take_output()));
+                    if diff.is_equal() {
+                        Outcome::Passed
+                    } else {
+                        Outcome::WrongOutput(diff.to_string())
+                    }
+                }
+            };
+            (case.name.clone(), outcome)
+        })
+        .collect();
+
+    Grade {
+        submission: submission.to_owned(),
+        outcomes,
+    }
+}
+
+/// Runs `source` with `interpreter`. The code of the first error when it does not parse or stops with one
+fn run(
+    source: &str,
+    interpreter: &Interpreter,
+    lexer_config: &LexerConfig,
+) -> Result<(), &'static str> {
+    let program = Parser::from(Lexer::with_config(source, lexer_config.clone()))
+        .parse()
+        .map_err(|errors| errors.first().map_or("P0001", ToDiagnostic::code))?;
+
+    let (statements, interner) = program.into_parts();
+    interpreter
+        .with_symbols_of(&interner, || {
+            statements
+                .into_iter()
+                .map(Statement::from)
+                .try_for_each(|statement| interpreter.execute(&statement))
+        })
+        .map_err(|error| error.code())
+}
+
+/// `grades` as CSV with a header row: the submission, cases passed, cases, score, then a column per case
+/// holding its [Outcome::summary]. All of `grades` must be for the same [Spec]
+pub fn to_csv(grades: &[Grade]) -> String {
+    let mut csv = String::from("submission,passed,total,score");
+    for (case, _) in grades.first().map_or(&[][..], |grade| grade.outcomes()) {
+        let _ = write!(csv, ",{}", csv_field(case));
+    }
+    csv.push('\n');
+
+    for grade in grades {
+        let _ = write!(
+            csv,
+            "{},{},{},{:.2}",
+            csv_field(&grade.submission),
+            grade.passed(),
+            grade.outcomes.len(),
+            grade.score()
+        );
+        for (_, outcome) in &grade.outcomes {
+            let _ = write!(csv, ",{}", outcome.summary());
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// `grades` as a JSON array with an object per submission, holding its name, cases passed, cases, score, and each case's
+/// name, [Outcome::summary], and for wrong output where it differs. For example
+/// `[{"submission":"ada.lox","passed":1,"total":1,"score":1,"cases":[{"case":"small","outcome":"pass"}]}]`
+pub fn to_json(grades: &[Grade]) -> String {
+    let objects = grades
+        .iter()
+        .map(|grade| {
+            let cases = grade
+                .outcomes
+                .iter()
+                .map(|(case, outcome)| {
+                    let difference = match outcome {
+                        Outcome::WrongOutput(difference) => {
+                            format!(",\"difference\":{}", json_string(difference))
+                        }
+                        _ => String::new(),
+                    };
+                    format!(
+                        "{{\"case\":{},\"outcome\":{}{}}}",
+                        json_string(case),
+                        json_string(outcome.summary()),
+                        difference
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!(
+                "{{\"submission\":{},\"passed\":{},\"total\":{},\"score\":{},\"cases\":[{}]}}",
+                json_string(&grade.submission),
+                grade.passed(),
+                grade.outcomes.len(),
+                grade.score(),
+                cases.join(",")
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]\n", objects.join(","))
+}
+
+/// `field` quoted when it holds a comma, quote, or line break, with its quotes doubled
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[test]
+fn grade_submissions() {
+    let spec = Spec::new(Vec::from([
+        Case::new("small", "var n = 2;", "4\n"),
+        Case::new("big", "var n = 10;", "100\n"),
+    ]));
+    assert_eq!(spec.cases()[0].name(), "big");
+
+    let grade_source =
+        |name: &str, source: &str| grade(&spec, name, source, 100, &LexerConfig::new());
+    let correct = grade_source("ada.lox", "print n * n;");
+    let looping = grade_source(
+        "bob, jr.lox",
+        "var i = 0; while (i < n) { if (n > 5) i = i - 1; else i = i + 1; } print i * i;",
+    );
+    let broken = grade_source("cy.lox", "print n n;");
+    let growing = grade_source("ed.lox", "var s = \"x\"; while (true) s = s + s;");
+    let flooding = grade_source(
+        "fay.lox",
+        "var s = \"x\"; for (var i = 0; i < 20; i = i + 1) s = s + s; print s; print s;",
+    );
+    let wrong = grade_source("di.lox", "print n + n;");
+
+    assert_eq!(correct.passed(), 2);
+    assert_eq!(correct.score(), 1.0);
+    assert_eq!(
+        looping.outcomes(),
+        [
+            ("big".to_owned(), Outcome::Error("R0010")),
+            ("small".to_owned(), Outcome::Passed),
+        ]
+    );
+    assert_eq!(broken.outcomes()[0].1, Outcome::Error("P0005"));
+    assert_eq!(
+        wrong.outcomes()[0].1,
+        Outcome::WrongOutput("strings differ at character 0: expected '1', found '2'".to_owned())
+    );
+    assert_eq!(wrong.outcomes()[1].1, Outcome::Passed);
+    // doubling a string fails on the size limit long before the step budget
+    assert_eq!(
+        growing.outcomes(),
+        [
+            ("big".to_owned(), Outcome::Error("R0011")),
+            ("small".to_owned(), Outcome::Error("R0011")),
+        ]
+    );
+    // a string at the limit can be made, but not printed twice
+    assert_eq!(flooding.outcomes()[0].1, Outcome::Error("R0011"));
+
+    let grades = [correct, looping, broken, wrong];
+    assert_eq!(
+        to_csv(&grades),
+        "submission,passed,total,score,big,small
+ada.lox,2,2,1.00,pass,pass
+\"bob, jr.lox\",1,2,0.50,R0010,pass
+cy.lox,0,2,0.00,P0005,P0005
+di.lox,1,2,0.50,fail,pass
+"
+    );
+    assert_eq!(
+        to_json(&grades[3..]),
+        "[{\"submission\":\"di.lox\",\"passed\":1,\"total\":2,\"score\":0.5,\"cases\":[\
+{\"case\":\"big\",\"outcome\":\"fail\",\"difference\":\"strings differ at character 0: expected '1', found '2'\"},\
+{\"case\":\"small\",\"outcome\":\"pass\"}]}]\n"
+    );
+}
@@ -0,0 +1,140 @@
+//! Sorts each piece of source into a [TokenClass] using the lexer, so the prompt and editor integrations
+//! color code the same way
+
+use crate::{
+    lexer::{Lexer, LexerConfig},
+    token::{Span, TokenKind},
+};
+use alloc::{string::String, vec::Vec};
+
+/// What a piece of source is, as far as coloring it goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClass {
+    /// including `true`, `false`, `nil`, and host-defined statement keywords
+    Keyword,
+    Identifier,
+    String,
+    Number,
+    /// a host-defined literal
+    CustomLiteral,
+    Comment,
+    Operator,
+    /// parentheses, braces, commas, dots, and semicolons
+    Punctuation,
+    Whitespace,
+    /// text the lexer rejected
+    Invalid,
+}
+impl TokenClass {
+    pub const fn of(kind: TokenKind) -> Self {
+        match kind {
+            TokenKind::Identifier => TokenClass::Identifier,
+            TokenKind::String => TokenClass::String,
+            TokenKind::Number => TokenClass::Number,
+            TokenKind::Custom(_) => TokenClass::CustomLiteral,
+            TokenKind::Comment => TokenClass::Comment,
+            TokenKind::Whitespace | TokenKind::EndOfFile => TokenClass::Whitespace,
+            TokenKind::Unrecognized => TokenClass::Invalid,
+            TokenKind::LeftParentheses
+            | TokenKind::RightParentheses
+            | TokenKind::LeftBrace
+            | TokenKind::RightBrace
+            | TokenKind::Comma
+            | TokenKind::Dot
+            | TokenKind::Semicolon => TokenClass::Punctuation,
+            TokenKind::Minus
+            | TokenKind::Plus
+            | TokenKind::Slash
+            | TokenKind::Star
+            | TokenKind::Bang
+            | TokenKind::BangEqual
+            | TokenKind::Equal
+            | TokenKind::EqualEqual
+            | TokenKind::Greater
+            | TokenKind::GreaterEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual => TokenClass::Operator,
+            _ => TokenClass::Keyword,
+        }
+    }
+    /// The escape code [highlight_ansi] starts this class with. [None] for plain text
+    pub const fn ansi_color(&self) -> Option<&'static str> {
+        match self {
+            TokenClass::Keyword => Some("\x1b[35m"),
+            TokenClass::String => Some("\x1b[32m"),
+            TokenClass::Number | TokenClass::CustomLiteral => Some("\x1b[36m"),
+            TokenClass::Comment => Some("\x1b[90m"),
+            TokenClass::Invalid => Some("\x1b[31m"),
+            TokenClass::Identifier
+            | TokenClass::Operator
+            | TokenClass::Punctuation
+            | TokenClass::Whitespace => None,
+        }
+    }
+}
+
+/// Every token of `source`, comments and whitespace included, with its class, in order.
+/// The spans cover the whole source. Lexer errors are [TokenClass::Invalid]
+pub fn classify(source: &str, config: LexerConfig) -> Vec<(Span, TokenClass)> {
+    Lexer::with_config(source, config.preserve_trivia(true))
+        .filter_map(|possible_token| match possible_token {
+            Ok(token) if token.kind().is_end_of_file() => None,
+            Ok(token) => Some((token.span(), TokenClass::of(token.kind()))),
+            Err(error) => Some((error.span(), TokenClass::Invalid)),
+        })
+        .collect()
+}
+
+/// `source` with each token colored by its [TokenClass::ansi_color], for a terminal
+pub fn highlight_ansi(source: &str, config: LexerConfig) -> String {
+    const RESET: &str = "\x1b[0m";
+
+    let mut output = String::with_capacity(source.len());
+    let mut end = 0;
+    for (span, class) in classify(source, config) {
+        // anything the lexer skipped is copied as is
+        output.push_str(&source[end..span.start.max(end)]);
+        let text = &source[span.start.max(end)..span.end.max(end)];
+        match class.ansi_color() {
+            Some(color) => {
+                output.push_str(color);
+                output.push_str(text);
+                output.push_str(RESET);
+            }
+            None => output.push_str(text),
+        }
+        end = span.end.max(end);
+    }
+    output.push_str(&source[end..]);
+
+    output
+}
+
+#[test]
+fn highlight_source() {
+    let source = "var a = \"s\" + 1; // note\n@";
+    let classes = classify(source, LexerConfig::new())
+        .into_iter()
+        .filter(|(_, class)| *class != TokenClass::Whitespace)
+        .map(|(span, class)| (&source[span.range()], class))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        classes,
+        [
+            ("var", TokenClass::Keyword),
+            ("a", TokenClass::Identifier),
+            ("=", TokenClass::Operator),
+            ("\"s\"", TokenClass::String),
+            ("+", TokenClass::Operator),
+            ("1", TokenClass::Number),
+            (";", TokenClass::Punctuation),
+            ("// note", TokenClass::Comment),
+            ("@", TokenClass::Invalid),
+        ]
+    );
+
+    assert_eq!(
+        highlight_ansi("nil; 2", LexerConfig::new()),
+        "\x1b[35mnil\x1b[0m; \x1b[36m2\x1b[0m"
+    );
+}
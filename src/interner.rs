@@ -0,0 +1,129 @@
+//! Deduplicates identifier and keyword lexemes so they can be referred to and compared by a small [Symbol]
+
+use alloc::{borrow::ToOwned, collections::BTreeMap, string::String, vec::Vec};
+
+/// A handle to a string in an [Interner]. Equal symbols from the same interner mean equal strings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+impl Symbol {
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Interner<'a> {
+    symbols: BTreeMap<&'a str, Symbol>,
+    strings: Vec<&'a str>,
+}
+impl<'a> Interner<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the symbol for `string`, adding it if it has not been interned before
+    pub fn intern(&mut self, string: &'a str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(
+            self.strings
+                .len()
+                .try_into()
+                .expect("fewer than u32::MAX distinct strings are interned"),
+        );
+        self.strings.push(string);
+        self.symbols.insert(string, symbol);
+
+        symbol
+    }
+    /// Returns the symbol for `string` only if it has already been interned
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(string).copied()
+    }
+    /// Returns the string `symbol` was created from. [None] if it belongs to a different interner
+    pub fn resolve(&self, symbol: Symbol) -> Option<&'a str> {
+        self.strings.get(symbol.index()).copied()
+    }
+    /// Every interned string, in the order of their symbols' [Symbol::index]
+    pub fn strings(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.strings.iter().copied()
+    }
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// An [Interner] that keeps its own copy of each string, for names that outlive the source they were read from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OwnedInterner {
+    symbols: BTreeMap<String, Symbol>,
+    strings: Vec<String>,
+}
+impl OwnedInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the symbol for `string`, adding a copy of it if it has not been interned before
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(
+            self.strings
+                .len()
+                .try_into()
+                .expect("fewer than u32::MAX distinct strings are interned"),
+        );
+        self.strings.push(string.to_owned());
+        self.symbols.insert(string.to_owned(), symbol);
+
+        symbol
+    }
+    /// Returns the symbol for `string` only if it has already been interned
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(string).copied()
+    }
+    /// Returns the string `symbol` was created from. [None] if it belongs to a different interner
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.strings.get(symbol.index()).map(String::as_str)
+    }
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[test]
+fn interning() {
+    use alloc::string::String;
+
+    let source = String::from("counter counter other");
+    let mut interner = Interner::new();
+
+    let words = source
+        .split(' ')
+        .map(|word| interner.intern(word))
+        .collect::<Vec<_>>();
+
+    assert_eq!(words[0], words[1]);
+    assert_ne!(words[0], words[2]);
+    assert_eq!(interner.len(), 2);
+    assert_eq!(interner.resolve(words[2]), Some("other"));
+    assert_eq!(interner.get("counter"), Some(words[0]));
+    assert_eq!(interner.get("missing"), None);
+    assert_eq!(interner.strings().collect::<Vec<_>>(), ["counter", "other"]);
+
+    let mut owned = OwnedInterner::new();
+    let symbol = owned.intern(&source[..7]);
+    drop(source);
+    assert_eq!(owned.intern("counter"), symbol);
+    assert_eq!(owned.resolve(symbol), Some("counter"));
+    assert_eq!(owned.get("other"), None);
+}
@@ -1,16 +1,54 @@
-use std::fmt::Display;
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+use core::{fmt::Display, ops::Range};
 
-use super::token::{Token, TokenKind};
+use super::{
+    dialect::Dialect,
+    excerpt::{SourceExcerpt, WithSource},
+    interner::Interner,
+    messages,
+    token::{KeywordTable, Span, Token, TokenKind},
+};
+
+/// Where a [crate::parser::Parser] gets its tokens. [Lexer] is the built-in backend; another lexer implements this to be parsed the same way.
+/// Must yield a [TokenKind::EndOfFile] token last, and may yield trivia, which the parser skips
+pub trait TokenSource<'a>: Iterator<Item = Result<Token<'a>, LexerError<'a>>> {
+    /// The interner the [Token::symbol]s of the yielded tokens came from. Called once the parser is done
+    fn into_interner(self) -> Interner<'a>;
+    /// The warnings about the tokens yielded so far. None by default
+    fn warnings(&self) -> &[LexerWarning<'a>] {
+        &[]
+    }
+}
+impl<'a> TokenSource<'a> for Lexer<'a> {
+    fn into_interner(self) -> Interner<'a> {
+        Lexer::into_interner(self)
+    }
+    fn warnings(&self) -> &[LexerWarning<'a>] {
+        Lexer::warnings(self)
+    }
+}
 
 /// Lazily split lox source code into tokens.
-/// When used as an [Iterator]: [None] represents a [TokenKind::EndOfFile]
+/// When used as an [Iterator]: [None] represents a [TokenKind::EndOfFile].
+/// After an error, lexing resumes right after the erroneous lexeme, so iterating to the end reports every error
+#[derive(Debug, Clone, PartialEq)]
 pub struct Lexer<'a> {
     source: &'a str,
     lexeme_start: usize,
     /// index of the byte currently being processed. one after the last byte in the current lexeme
     lexeme_end: usize,
+    /// line of the byte at `lexeme_end`
     line_number: usize,
+    /// index of the first byte of the line containing `lexeme_end`
+    line_start: usize,
+    /// `line_number` and `line_start` as they were at `lexeme_start`
+    lexeme_line_number: usize,
+    lexeme_line_start: usize,
     end_of_file_emitted: bool,
+    config: LexerConfig,
+    /// holds the lexemes of every identifier and keyword token
+    interner: Interner<'a>,
+    warnings: Vec<LexerWarning<'a>>,
 }
 impl<'a> Iterator for Lexer<'a> {
     type Item = Result<Token<'a>, LexerError<'a>>;
@@ -26,23 +64,77 @@ impl<'a> Iterator for Lexer<'a> {
     }
 }
 impl<'a> Lexer<'a> {
-    pub const fn new(source: &'a str) -> Self {
+    pub fn new(source: &'a str) -> Self {
+        Self::with_config(source, LexerConfig::new())
+    }
+    pub fn with_config(source: &'a str, config: LexerConfig) -> Self {
         Self {
             source,
             lexeme_start: 0,
             lexeme_end: 0,
             end_of_file_emitted: false,
             line_number: 1,
+            line_start: 0,
+            lexeme_line_number: 1,
+            lexeme_line_start: 0,
+            config,
+            interner: Interner::new(),
+            warnings: Vec::new(),
         }
     }
 
+    /// The interner that the [Token::symbol]s of this lexer's tokens belong to
+    pub const fn interner(&self) -> &Interner<'a> {
+        &self.interner
+    }
+    pub fn into_interner(self) -> Interner<'a> {
+        self.interner
+    }
+    /// Warnings about the tokens lexed so far. Only [LexerConfig::warn_on_precision_loss] produces any
+    pub fn warnings(&self) -> &[LexerWarning<'a>] {
+        &self.warnings
+    }
+
+    /// Lexes the whole source, collecting tokens (ending with [TokenKind::EndOfFile]) and errors separately
+    pub fn lex_all(self) -> (Vec<Token<'a>>, Vec<LexerError<'a>>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+
+        for possible_token in self {
+            match possible_token {
+                Ok(token) => tokens.push(token),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        (tokens, errors)
+    }
+
     pub fn next_token(&mut self) -> Result<Token<'a>, LexerError<'a>> {
+        // skipped trivia loops here rather than recursing so long runs of comments and blank lines cannot overflow the stack
+        loop {
+            if let Some(token) = self.next_token_or_trivia()? {
+                return Ok(token);
+            }
+        }
+    }
+    /// Lexes one lexeme. [None] when it was trivia that [LexerConfig::preserve_trivia] says to skip
+    fn next_token_or_trivia(&mut self) -> Result<Option<Token<'a>>, LexerError<'a>> {
         if !self.current_byte_available() {
             self.end_of_file_emitted = true;
-            return Ok(Token::end_of_file(self.line_number));
+            let end_of_file = Span::new(self.source.len(), self.source.len());
+            return Ok(Some(
+                Token::end_of_file(self.line_number).with_span(end_of_file),
+            ));
         }
 
         self.lexeme_start = self.lexeme_end;
+        self.lexeme_line_number = self.line_number;
+        self.lexeme_line_start = self.line_start;
+
+        if let Some(token) = self.consume_custom_literal()? {
+            return Ok(Some(token));
+        }
 
         let previous_byte = self.get_current_byte();
 
@@ -81,7 +173,11 @@ impl<'a> Lexer<'a> {
             b'>' => self.get_current_token(TokenKind::Greater),
             b'/' if self.current_byte_available() && self.get_current_byte() == b'/' => {
                 self.consume_comment_line();
-                self.next_token()?
+                if self.config.preserves_trivia() {
+                    self.get_current_token(TokenKind::Comment)
+                } else {
+                    return Ok(None);
+                }
             }
             b'/' => self.get_current_token(TokenKind::Slash),
             b'"' => {
@@ -90,23 +186,42 @@ impl<'a> Lexer<'a> {
                 // ignore start and end '"'
                 let string_literal_lexeme =
                     &self.source[self.lexeme_start + 1..self.lexeme_end - 1];
-                Token::new(TokenKind::String, string_literal_lexeme, self.line_number)
+                Token::new(
+                    TokenKind::String,
+                    string_literal_lexeme,
+                    self.lexeme_line_number,
+                )
+                .with_span(self.get_current_span())
             }
             number if number.is_ascii_digit() => {
-                self.consume_number_literal()?;
-                self.get_current_token(TokenKind::Number)
+                let number_value = self.consume_number_literal(number)?;
+                let token = self
+                    .get_current_token(TokenKind::Number)
+                    .with_number_value(number_value);
+                if self.config.warns_on_precision_loss()
+                    && loses_precision(token.lexeme(), number_value)
+                {
+                    let warning = self.warning(token, LexerWarningKind::PrecisionLoss);
+                    self.warnings.push(warning);
+                }
+                token
             }
             alpha if alpha.is_ascii_alphabetic() || alpha == b'_' => {
                 self.consume_identifier();
-                let token_kind = TokenKind::parse_keyword(self.get_current_lexeme());
-                self.get_current_token(token_kind)
+                let token_kind = self
+                    .config
+                    .keyword_table()
+                    .parse_keyword(self.get_current_lexeme());
+                let symbol = self.interner.intern(self.get_current_lexeme());
+                self.get_current_token(token_kind).with_symbol(symbol)
             }
             whitespace if whitespace.is_ascii_whitespace() => {
-                if whitespace == b'\n' {
-                    self.line_number += 1;
-                }
                 self.consume_whitespace();
-                self.next_token()?
+                if self.config.preserves_trivia() {
+                    self.get_current_token(TokenKind::Whitespace)
+                } else {
+                    return Ok(None);
+                }
             }
             _ => {
                 self.consume_unrecognized_lexeme();
@@ -115,11 +230,16 @@ impl<'a> Lexer<'a> {
             }
         };
 
-        Ok(token)
+        Ok(Some(token))
     }
 
-    /// Increments `self.lexeme_end` making the current lexeme one byte larger
+    /// Increments `self.lexeme_end` making the current lexeme one byte larger.
+    /// Consuming a `'\n'` moves `self.line_number` and `self.line_start` to the next line
     fn consume_current_byte(&mut self) {
+        if self.get_current_byte() == b'\n' {
+            self.line_number += 1;
+            self.line_start = self.lexeme_end + 1;
+        }
         self.lexeme_end += 1;
     }
 
@@ -146,12 +266,59 @@ impl<'a> Lexer<'a> {
         &self.source[self.lexeme_start..self.lexeme_end]
     }
 
-    /// Creates a new [Token] using [Self::get_current_lexeme] for the lexeme and the given [TokenKind]
+    /// Returns the span of the current lexeme `self.lexeme_start..self.lexeme_end`
+    fn get_current_span(&self) -> Span {
+        Span::new(self.lexeme_start, self.lexeme_end)
+    }
+
+    /// Creates a new [Token] using [Self::get_current_lexeme] for the lexeme and the given [TokenKind].
+    /// The token's line is the line the lexeme starts on.
+    /// When the lexeme is spelled exactly like [TokenKind::canonical_lexeme] that `&'static str` is used instead of a slice of the source
     fn get_current_token(&self, kind: TokenKind) -> Token<'a> {
-        Token::new(kind, self.get_current_lexeme(), self.line_number)
+        let current_lexeme = self.get_current_lexeme();
+        let lexeme = kind
+            .canonical_lexeme()
+            .filter(|canonical_lexeme| *canonical_lexeme == current_lexeme)
+            .unwrap_or(current_lexeme);
+
+        Token::new(kind, lexeme, self.lexeme_line_number).with_span(self.get_current_span())
     }
 
-    /// Makes the current lexeme include all bytes up to and including the first `'\n'`. Only call after `"//"` is found
+    /// Makes the current lexeme include the [CustomLiteral] with the longest prefix at the current byte, if any, and returns its token
+    /// # Error
+    /// When the terminator never appears after the prefix
+    fn consume_custom_literal(&mut self) -> Result<Option<Token<'a>>, LexerError<'a>> {
+        let rest = &self.source[self.lexeme_end..];
+
+        let Some((kind, length)) = self
+            .config
+            .custom_literals()
+            .iter()
+            .filter(|literal| rest.starts_with(literal.prefix()))
+            .max_by_key(|literal| literal.prefix().len())
+            .map(|literal| {
+                let body = &rest[literal.prefix().len()..];
+                let length = body
+                    .find(literal.terminator())
+                    .map(|index| literal.prefix().len() + index + literal.terminator().len());
+                (TokenKind::Custom(literal.id()), length)
+            })
+        else {
+            return Ok(None);
+        };
+
+        for _ in 0..length.unwrap_or(rest.len()) {
+            self.consume_current_byte();
+        }
+
+        let token = self.get_current_token(kind);
+
+        match length {
+            Some(_) => Ok(Some(token)),
+            None => Err(self.error(token, LexerErrorKind::UnterminatedCustomLiteral)),
+        }
+    }
+    /// Makes the current lexeme include all bytes up to but not including the first `'\n'`. Only call after `"//"` is found
     fn consume_comment_line(&mut self) {
         while self.current_byte_available() && self.get_current_byte() != b'\n' {
             self.consume_current_byte();
@@ -160,14 +327,12 @@ impl<'a> Lexer<'a> {
     /// Makes the current lexeme include all bytes up to the first non-ascii whitespace (see [u8::is_ascii_whitespace])
     fn consume_whitespace(&mut self) {
         while self.current_byte_available() && self.get_current_byte().is_ascii_whitespace() {
-            if self.get_current_byte() == b'\n' {
-                self.line_number += 1;
-            }
             self.consume_current_byte();
         }
     }
 
-    /// Makes the current lexeme include all bytes up to and including the closing `'"'`. Only call after an opening '"'
+    /// Makes the current lexeme include all bytes up to and including the closing `'"'`. Only call after an opening '"'.
+    /// The string may span multiple lines
     /// # Error
     /// When there is no closing `'"'`
     fn consume_string_literal(&mut self) -> Result<(), LexerError<'a>> {
@@ -184,32 +349,99 @@ impl<'a> Lexer<'a> {
         let token = self.get_current_token(TokenKind::String);
         Err(self.error(token, LexerErrorKind::UnterminatedStringLiteral))
     }
-    fn consume_number_literal(&mut self) -> Result<(), LexerError<'a>> {
-        // consume all digit bytes before the dot
-        while self.current_byte_available() && self.get_current_byte().is_ascii_digit() {
-            self.consume_current_byte();
-        }
+    /// Makes the current lexeme include the rest of a number literal and returns its value. Only call after the first digit.
+    /// Accepts decimal (`12.5`, `1e-3`), hexadecimal (`0x1F`), and binary (`0b1010`) forms, with `_` allowed between digits.
+    /// A `'.'` not followed by a digit is left for the next token so `123.sqrt()` lexes as a number, a dot, and an identifier
+    /// # Error
+    /// - When a `'.'` is not followed by a digit and [LexerConfig::strict] is set
+    /// - When an exponent or a radix prefix has no digits after it
+    fn consume_number_literal(&mut self, first_digit: u8) -> Result<f64, LexerError<'a>> {
+        if first_digit == b'0' && self.current_byte_available() {
+            let radix = match self.get_current_byte() {
+                b'x' | b'X' => Some(16),
+                b'b' | b'B' => Some(2),
+                _ => None,
+            };
 
-        if !self.current_byte_available() {
-            return Ok(());
+            if let Some(radix) = radix {
+                return self.consume_radix_digits(radix);
+            }
         }
 
-        if self.get_current_byte() == b'.' {
-            // there must be a number after the dot
-            if !self.next_byte_available() || !self.get_next_byte().is_ascii_digit() {
+        self.consume_digits(|byte| byte.is_ascii_digit());
+
+        if self.current_byte_available() && self.get_current_byte() == b'.' {
+            let is_fraction = self.next_byte_available() && self.get_next_byte().is_ascii_digit();
+
+            if is_fraction {
+                // consume the dot
+                self.consume_current_byte();
+                self.consume_digits(|byte| byte.is_ascii_digit());
+            } else if self.config.is_strict() {
                 let token = self.get_current_token(TokenKind::Number);
                 return Err(self.error(token, LexerErrorKind::NumberTrailingDot));
             }
+        }
 
-            // consume the dot
+        if self.current_byte_available() && matches!(self.get_current_byte(), b'e' | b'E') {
+            // consume the e
             self.consume_current_byte();
 
-            while self.current_byte_available() && self.get_current_byte().is_ascii_digit() {
+            if self.current_byte_available() && matches!(self.get_current_byte(), b'+' | b'-') {
                 self.consume_current_byte();
             }
+
+            if !self.current_byte_available() || !self.get_current_byte().is_ascii_digit() {
+                let token = self.get_current_token(TokenKind::Number);
+                return Err(self.error(token, LexerErrorKind::MissingExponentDigits));
+            }
+
+            self.consume_digits(|byte| byte.is_ascii_digit());
         }
 
-        Ok(())
+        let digits = self.get_current_lexeme().replace('_', "");
+        Ok(digits
+            .parse()
+            .expect("the lexeme is a valid float literal once underscores are removed"))
+    }
+    /// Makes the current lexeme include a radix prefix (`x` or `b`) and the digits after it. Only call after a leading `'0'`
+    /// # Error
+    /// When there are no digits after the prefix
+    fn consume_radix_digits(&mut self, radix: u32) -> Result<f64, LexerError<'a>> {
+        // consume the prefix
+        self.consume_current_byte();
+
+        let is_digit = |byte: u8| (byte as char).is_digit(radix);
+
+        if !self.current_byte_available() || !is_digit(self.get_current_byte()) {
+            let token = self.get_current_token(TokenKind::Number);
+            return Err(self.error(token, LexerErrorKind::MissingRadixDigits));
+        }
+
+        self.consume_digits(is_digit);
+
+        let value = self.get_current_lexeme()[2..]
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .fold(0.0, |value, digit| value * radix as f64 + digit as f64);
+
+        Ok(value)
+    }
+    /// Makes the current lexeme include all bytes matching `is_digit`, and any `'_'` that is followed by a digit
+    fn consume_digits(&mut self, is_digit: impl Fn(u8) -> bool) {
+        while self.current_byte_available() {
+            let current_byte = self.get_current_byte();
+
+            let is_separator = current_byte == b'_'
+                && self.next_byte_available()
+                && is_digit(self.get_next_byte());
+
+            if !is_digit(current_byte) && !is_separator {
+                break;
+            }
+
+            self.consume_current_byte();
+        }
     }
     fn consume_identifier(&mut self) {
         while self.current_byte_available()
@@ -218,14 +450,23 @@ impl<'a> Lexer<'a> {
             self.consume_current_byte();
         }
     }
+    /// Whether the current byte cannot start a token, so it continues a run of unrecognized bytes.
+    /// Every byte of a non-ascii character is unrecognized, so runs always end on a character boundary
     fn is_current_byte_unrecognized(&self) -> bool {
-        match self.get_current_byte() {
+        let starts_token = match self.get_current_byte() {
             b'(' | b')' | b'{' | b'}' | b',' | b'.' | b'-' | b'+' | b';' | b'*' | b'!' | b'='
             | b'<' | b'>' | b'/' | b'"' => true,
-            b if b.is_ascii_alphanumeric() || b.is_ascii_whitespace() || b == b'_' => false,
-            _ => true,
-        }
+            byte => byte.is_ascii_alphanumeric() || byte.is_ascii_whitespace() || byte == b'_',
+        };
+
+        let starts_custom_literal = self.config.custom_literals().iter().any(|literal| {
+            self.source.as_bytes()[self.lexeme_end..].starts_with(literal.prefix().as_bytes())
+        });
+
+        !starts_token && !starts_custom_literal
     }
+    /// Makes the current lexeme include the rest of a run of bytes that cannot start a token.
+    /// Only call after the first unrecognized byte is consumed
     fn consume_unrecognized_lexeme(&mut self) {
         while self.current_byte_available() && self.is_current_byte_unrecognized() {
             self.consume_current_byte();
@@ -233,28 +474,297 @@ impl<'a> Lexer<'a> {
     }
 }
 
-// Error helpers
+// Incremental relexing
 impl<'a> Lexer<'a> {
-    fn calculate_lexeme_position(&self) -> (usize, usize) {
-        use unicode_segmentation::UnicodeSegmentation;
+    /// Re-tokenizes a source after an edit, reusing `previous_tokens` (the tokens of the source before the edit) outside of the edited region.
+    /// `self` must be a lexer over the new source with the same config that produced `previous_tokens`.
+    /// The edit replaced the bytes in `edited_range` of the old source with `new_text`.
+    /// Relexing starts after the last token whose lexing read none of the edited bytes,
+    /// and goes on past the edit until a token starts where a previous token, shifted by the edit, started with the same kind.
+    ///
+    /// Returns every token of the new source and the errors found in the part that was relexed.
+    /// Errors outside of that part are unchanged from the previous lex
+    pub fn relex(
+        &mut self,
+        edited_range: Range<usize>,
+        new_text: &str,
+        previous_tokens: &[Token<'_>],
+    ) -> (Vec<Token<'a>>, Vec<LexerError<'a>>) {
+        let edited_end = edited_range.start + new_text.len();
+        let offset = new_text.len() as isize - edited_range.len() as isize;
 
-        let mut column_number = 1;
+        // tokens whose lexing read no byte of the edit lex the same way. See [LexerConfig::reach]
+        let kept_count = previous_tokens
+            .iter()
+            .take_while(|token| {
+                !token.is_end_of_file() && self.config.reach(token.span()) <= edited_range.start
+            })
+            .count();
 
-        for (i, _c) in self
-            .source
-            .lines()
-            .nth(self.line_number - 1)
-            .unwrap()
-            .grapheme_indices(true)
-        {
-            if i == self.lexeme_start {
-                break;
+        let restart = match kept_count.checked_sub(1) {
+            Some(last_kept) => previous_tokens[last_kept].span().end,
+            None => 0,
+        };
+        self.seek(restart);
+
+        let mut tokens = previous_tokens[..kept_count]
+            .iter()
+            .map(|token| self.moved_token(token, 0, 0))
+            .collect::<Vec<_>>();
+        let mut errors = Vec::new();
+        let mut previous_index = kept_count;
+
+        while let Some(possible_token) = self.next() {
+            let token = match possible_token {
+                Ok(token) => token,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            if token.span().start >= edited_end {
+                // a token starting at the same place in the unchanged text means the rest of the tokens are unchanged too
+                let previous_start = (token.span().start as isize - offset) as usize;
+                while previous_tokens
+                    .get(previous_index)
+                    .is_some_and(|previous| previous.span().start < previous_start)
+                {
+                    previous_index += 1;
+                }
+
+                if let Some(previous) = previous_tokens.get(previous_index).filter(|previous| {
+                    previous.span().start == previous_start && previous.kind() == token.kind()
+                }) {
+                    let line_offset =
+                        token.line_number() as isize - previous.line_number() as isize;
+                    for previous in &previous_tokens[previous_index..] {
+                        tokens.push(self.moved_token(previous, offset, line_offset));
+                    }
+                    return (tokens, errors);
+                }
             }
 
-            column_number += 1;
+            tokens.push(token);
+        }
+
+        (tokens, errors)
+    }
+
+    /// Moves the lexer to `position`, which must be where a token ends or starts
+    fn seek(&mut self, position: usize) {
+        let before = &self.source.as_bytes()[..position];
+
+        self.lexeme_start = position;
+        self.lexeme_end = position;
+        self.line_number = 1 + before.iter().filter(|byte| **byte == b'\n').count();
+        self.line_start = before
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        self.end_of_file_emitted = false;
+    }
+
+    /// Recreates `token` from an older version of the source, with its span moved by `offset` bytes and its line by `line_offset`.
+    /// Its symbol is re-interned in this lexer's interner
+    fn moved_token(&mut self, token: &Token<'_>, offset: isize, line_offset: isize) -> Token<'a> {
+        let span = Span::new(
+            (token.span().start as isize + offset) as usize,
+            (token.span().end as isize + offset) as usize,
+        );
+        let line_number = (token.line_number() as isize + line_offset) as usize;
+
+        let source = self.source;
+        let lexeme = match token.kind() {
+            TokenKind::EndOfFile => "",
+            // ignore start and end '"'
+            TokenKind::String => &source[span.start + 1..span.end - 1],
+            kind => kind
+                .canonical_lexeme()
+                .filter(|canonical_lexeme| *canonical_lexeme == token.lexeme())
+                .unwrap_or(&source[span.range()]),
+        };
+
+        let mut moved = Token::new(token.kind(), lexeme, line_number).with_span(span);
+        if let Some(number_value) = token.number_value() {
+            moved = moved.with_number_value(number_value);
+        }
+        if token.symbol().is_some() {
+            moved = moved.with_symbol(self.interner.intern(lexeme));
+        }
+        moved
+    }
+}
+
+/// Options that change how a [Lexer] splits source code
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LexerConfig {
+    strict: bool,
+    preserve_trivia: bool,
+    keywords: KeywordTable,
+    custom_literals: Vec<CustomLiteral>,
+    warn_on_precision_loss: bool,
+}
+impl LexerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// The strict dialect rejects number literals ending in `'.'` with [LexerErrorKind::NumberTrailingDot]
+    pub fn strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
+    }
+    /// Emit [TokenKind::Comment] and [TokenKind::Whitespace] tokens instead of skipping them, so the source can be reproduced exactly
+    pub fn preserve_trivia(self, preserve_trivia: bool) -> Self {
+        Self {
+            preserve_trivia,
+            ..self
+        }
+    }
+    /// Replaces the default [KeywordTable]
+    pub fn keywords(self, keywords: KeywordTable) -> Self {
+        Self { keywords, ..self }
+    }
+    /// Lexes `literal` as a [TokenKind::Custom] token. When several prefixes match, the longest wins
+    pub fn custom_literal(mut self, literal: CustomLiteral) -> Self {
+        self.custom_literals.push(literal);
+        self
+    }
+    /// Records a [LexerWarningKind::PrecisionLoss] in [Lexer::warnings] for each integer literal that an `f64` cannot hold exactly
+    pub fn warn_on_precision_loss(self, warn_on_precision_loss: bool) -> Self {
+        Self {
+            warn_on_precision_loss,
+            ..self
+        }
+    }
+    /// Uses the keywords and strictness of `dialect`
+    pub fn dialect(self, dialect: &Dialect) -> Self {
+        Self {
+            strict: dialect.is_strict(),
+            keywords: dialect.keyword_table().clone(),
+            ..self
+        }
+    }
+    pub const fn is_strict(&self) -> bool {
+        self.strict
+    }
+    pub const fn preserves_trivia(&self) -> bool {
+        self.preserve_trivia
+    }
+    pub const fn keyword_table(&self) -> &KeywordTable {
+        &self.keywords
+    }
+    pub fn custom_literals(&self) -> &[CustomLiteral] {
+        &self.custom_literals
+    }
+    pub const fn warns_on_precision_loss(&self) -> bool {
+        self.warn_on_precision_loss
+    }
+    /// One past the last byte the lexer may read to lex the token at `span`.
+    /// Built in tokens read at most two bytes past their end, as in `1.5` or `1e+5`,
+    /// and every [CustomLiteral] prefix is checked at the token's start
+    fn reach(&self, span: Span) -> usize {
+        let longest_prefix = self
+            .custom_literals
+            .iter()
+            .map(|literal| literal.prefix().len())
+            .max()
+            .unwrap_or(0);
+
+        (span.end + 2).max(span.start + longest_prefix)
+    }
+}
+
+/// Whether `value` differs from the integer that the number literal `lexeme` spells out.
+/// Literals with a fraction or an exponent are never reported, since most decimal fractions have no exact `f64` anyway
+fn loses_precision(lexeme: &str, value: f64) -> bool {
+    let digits = lexeme.replace('_', "");
+
+    if let Some(digits) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        return radix_loses_precision(digits, 16);
+    }
+    if let Some(digits) = digits.strip_prefix("0b").or(digits.strip_prefix("0B")) {
+        return radix_loses_precision(digits, 2);
+    }
+    if digits.contains(['.', 'e', 'E']) {
+        return false;
+    }
+
+    let digits = match digits.trim_start_matches('0') {
+        "" => "0",
+        digits => digits,
+    };
+
+    // `{:.0}` prints every digit of the rounded value, or `inf` when it overflowed
+    format!("{:.0}", value) != digits
+}
+/// Whether the hexadecimal or binary `digits` need more than the 53 significant bits of an `f64`, or overflow it
+fn radix_loses_precision(digits: &str, radix: u32) -> bool {
+    let bits_per_digit = radix.trailing_zeros();
+    let bits = digits
+        .chars()
+        .filter_map(|c| c.to_digit(radix))
+        .flat_map(|digit| {
+            (0..bits_per_digit)
+                .rev()
+                .map(move |bit| digit >> bit & 1 == 1)
+        })
+        .collect::<Vec<_>>();
+
+    let Some(highest) = bits.iter().position(|&bit| bit) else {
+        return false;
+    };
+    let lowest = bits.iter().rposition(|&bit| bit).unwrap_or(highest);
+
+    bits.len() - highest > f64::MAX_EXP as usize
+        || lowest - highest >= f64::MANTISSA_DIGITS as usize
+}
+
+/// A host-defined literal such as `#rgb(ff8000)` or `@date"2024-01-31"`: a prefix, any text, then a terminator.
+/// The whole literal, prefix and terminator included, is the lexeme of one [TokenKind::Custom] token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomLiteral {
+    id: u16,
+    prefix: String,
+    terminator: String,
+}
+impl CustomLiteral {
+    /// # Panics
+    /// When `prefix` is empty
+    pub fn new(id: u16, prefix: &str, terminator: &str) -> Self {
+        assert!(
+            !prefix.is_empty(),
+            "custom literal prefixes cannot be empty"
+        );
+        Self {
+            id,
+            prefix: prefix.to_owned(),
+            terminator: terminator.to_owned(),
         }
+    }
+    /// The `u16` in the [TokenKind::Custom] of tokens for this literal
+    pub const fn id(&self) -> u16 {
+        self.id
+    }
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+    pub fn terminator(&self) -> &str {
+        &self.terminator
+    }
+}
+
+// Error helpers
+impl<'a> Lexer<'a> {
+    /// Returns the line and column (counted in graphemes) of the first byte in the current lexeme
+    fn calculate_lexeme_position(&self) -> (usize, usize) {
+        use unicode_segmentation::UnicodeSegmentation;
 
-        (self.line_number, column_number)
+        let column_number = self.source[self.lexeme_line_start..self.lexeme_start]
+            .graphemes(true)
+            .count()
+            + 1;
+
+        (self.lexeme_line_number, column_number)
     }
     fn error(&mut self, token: Token<'a>, kind: LexerErrorKind) -> LexerError<'a> {
         let (line_number, column_number) = self.calculate_lexeme_position();
@@ -266,6 +776,16 @@ impl<'a> Lexer<'a> {
             column_number,
         }
     }
+    fn warning(&self, token: Token<'a>, kind: LexerWarningKind) -> LexerWarning<'a> {
+        let (line_number, column_number) = self.calculate_lexeme_position();
+
+        LexerWarning {
+            kind,
+            token,
+            line_number,
+            column_number,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -273,18 +793,30 @@ pub enum LexerErrorKind {
     Unrecognized,
     UnterminatedStringLiteral,
     NumberTrailingDot,
+    MissingExponentDigits,
+    MissingRadixDigits,
+    UnterminatedCustomLiteral,
 }
-impl Display for LexerErrorKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl LexerErrorKind {
+    /// Stable identifier used to look up this error's message in [crate::messages]
+    pub const fn code(&self) -> &'static str {
         match self {
-            LexerErrorKind::NumberTrailingDot => write!(f, "{:?}", self),
-            LexerErrorKind::UnterminatedStringLiteral => write!(f, "{:?}", self),
-            LexerErrorKind::Unrecognized => write!(f, "Unrecognized token"),
+            LexerErrorKind::UnterminatedStringLiteral => "L0001",
+            LexerErrorKind::Unrecognized => "L0002",
+            LexerErrorKind::NumberTrailingDot => "L0003",
+            LexerErrorKind::MissingExponentDigits => "L0004",
+            LexerErrorKind::MissingRadixDigits => "L0005",
+            LexerErrorKind::UnterminatedCustomLiteral => "L0006",
         }
     }
 }
+impl Display for LexerErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", messages::message(self.code()))
+    }
+}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LexerError<'a> {
     kind: LexerErrorKind,
     token: Token<'a>,
@@ -292,23 +824,457 @@ pub struct LexerError<'a> {
     column_number: usize,
 }
 impl<'a> LexerError<'a> {
+    pub const fn kind(&self) -> &LexerErrorKind {
+        &self.kind
+    }
     pub const fn line_number(&self) -> usize {
         self.line_number
     }
+    pub const fn column_number(&self) -> usize {
+        self.column_number
+    }
     pub const fn token(&self) -> Token<'a> {
         self.token
     }
+    pub const fn span(&self) -> Span {
+        self.token.span()
+    }
+    /// Displays the error followed by the line of `source` it is on, marking the erroneous lexeme
+    pub fn with_source<'s>(&'s self, source: &'s str) -> WithSource<'s, Self> {
+        WithSource::new(self, SourceExcerpt::new(source, self.span()))
+    }
 }
 impl Display for LexerError<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Error lexing {} at line {}, column {}: {}",
-            self.token.lexeme(),
-            self.line_number,
-            self.column_number,
-            self.kind,
-        )
-    }
-}
-impl std::error::Error for LexerError<'_> {}
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let output = messages::format_message(
+            messages::LEXER_ERROR_TEMPLATE,
+            &[
+                ("lexeme", &self.token.lexeme()),
+                ("line", &self.line_number),
+                ("column", &self.column_number),
+                ("message", &self.kind),
+            ],
+        );
+
+        write!(f, "{}", output)
+    }
+}
+impl core::error::Error for LexerError<'_> {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexerWarningKind {
+    PrecisionLoss,
+}
+impl LexerWarningKind {
+    /// Stable identifier used to look up this warning's message in [crate::messages]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            LexerWarningKind::PrecisionLoss => "W0001",
+        }
+    }
+}
+impl Display for LexerWarningKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", messages::message(self.code()))
+    }
+}
+
+/// Something that lexed fine but probably does not mean what it says. Collected in [Lexer::warnings]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexerWarning<'a> {
+    kind: LexerWarningKind,
+    token: Token<'a>,
+    line_number: usize,
+    column_number: usize,
+}
+impl<'a> LexerWarning<'a> {
+    pub const fn kind(&self) -> &LexerWarningKind {
+        &self.kind
+    }
+    pub const fn line_number(&self) -> usize {
+        self.line_number
+    }
+    pub const fn column_number(&self) -> usize {
+        self.column_number
+    }
+    pub const fn token(&self) -> Token<'a> {
+        self.token
+    }
+    pub const fn span(&self) -> Span {
+        self.token.span()
+    }
+    /// Displays the warning followed by the line of `source` it is on, marking the lexeme
+    pub fn with_source<'s>(&'s self, source: &'s str) -> WithSource<'s, Self> {
+        WithSource::new(self, SourceExcerpt::new(source, self.span()))
+    }
+}
+impl Display for LexerWarning<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let output = messages::format_message(
+            messages::LEXER_WARNING_TEMPLATE,
+            &[
+                ("lexeme", &self.token.lexeme()),
+                ("line", &self.line_number),
+                ("column", &self.column_number),
+                ("message", &self.kind),
+            ],
+        );
+
+        write!(f, "{}", output)
+    }
+}
+
+#[test]
+fn multi_line_string_positions() {
+    const SOURCE: &str = "print \"one\ntwo\nthree\";\n  identifier \"🍎\n";
+
+    let mut lexer = Lexer::new(SOURCE);
+
+    assert_eq!(lexer.next().unwrap().unwrap().line_number(), 1);
+    let string = lexer.next().unwrap().unwrap();
+    assert_eq!(string.lexeme(), "one\ntwo\nthree");
+    assert_eq!(string.line_number(), 1);
+    assert_eq!(lexer.next().unwrap().unwrap().line_number(), 3);
+    assert_eq!(lexer.next().unwrap().unwrap().line_number(), 4);
+
+    let error = lexer.next().unwrap().unwrap_err();
+    assert_eq!(error.line_number(), 4);
+    assert_eq!(error.column_number(), 14);
+
+    let end_of_file = lexer.next().unwrap().unwrap();
+    assert!(end_of_file.is_end_of_file());
+    assert_eq!(end_of_file.line_number(), 5);
+}
+
+#[test]
+fn number_literals() {
+    const SOURCE: &str = "12 3.25 0x1F 0b1010 1e-3 2.5E+2 1_000 0xff_ff 1_ 0x 1e";
+
+    let mut lexer = Lexer::new(SOURCE);
+    let mut next_number = || {
+        let token = lexer.next().unwrap().unwrap();
+        (token.lexeme(), token.number_value())
+    };
+
+    assert_eq!(next_number(), ("12", Some(12.0)));
+    assert_eq!(next_number(), ("3.25", Some(3.25)));
+    assert_eq!(next_number(), ("0x1F", Some(31.0)));
+    assert_eq!(next_number(), ("0b1010", Some(10.0)));
+    assert_eq!(next_number(), ("1e-3", Some(0.001)));
+    assert_eq!(next_number(), ("2.5E+2", Some(250.0)));
+    assert_eq!(next_number(), ("1_000", Some(1000.0)));
+    assert_eq!(next_number(), ("0xff_ff", Some(65535.0)));
+    assert_eq!(next_number(), ("1", Some(1.0)));
+    assert_eq!(next_number(), ("_", None));
+
+    let error = lexer.next().unwrap().unwrap_err();
+    assert_eq!(error.kind(), &LexerErrorKind::MissingRadixDigits);
+    assert_eq!(error.token().lexeme(), "0x");
+
+    let error = lexer.next().unwrap().unwrap_err();
+    assert_eq!(error.kind(), &LexerErrorKind::MissingExponentDigits);
+    assert_eq!(error.token().lexeme(), "1e");
+}
+
+#[test]
+fn number_trailing_dot() {
+    let kinds = Lexer::new("123.sqrt 1.5.abs 7.")
+        .map(|token| token.unwrap().kind())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Number,
+            TokenKind::Dot,
+            TokenKind::Identifier,
+            TokenKind::Number,
+            TokenKind::Dot,
+            TokenKind::Identifier,
+            TokenKind::Number,
+            TokenKind::Dot,
+            TokenKind::EndOfFile,
+        ]
+    );
+
+    let error = Lexer::with_config("123.sqrt", LexerConfig::new().strict(true))
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(error.kind(), &LexerErrorKind::NumberTrailingDot);
+}
+
+#[test]
+fn configured_keywords() {
+    let config = LexerConfig::new().keywords(
+        KeywordTable::default()
+            .case_insensitive(true)
+            .with_keyword("function", TokenKind::Fun),
+    );
+
+    let kinds = Lexer::with_config("FUN function Fun fn", config)
+        .map(|token| token.unwrap().kind())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Fun,
+            TokenKind::Fun,
+            TokenKind::Fun,
+            TokenKind::Identifier,
+            TokenKind::EndOfFile,
+        ]
+    );
+}
+
+#[test]
+fn token_spans() {
+    const SOURCE: &str = "var name = \"🍎 pie\"; // comment\n1.5 @@";
+
+    let mut lexer = Lexer::new(SOURCE);
+    let mut next_slice = || {
+        let span = match lexer.next().unwrap() {
+            Ok(token) => token.span(),
+            Err(error) => error.span(),
+        };
+        &SOURCE[span.range()]
+    };
+
+    assert_eq!(next_slice(), "var");
+    assert_eq!(next_slice(), "name");
+    assert_eq!(next_slice(), "=");
+    assert_eq!(next_slice(), "\"🍎 pie\"");
+    assert_eq!(next_slice(), ";");
+    assert_eq!(next_slice(), "1.5");
+    assert_eq!(next_slice(), "@@");
+    assert_eq!(next_slice(), "");
+}
+
+#[test]
+fn canonical_lexemes() {
+    let source = String::from("var x = VAR;");
+    let config = LexerConfig::new().keywords(KeywordTable::default().case_insensitive(true));
+
+    let tokens = Lexer::with_config(&source, config)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+    let canonical = tokens
+        .iter()
+        .map(Token::has_canonical_lexeme)
+        .collect::<Vec<_>>();
+
+    assert_eq!(canonical, [true, false, true, false, true, false]);
+    assert!(core::ptr::eq(
+        tokens[0].lexeme(),
+        TokenKind::Var.canonical_lexeme().unwrap()
+    ));
+    assert_eq!(tokens[3].lexeme(), "VAR");
+}
+
+#[test]
+fn trivia_tokens() {
+    const SOURCE: &str = "print 1; // one\n  \n// two";
+
+    let tokens = Lexer::with_config(SOURCE, LexerConfig::new().preserve_trivia(true))
+        .map(Result::unwrap)
+        .map(|token| (token.kind(), token.lexeme()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        tokens,
+        [
+            (TokenKind::Print, "print"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Number, "1"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::Whitespace, " "),
+            (TokenKind::Comment, "// one"),
+            (TokenKind::Whitespace, "\n  \n"),
+            (TokenKind::Comment, "// two"),
+            (TokenKind::EndOfFile, ""),
+        ]
+    );
+
+    let lossless = tokens.iter().map(|(_, lexeme)| *lexeme).collect::<String>();
+    assert_eq!(lossless, SOURCE);
+}
+
+#[test]
+fn lex_all_recovers() {
+    const SOURCE: &str = "var @ = 0x;\nprint 1e + 2;\n\"open";
+
+    let (tokens, errors) = Lexer::new(SOURCE).lex_all();
+
+    let kinds = tokens.iter().map(Token::kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Var,
+            TokenKind::Equal,
+            TokenKind::Semicolon,
+            TokenKind::Print,
+            TokenKind::Plus,
+            TokenKind::Number,
+            TokenKind::Semicolon,
+            TokenKind::EndOfFile,
+        ]
+    );
+
+    let errors = errors
+        .iter()
+        .map(|error| {
+            (
+                error.kind().clone(),
+                error.line_number(),
+                error.column_number(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            (LexerErrorKind::Unrecognized, 1, 5),
+            (LexerErrorKind::MissingRadixDigits, 1, 9),
+            (LexerErrorKind::MissingExponentDigits, 2, 7),
+            (LexerErrorKind::UnterminatedStringLiteral, 3, 1),
+        ]
+    );
+}
+
+#[test]
+fn relex_matches_full_lex() {
+    const SOURCE: &str =
+        "var a = 1.5;\nprint a + \"two\nlines\";\n// note\nwhile (a < 10) a = a * 2;\n";
+
+    let edits = [
+        (4..5, "alpha"),
+        (11..11, "0"),
+        (9..10, ""),
+        (21..21, "\""),
+        (0..0, "// "),
+        (43..43, "\n\n"),
+        (50..58, "@"),
+        (SOURCE.len()..SOURCE.len(), "print 0x;"),
+    ];
+
+    let (previous_tokens, _) = Lexer::new(SOURCE).lex_all();
+
+    for (edited_range, new_text) in edits {
+        let mut new_source = SOURCE.to_owned();
+        new_source.replace_range(edited_range.clone(), new_text);
+
+        let (expected, _) = Lexer::new(&new_source).lex_all();
+        let (relexed, _) =
+            Lexer::new(&new_source).relex(edited_range.clone(), new_text, &previous_tokens);
+
+        assert_eq!(relexed, expected, "edit {:?} {:?}", edited_range, new_text);
+    }
+
+    // `re` is checked for the prefix `re\"\"\"` reaching into the edit, more than two bytes past its end
+    const CUSTOM_SOURCE: &str = "print re\"\"; #rgb(1)";
+    let config = || {
+        LexerConfig::new()
+            .custom_literal(CustomLiteral::new(0, "re\"\"\"", "\"\"\""))
+            .custom_literal(CustomLiteral::new(1, "#rgb(", ")"))
+    };
+    let custom_edits = [
+        (10..10, "\"x\"\"\""),
+        (16..17, "0"),
+        (12..13, "#r"),
+        (11..11, " #rgb"),
+    ];
+
+    let (previous_tokens, _) = Lexer::with_config(CUSTOM_SOURCE, config()).lex_all();
+
+    for (edited_range, new_text) in custom_edits {
+        let mut new_source = CUSTOM_SOURCE.to_owned();
+        new_source.replace_range(edited_range.clone(), new_text);
+
+        let (expected, _) = Lexer::with_config(&new_source, config()).lex_all();
+        let (relexed, _) = Lexer::with_config(&new_source, config()).relex(
+            edited_range.clone(),
+            new_text,
+            &previous_tokens,
+        );
+
+        assert_eq!(relexed, expected, "edit {:?} {:?}", edited_range, new_text);
+    }
+}
+
+#[test]
+fn identifier_symbols() {
+    let mut lexer = Lexer::new("count = count + limit; var");
+    let tokens = lexer.by_ref().map(Result::unwrap).collect::<Vec<_>>();
+    let interner = lexer.into_interner();
+
+    assert_eq!(tokens[0].symbol(), tokens[2].symbol());
+    assert_ne!(tokens[0].symbol(), tokens[4].symbol());
+    assert_eq!(tokens[1].symbol(), None);
+    assert_eq!(interner.resolve(tokens[6].symbol().unwrap()), Some("var"));
+    assert_eq!(interner.len(), 3);
+}
+
+#[test]
+fn custom_literals() {
+    let config = LexerConfig::new()
+        .custom_literal(CustomLiteral::new(0, "#rgb(", ")"))
+        .custom_literal(CustomLiteral::new(1, "@", "\""))
+        .custom_literal(CustomLiteral::new(2, "@date\"", "\""));
+
+    let (tokens, errors) =
+        Lexer::with_config("#rgb(ff8000) + @date\"2024-01-31\" #rgb(", config).lex_all();
+
+    let tokens = tokens
+        .iter()
+        .map(|token| (token.kind(), token.lexeme()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        [
+            (TokenKind::Custom(0), "#rgb(ff8000)"),
+            (TokenKind::Plus, "+"),
+            (TokenKind::Custom(2), "@date\"2024-01-31\""),
+            (TokenKind::EndOfFile, ""),
+        ]
+    );
+    assert_eq!(errors[0].kind(), &LexerErrorKind::UnterminatedCustomLiteral);
+    assert_eq!(errors[0].token().lexeme(), "#rgb(");
+}
+
+#[test]
+fn unrecognized_runs() {
+    let config = LexerConfig::new().custom_literal(CustomLiteral::new(0, "#rgb(", ")"));
+    let (tokens, errors) = Lexer::with_config("@$(1)~#rgb(fff) é€-\\", config).lex_all();
+
+    let lexemes = tokens.iter().map(Token::lexeme).collect::<Vec<_>>();
+    assert_eq!(lexemes, ["(", "1", ")", "#rgb(fff)", "-", ""]);
+
+    let errors = errors
+        .iter()
+        .map(|error| (error.token().lexeme(), error.column_number()))
+        .collect::<Vec<_>>();
+    assert_eq!(errors, [("@$", 1), ("~", 6), ("é€", 17), ("\\", 20)]);
+}
+
+#[test]
+fn precision_loss_warnings() {
+    const SOURCE: &str =
+        "9007199254740992 9007199254740993 0x20_0000_0000_0001 0b1000 0.1 1e400 007";
+
+    let mut lexer = Lexer::with_config(SOURCE, LexerConfig::new().warn_on_precision_loss(true));
+    lexer.by_ref().for_each(drop);
+
+    let lexemes = lexer
+        .warnings()
+        .iter()
+        .map(|warning| warning.token().lexeme())
+        .collect::<Vec<_>>();
+    assert_eq!(lexemes, ["9007199254740993", "0x20_0000_0000_0001"]);
+    assert_eq!(lexer.warnings()[0].column_number(), 18);
+
+    let mut lexer = Lexer::new(SOURCE);
+    lexer.by_ref().for_each(drop);
+    assert!(lexer.warnings().is_empty());
+}
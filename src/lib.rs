@@ -1,7 +1,51 @@
+//! A lox front end. Everything except `bug_report`, `differential`, `grade`, `repl`, `round_trip`, and `timing` builds without the `std` feature, on `core` and `alloc`
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[doc(hidden)]
+pub extern crate alloc as __alloc;
+
+#[cfg(feature = "std")]
+pub mod bug_report;
+pub mod cli;
+pub mod constant_propagation;
+pub mod dead_code;
+pub mod diagnostics;
+pub mod dialect;
+#[cfg(feature = "std")]
+pub mod differential;
+pub mod excerpt;
+pub mod explain;
+pub mod features;
+pub mod formatter;
+pub mod fuzz;
+#[cfg(feature = "std")]
+pub mod grade;
+pub mod highlight;
+pub mod interner;
 pub mod lexer;
+pub mod line_index;
+pub mod lint;
+pub mod messages;
+pub mod metrics;
 pub mod token;
+pub mod token_stream;
 
 pub mod abstract_syntax_tree;
 pub mod abstract_syntax_tree_visitor_pattern;
 
 pub mod parser;
+pub mod precedence;
+pub mod query;
+#[cfg(feature = "std")]
+pub mod repl;
+#[cfg(feature = "std")]
+pub mod round_trip;
+pub mod sink;
+pub mod suggest;
+#[cfg(feature = "std")]
+pub mod timing;
+pub mod walk;
+
+pub use features::features;
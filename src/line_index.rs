@@ -0,0 +1,134 @@
+//! Converts byte offsets to and from the positions the language server protocol uses:
+//! a line and a character, both from 0, with the character counted in UTF-16 code units
+
+use crate::{excerpt::floor_char_boundary, token::Span};
+use alloc::vec::Vec;
+
+/// A place in the source as an editor counts it. The character is in UTF-16 code units from the start of the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Utf16Position {
+    line: usize,
+    character: usize,
+}
+impl Utf16Position {
+    pub const fn new(line: usize, character: usize) -> Self {
+        Self { line, character }
+    }
+    /// From 0
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+    /// From 0, in UTF-16 code units
+    pub const fn character(&self) -> usize {
+        self.character
+    }
+}
+
+/// The [Utf16Position]s of the start and the exclusive end of a [Span]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Utf16Range {
+    start: Utf16Position,
+    end: Utf16Position,
+}
+impl Utf16Range {
+    pub const fn new(start: Utf16Position, end: Utf16Position) -> Self {
+        Self { start, end }
+    }
+    pub const fn start(&self) -> Utf16Position {
+        self.start
+    }
+    pub const fn end(&self) -> Utf16Position {
+        self.end
+    }
+}
+
+/// Where each line of a source starts, so positions can be converted without rescanning the source each time.
+/// Lines end at `\n`, so a `\r` before it is the last character of its line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex<'s> {
+    source: &'s str,
+    /// the byte offset of the start of each line
+    line_starts: Vec<usize>,
+}
+impl<'s> LineIndex<'s> {
+    pub fn new(source: &'s str) -> Self {
+        let line_starts = core::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Self {
+            source,
+            line_starts,
+        }
+    }
+    pub const fn source(&self) -> &'s str {
+        self.source
+    }
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+    /// The position of byte `offset`. An offset inside a character counts as the start of it
+    pub fn position(&self, offset: usize) -> Utf16Position {
+        let offset = floor_char_boundary(self.source, offset);
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let character = self.source[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+
+        Utf16Position::new(line, character)
+    }
+    pub fn range(&self, span: Span) -> Utf16Range {
+        Utf16Range::new(self.position(span.start), self.position(span.end))
+    }
+    /// The byte offset of `position`, for a request from an editor.
+    /// A character past the end of its line is the end of the line, and one inside a character is the start of it.
+    /// [None] when there is no such line
+    pub fn offset(&self, position: Utf16Position) -> Option<usize> {
+        let line_start = *self.line_starts.get(position.line)?;
+        let line_end = self
+            .line_starts
+            .get(position.line + 1)
+            .map_or(self.source.len(), |next_start| next_start - 1);
+
+        let mut units = 0;
+        for (index, character) in self.source[line_start..line_end].char_indices() {
+            units += character.len_utf16();
+            if units > position.character {
+                return Some(line_start + index);
+            }
+        }
+        Some(line_end)
+    }
+    /// The span from `range`'s start to its end. See [Self::offset]
+    pub fn span(&self, range: Utf16Range) -> Option<Span> {
+        Some(Span::new(
+            self.offset(range.start)?,
+            self.offset(range.end)?,
+        ))
+    }
+}
+
+#[test]
+fn utf16_positions() {
+    // 'é' is 2 bytes and 1 code unit, '😀' is 4 bytes and 2 code units
+    const SOURCE: &str = "var é = 1;\r\nprint \"😀\" + x;\n";
+    let index = LineIndex::new(SOURCE);
+    assert_eq!(index.line_count(), 3);
+
+    let x = SOURCE.find('x').unwrap();
+    assert_eq!(index.position(x), Utf16Position::new(1, 13));
+    assert_eq!(index.offset(Utf16Position::new(1, 13)), Some(x));
+    assert_eq!(index.position(SOURCE.find('=').unwrap()).character(), 6);
+
+    let emoji = SOURCE.find('😀').unwrap();
+    assert_eq!(index.position(emoji + 2), Utf16Position::new(1, 7));
+    // between the two code units of '😀'
+    assert_eq!(index.offset(Utf16Position::new(1, 8)), Some(emoji));
+
+    assert_eq!(index.offset(Utf16Position::new(0, 99)), Some(12));
+    assert_eq!(index.offset(Utf16Position::new(2, 0)), Some(SOURCE.len()));
+    assert_eq!(index.offset(Utf16Position::new(3, 0)), None);
+
+    let span = Span::new(x, x + 1);
+    assert_eq!(index.span(index.range(span)), Some(span));
+}
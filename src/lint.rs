@@ -0,0 +1,442 @@
+//! Warnings about code that runs but is probably a mistake: locals that are never read, declarations that
+//! shadow another, and loops whose condition or expressions cannot change from one iteration to the next.
+//! There is no resolver yet, so [lint] tracks block scopes itself.
+//! Unreachable code is found by [crate::dead_code] instead
+
+use crate::{
+    abstract_syntax_tree::{Expression, Program, Statement},
+    messages,
+    token::{Span, Token},
+    walk::{self, Walk},
+};
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+/// Each warning [lint] can report, which can be turned off on its own with [LintConfig::allow]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// a variable declared in a block that is never read. Assigning to it does not count
+    UnusedVariable,
+    /// a variable declared in a block with the same name as one in scope
+    ShadowedVariable,
+    /// a `while` or `for` condition that only reads variables the loop never assigns,
+    /// so the loop runs forever or not at all, since there is no `break`
+    InvariantLoopCondition,
+    /// an operator in a loop body that only reads variables the loop never assigns,
+    /// so it computes the same value on every iteration
+    LoopInvariantExpression,
+}
+impl LintKind {
+    pub const ALL: &[LintKind] = &[
+        LintKind::UnusedVariable,
+        LintKind::ShadowedVariable,
+        LintKind::InvariantLoopCondition,
+        LintKind::LoopInvariantExpression,
+    ];
+
+    /// Stable identifier used to look up this warning's message in [crate::messages]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            LintKind::UnusedVariable => "W0003",
+            LintKind::ShadowedVariable => "W0004",
+            LintKind::InvariantLoopCondition => "W0005",
+            LintKind::LoopInvariantExpression => "W0006",
+        }
+    }
+    /// The name it is allowed by on the command line, such as `unused-variable`
+    pub const fn name(&self) -> &'static str {
+        match self {
+            LintKind::UnusedVariable => "unused-variable",
+            LintKind::ShadowedVariable => "shadowed-variable",
+            LintKind::InvariantLoopCondition => "invariant-loop-condition",
+            LintKind::LoopInvariantExpression => "loop-invariant-expression",
+        }
+    }
+    /// The kind called `name`, or the one with the code `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name || kind.code().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Which warnings [lint] reports. All of them by default
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LintConfig {
+    allowed: Vec<LintKind>,
+}
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Stops reporting `kind`
+    pub fn allow(mut self, kind: LintKind) -> Self {
+        self.allowed.push(kind);
+        self
+    }
+    pub fn is_allowed(&self, kind: LintKind) -> bool {
+        self.allowed.contains(&kind)
+    }
+}
+
+/// A warning from [lint]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lint<'a> {
+    kind: LintKind,
+    /// the name in the declaration warned about, or the first token of the expression warned about
+    name: Token<'a>,
+    span: Span,
+    /// the declaration it shadows, for [LintKind::ShadowedVariable]
+    shadowed: Option<Span>,
+}
+impl<'a> Lint<'a> {
+    pub const fn kind(&self) -> LintKind {
+        self.kind
+    }
+    pub const fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+    pub const fn name(&self) -> Token<'a> {
+        self.name
+    }
+    /// The name in the declaration, or the whole expression, warned about
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+    /// The name in the declaration this one shadows, for [LintKind::ShadowedVariable]
+    pub const fn shadowed(&self) -> Option<Span> {
+        self.shadowed
+    }
+}
+impl Display for Lint<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", messages::message(self.code()))
+    }
+}
+
+/// The warnings `config` allows for `program`, in the order of the declarations they are about.
+/// Variables whose name starts with `_` are never reported as unused, and top level ones never are,
+/// since a later script or prompt input may read them
+pub fn lint<'a>(program: &Program<'a>, config: &LintConfig) -> Vec<Lint<'a>> {
+    let mut linter = Linter {
+        scopes: Vec::from([Vec::new()]),
+        lints: Vec::new(),
+    };
+    linter.walk_program(program);
+
+    let mut lints = linter.lints;
+    lints.retain(|lint| !config.is_allowed(lint.kind));
+    lints.sort_by_key(|lint| lint.span().start);
+    lints
+}
+
+/// A declared variable and whether it has been read
+struct Local<'a> {
+    name: Token<'a>,
+    is_read: bool,
+}
+
+struct Linter<'a> {
+    /// the variables of each scope, the top level first
+    scopes: Vec<Vec<Local<'a>>>,
+    lints: Vec<Lint<'a>>,
+}
+impl<'a> Linter<'a> {
+    fn begin_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+    fn end_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        self.lints.extend(
+            scope
+                .into_iter()
+                .filter(|local| !local.is_read && !local.name.lexeme().starts_with('_'))
+                .map(|local| Lint {
+                    kind: LintKind::UnusedVariable,
+                    name: local.name,
+                    span: local.name.span(),
+                    shadowed: None,
+                }),
+        );
+    }
+    fn find(&mut self, name: &str) -> Option<&mut Local<'a>> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|local| local.name.lexeme() == name)
+    }
+    fn declare(&mut self, name: Token<'a>) {
+        // redeclaring a top level variable is allowed and common at the prompt
+        if self.scopes.len() > 1 {
+            if let Some(shadowed) = self.find(name.lexeme()) {
+                let shadowed = Some(shadowed.name.span());
+                self.lints.push(Lint {
+                    kind: LintKind::ShadowedVariable,
+                    name,
+                    span: name.span(),
+                    shadowed,
+                });
+            }
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Local {
+                name,
+                is_read: false,
+            });
+        }
+    }
+    /// Warns about `condition` and the expressions in `body` that cannot change between iterations.
+    /// A loop running a custom statement is skipped, since its handler may change any variable
+    fn check_loop(
+        &mut self,
+        condition: Option<&Expression<'a>>,
+        increment: Option<&Expression<'a>>,
+        body: &Statement<'a>,
+    ) {
+        let mut changes = Changes::default();
+        changes.walk_statement(body);
+        for expression in condition.into_iter().chain(increment) {
+            changes.walk_expression(expression);
+        }
+        if changes.runs_custom_statement {
+            return;
+        }
+
+        if let Some(condition) = condition.filter(|condition| changes.is_invariant(condition)) {
+            self.lints.push(Lint {
+                kind: LintKind::InvariantLoopCondition,
+                name: *condition.first_token(),
+                span: condition.span(),
+                shadowed: None,
+            });
+        }
+
+        let mut invariants = Invariants {
+            changes: &changes,
+            found: Vec::new(),
+        };
+        invariants.walk_statement(body);
+        self.lints
+            .extend(invariants.found.into_iter().map(|(name, span)| Lint {
+                kind: LintKind::LoopInvariantExpression,
+                name,
+                span,
+                shadowed: None,
+            }));
+    }
+}
+impl<'a> Walk<'a> for Linter<'a> {
+    fn walk_statement(&mut self, statement: &Statement<'a>) {
+        match statement {
+            Statement::Var {
+                name, initializer, ..
+            } => {
+                // the initializer is read before the new variable exists
+                if let Some(initializer) = initializer {
+                    self.walk_expression(initializer);
+                }
+                self.declare(*name);
+            }
+            Statement::Block { .. } => {
+                self.begin_scope();
+                walk::walk_statement(self, statement);
+                self.end_scope();
+            }
+            // the initializer of a `for` is scoped to the loop
+            Statement::For {
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                self.check_loop(condition.as_deref(), increment.as_deref(), body);
+                self.begin_scope();
+                walk::walk_statement(self, statement);
+                self.end_scope();
+            }
+            Statement::While {
+                condition, body, ..
+            } => {
+                self.check_loop(Some(condition), None, body);
+                walk::walk_statement(self, statement);
+            }
+            _ => walk::walk_statement(self, statement),
+        }
+    }
+    /// Only reads reach here. The name an assignment stores to is not walked
+    fn walk_variable(&mut self, name: &Token<'a>) {
+        if let Some(local) = self.find(name.lexeme()) {
+            local.is_read = true;
+        }
+    }
+}
+
+/// The variables a loop may change between iterations
+#[derive(Default)]
+struct Changes<'a> {
+    /// assigned anywhere in the loop
+    assigned: Vec<&'a str>,
+    /// declared in the body, so a new variable each iteration
+    declared: Vec<&'a str>,
+    runs_custom_statement: bool,
+}
+impl<'a> Changes<'a> {
+    /// Whether `expression` only applies operators to literals and variables that do not change
+    fn is_invariant(&self, expression: &Expression<'a>) -> bool {
+        match expression {
+            Expression::Literal(_) => true,
+            Expression::Variable(name) => {
+                !self.assigned.contains(&name.lexeme()) && !self.declared.contains(&name.lexeme())
+            }
+            Expression::Unary { right_operand, .. } => self.is_invariant(right_operand),
+            Expression::Binary {
+                left_operand,
+                right_operand,
+                ..
+            }
+            | Expression::Logical {
+                left_operand,
+                right_operand,
+                ..
+            } => self.is_invariant(left_operand) && self.is_invariant(right_operand),
+            Expression::Grouping { expression, .. } => self.is_invariant(expression),
+            Expression::Assign { .. } | Expression::Call { .. } | Expression::Get { .. } => false,
+        }
+    }
+}
+impl<'a> Walk<'a> for Changes<'a> {
+    fn walk_statement(&mut self, statement: &Statement<'a>) {
+        match statement {
+            Statement::Var { name, .. } => self.declared.push(name.lexeme()),
+            Statement::Custom { .. } => self.runs_custom_statement = true,
+            _ => {}
+        }
+        walk::walk_statement(self, statement);
+    }
+    fn walk_expression(&mut self, expression: &Expression<'a>) {
+        if let Expression::Assign { name, .. } = expression {
+            self.assigned.push(name.lexeme());
+        }
+        walk::walk_expression(self, expression);
+    }
+}
+
+/// Collects the outermost operators in a loop body that read a variable and are invariant by [Changes::is_invariant].
+/// Nested loops are left to their own [Linter::check_loop]
+struct Invariants<'c, 'a> {
+    changes: &'c Changes<'a>,
+    /// the first token and span of each
+    found: Vec<(Token<'a>, Span)>,
+}
+impl<'a> Walk<'a> for Invariants<'_, 'a> {
+    fn walk_statement(&mut self, statement: &Statement<'a>) {
+        if !matches!(statement, Statement::While { .. } | Statement::For { .. }) {
+            walk::walk_statement(self, statement);
+        }
+    }
+    fn walk_expression(&mut self, expression: &Expression<'a>) {
+        let is_operator = !matches!(
+            expression,
+            Expression::Literal(_) | Expression::Variable(_) | Expression::Grouping { .. }
+        );
+        if is_operator && reads_variable(expression) && self.changes.is_invariant(expression) {
+            self.found
+                .push((*expression.first_token(), expression.span()));
+        } else {
+            walk::walk_expression(self, expression);
+        }
+    }
+}
+
+fn reads_variable(expression: &Expression) -> bool {
+    struct Reads(bool);
+    impl<'a> Walk<'a> for Reads {
+        fn walk_variable(&mut self, _name: &Token<'a>) {
+            self.0 = true;
+        }
+    }
+
+    let mut reads = Reads(false);
+    reads.walk_expression(expression);
+    reads.0
+}
+
+#[test]
+fn lint_warnings() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const SOURCE: &str = "var a = 1; var a = 2; var unused_global;
+{ var b = a; var _c; var a = b; a = 3; }
+for (var i = 0; i < 3; i = i + 1) { var i = 1; print i; }";
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+
+    let lints = lint(&program, &LintConfig::new())
+        .iter()
+        .map(|lint| (lint.kind(), lint.name().lexeme(), lint.name().line_number()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lints,
+        [
+            (LintKind::ShadowedVariable, "a", 2),
+            (LintKind::UnusedVariable, "a", 2),
+            (LintKind::ShadowedVariable, "i", 3),
+        ]
+    );
+
+    let config = LintConfig::new().allow(LintKind::from_name("shadowed-variable").unwrap());
+    let lints = lint(&program, &config);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].code(), "W0003");
+    assert_eq!(
+        LintKind::from_name("w0004"),
+        Some(LintKind::ShadowedVariable)
+    );
+}
+
+#[test]
+fn loop_invariant_warnings() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const SOURCE: &str = "var n = 3; var done = false;
+while (!done) print n * 2;
+for (var i = 0; i < n; i = i + 1) { var half = i / 2; print half + n * 2; print -(n); }
+for (var i = 0; i < n;) print i;
+while (n > 0) { n = n - 1; while (done) { print n * 2; } }
+while (true) n = n;";
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+
+    let lints = lint(&program, &LintConfig::new())
+        .iter()
+        .map(|lint| {
+            (
+                lint.code(),
+                &SOURCE[lint.span().range()],
+                lint.name().lexeme(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lints,
+        [
+            ("W0005", "!done", "!"),
+            ("W0006", "n * 2", "n"),
+            ("W0006", "n * 2", "n"),
+            ("W0006", "-(n)", "-"),
+            ("W0005", "i < n", "i"),
+            ("W0005", "done", "done"),
+            // n only changes in the outer loop
+            ("W0006", "n * 2", "n"),
+            ("W0005", "true", "true"),
+        ]
+    );
+
+    let config = LintConfig::new().allow(LintKind::LoopInvariantExpression);
+    assert!(lint(&program, &config)
+        .iter()
+        .all(|lint| lint.kind() == LintKind::InvariantLoopCondition));
+}
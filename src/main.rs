@@ -1,49 +1,947 @@
-use lox::lexer::Lexer;
+use lox::{
+    abstract_syntax_tree_visitor_pattern::{
+        interpreter::{Event, Interpreter, Value},
+        Statement,
+    },
+    bug_report,
+    cli::{Arguments, Shell, LOX},
+    constant_propagation::Constants,
+    dead_code,
+    diagnostics::{self, Diagnostic, Diagnostics, Severity, ToDiagnostic, DEFAULT_MAX_DIAGNOSTICS},
+    differential::{self, Run},
+    explain::{explain, EXPLANATIONS},
+    formatter,
+    grade::{self, Spec},
+    highlight,
+    lexer::{Lexer, LexerConfig},
+    lint::{self, LintConfig, LintKind},
+    parser::Parser,
+    repl::{Command, ReplConfig, Session, SessionError},
+    suggest,
+    timing::{TimingOrder, TimingTrace},
+};
 use std::{
     env, fs,
-    io::{self, Write},
+    io::{self, IsTerminal, Read, Write},
+    path::Path,
+    process::{self, ExitCode},
+    thread,
+    time::{Duration, SystemTime},
 };
 
-fn main() -> Result<(), io::Error> {
+/// How scripts and prompt inputs are processed
+#[derive(Clone)]
+struct RunOptions {
+    lexer_config: LexerConfig,
+    /// print the extended explanation after each error
+    explain_errors: bool,
+    /// run [dead_code::eliminate_dead_code] before a script runs and report what it removes
+    optimize: bool,
+    /// which [lint::lint] warnings are reported before a script runs
+    lint_config: LintConfig,
+    /// fail instead of running a script with lexer, [lint::lint], or [dead_code] warnings
+    deny_warnings: bool,
+    /// how many errors from one stage are reported before the rest are only counted
+    max_errors: usize,
+    /// print the tokens of a script before anything else
+    print_tokens: bool,
+    /// print the syntax tree of a script before running it
+    print_tree: bool,
+    /// print each [Event] between the program's output, see [teaching_trace]
+    teach: bool,
+    /// time each statement, see [report_timing]
+    timing: Option<TimingTrace>,
+    timing_order: TimingOrder,
+    /// where to write the folded stacks of the [TimingTrace]
+    timing_folded: Option<String>,
+    /// how many statements may run, see [Interpreter::with_step_budget]
+    max_steps: Option<u64>,
+    /// color the first line of each error and warning
+    color: bool,
+    diagnostic_format: DiagnosticFormat,
+    /// the path of the script being run, reported with each diagnostic as JSON. [None] for stdin, `--eval`, and the prompt
+    path: Option<String>,
+}
+
+/// How [report] writes errors and warnings, from `--format`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DiagnosticFormat {
+    /// the message, then the line of source it is about
+    Text,
+    /// one [diagnostics::to_json] object per line
+    Json,
+}
+
+impl RunOptions {
+    /// These options with [Self::path] set to `path`, unless it is [STDIN_PATH]
+    fn for_script(&self, path: &str) -> Self {
+        Self {
+            path: (path != STDIN_PATH).then(|| path.to_owned()),
+            ..self.clone()
+        }
+    }
+}
+
+/// What [run_source] does with a script after printing what [RunOptions] asks for
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Tokenize,
+    Parse,
+    Run,
+}
+
+/// Why `lox` stopped early. Each is exited with its status from sysexits.h, as in the book
+#[derive(Debug)]
+enum Failure {
+    /// `fmt --check` found a script that is not formatted
+    NotFormatted,
+    /// `compare` found a script that the reference interpreter runs differently
+    Diverged,
+    /// the command line was invalid
+    Usage,
+    /// the script did not lex or parse
+    Data,
+    /// the script stopped with a runtime error
+    Software,
+    /// reading a script or writing output failed
+    Io(io::Error),
+}
+impl Failure {
+    const fn exit_code(&self) -> u8 {
+        match self {
+            Failure::NotFormatted | Failure::Diverged => 1,
+            Failure::Usage => 64,
+            Failure::Data => 65,
+            Failure::Software => 70,
+            Failure::Io(_) => 74,
+        }
+    }
+}
+impl From<io::Error> for Failure {
+    fn from(value: io::Error) -> Self {
+        Failure::Io(value)
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            if let Failure::Io(error) = &failure {
+                eprintln!("{}", error);
+            }
+            ExitCode::from(failure.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<(), Failure> {
     let args = env::args().collect::<Vec<_>>();
 
-    if !args.get(0).is_some_and(|s| s.ends_with("lox")) {
+    if !args.first().is_some_and(|s| s.ends_with("lox")) {
         panic!("Expected the first argument to be the program name")
     }
 
-    match args.len() {
-        1 => run_prompt()?,
-        2 => run_file(&args[1])?,
-        _ => eprintln!("Usage: lox [script]"),
+    let arguments = match LOX.parse_arguments(&args[1..]) {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            eprint!("{}\n\n{}", error, LOX.usage());
+            return Err(Failure::Usage);
+        }
+    };
+    if arguments.flag("help") {
+        return Ok(write!(io::stdout(), "{}", LOX.usage())?);
+    }
+
+    let diagnostic_format = match arguments.value("format") {
+        None | Some("text") => DiagnosticFormat::Text,
+        Some("json") => DiagnosticFormat::Json,
+        Some(format) => {
+            eprintln!("Unknown format {}. Expected text or json", format);
+            return Err(Failure::Usage);
+        }
+    };
+    let max_errors = match arguments.value("max-errors").map(str::parse::<usize>) {
+        None => DEFAULT_MAX_DIAGNOSTICS,
+        Some(Ok(0)) => usize::MAX,
+        Some(Ok(max_errors)) => max_errors,
+        Some(Err(_)) => {
+            eprintln!("--max-errors expects a count, or 0 for no limit");
+            return Err(Failure::Usage);
+        }
+    };
+    let mut lint_config = LintConfig::new();
+    for name in arguments.values("allow") {
+        let Some(kind) = LintKind::from_name(name) else {
+            let names = LintKind::ALL
+                .iter()
+                .map(|kind| kind.name())
+                .collect::<Vec<_>>();
+            eprintln!("Unknown warning {}. Expected {}", name, names.join(" or "));
+            return Err(Failure::Usage);
+        };
+        lint_config = lint_config.allow(kind);
+    }
+    let timing_order = match arguments.value("trace-timing-sort") {
+        None => TimingOrder::default(),
+        Some(name) => match TimingOrder::from_name(name) {
+            Some(order) => order,
+            None => {
+                eprintln!(
+                    "Unknown order {}. Expected {}",
+                    name,
+                    TimingOrder::NAMES.join(" or ")
+                );
+                return Err(Failure::Usage);
+            }
+        },
+    };
+    let timing_folded = arguments.value("trace-timing-folded").map(str::to_owned);
+    let max_steps = match arguments.value("max-steps").map(str::parse::<u64>) {
+        None => None,
+        Some(Ok(max_steps)) => Some(max_steps),
+        Some(Err(_)) => {
+            eprintln!("--max-steps expects a count");
+            return Err(Failure::Usage);
+        }
+    };
+    let options = RunOptions {
+        lexer_config: LexerConfig::new()
+            .warn_on_precision_loss(arguments.flag("warn-precision-loss")),
+        explain_errors: arguments.flag("explain"),
+        optimize: arguments.flag("optimize"),
+        lint_config,
+        deny_warnings: arguments.flag("deny-warnings"),
+        max_errors,
+        print_tokens: arguments.flag("tokens"),
+        print_tree: arguments.flag("ast"),
+        teach: arguments.flag("teach"),
+        timing: (arguments.flag("trace-timing") || timing_folded.is_some()).then(TimingTrace::new),
+        timing_order,
+        timing_folded,
+        max_steps,
+        color: !arguments.flag("no-color")
+            && env::var_os("NO_COLOR").is_none()
+            && io::stderr().is_terminal(),
+        diagnostic_format,
+        path: None,
+    };
+
+    if arguments.flag("bug-report") || env::var_os("LOX_BUG_REPORT").is_some() {
+        bug_report::install(args[1..].join(" "));
+    }
+
+    if let Some(code) = arguments.value("eval") {
+        if arguments.subcommand().is_some() || arguments.argument().is_some() {
+            eprint!("--eval takes the place of a script\n\n{}", LOX.usage());
+            return Err(Failure::Usage);
+        }
+        return run_source(code, Action::Run, &new_interpreter(&options), &options);
+    }
+
+    match (arguments.subcommand(), arguments.argument()) {
+        (None, None) if !io::stdin().is_terminal() => {
+            run_files(&[STDIN_PATH], Action::Run, &options)?
+        }
+        (None, None) => run_prompt(&repl_config(&arguments), &options)?,
+        (Some("run") | None, Some(_)) => run_files(arguments.arguments(), Action::Run, &options)?,
+        (Some("tokenize"), Some(path)) => run_files(&[path], Action::Tokenize, &options)?,
+        (Some("parse"), Some(path)) => run_files(&[path], Action::Parse, &options)?,
+        (Some("watch"), Some(path)) => watch_file(path, &options)?,
+        (Some("explain"), Some(code)) => print_explanation(code)?,
+        (Some("completions"), Some(shell)) => print_completions(shell)?,
+        (Some("fmt"), Some(_)) => {
+            format_files(arguments.arguments(), arguments.flag("check"), &options)?
+        }
+        (Some("compare"), Some(_)) => compare_files(arguments.arguments(), &arguments, &options)?,
+        (Some("grade"), Some(submissions)) => grade_submissions(submissions, &arguments, &options)?,
+        (Some("man"), _) => write!(io::stdout(), "{}", LOX.man_page())?,
+        _ => {
+            eprint!("{}", LOX.usage());
+            return Err(Failure::Usage);
+        }
     };
 
     Ok(())
 }
 
-fn run_prompt() -> Result<(), io::Error> {
+/// The prompt settings from the config file, then the environment, then the command line
+fn repl_config(arguments: &Arguments) -> ReplConfig {
+    let mut repl_config = ReplConfig::new()
+        .apply_default_config_file()
+        .apply_environment();
+    if let Some(prompt) = arguments.value("prompt") {
+        repl_config = repl_config.prompt(prompt.to_owned());
+    }
+    if let Some(continuation_prompt) = arguments.value("continuation-prompt") {
+        repl_config = repl_config.continuation_prompt(continuation_prompt.to_owned());
+    }
+    if let Some(banner) = arguments.value("banner") {
+        repl_config = repl_config.banner(Some(banner.to_owned()));
+    }
+    if arguments.flag("no-banner") {
+        repl_config = repl_config.banner(None);
+    }
+
+    repl_config
+}
+
+/// Reads and evaluates one input at a time until stdin ends. Definitions carry over between inputs
+fn run_prompt(repl_config: &ReplConfig, options: &RunOptions) -> Result<(), io::Error> {
+    if let Some(banner) = repl_config.get_banner() {
+        writeln!(io::stdout(), "{}", banner)?;
+    }
+
+    let session = Session::new()
+        .lexer_config(options.lexer_config.clone())
+        .with_interpreter(new_interpreter(options));
+
     loop {
-        let source = get_input("> ")?;
-        print_tokens(&source)?;
+        let Some(mut source) = read_line(repl_config.get_prompt(), &session)? else {
+            return Ok(());
+        };
+        redraw_highlighted(repl_config.get_prompt(), &source, options)?;
+
+        while Command::parse(&source).is_none() && session.is_incomplete(&source) {
+            let Some(line) = read_line(repl_config.get_continuation_prompt(), &session)? else {
+                break;
+            };
+            redraw_highlighted(repl_config.get_continuation_prompt(), &line, options)?;
+            source.push('\n');
+            source.push_str(&line);
+        }
+
+        evaluate_input(&session, &source, options)?;
     }
 }
 
-fn run_file(path: &str) -> Result<(), io::Error> {
-    let source = fs::read_to_string(path)?;
-    print_tokens(&source)?;
+/// Redraws the line just entered after `prompt` with [highlight::highlight_ansi] when [RunOptions::color] is set,
+/// stdout is a terminal, and the line is not a [Command]. [read_line] only completes words, so the line is colored once entered
+fn redraw_highlighted(prompt: &str, line: &str, options: &RunOptions) -> Result<(), io::Error> {
+    if !options.color || !io::stdout().is_terminal() || Command::parse(line).is_some() {
+        return Ok(());
+    }
+
+    writeln!(
+        io::stdout(),
+        "\x1b[1A\r{}{}\x1b[K",
+        prompt,
+        highlight::highlight_ansi(line, options.lexer_config.clone())
+    )
+}
+
+/// Evaluates one prompt input, or runs it when it is a [Command], printing its output and then any errors
+fn evaluate_input(session: &Session, source: &str, options: &RunOptions) -> Result<(), io::Error> {
+    match Command::parse(source) {
+        Some(Ok(command)) => {
+            return match session.run_command(command) {
+                Ok(output) => write!(io::stdout(), "{}", output),
+                Err(errors) => write!(io::stderr(), "{}", errors),
+            };
+        }
+        Some(Err(name)) => {
+            return writeln!(
+                io::stderr(),
+                "Unknown command :{}. Type :help for a list of commands",
+                name
+            );
+        }
+        None => {}
+    }
+
+    bug_report::set_source(source);
+    let result = session.evaluate(source);
+    for warning in session.take_warnings() {
+        report_diagnostic(warning, source, options, &[])?;
+    }
+    write!(io::stdout(), "{}", session.interpreter().take_output())?;
+    report_timing(source, options)?;
+
+    match result {
+        Ok(()) => {}
+        Err(SessionError::Parse(errors)) => {
+            for error in &errors {
+                report(error, source, options)?;
+            }
+        }
+        Err(SessionError::Runtime(error)) => {
+            report_in_scope(&error, source, options, &session.interpreter().globals())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The script path that reads all of stdin instead, so `cat script.lox | lox -` behaves like `lox script.lox`.
+/// Piped stdin with no script is read the same way
+const STDIN_PATH: &str = "-";
+
+/// Reads the script at `path`, or all of stdin for [STDIN_PATH]
+fn read_script(path: &str) -> Result<String, io::Error> {
+    if path == STDIN_PATH {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// Does `action` with each script at `paths` in order, running them against one [Interpreter]
+/// so that a script can use the globals defined by those before it. Stops at the first that fails
+fn run_files<P: AsRef<str>>(
+    paths: &[P],
+    action: Action,
+    options: &RunOptions,
+) -> Result<(), Failure> {
+    let interpreter = new_interpreter(options);
+    for path in paths {
+        let path = path.as_ref();
+        let source = read_script(path)?;
+        run_source(&source, action, &interpreter, &options.for_script(path))?;
+    }
+    Ok(())
+}
+
+/// An [Interpreter] with the hooks [RunOptions] asks for
+fn new_interpreter(options: &RunOptions) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    if let Some(max_steps) = options.max_steps {
+        interpreter = interpreter.with_step_budget(max_steps);
+    }
+    let teach = options.teach;
+    let timing = options.timing.clone();
+    if !teach && timing.is_none() {
+        return interpreter;
+    }
+
+    interpreter.with_hook(move |interpreter, event| {
+        if teach {
+            teaching_trace(interpreter, event);
+        }
+        if let Some(timing) = &timing {
+            timing.record(event);
+        }
+    })
+}
+
+/// Prints the [TimingTrace] of `source` to stderr for `--trace-timing` and writes its folded stacks
+/// when [RunOptions::timing_folded] is set, then clears it for the next script or input
+fn report_timing(source: &str, options: &RunOptions) -> Result<(), io::Error> {
+    let Some(timing) = &options.timing else {
+        return Ok(());
+    };
+
+    write!(
+        io::stderr(),
+        "{}",
+        timing.report(source, options.timing_order)
+    )?;
+    if let Some(path) = &options.timing_folded {
+        fs::write(path, timing.folded_stacks(source))?;
+    }
+    timing.clear();
+    Ok(())
+}
+
+/// Prints `event` for `--teach` as a line of the program's output, indented two spaces for each scope it is in,
+/// so a class can follow how blocks create and drop variables
+fn teaching_trace(interpreter: &Interpreter, event: &Event) {
+    let describe = |value: &Value| match value {
+        Value::String(string) => format!("{:?}", string),
+        value => value.to_string(),
+    };
+    let (depth, line) = match event {
+        Event::Define { name, value } => (
+            interpreter.depth(),
+            format!("define {} = {}", name, describe(value)),
+        ),
+        Event::Assign { name, old, new } => (
+            interpreter.depth(),
+            format!("assign {}: {} -> {}", name, describe(old), describe(new)),
+        ),
+        Event::EnterScope => (
+            interpreter.depth() - 1,
+            format!("enter scope {}", interpreter.depth()),
+        ),
+        Event::ExitScope => (
+            interpreter.depth(),
+            format!("exit scope {}", interpreter.depth() + 1),
+        ),
+        Event::Execute { .. } | Event::Executed { .. } => return,
+    };
+
+    interpreter.print(&Value::String(format!("{}{}", "  ".repeat(depth), line)));
+}
+
+/// How often [watch_file] checks whether the script has changed
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs the script at `path`, then again each time it is saved, clearing the screen before each run.
+/// Only the script itself is watched, since lox has no imports. Never returns unless reading it fails
+fn watch_file(path: &str, options: &RunOptions) -> Result<(), Failure> {
+    let modified = || -> Result<SystemTime, io::Error> { fs::metadata(path)?.modified() };
+
+    loop {
+        let last_modified = modified()?;
+
+        write!(io::stdout(), "\x1b[2J\x1b[H")?;
+        if let Err(Failure::Io(error)) = run_files(&[path], Action::Run, options) {
+            return Err(Failure::Io(error));
+        }
+        writeln!(io::stdout(), "\nWaiting for changes to {}", path)?;
+
+        // an editor may briefly remove the file while saving it
+        while modified().map_or(true, |time| time == last_modified) {
+            thread::sleep(WATCH_INTERVAL);
+        }
+    }
+}
+
+/// Prints the tokens and tree when asked to, then does `action`. Stops at the first stage with errors.
+/// In debug builds, or when `LOX_ROUND_TRIP_CHECK` asks for it, then panics if the script does not [lox::round_trip::check]
+fn run_source(
+    source: &str,
+    action: Action,
+    interpreter: &Interpreter,
+    options: &RunOptions,
+) -> Result<(), Failure> {
+    bug_report::set_source(source);
+    if action == Action::Tokenize || options.print_tokens {
+        print_tokens(source, options)?;
+    }
+    if action == Action::Parse || options.print_tree {
+        print_tree(source, options)?;
+    }
+    if action == Action::Run {
+        run_program(source, interpreter, options)?;
+    }
+    if round_trip_check_enabled() {
+        lox::round_trip::assert_round_trips(source);
+    }
     Ok(())
 }
 
-fn print_tokens(source: &str) -> Result<(), io::Error> {
-    for possible_token in Lexer::new(source) {
+/// On in debug builds, unless `LOX_ROUND_TRIP_CHECK` is `0`, and in release builds only when it is set to something else
+fn round_trip_check_enabled() -> bool {
+    match env::var_os("LOX_ROUND_TRIP_CHECK") {
+        Some(value) => value != "0",
+        None => cfg!(debug_assertions),
+    }
+}
+
+/// Fails with [Failure::Data] when there were lexer errors
+fn print_tokens(source: &str, options: &RunOptions) -> Result<(), Failure> {
+    let mut errors = Vec::new();
+    let mut lexer = Lexer::with_config(source, options.lexer_config.clone());
+
+    for possible_token in lexer.by_ref() {
         match possible_token {
             Ok(token) => writeln!(io::stdout(), "{}", token)?,
-            Err(error) => writeln!(io::stderr(), "{}", error)?,
+            Err(error) => errors.push(error),
+        }
+    }
+
+    for warning in lexer.warnings() {
+        report(warning, source, options)?;
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        report_errors(errors, source, options)?;
+        Err(Failure::Data)
+    }
+}
+
+/// Fails with [Failure::Data] when the script does not parse
+fn print_tree(source: &str, options: &RunOptions) -> Result<(), Failure> {
+    match Parser::from(Lexer::with_config(source, options.lexer_config.clone())).parse() {
+        Ok(program) => {
+            writeln!(io::stdout(), "{}", program)?;
+            Ok(())
+        }
+        Err(errors) => {
+            report_errors(errors, source, options)?;
+            Err(Failure::Data)
+        }
+    }
+}
+
+/// Reports the lexer's warnings about the script. Then, if [RunOptions::optimize] is set, removes its dead code,
+/// propagates its [Constants], and removes the code that makes dead, printing the tree again if [RunOptions::print_tree] is set.
+/// Each removal is reported, then the [lint::lint] warnings about what was left before propagating. Then executes that with `interpreter`, printing its output, its timing when [RunOptions::timing] is set, and then the error that stopped it, if any.
+/// Fails with [Failure::Data] when it does not parse or has warnings and [RunOptions::deny_warnings] is set,
+/// and [Failure::Software] when it stops with an error
+fn run_program(
+    source: &str,
+    interpreter: &Interpreter,
+    options: &RunOptions,
+) -> Result<(), Failure> {
+    let (result, lexer_warnings) =
+        Parser::from(Lexer::with_config(source, options.lexer_config.clone()))
+            .parse_with_warnings();
+    for warning in &lexer_warnings {
+        report(warning, source, options)?;
+    }
+    let program = match result {
+        Ok(program) => program,
+        Err(errors) => {
+            report_errors(errors, source, options)?;
+            return Err(Failure::Data);
+        }
+    };
+
+    let (program, mut removed) = if options.optimize {
+        dead_code::eliminate_dead_code(program)
+    } else {
+        (program, Vec::new())
+    };
+    // before the reads of constant variables are replaced, which would make them look unused
+    let warnings = lint::lint(&program, &options.lint_config);
+    let constants;
+    let program = if options.optimize {
+        constants = Constants::find(&program);
+        let (program, newly_dead) = dead_code::eliminate_dead_code(constants.propagate(program));
+        removed.extend(newly_dead);
+        program
+    } else {
+        program
+    };
+
+    for dead_code in &removed {
+        report(dead_code, source, options)?;
+    }
+    for warning in &warnings {
+        report(warning, source, options)?;
+    }
+    if options.optimize && options.print_tree {
+        writeln!(io::stdout(), "{}", program)?;
+    }
+    if options.deny_warnings
+        && !(lexer_warnings.is_empty() && removed.is_empty() && warnings.is_empty())
+    {
+        if options.diagnostic_format == DiagnosticFormat::Text {
+            writeln!(
+                io::stderr(),
+                "Not running the script because warnings are denied"
+            )?;
+        }
+        return Err(Failure::Data);
+    }
+
+    let (statements, interner) = program.into_parts();
+    interpreter.reset_steps();
+    let result = interpreter.with_symbols_of(&interner, || {
+        statements
+            .into_iter()
+            .map(Statement::from)
+            .try_for_each(|statement| interpreter.execute(&statement))
+    });
+    write!(io::stdout(), "{}", interpreter.take_output())?;
+    report_timing(source, options)?;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            report_in_scope(&error, source, options, &interpreter.globals())?;
+            Err(Failure::Software)
+        }
+    }
+}
+
+/// The escape codes [report] colors the first line of errors and warnings with
+const ERROR_COLOR: &str = "\x1b[1;31m";
+const WARNING_COLOR: &str = "\x1b[1;33m";
+const RESET_COLOR: &str = "\x1b[0m";
+
+/// Writes `diagnostic` to stderr in [RunOptions::diagnostic_format].
+/// As text, it is followed by the line of `source` it is about, colored if [RunOptions::color] is set,
+/// and an error is followed by its explanation if [RunOptions::explain_errors] is set
+fn report(
+    diagnostic: &impl ToDiagnostic,
+    source: &str,
+    options: &RunOptions,
+) -> Result<(), io::Error> {
+    report_in_scope(diagnostic, source, options, &[])
+}
+
+/// Reports the first [RunOptions::max_errors] of `errors` that stopped `source` from running.
+/// As text, they are followed by how many more there were and a line saying the script was not run
+fn report_errors<T: ToDiagnostic>(
+    errors: impl IntoIterator<Item = T>,
+    source: &str,
+    options: &RunOptions,
+) -> Result<(), io::Error> {
+    let mut diagnostics = Diagnostics::new().max_diagnostics(options.max_errors);
+    diagnostics.extend(errors);
+
+    for error in diagnostics.reported() {
+        report(error, source, options)?;
+    }
+    if options.diagnostic_format == DiagnosticFormat::Text {
+        for summary in diagnostics
+            .summary()
+            .into_iter()
+            .chain(diagnostics.aborting_summary())
+        {
+            writeln!(io::stderr(), "{}", summary)?;
         }
     }
     Ok(())
 }
 
-fn get_input(prompt: &str) -> Result<String, io::Error> {
+/// Like [report], but a misspelled name may also be suggested from `names`, the variables in scope
+fn report_in_scope(
+    diagnostic: &impl ToDiagnostic,
+    source: &str,
+    options: &RunOptions,
+    names: &[String],
+) -> Result<(), io::Error> {
+    report_diagnostic(diagnostic.to_diagnostic(), source, options, names)
+}
+
+/// [report_in_scope] for a diagnostic that was already made, such as from [Session::take_warnings]
+fn report_diagnostic(
+    diagnostic: Diagnostic,
+    source: &str,
+    options: &RunOptions,
+    names: &[String],
+) -> Result<(), io::Error> {
+    let keywords = options
+        .lexer_config
+        .keyword_table()
+        .all_spellings()
+        .collect::<Vec<_>>();
+    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+    let diagnostic = suggest::did_you_mean(diagnostic, source, &keywords, &names);
+    if options.diagnostic_format == DiagnosticFormat::Json {
+        return writeln!(
+            io::stderr(),
+            "{}",
+            diagnostics::to_json(&diagnostic, source, options.path.as_deref())
+        );
+    }
+
+    let text = diagnostic
+        .render(source, options.path.as_deref())
+        .to_string();
+    if options.color {
+        let color = match diagnostic.severity() {
+            Severity::Error => ERROR_COLOR,
+            Severity::Warning => WARNING_COLOR,
+        };
+        let (first_line, rest) = text
+            .split_once('\n')
+            .map_or((text.as_str(), None), |(first, rest)| (first, Some(rest)));
+        write!(io::stderr(), "{}{}{}", color, first_line, RESET_COLOR)?;
+        match rest {
+            Some(rest) => writeln!(io::stderr(), "\n{}", rest)?,
+            None => writeln!(io::stderr())?,
+        }
+    } else {
+        writeln!(io::stderr(), "{}", text)?;
+    }
+
+    if let Some(explanation) = (options.explain_errors && diagnostic.severity() == Severity::Error)
+        .then(|| explain(diagnostic.code()))
+        .flatten()
+    {
+        writeln!(io::stderr(), "\n{}\n", explanation)?;
+    }
+    Ok(())
+}
+
+/// Prints the [explain] text for `code`, or the codes there are when it has none
+fn print_explanation(code: &str) -> Result<(), Failure> {
+    let Some(explanation) = explain(code) else {
+        let codes = EXPLANATIONS
+            .iter()
+            .map(|explanation| explanation.code())
+            .collect::<Vec<_>>();
+        writeln!(
+            io::stderr(),
+            "No explanation found for error code {}. Known codes: {}",
+            code,
+            codes.join(", ")
+        )?;
+        return Err(Failure::Usage);
+    };
+
+    writeln!(io::stdout(), "{}", explanation)?;
+    Ok(())
+}
+
+/// Formats each script at `paths` with [format_file], going on past those that do not parse or are not formatted
+/// so `--check` names all of them, then fails like the first of those did. Stops at the first that cannot be read or written
+fn format_files<P: AsRef<str>>(
+    paths: &[P],
+    check: bool,
+    options: &RunOptions,
+) -> Result<(), Failure> {
+    let mut first_failure = None;
+    for path in paths {
+        let path = path.as_ref();
+        match format_file(path, check, options) {
+            Ok(()) => {}
+            Err(Failure::Io(error)) => {
+                return Err(Failure::Io(io::Error::new(
+                    error.kind(),
+                    format!("{}: {}", path, error),
+                )))
+            }
+            Err(failure) => {
+                first_failure.get_or_insert(failure);
+            }
+        }
+    }
+
+    first_failure.map_or(Ok(()), Err)
+}
+
+/// Rewrites the script at `path` in the canonical layout, or prints it for [STDIN_PATH].
+/// With `check`, fails with [Failure::NotFormatted] instead if it is not already
+fn format_file(path: &str, check: bool, options: &RunOptions) -> Result<(), Failure> {
+    let source = read_script(path)?;
+    let options = &options.for_script(path);
+
+    let formatted = match formatter::format(&source) {
+        Ok(formatted) => formatted,
+        Err(errors) => {
+            report_errors(errors, &source, options)?;
+            return Err(Failure::Data);
+        }
+    };
+
+    if path == STDIN_PATH && !check {
+        return Ok(write!(io::stdout(), "{}", formatted)?);
+    }
+    if formatted == source {
+        return Ok(());
+    }
+    if check {
+        writeln!(io::stderr(), "{} is not formatted", path)?;
+        return Err(Failure::NotFormatted);
+    }
+
+    Ok(fs::write(path, formatted)?)
+}
+
+/// Grades each `.lox` script in the `submissions` directory, in order of name, against the [Spec] in the `--spec` directory,
+/// allowing each case [RunOptions::max_steps] or [grade::DEFAULT_STEP_BUDGET] steps, and prints the `--report`.
+/// A submission that fails its cases still succeeds; only a missing spec or unreadable file fails
+fn grade_submissions(
+    submissions: &str,
+    arguments: &Arguments,
+    options: &RunOptions,
+) -> Result<(), Failure> {
+    let Some(spec) = arguments.value("spec") else {
+        eprint!("grade needs --spec\n\n{}", LOX.usage());
+        return Err(Failure::Usage);
+    };
+    let to_report = match arguments.value("report") {
+        None | Some("csv") => grade::to_csv,
+        Some("json") => grade::to_json,
+        Some(format) => {
+            eprintln!("Unknown report format {}. Expected csv or json", format);
+            return Err(Failure::Usage);
+        }
+    };
+    let spec = Spec::load(Path::new(spec))?;
+    if spec.cases().is_empty() {
+        writeln!(io::stderr(), "warning: the spec has no .out files")?;
+    }
+
+    let mut paths = fs::read_dir(submissions)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "lox"));
+    paths.sort();
+
+    let mut grades = Vec::new();
+    for path in paths {
+        let source = fs::read_to_string(&path)?;
+        let name = path
+            .file_name()
+            .map_or(path.to_string_lossy(), |name| name.to_string_lossy());
+        grades.push(grade::grade(
+            &spec,
+            &name,
+            &source,
+            options.max_steps.unwrap_or(grade::DEFAULT_STEP_BUDGET),
+            &options.lexer_config,
+        ));
+    }
+
+    Ok(write!(io::stdout(), "{}", to_report(&grades))?)
+}
+
+/// Runs each script at `paths` with this program and with the `--reference` interpreter, then prints whether they matched
+/// or each [differential::Divergence]. `--optimize` and `--max-steps` are passed on to this program's runs.
+/// Fails with [Failure::Diverged] after all of them ran when any differed
+fn compare_files<P: AsRef<str>>(
+    paths: &[P],
+    arguments: &Arguments,
+    options: &RunOptions,
+) -> Result<(), Failure> {
+    let Some(reference) = arguments.value("reference") else {
+        eprint!("compare needs --reference\n\n{}", LOX.usage());
+        return Err(Failure::Usage);
+    };
+    let lox = env::current_exe()?;
+
+    let mut diverged = false;
+    for path in paths {
+        let path = path.as_ref();
+        let mut ours = process::Command::new(&lox);
+        ours.arg("--no-color");
+        if options.optimize {
+            ours.arg("--optimize");
+        }
+        if let Some(max_steps) = options.max_steps {
+            ours.args(["--max-steps", &max_steps.to_string()]);
+        }
+        let ours = Run::of(ours.args(["run", path]), differential::DEFAULT_TIMEOUT)?;
+        let theirs = Run::of(
+            process::Command::new(reference).arg(path),
+            differential::DEFAULT_TIMEOUT,
+        )
+        .map_err(|error| io::Error::new(error.kind(), format!("{}: {}", reference, error)))?;
+
+        let divergences = differential::compare(&ours, &theirs);
+        if divergences.is_empty() {
+            writeln!(io::stdout(), "{}: matches", path)?;
+            continue;
+        }
+        diverged = true;
+        writeln!(io::stdout(), "{}: differs", path)?;
+        for divergence in divergences {
+            writeln!(io::stdout(), "  {}", divergence)?;
+        }
+    }
+
+    if diverged {
+        Err(Failure::Diverged)
+    } else {
+        Ok(())
+    }
+}
+
+fn print_completions(shell: &str) -> Result<(), Failure> {
+    match Shell::from_name(shell) {
+        Some(shell) => Ok(write!(io::stdout(), "{}", LOX.completions(shell))?),
+        None => {
+            writeln!(
+                io::stderr(),
+                "Unknown shell {}. Expected one of: {}",
+                shell,
+                Shell::NAMES.join(", ")
+            )?;
+            Err(Failure::Usage)
+        }
+    }
+}
+
+/// Shows `prompt` and reads one line. [None] when stdin has ended
+fn get_input(prompt: &str) -> Result<Option<String>, io::Error> {
     {
         let mut stdout = io::stdout();
         stdout.write_all(prompt.as_bytes())?;
@@ -51,8 +949,147 @@ fn get_input(prompt: &str) -> Result<String, io::Error> {
     }
 
     let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Ok(None);
+    }
     input.truncate(input.trim_end().len());
 
-    Ok(input)
+    Ok(Some(input))
+}
+
+/// Like [get_input], but when stdin and stdout are terminals the line is edited in raw mode so Tab completes the word
+/// before the cursor with [Session::complete]. Falls back to [get_input] when the terminal cannot be switched
+fn read_line(prompt: &str, session: &Session) -> Result<Option<String>, io::Error> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return get_input(prompt);
+    }
+    let Some(_raw_mode) = RawMode::enable() else {
+        return get_input(prompt);
+    };
+
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", prompt)?;
+    stdout.flush()?;
+
+    let mut line = String::new();
+    // the bytes of a character that has not been read completely
+    let mut pending = Vec::new();
+    let mut bytes = io::stdin().lock().bytes();
+    loop {
+        let Some(byte) = bytes.next().transpose()? else {
+            return Ok(None);
+        };
+
+        match byte {
+            b'\r' | b'\n' => {
+                writeln!(stdout)?;
+                return Ok(Some(line));
+            }
+            // Ctrl+D ends the prompt on an empty line like end of input does
+            0x04 if line.is_empty() => {
+                writeln!(stdout)?;
+                return Ok(None);
+            }
+            // Ctrl+C discards the line
+            0x03 => {
+                line.clear();
+                write!(stdout, "\n{}", prompt)?;
+            }
+            0x7f | 0x08 => {
+                line.pop();
+                write!(stdout, "\r{}{}\x1b[K", prompt, line)?;
+            }
+            b'\t' => complete_word(&mut line, prompt, session)?,
+            // an escape sequence, such as from an arrow key, which is skipped
+            0x1b => {
+                if bytes.next().transpose()? == Some(b'[') {
+                    while bytes
+                        .next()
+                        .transpose()?
+                        .is_some_and(|byte| !byte.is_ascii_alphabetic() && byte != b'~')
+                    {
+                    }
+                }
+            }
+            byte if byte < 0x20 => {}
+            byte => {
+                pending.push(byte);
+                if let Ok(character) = std::str::from_utf8(&pending) {
+                    write!(stdout, "{}", character)?;
+                    line.push_str(character);
+                    pending.clear();
+                } else if pending.len() >= 4 {
+                    pending.clear();
+                }
+            }
+        }
+        stdout.flush()?;
+    }
+}
+
+/// Completes the word at the end of `line` as far as every [Session::complete] candidate agrees,
+/// listing the candidates when that adds nothing and there are several
+fn complete_word(line: &mut String, prompt: &str, session: &Session) -> Result<(), io::Error> {
+    let candidates = session.complete(line);
+    let word_length = line
+        .chars()
+        .rev()
+        .take_while(|character| character.is_alphanumeric() || *character == '_')
+        .map(char::len_utf8)
+        .sum::<usize>();
+
+    let Some(first) = candidates.first() else {
+        return Ok(());
+    };
+    let common = candidates.iter().fold(first.as_str(), |common, candidate| {
+        let length = common
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(common.len().min(candidate.len()), |((index, _), _)| index);
+        &common[..length]
+    });
+
+    let mut stdout = io::stdout();
+    if common.len() > word_length {
+        let completion = &common[word_length..];
+        line.push_str(completion);
+        write!(stdout, "{}", completion)?;
+    } else if candidates.len() > 1 {
+        write!(stdout, "\n{}\n{}{}", candidates.join("  "), prompt, line)?;
+    }
+    Ok(())
+}
+
+/// The terminal switched to reading each key as it is pressed, without echo or signals, until this is dropped.
+/// Uses `stty` so no terminal library is needed
+struct RawMode {
+    /// the settings to restore, from `stty -g`
+    saved: String,
+}
+impl RawMode {
+    /// [None] when there is no `stty` or it fails, such as on Windows
+    fn enable() -> Option<Self> {
+        let output = stty(&["-g"])?;
+        let saved = String::from_utf8(output).ok()?.trim().to_owned();
+        stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+
+        Some(Self { saved })
+    }
+}
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[self.saved.as_str()]);
+    }
+}
+
+/// Runs `stty` on the terminal stdin is, returning what it printed. [None] when it cannot run or fails
+fn stty(arguments: &[&str]) -> Option<Vec<u8>> {
+    let output = std::process::Command::new("stty")
+        .args(arguments)
+        .stdin(std::process::Stdio::inherit())
+        .output()
+        .ok()?;
+
+    output.status.success().then_some(output.stdout)
 }
@@ -0,0 +1,262 @@
+//! Catalog of user-facing diagnostic strings keyed by error code (`L0001`, `P0003`, ...) so they can be translated.
+//! [English] is the default. Install another locale with `set_locale`, or for one thread with `with_locale` (both require the `std` feature)
+
+use alloc::{borrow::ToOwned, string::String};
+use core::fmt::Write;
+#[cfg(feature = "std")]
+use std::{cell::RefCell, sync::RwLock};
+
+/// Key of the template wrapping every lexer error message.
+/// Placeholders: `{lexeme}`, `{line}`, `{column}`, `{message}`
+pub const LEXER_ERROR_TEMPLATE: &str = "lexer_error";
+/// Key of the template wrapping every lexer warning message.
+/// Placeholders: `{lexeme}`, `{line}`, `{column}`, `{message}`
+pub const LEXER_WARNING_TEMPLATE: &str = "lexer_warning";
+/// Key of the template wrapping every parse error message.
+/// Placeholders: `{kind}`, `{lexeme}`, `{line}`, `{message}`
+pub const PARSE_ERROR_TEMPLATE: &str = "parse_error";
+/// Key of the template wrapping every runtime error message.
+/// Placeholders: `{lexeme}`, `{line}`, `{message}`
+pub const RUNTIME_ERROR_TEMPLATE: &str = "runtime_error";
+/// Key of the line printed after the diagnostics that were reported when the rest were left out.
+/// Placeholders: `{count}`
+pub const DIAGNOSTICS_OMITTED_TEMPLATE: &str = "diagnostics_omitted";
+/// Key of the last line printed after errors that stopped the input from running, when there were several.
+/// Placeholders: `{count}`
+pub const ABORTING_TEMPLATE: &str = "aborting";
+/// Key of the last line printed after the one error that stopped the input from running
+pub const ABORTING_AFTER_ONE_ERROR: &str = "aborting_after_one_error";
+/// Key of the label on the `(` or `{` that a missing closing one belongs to.
+/// Placeholders: `{lexeme}`
+pub const OPENED_HERE_TEMPLATE: &str = "opened_here";
+/// Key of the label on the declaration that a [crate::lint::LintKind::ShadowedVariable] shadows.
+/// Placeholders: `{lexeme}`
+pub const SHADOWED_HERE_TEMPLATE: &str = "shadowed_here";
+/// Key of the note on a [crate::abstract_syntax_tree_visitor_pattern::interpreter::RuntimeErrorKind::StepBudgetExhausted]
+/// saying how many of the steps its loop took. Placeholders: `{steps}`, `{budget}`
+pub const LOOP_STEPS_TEMPLATE: &str = "loop_steps";
+/// Key of the note listing the loop's variables and their values when the step budget ran out.
+/// Placeholders: `{variables}`
+pub const LOOP_VARIABLES_TEMPLATE: &str = "loop_variables";
+/// Key of the note suggesting what a misspelled name was meant to be.
+/// Placeholders: `{name}`
+pub const DID_YOU_MEAN_TEMPLATE: &str = "did_you_mean";
+
+pub trait MessageCatalog: Send + Sync {
+    /// Returns the message for `key`. [None] falls back to [English]
+    fn message(&self, key: &str) -> Option<&str>;
+}
+
+/// The default locale. Has a message for every key
+pub struct English;
+impl MessageCatalog for English {
+    fn message(&self, key: &str) -> Option<&str> {
+        let message = match key {
+            LEXER_ERROR_TEMPLATE => {
+                "Error lexing {lexeme} at line {line}, column {column}: {message}"
+            }
+            LEXER_WARNING_TEMPLATE => {
+                "Warning lexing {lexeme} at line {line}, column {column}: {message}"
+            }
+            PARSE_ERROR_TEMPLATE => {
+                "Error parsing {kind} token: \"{lexeme}\" on line {line}: {message}"
+            }
+            RUNTIME_ERROR_TEMPLATE => "Runtime error at {lexeme} on line {line}: {message}",
+            DIAGNOSTICS_OMITTED_TEMPLATE => "{count} more errors omitted",
+            ABORTING_TEMPLATE => "aborting due to {count} previous errors",
+            ABORTING_AFTER_ONE_ERROR => "aborting due to the previous error",
+            OPENED_HERE_TEMPLATE => "'{lexeme}' opened here",
+            SHADOWED_HERE_TEMPLATE => "'{lexeme}' is first declared here",
+            DID_YOU_MEAN_TEMPLATE => "did you mean '{name}'?",
+            LOOP_STEPS_TEMPLATE => "this loop ran {steps} of the {budget} steps",
+            LOOP_VARIABLES_TEMPLATE => "its variables when it stopped: {variables}",
+            "L0001" => "Unterminated string literal",
+            "L0002" => "Unrecognized token",
+            "L0003" => "Number literal must have a digit after the '.'",
+            "L0004" => "Number exponent must have at least one digit",
+            "L0005" => "Number radix prefix must be followed by at least one digit",
+            "L0006" => "Unterminated custom literal",
+            "P0001" => "No rule matched. Expected expression",
+            "P0002" => "Unary operator must have an expression after",
+            "P0003" => "Missing closing parenthesis",
+            "P0004" => "Code is nested too deeply",
+            "P0005" => "Expected ';' after statement",
+            "P0006" => "Expected a variable name after 'var'",
+            "P0007" => "Invalid assignment target",
+            "P0008" => "Expected '(' after 'if', 'while', or 'for'",
+            "P0009" => "Missing closing brace",
+            "P0010" => "Unexpected end of input",
+            "P0011" => "Can't have more than 255 arguments",
+            "P0012" => "Expected ')' after arguments",
+            "P0013" => "Expected a property name after '.'",
+            "R0001" => "Operand must be a number",
+            "R0002" => "Operands must be numbers",
+            "R0003" => "Operands must be two numbers or two strings",
+            "R0004" => "Undefined variable",
+            "R0005" => "Can only call functions and classes",
+            "R0006" => "Operator cannot be used here",
+            "R0007" => "Literal has no value",
+            "R0008" => "Only instances have properties",
+            "R0009" => "Custom statement cannot be run without its host",
+            "R0010" => "Step limit reached; the program may be stuck in a loop",
+            "R0011" => "String or output is larger than the size limit",
+            "W0001" => "Number literal cannot be represented exactly and will be rounded",
+            "W0002" => "Unreachable code removed",
+            "W0003" => "Variable is never read",
+            "W0004" => "Variable shadows another with the same name",
+            "W0005" => "Loop condition never changes, so the loop runs forever or not at all",
+            "W0006" => "Expression computes the same value on every iteration of the loop",
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+/// A locale backed by a static `(key, message)` table
+pub struct TableCatalog(pub &'static [(&'static str, &'static str)]);
+impl MessageCatalog for TableCatalog {
+    fn message(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(entry_key, _)| *entry_key == key)
+            .map(|(_, message)| *message)
+    }
+}
+
+#[cfg(feature = "std")]
+static LOCALE: RwLock<Option<Box<dyn MessageCatalog>>> = RwLock::new(None);
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// set by [with_locale], and used before [LOCALE]
+    static THREAD_LOCALE: RefCell<Option<Box<dyn MessageCatalog>>> = const { RefCell::new(None) };
+}
+
+/// Replaces the locale used by every diagnostic's [core::fmt::Display]
+#[cfg(feature = "std")]
+pub fn set_locale(catalog: impl MessageCatalog + 'static) {
+    *LOCALE.write().unwrap_or_else(|error| error.into_inner()) = Some(Box::new(catalog));
+}
+/// Goes back to [English]
+#[cfg(feature = "std")]
+pub fn reset_locale() {
+    *LOCALE.write().unwrap_or_else(|error| error.into_inner()) = None;
+}
+/// Runs `run` with `catalog` as the locale on this thread only, such as for one client of a server
+/// or a test that must not change the messages other threads see
+#[cfg(feature = "std")]
+pub fn with_locale<T>(catalog: impl MessageCatalog + 'static, run: impl FnOnce() -> T) -> T {
+    /// Puts the previous thread locale back, even when `run` panics
+    struct Restore(Option<Box<dyn MessageCatalog>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            THREAD_LOCALE.with_borrow_mut(|locale| *locale = self.0.take());
+        }
+    }
+
+    let _restore = Restore(THREAD_LOCALE.replace(Some(Box::new(catalog))));
+    run()
+}
+
+/// Looks up `key` in this thread's locale from [with_locale], then the current locale, then [English].
+/// Unknown keys return the key itself
+#[cfg(feature = "std")]
+pub fn message(key: &str) -> String {
+    let thread_message = THREAD_LOCALE.with_borrow(|locale| {
+        locale
+            .as_ref()
+            .and_then(|catalog| catalog.message(key))
+            .map(str::to_owned)
+    });
+    if let Some(message) = thread_message {
+        return message;
+    }
+
+    let locale = LOCALE.read().unwrap_or_else(|error| error.into_inner());
+    locale
+        .as_ref()
+        .and_then(|catalog| catalog.message(key))
+        .or_else(|| English.message(key))
+        .unwrap_or(key)
+        .to_owned()
+}
+/// Looks up `key` in [English]. Unknown keys return the key itself
+#[cfg(not(feature = "std"))]
+pub fn message(key: &str) -> String {
+    English.message(key).unwrap_or(key).to_owned()
+}
+
+/// Looks up `key` with [message] then replaces each `{name}` placeholder with its value.
+/// The template is scanned once, so a value containing something like `{line}` is kept as it is.
+/// A placeholder with no value is kept too
+pub fn format_message(key: &str, arguments: &[(&str, &dyn core::fmt::Display)]) -> String {
+    let template = message(key);
+    let mut output = String::with_capacity(template.len());
+
+    let mut rest = template.as_str();
+    while let Some(open) = rest.find('{') {
+        output.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        let value = rest.find('}').and_then(|close| {
+            let name = &rest[1..close];
+            arguments
+                .iter()
+                .find(|(argument, _)| *argument == name)
+                .map(|(_, value)| (close, value))
+        });
+        match value {
+            Some((close, value)) => {
+                let _ = write!(output, "{}", value);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn localized_messages() {
+    use crate::lexer::Lexer;
+
+    const SPANISH: TableCatalog = TableCatalog(&[
+        (
+            LEXER_ERROR_TEMPLATE,
+            "Error léxico en {lexeme}, línea {line}, columna {column}: {message}",
+        ),
+        ("L0002", "Símbolo no reconocido"),
+    ]);
+
+    let error = Lexer::new("1 + @").nth(2).unwrap().unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "Error lexing @ at line 1, column 5: Unrecognized token"
+    );
+
+    let (localized, fallback) = with_locale(SPANISH, || (error.to_string(), message("L0001")));
+
+    assert_eq!(
+        localized,
+        "Error léxico en @, línea 1, columna 5: Símbolo no reconocido"
+    );
+    assert_eq!(fallback, "Unterminated string literal");
+}
+
+#[test]
+fn message_placeholders() {
+    assert_eq!(
+        format_message(
+            LEXER_ERROR_TEMPLATE,
+            &[("lexeme", &"{line}"), ("line", &1), ("message", &"{x}")]
+        ),
+        "Error lexing {line} at line 1, column {column}: {x}"
+    );
+}
@@ -0,0 +1,126 @@
+//! Size and complexity measurements of a [Program] for linting and analysis tools
+
+use crate::{
+    abstract_syntax_tree::{Expression, Program, Statement},
+    token::TokenKind,
+    walk::{self, Walk},
+};
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Metrics {
+    node_count: usize,
+    max_depth: usize,
+    /// each operator kind with how many times it is used, in order of first use
+    operators: Vec<(TokenKind, usize)>,
+    complexity: usize,
+}
+impl Metrics {
+    /// How many statements and expressions there are
+    pub const fn node_count(&self) -> usize {
+        self.node_count
+    }
+    /// How many nodes deep the deepest one is. A top level statement is at depth 1
+    pub const fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+    /// Every unary, binary, logical, and assignment operator kind used, with its count, in order of first use
+    pub fn operators(&self) -> &[(TokenKind, usize)] {
+        &self.operators
+    }
+    pub fn operator_count(&self, kind: TokenKind) -> usize {
+        self.operators
+            .iter()
+            .find(|(operator, _)| *operator == kind)
+            .map_or(0, |(_, count)| *count)
+    }
+    /// Cyclomatic complexity: one plus the number of `if`, `while`, `for`, `and`, and `or` decisions.
+    /// Measured for the whole script, since there are no function declarations to measure separately
+    pub const fn complexity(&self) -> usize {
+        self.complexity
+    }
+}
+
+pub fn metrics(program: &Program) -> Metrics {
+    let mut counter = MetricsCounter {
+        metrics: Metrics {
+            complexity: 1,
+            ..Metrics::default()
+        },
+        depth: 0,
+    };
+    counter.walk_program(program);
+    counter.metrics
+}
+
+struct MetricsCounter {
+    metrics: Metrics,
+    depth: usize,
+}
+impl MetricsCounter {
+    fn enter(&mut self) {
+        self.metrics.node_count += 1;
+        self.depth += 1;
+        self.metrics.max_depth = self.metrics.max_depth.max(self.depth);
+    }
+    fn count_operator(&mut self, kind: TokenKind) {
+        match self
+            .metrics
+            .operators
+            .iter_mut()
+            .find(|(operator, _)| *operator == kind)
+        {
+            Some((_, count)) => *count += 1,
+            None => self.metrics.operators.push((kind, 1)),
+        }
+    }
+}
+impl<'a> Walk<'a> for MetricsCounter {
+    fn walk_statement(&mut self, statement: &Statement<'a>) {
+        self.enter();
+        if matches!(
+            statement,
+            Statement::If { .. } | Statement::While { .. } | Statement::For { .. }
+        ) {
+            self.metrics.complexity += 1;
+        }
+        walk::walk_statement(self, statement);
+        self.depth -= 1;
+    }
+    fn walk_expression(&mut self, expression: &Expression<'a>) {
+        self.enter();
+        match expression {
+            Expression::Logical { operator, .. } => {
+                self.metrics.complexity += 1;
+                self.count_operator(operator.kind());
+            }
+            Expression::Binary { operator, .. } | Expression::Unary { operator, .. } => {
+                self.count_operator(operator.kind())
+            }
+            Expression::Assign { .. } => self.count_operator(TokenKind::Equal),
+            _ => {}
+        }
+        walk::walk_expression(self, expression);
+        self.depth -= 1;
+    }
+}
+
+#[test]
+fn program_metrics() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const SOURCE: &str = "var a = 1 + 2 * 3;\nif (a > 1 and a < 9 or !a) { while (a) a = a - 1; }";
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+    let metrics = metrics(&program);
+
+    assert_eq!(metrics.node_count(), 25);
+    // if, block, while, expression statement, =, -, a
+    assert_eq!(metrics.max_depth(), 7);
+    assert_eq!(metrics.operator_count(TokenKind::Plus), 1);
+    assert_eq!(metrics.operator_count(TokenKind::Minus), 1);
+    assert_eq!(metrics.operator_count(TokenKind::Equal), 1);
+    assert_eq!(metrics.operator_count(TokenKind::Slash), 0);
+    assert_eq!(metrics.operators()[0], (TokenKind::Plus, 1));
+    // if, while, and, or
+    assert_eq!(metrics.complexity(), 5);
+}
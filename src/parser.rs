@@ -1,21 +1,92 @@
 use crate::{
-    abstract_syntax_tree::Expression,
-    lexer::{Lexer, LexerError},
-    token::{Token, TokenKind},
+    abstract_syntax_tree::{Expression, Program, Statement},
+    abstract_syntax_tree_visitor_pattern,
+    excerpt::{SourceExcerpt, WithSource},
+    interner::Interner,
+    lexer::{Lexer, LexerError, LexerErrorKind, LexerWarning, TokenSource},
+    messages,
+    precedence::{self, Associativity},
+    token::{Span, Token, TokenKind},
 };
+use alloc::{boxed::Box, collections::BTreeMap, format, vec::Vec};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Parser<'a> {
+/// Builds the expression for a [TokenKind::Custom] literal, registered with [Parser::with_custom_literal_parser].
+/// Hosts can return their own node built from standard expressions, or an error kind that is reported at the literal
+pub type CustomLiteralParser<'a> = fn(Token<'a>) -> Result<Box<Expression<'a>>, ParseErrorKind<'a>>;
+
+/// Parses the rest of a [Statement::Custom] after its keyword, registered with [Parser::with_custom_statement_parser].
+/// The public rules of [Parser] such as [Parser::expression_rule] and [Parser::consume_expected_token] can be used to parse the statement's parts
+pub type CustomStatementParser<'a, S = Lexer<'a>> =
+    fn(&mut Parser<'a, S>, keyword: Token<'a>) -> Result<Statement<'a>, ParseError<'a>>;
+
+/// How many arguments a call may have before [ParseErrorKind::TooManyArguments] is reported
+pub const MAX_ARGUMENTS: usize = 255;
+
+/// How many unary operators, parentheses, calls, properties, assignments, left associative binary operators, blocks, and statement bodies may be nested inside each other
+/// before parsing stops with [ParseErrorKind::TooDeeplyNested]. Keeps adversarial input like `((((...` from overflowing the stack.
+/// Each level of parentheses takes several rules' stack frames, and 256 levels overflowed the 2 MiB stack of a test thread in a debug build
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parser<'a, S = Lexer<'a>> {
+    /// every token when made with [Self::new]. While streaming from `lexer` only the previous and current tokens
     tokens: Vec<Token<'a>>,
     current_token_index: usize,
+    /// where the rest of the tokens come from when made from a [Lexer] or another [TokenSource]
+    lexer: Option<S>,
+    /// how many [Self::nested] rules are currently being parsed
+    nesting_depth: usize,
+    /// resolves the [Token::symbol]s of `tokens`
+    interner: Interner<'a>,
+    /// keyed by the id in [TokenKind::Custom]
+    custom_literal_parsers: BTreeMap<u16, CustomLiteralParser<'a>>,
+    /// keyed by the id in [TokenKind::CustomKeyword]
+    custom_statement_parsers: BTreeMap<u16, CustomStatementParser<'a, S>>,
+    /// lexer errors and the errors that were recovered from by [Self::synchronize]
+    errors: Vec<ParseError<'a>>,
 }
 impl<'a> Parser<'a> {
-    pub const fn new(tokens: Vec<Token<'a>>) -> Self {
+    /// The tokens may come from any number of lexers, so every [Token::symbol] is replaced by one from the parser's own interner
+    pub fn new(mut tokens: Vec<Token<'a>>) -> Self {
+        let mut interner = Interner::new();
+        reintern(&mut tokens, &mut interner);
+
+        Self::with_interner(tokens, interner)
+    }
+    /// `interner` must be the one that the [Token::symbol]s of `tokens` came from
+    pub const fn with_interner(tokens: Vec<Token<'a>>, interner: Interner<'a>) -> Self {
+        Self::from_parts(tokens, interner)
+    }
+}
+impl<'a, S: TokenSource<'a>> Parser<'a, S> {
+    const fn from_parts(tokens: Vec<Token<'a>>, interner: Interner<'a>) -> Self {
         Self {
             tokens,
             current_token_index: 0,
+            lexer: None,
+            nesting_depth: 0,
+            interner,
+            custom_literal_parsers: BTreeMap::new(),
+            custom_statement_parsers: BTreeMap::new(),
+            errors: Vec::new(),
         }
     }
+    /// Parses [TokenKind::Custom] tokens with id `id` using `parser`.
+    /// Without one the token becomes an [Expression::Literal] for later stages to interpret
+    pub fn with_custom_literal_parser(mut self, id: u16, parser: CustomLiteralParser<'a>) -> Self {
+        self.custom_literal_parsers.insert(id, parser);
+        self
+    }
+    /// Parses statements starting with a [TokenKind::CustomKeyword] with id `id` using `parser`.
+    /// Without one the default syntax described at [Statement::Custom] is used
+    pub fn with_custom_statement_parser(
+        mut self,
+        id: u16,
+        parser: CustomStatementParser<'a, S>,
+    ) -> Self {
+        self.custom_statement_parsers.insert(id, parser);
+        self
+    }
     fn consume_current_token_of_kind(&mut self, kinds: &[TokenKind]) -> bool {
         for kind in kinds {
             if self.is_current_token(*kind) {
@@ -26,96 +97,678 @@ impl<'a> Parser<'a> {
 
         false
     }
+    /// Consumes and returns the current token if it is of `kind`
+    /// # Error
+    /// `error` at the current token when it is not
+    pub fn consume_expected_token(
+        &mut self,
+        kind: TokenKind,
+        error: ParseErrorKind<'a>,
+    ) -> Result<Token<'a>, ParseError<'a>> {
+        if self.consume_current_token_of_kind(&[kind]) {
+            return Ok(self.peek_previous_token());
+        }
+
+        Err(self.error_at_current_token(error))
+    }
+    /// An error of `kind` at the current token, as [ParseErrorKind::UnexpectedEof] when the input ran out
+    fn error_at_current_token(&self, kind: ParseErrorKind<'a>) -> ParseError<'a> {
+        let token = self.peek_current_token();
+
+        let kind = if token.is_end_of_file() {
+            ParseErrorKind::UnexpectedEof {
+                expected: Box::new(kind),
+            }
+        } else {
+            kind
+        };
+
+        ParseError::new(kind, token)
+    }
     fn is_current_token(&self, kind: TokenKind) -> bool {
         !self.is_at_end() && self.peek_current_token().kind() == kind
     }
     fn consume_current_token(&mut self) {
-        if !self.is_at_end() {
-            self.current_token_index += 1;
+        if self.is_at_end() {
+            return;
+        }
+
+        self.current_token_index += 1;
+
+        if self.lexer.is_some() {
+            // only the previous token is kept, for [Self::peek_previous_token]
+            self.tokens.drain(..self.current_token_index - 1);
+            self.current_token_index = 1;
+            self.lex_next_token();
+        }
+    }
+    /// Appends the next token that is not trivia from `lexer`.
+    /// Lexer errors on the way are recorded as [ParseErrorKind::LexerError]s and their lexemes skipped
+    fn lex_next_token(&mut self) {
+        let Some(lexer) = self.lexer.as_mut() else {
+            return;
+        };
+
+        for possible_token in lexer {
+            match possible_token {
+                Ok(token) if token.kind().is_trivia() => {}
+                Ok(token) => {
+                    self.tokens.push(token);
+                    return;
+                }
+                Err(error) => self.errors.push(error.into()),
+            }
         }
     }
-    fn is_at_end(&self) -> bool {
+    /// Lexer errors and the errors recovered from so far. [Self::parse] returns these,
+    /// but callers of individual rules such as [Self::expression_rule] should check them too
+    pub fn errors(&self) -> &[ParseError<'a>] {
+        &self.errors
+    }
+    pub(crate) fn is_at_end(&self) -> bool {
         self.peek_current_token().is_end_of_file()
     }
+    /// Past the last token (or when there are no tokens) this is an end of file token
     fn peek_current_token(&self) -> Token<'a> {
-        self.tokens[self.current_token_index]
+        self.tokens
+            .get(self.current_token_index)
+            .copied()
+            .unwrap_or_else(|| self.end_of_file())
     }
+    /// Only called right after a token is consumed. Before the first token this is an end of file token
     fn peek_previous_token(&self) -> Token<'a> {
-        self.tokens[self.current_token_index - 1]
+        self.current_token_index
+            .checked_sub(1)
+            .and_then(|index| self.tokens.get(index))
+            .copied()
+            .unwrap_or_else(|| self.end_of_file())
+    }
+    fn end_of_file(&self) -> Token<'a> {
+        self.tokens.last().map_or_else(
+            || Token::end_of_file(1),
+            |last| {
+                Token::end_of_file(last.line_number())
+                    .with_span(Span::new(last.span().end, last.span().end))
+            },
+        )
+    }
+    /// From `start` through the end of the last consumed token.
+    /// A [CustomStatementParser] can use it with its keyword's span to get the span of the statement it parsed
+    pub fn span_since(&self, start: Span) -> Span {
+        start.to(self.peek_previous_token().span())
+    }
+    /// Runs `rule` one level deeper
+    /// # Error
+    /// When that would be deeper than [MAX_NESTING_DEPTH]
+    fn nested<T>(
+        &mut self,
+        rule: impl FnOnce(&mut Self) -> Result<T, ParseError<'a>>,
+    ) -> Result<T, ParseError<'a>> {
+        if self.nesting_depth >= MAX_NESTING_DEPTH {
+            return Err(ParseError::new(
+                ParseErrorKind::TooDeeplyNested,
+                self.peek_current_token(),
+            ));
+        }
+
+        self.nesting_depth += 1;
+        let result = rule(self);
+        self.nesting_depth -= 1;
+
+        result
+    }
+    /// Runs `rule`, which builds a chain like `1 + 2 + 3` or `a.b.c` by calling [Self::deepen] for each link,
+    /// and returns to the depth it started at
+    fn chain<T>(
+        &mut self,
+        rule: impl FnOnce(&mut Self) -> Result<T, ParseError<'a>>,
+    ) -> Result<T, ParseError<'a>> {
+        let depth = self.nesting_depth;
+        let result = rule(self);
+        self.nesting_depth = depth;
+
+        result
+    }
+    /// Counts one more level until the end of the enclosing [Self::chain]. The tree a chain builds is as deep as it is long
+    /// # Error
+    /// At the previous token, which joined the link, when that would be deeper than [MAX_NESTING_DEPTH]
+    fn deepen(&mut self) -> Result<(), ParseError<'a>> {
+        if self.nesting_depth >= MAX_NESTING_DEPTH {
+            return Err(ParseError::new(
+                ParseErrorKind::TooDeeplyNested,
+                self.peek_previous_token(),
+            ));
+        }
+        self.nesting_depth += 1;
+
+        Ok(())
     }
 }
-impl<'a> TryFrom<Lexer<'a>> for Parser<'a> {
-    type Error = ParseError<'a>;
-    fn try_from(value: Lexer<'a>) -> Result<Self, Self::Error> {
-        let tokens = value.collect::<Result<_, _>>()?;
-        Ok(Self::new(tokens))
+/// Replaces the [Token::symbol] of every identifier and keyword in `tokens` with one from `interner`
+fn reintern<'a>(tokens: &mut [Token<'a>], interner: &mut Interner<'a>) {
+    for token in tokens {
+        if token.symbol().is_some() {
+            *token = token.with_symbol(interner.intern(token.lexeme()));
+        }
     }
 }
-impl<'a> Parser<'a> {
-    fn expression_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
-        self.equality_rule()
+
+// Chunked input
+impl<'a, S: TokenSource<'a>> Parser<'a, S> {
+    /// Appends `tokens` (the next chunk of input, ending with its own [TokenKind::EndOfFile]) in place of the current end of file
+    /// and rewinds to the first token, so that parsing again sees the whole input.
+    /// Used after parsing fails with only [ParseError::is_incomplete] errors, such as a REPL line that opens a block
+    /// # Error
+    /// [FeedError::Streaming] when the parser was made from a [Lexer], since it does not keep the tokens it has consumed.
+    /// Nothing is changed, and the joined input can be parsed again by a new parser instead
+    pub fn feed_more_tokens(
+        &mut self,
+        tokens: impl IntoIterator<Item = Token<'a>>,
+    ) -> Result<(), FeedError> {
+        if self.lexer.is_some() {
+            return Err(FeedError::Streaming);
+        }
+
+        if self.tokens.last().is_some_and(Token::is_end_of_file) {
+            self.tokens.pop();
+        }
+
+        let start = self.tokens.len();
+        self.tokens.extend(tokens);
+        reintern(&mut self.tokens[start..], &mut self.interner);
+
+        self.current_token_index = 0;
+        self.nesting_depth = 0;
+        self.errors.clear();
+
+        Ok(())
     }
-    fn equality_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
-        let mut expression = self.comparison_rule()?;
+}
 
-        while self.consume_current_token_of_kind(TokenKind::EQUALITY_OPERATORS) {
-            expression = Box::new(Expression::Binary {
-                left_operand: expression,
-                operator: self.peek_previous_token(),
-                right_operand: self.comparison_rule()?,
-            });
+/// Why [Parser::feed_more_tokens] could not add the next chunk of input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedError {
+    /// the parser streams its tokens from a [TokenSource], so it cannot rewind to the first token.
+    /// Make it with [Parser::new] to feed it
+    Streaming,
+}
+impl core::fmt::Display for FeedError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FeedError::Streaming => write!(
+                f,
+                "Only a parser made from a list of tokens can be fed more tokens"
+            ),
         }
+    }
+}
+impl core::error::Error for FeedError {}
 
-        Ok(expression)
+/// Tokens are lexed one at a time as the parser needs them, skipping trivia.
+/// Lexer errors become [ParseErrorKind::LexerError]s in [Parser::errors]
+impl<'a, S: TokenSource<'a>> From<S> for Parser<'a, S> {
+    fn from(value: S) -> Self {
+        let mut parser = Self::from_parts(Vec::new(), Interner::new());
+        parser.lexer = Some(value);
+        parser.lex_next_token();
+        parser
     }
-    fn comparison_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
-        let mut expression = self.term_rule()?;
+}
 
-        while self.consume_current_token_of_kind(TokenKind::COMPARISON_OPERATORS) {
-            expression = Box::new(Expression::Binary {
-                left_operand: expression,
-                operator: self.peek_previous_token(),
-                right_operand: self.term_rule()?,
-            });
+// Statements
+impl<'a, S: TokenSource<'a>> Parser<'a, S> {
+    /// Parses every token as a lox program.
+    /// After an error parsing continues from the next statement (see [Self::synchronize]) so every error in the program is returned,
+    /// lexer errors included, sorted by where they start in the source
+    pub fn parse(self) -> Result<Program<'a>, Vec<ParseError<'a>>> {
+        self.parse_with_warnings().0
+    }
+    /// Like [Self::parse], but also returns the [TokenSource::warnings], which are there whether or not the program parsed
+    pub fn parse_with_warnings(
+        mut self,
+    ) -> (
+        Result<Program<'a>, Vec<ParseError<'a>>>,
+        Vec<LexerWarning<'a>>,
+    ) {
+        let mut statements = Vec::new();
+
+        while !self.is_at_end() {
+            if let Some(statement) = self.recovering_declaration_rule() {
+                statements.push(statement);
+            }
         }
 
-        Ok(expression)
+        let warnings = self
+            .lexer
+            .as_ref()
+            .map_or_else(Vec::new, |lexer| lexer.warnings().to_vec());
+
+        if !self.errors.is_empty() {
+            // stable, so errors at the same position stay in the order they were found
+            self.errors.sort_by_key(|error| error.span().start);
+            return (Err(self.errors), warnings);
+        }
+
+        let interner = match self.lexer {
+            Some(lexer) => lexer.into_interner(),
+            None => self.interner,
+        };
+
+        (Ok(Program::new(statements, interner)), warnings)
+    }
+    /// Like [Self::parse], but returns the statements as [crate::abstract_syntax_tree_visitor_pattern] nodes for visitor-based tools.
+    /// Spans and the interner are dropped
+    pub fn parse_into_visitor_pattern(
+        self,
+    ) -> Result<Vec<abstract_syntax_tree_visitor_pattern::Statement<'a>>, Vec<ParseError<'a>>> {
+        let program = self.parse()?;
+        Ok(program
+            .into_statements()
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+    /// Like [Self::declaration_rule], but an error is recorded and skipped with [Self::synchronize]
+    fn recovering_declaration_rule(&mut self) -> Option<Statement<'a>> {
+        let error_count = self.errors.len();
+
+        match self.declaration_rule() {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                // a statement containing a skipped lexeme usually fails because of it, so only the lexer error is reported
+                let has_lexer_error = self.errors[error_count..]
+                    .iter()
+                    .any(|error| matches!(error.kind, ParseErrorKind::LexerError(_)));
+                if !has_lexer_error {
+                    self.errors.push(error);
+                }
+                self.synchronize();
+                None
+            }
+        }
+    }
+    /// Skips tokens until the next likely statement boundary: just past a `;`, or before a keyword that starts a statement.
+    /// Call after a [ParseError] so parsing can resume without reporting errors caused by the first one
+    pub fn synchronize(&mut self) {
+        self.consume_current_token();
+
+        while !self.is_at_end() {
+            if self.peek_previous_token().kind() == TokenKind::Semicolon {
+                return;
+            }
+
+            let kind = self.peek_current_token().kind();
+            if matches!(kind, TokenKind::CustomKeyword(_)) {
+                return;
+            }
+            if kind.is_any(&[
+                TokenKind::Class,
+                TokenKind::Fun,
+                TokenKind::Var,
+                TokenKind::For,
+                TokenKind::If,
+                TokenKind::While,
+                TokenKind::Print,
+                TokenKind::Return,
+            ]) {
+                return;
+            }
+
+            self.consume_current_token();
+        }
+    }
+    fn declaration_rule(&mut self) -> Result<Statement<'a>, ParseError<'a>> {
+        if self.consume_current_token_of_kind(&[TokenKind::Var]) {
+            return self.variable_declaration_rule();
+        }
+
+        self.statement_rule()
     }
-    fn term_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
-        let mut expression = self.factor_rule()?;
+    /// Only call after `var`
+    fn variable_declaration_rule(&mut self) -> Result<Statement<'a>, ParseError<'a>> {
+        let start = self.peek_previous_token().span();
+        let name = self
+            .consume_expected_token(TokenKind::Identifier, ParseErrorKind::ExpectedVariableName)?;
+
+        let initializer = if self.consume_current_token_of_kind(&[TokenKind::Equal]) {
+            Some(self.expression_rule()?)
+        } else {
+            None
+        };
+
+        self.consume_expected_token(TokenKind::Semicolon, ParseErrorKind::MissingSemicolon)?;
 
-        while self.consume_current_token_of_kind(TokenKind::TERM_OPERATORS) {
-            expression = Box::new(Expression::Binary {
-                left_operand: expression,
-                operator: self.peek_previous_token(),
-                right_operand: self.factor_rule()?,
+        Ok(Statement::Var {
+            name,
+            initializer,
+            span: self.span_since(start),
+        })
+    }
+    fn statement_rule(&mut self) -> Result<Statement<'a>, ParseError<'a>> {
+        if self.consume_current_token_of_kind(&[TokenKind::Print]) {
+            let start = self.peek_previous_token().span();
+            let expression = self.expression_rule()?;
+            self.consume_expected_token(TokenKind::Semicolon, ParseErrorKind::MissingSemicolon)?;
+            return Ok(Statement::Print {
+                expression,
+                span: self.span_since(start),
+            });
+        }
+        if self.consume_current_token_of_kind(&[TokenKind::LeftBrace]) {
+            let start = self.peek_previous_token().span();
+            let statements = self.nested(Self::block_rule)?;
+            return Ok(Statement::Block {
+                statements,
+                span: self.span_since(start),
             });
         }
+        if self.consume_current_token_of_kind(&[TokenKind::If]) {
+            return self.if_statement_rule();
+        }
+        if self.consume_current_token_of_kind(&[TokenKind::While]) {
+            return self.while_statement_rule();
+        }
+        if self.consume_current_token_of_kind(&[TokenKind::For]) {
+            return self.for_statement_rule();
+        }
+        if let TokenKind::CustomKeyword(id) = self.peek_current_token().kind() {
+            self.consume_current_token();
+            let keyword = self.peek_previous_token();
 
-        Ok(expression)
+            return match self.custom_statement_parsers.get(&id) {
+                Some(parser) => parser(self, keyword),
+                None => self.custom_statement_rule(keyword),
+            };
+        }
+
+        self.expression_statement_rule()
+    }
+    /// The default syntax of a [Statement::Custom]. Only call after its keyword
+    fn custom_statement_rule(
+        &mut self,
+        keyword: Token<'a>,
+    ) -> Result<Statement<'a>, ParseError<'a>> {
+        let mut expressions = Vec::new();
+
+        if !self.is_current_token(TokenKind::Semicolon)
+            && !self.is_current_token(TokenKind::LeftBrace)
+        {
+            expressions.push(*self.expression_rule()?);
+            while self.consume_current_token_of_kind(&[TokenKind::Comma]) {
+                expressions.push(*self.expression_rule()?);
+            }
+        }
+
+        let statements = if self.consume_current_token_of_kind(&[TokenKind::LeftBrace]) {
+            self.nested(Self::block_rule)?
+        } else {
+            self.consume_expected_token(TokenKind::Semicolon, ParseErrorKind::MissingSemicolon)?;
+            Vec::new()
+        };
+
+        Ok(Statement::Custom {
+            keyword,
+            expressions,
+            statements,
+            span: self.span_since(keyword.span()),
+        })
+    }
+    fn expression_statement_rule(&mut self) -> Result<Statement<'a>, ParseError<'a>> {
+        let expression = self.expression_rule()?;
+        self.consume_expected_token(TokenKind::Semicolon, ParseErrorKind::MissingSemicolon)?;
+        Ok(Statement::Expression {
+            span: self.span_since(expression.span()),
+            expression,
+        })
+    }
+    /// Only call after `{`. Consumes the closing `}`
+    fn block_rule(&mut self) -> Result<Vec<Statement<'a>>, ParseError<'a>> {
+        let opening_brace = self.peek_previous_token();
+        let mut statements = Vec::new();
+
+        // recovering inside the block keeps its closing `}` from being parsed as a statement after an error
+        while !self.is_at_end() && !self.is_current_token(TokenKind::RightBrace) {
+            if let Some(statement) = self.recovering_declaration_rule() {
+                statements.push(statement);
+            }
+        }
+
+        self.consume_expected_token(TokenKind::RightBrace, ParseErrorKind::MissingRightBrace)
+            .map_err(|error| error.opened_by(opening_brace))?;
+
+        Ok(statements)
+    }
+    /// Parses `( expression )`, the condition of an `if` or `while`
+    fn condition_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
+        let opening_parenthesis = self.consume_expected_token(
+            TokenKind::LeftParentheses,
+            ParseErrorKind::MissingLeftParenthesis,
+        )?;
+        let condition = self.expression_rule()?;
+        self.consume_expected_token(
+            TokenKind::RightParentheses,
+            ParseErrorKind::MissingRightParenthesis,
+        )
+        .map_err(|error| error.opened_by(opening_parenthesis))?;
+
+        Ok(condition)
+    }
+    /// Only call after `if`
+    fn if_statement_rule(&mut self) -> Result<Statement<'a>, ParseError<'a>> {
+        let start = self.peek_previous_token().span();
+        let condition = self.condition_rule()?;
+        let then_branch = Box::new(self.nested(Self::statement_rule)?);
+
+        let else_branch = if self.consume_current_token_of_kind(&[TokenKind::Else]) {
+            Some(Box::new(self.nested(Self::statement_rule)?))
+        } else {
+            None
+        };
+
+        Ok(Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            span: self.span_since(start),
+        })
+    }
+    /// Only call after `while`
+    fn while_statement_rule(&mut self) -> Result<Statement<'a>, ParseError<'a>> {
+        let start = self.peek_previous_token().span();
+        let condition = self.condition_rule()?;
+        let body = Box::new(self.nested(Self::statement_rule)?);
+
+        Ok(Statement::While {
+            condition,
+            body,
+            span: self.span_since(start),
+        })
+    }
+    /// Only call after `for`
+    fn for_statement_rule(&mut self) -> Result<Statement<'a>, ParseError<'a>> {
+        let start = self.peek_previous_token().span();
+        let opening_parenthesis = self.consume_expected_token(
+            TokenKind::LeftParentheses,
+            ParseErrorKind::MissingLeftParenthesis,
+        )?;
+
+        let initializer = if self.consume_current_token_of_kind(&[TokenKind::Semicolon]) {
+            None
+        } else if self.consume_current_token_of_kind(&[TokenKind::Var]) {
+            Some(Box::new(self.variable_declaration_rule()?))
+        } else {
+            Some(Box::new(self.expression_statement_rule()?))
+        };
+
+        let condition = if self.is_current_token(TokenKind::Semicolon) {
+            None
+        } else {
+            Some(self.expression_rule()?)
+        };
+        self.consume_expected_token(TokenKind::Semicolon, ParseErrorKind::MissingSemicolon)?;
+
+        let increment = if self.is_current_token(TokenKind::RightParentheses) {
+            None
+        } else {
+            Some(self.expression_rule()?)
+        };
+        self.consume_expected_token(
+            TokenKind::RightParentheses,
+            ParseErrorKind::MissingRightParenthesis,
+        )
+        .map_err(|error| error.opened_by(opening_parenthesis))?;
+
+        let body = Box::new(self.nested(Self::statement_rule)?);
+
+        Ok(Statement::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            span: self.span_since(start),
+        })
+    }
+}
+
+// Expressions
+impl<'a, S: TokenSource<'a>> Parser<'a, S> {
+    pub fn expression_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
+        self.binary_rule(precedence::ASSIGNMENT)
+    }
+    /// Parses operands joined by operators from the [precedence::precedence] table that bind at least as tightly as `minimum_level`.
+    /// Adding a binary operator only needs a row in that table
+    fn binary_rule(&mut self, minimum_level: u8) -> Result<Box<Expression<'a>>, ParseError<'a>> {
+        self.chain(|parser| parser.binary_chain(minimum_level))
     }
-    fn factor_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
+    fn binary_chain(&mut self, minimum_level: u8) -> Result<Box<Expression<'a>>, ParseError<'a>> {
         let mut expression = self.unary_rule()?;
 
-        while self.consume_current_token_of_kind(TokenKind::FACTOR_OPERATORS) {
-            expression = Box::new(Expression::Binary {
-                left_operand: expression,
-                operator: self.peek_previous_token(),
-                right_operand: self.unary_rule()?,
-            });
+        while let Some((level, associativity)) =
+            precedence::precedence(self.peek_current_token().kind())
+                .filter(|(level, _)| *level >= minimum_level)
+        {
+            self.consume_current_token();
+            let operator = self.peek_previous_token();
+
+            let right_operand = match associativity {
+                // each operator of a chain like `1 + 2 + 3` nests the expression so far one deeper
+                Associativity::Left => {
+                    self.deepen()?;
+                    self.binary_rule(level + 1)?
+                }
+                Associativity::Right => self.nested(|parser| parser.binary_rule(level))?,
+            };
+
+            let span = expression.span().to(right_operand.span());
+            expression = match operator.kind() {
+                TokenKind::Equal => match *expression {
+                    Expression::Variable(name) => Box::new(Expression::Assign {
+                        name,
+                        value: right_operand,
+                        span,
+                    }),
+                    _ => {
+                        return Err(ParseError::new(
+                            ParseErrorKind::InvalidAssignmentTarget,
+                            operator,
+                        ))
+                    }
+                },
+                TokenKind::And | TokenKind::Or => Box::new(Expression::Logical {
+                    left_operand: expression,
+                    operator,
+                    right_operand,
+                    span,
+                }),
+                _ => Box::new(Expression::Binary {
+                    left_operand: expression,
+                    operator,
+                    right_operand,
+                    span,
+                }),
+            };
         }
 
         Ok(expression)
     }
     fn unary_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
         if self.consume_current_token_of_kind(TokenKind::UNARY_OPERATORS) {
+            let operator = self.peek_previous_token();
+            let right_operand = self.nested(Self::unary_rule)?;
             Ok(Box::new(Expression::Unary {
-                operator: self.peek_previous_token(),
-                right_operand: self.unary_rule()?,
+                span: operator.span().to(right_operand.span()),
+                operator,
+                right_operand,
             }))
         } else {
-            self.primary_rule()
+            self.call_rule()
+        }
+    }
+    fn call_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
+        self.chain(Self::call_chain)
+    }
+    fn call_chain(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
+        let mut expression = self.primary_rule()?;
+
+        loop {
+            // each call or property of a chain like `a.b(c).d` nests the expression so far one deeper
+            if self.consume_current_token_of_kind(&[TokenKind::LeftParentheses]) {
+                self.deepen()?;
+                expression = self.finish_call_rule(expression)?;
+            } else if self.consume_current_token_of_kind(&[TokenKind::Dot]) {
+                self.deepen()?;
+                let name = self.consume_expected_token(
+                    TokenKind::Identifier,
+                    ParseErrorKind::ExpectedPropertyName,
+                )?;
+                expression = Box::new(Expression::Get {
+                    span: expression.span().to(name.span()),
+                    object: expression,
+                    name,
+                });
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+    /// Only call after the `(` following `callee`. Consumes the closing `)`
+    fn finish_call_rule(
+        &mut self,
+        callee: Box<Expression<'a>>,
+    ) -> Result<Box<Expression<'a>>, ParseError<'a>> {
+        let opening_parenthesis = self.peek_previous_token();
+        let mut arguments = Vec::new();
+
+        if !self.is_current_token(TokenKind::RightParentheses) {
+            loop {
+                if arguments.len() == MAX_ARGUMENTS {
+                    // the call is still well formed, so parsing carries on
+                    self.errors.push(ParseError::new(
+                        ParseErrorKind::TooManyArguments,
+                        self.peek_current_token(),
+                    ));
+                }
+                arguments.push(*self.nested(Self::expression_rule)?);
+
+                if !self.consume_current_token_of_kind(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
         }
+
+        let closing_parenthesis = self
+            .consume_expected_token(
+                TokenKind::RightParentheses,
+                ParseErrorKind::MissingRightParenthesisAfterArguments,
+            )
+            .map_err(|error| error.opened_by(opening_parenthesis))?;
+
+        Ok(Box::new(Expression::Call {
+            span: callee.span().to(closing_parenthesis.span()),
+            callee,
+            closing_parenthesis,
+            arguments,
+        }))
     }
     fn primary_rule(&mut self) -> Result<Box<Expression<'a>>, ParseError<'a>> {
         if self.consume_current_token_of_kind(&[TokenKind::False]) {
@@ -130,81 +783,491 @@ impl<'a> Parser<'a> {
         if self.consume_current_token_of_kind(&[TokenKind::Number, TokenKind::String]) {
             return Ok(Box::new(Expression::Literal(self.peek_previous_token())));
         }
+        if let TokenKind::Custom(id) = self.peek_current_token().kind() {
+            self.consume_current_token();
+            let token = self.peek_previous_token();
+
+            return match self.custom_literal_parsers.get(&id) {
+                Some(parser) => parser(token).map_err(|kind| ParseError::new(kind, token)),
+                None => Ok(Box::new(Expression::Literal(token))),
+            };
+        }
+        if self.consume_current_token_of_kind(&[TokenKind::Identifier]) {
+            return Ok(Box::new(Expression::Variable(self.peek_previous_token())));
+        }
         if self.consume_current_token_of_kind(&[TokenKind::LeftParentheses]) {
-            let expression = self.expression_rule()?;
-            if !self.consume_current_token_of_kind(&[TokenKind::RightParentheses]) {
-                return Err(ParseError {
-                    kind: ParseErrorKind::MissingRightParenthesis,
-                    token: self.peek_current_token(),
-                });
-            }
-            return Ok(Box::new(Expression::Grouping(expression)));
+            let opening_parenthesis = self.peek_previous_token();
+            let start = opening_parenthesis.span();
+            let expression = self.nested(Self::expression_rule)?;
+            self.consume_expected_token(
+                TokenKind::RightParentheses,
+                ParseErrorKind::MissingRightParenthesis,
+            )
+            .map_err(|error| error.opened_by(opening_parenthesis))?;
+            return Ok(Box::new(Expression::Grouping {
+                expression,
+                span: self.span_since(start),
+            }));
         }
 
-        Err(ParseError {
-            kind: ParseErrorKind::ExpectedExpression,
-            token: self.peek_current_token(),
-        })
+        Err(self.error_at_current_token(ParseErrorKind::ExpectedExpression))
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ParseError<'a> {
     kind: ParseErrorKind<'a>,
     token: Token<'a>,
+    /// where the `(` or `{` left unclosed is, when the error is a missing closing one
+    opening: Option<Span>,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParseErrorKind<'a> {
     MissingRightParenthesis,
     ExpectedExpression,
     UnaryExpressionMissingOperand,
-    LexerError(LexerError<'a>),
+    TooDeeplyNested,
+    MissingSemicolon,
+    ExpectedVariableName,
+    InvalidAssignmentTarget,
+    MissingLeftParenthesis,
+    MissingRightBrace,
+    TooManyArguments,
+    MissingRightParenthesisAfterArguments,
+    ExpectedPropertyName,
+    /// The input ended where `expected` would otherwise have been reported. More input may complete the program
+    UnexpectedEof {
+        expected: Box<ParseErrorKind<'a>>,
+    },
+    LexerError(Box<LexerError<'a>>),
+}
+impl<'a> ParseError<'a> {
+    pub const fn new(kind: ParseErrorKind<'a>, token: Token<'a>) -> Self {
+        Self {
+            kind,
+            token,
+            opening: None,
+        }
+    }
+    /// Records the `(` or `{` that `self` is missing the closing pair of
+    pub const fn opened_by(mut self, opening: Token<'a>) -> Self {
+        self.opening = Some(opening.span());
+        self
+    }
+    pub const fn kind(&self) -> &ParseErrorKind<'a> {
+        &self.kind
+    }
+    pub const fn token(&self) -> Token<'a> {
+        self.token
+    }
+    pub const fn span(&self) -> Span {
+        self.token.span()
+    }
+    /// Where the `(` or `{` left unclosed is, for errors about a missing closing one
+    pub const fn opening(&self) -> Option<Span> {
+        self.opening
+    }
+    /// Displays the error followed by the line of `source` it is on, marking the token it was found at
+    pub fn with_source<'s>(&'s self, source: &'s str) -> WithSource<'s, Self> {
+        WithSource::new(self, SourceExcerpt::new(source, self.span()))
+    }
+    /// True when the error is only because the input ended early, such as an unclosed block or string.
+    /// Appending more input with [Parser::feed_more_tokens] may fix it
+    pub fn is_incomplete(&self) -> bool {
+        match &self.kind {
+            ParseErrorKind::UnexpectedEof { .. } => true,
+            ParseErrorKind::LexerError(lexer_error) => matches!(
+                lexer_error.kind(),
+                LexerErrorKind::UnterminatedStringLiteral
+                    | LexerErrorKind::UnterminatedCustomLiteral
+            ),
+            _ => false,
+        }
+    }
 }
 impl<'a> From<LexerError<'a>> for ParseError<'a> {
     fn from(value: LexerError<'a>) -> Self {
-        Self {
-            token: value.token(),
-            kind: ParseErrorKind::LexerError(value),
+        let token = value.token();
+        Self::new(ParseErrorKind::LexerError(Box::new(value)), token)
+    }
+}
+impl ParseErrorKind<'_> {
+    /// Stable identifier used to look up this error's message in [crate::messages]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            ParseErrorKind::ExpectedExpression => "P0001",
+            ParseErrorKind::UnaryExpressionMissingOperand => "P0002",
+            ParseErrorKind::MissingRightParenthesis => "P0003",
+            ParseErrorKind::TooDeeplyNested => "P0004",
+            ParseErrorKind::MissingSemicolon => "P0005",
+            ParseErrorKind::ExpectedVariableName => "P0006",
+            ParseErrorKind::InvalidAssignmentTarget => "P0007",
+            ParseErrorKind::MissingLeftParenthesis => "P0008",
+            ParseErrorKind::MissingRightBrace => "P0009",
+            ParseErrorKind::UnexpectedEof { .. } => "P0010",
+            ParseErrorKind::TooManyArguments => "P0011",
+            ParseErrorKind::MissingRightParenthesisAfterArguments => "P0012",
+            ParseErrorKind::ExpectedPropertyName => "P0013",
+            ParseErrorKind::LexerError(lexer_error) => lexer_error.kind().code(),
         }
     }
 }
-impl std::fmt::Display for ParseErrorKind<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for ParseErrorKind<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            ParseErrorKind::MissingRightParenthesis => write!(f, "Missing closing parenthesis"),
-            ParseErrorKind::ExpectedExpression => write!(f, "No rule matched. Expected expression"),
-            ParseErrorKind::UnaryExpressionMissingOperand => {
-                write!(f, "Unary operator must have an expression after")
-            }
             ParseErrorKind::LexerError(lexer_error) => write!(f, "{}", lexer_error),
+            ParseErrorKind::UnexpectedEof { expected } => {
+                write!(f, "{}: {}", messages::message(self.code()), expected)
+            }
+            _ => write!(f, "{}", messages::message(self.code())),
         }
     }
 }
 
-impl std::fmt::Display for ParseError<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Error parsing {:?} token: \"{}\" on line {}: {}",
-            self.token.kind(),
-            self.token.lexeme(),
-            self.token.line_number(),
-            self.kind
-        )
+impl core::fmt::Display for ParseError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let output = messages::format_message(
+            messages::PARSE_ERROR_TEMPLATE,
+            &[
+                ("kind", &format!("{:?}", self.token.kind())),
+                ("lexeme", &self.token.lexeme()),
+                ("line", &self.token.line_number()),
+                ("message", &self.kind),
+            ],
+        );
+
+        write!(f, "{}", output)
     }
 }
 
 #[test]
 fn test_parser() {
     const SOURCE: &str = include_str!("../simple_example.lox");
-    let lexer = Lexer::new(SOURCE);
-    let mut parser = Parser::try_from(lexer).unwrap();
+    let source = SOURCE.replace('\n', ";\n") + ";";
 
-    loop {
-        match parser.equality_rule() {
-            Ok(expression) => println!("{}", expression),
-            Err(parse_error) if !parse_error.token.is_end_of_file() => eprintln!("{}", parse_error),
-            _ => break,
-        }
+    let program = Parser::from(Lexer::new(&source)).parse().unwrap();
+
+    assert_eq!(program.statements().len(), SOURCE.lines().count());
+    for statement in program.statements() {
+        assert!(matches!(statement, Statement::Expression { .. }));
+    }
+}
+
+#[test]
+fn statement_errors() {
+    let cases = [
+        ("print 1;;", ParseErrorKind::ExpectedExpression),
+        ("var = 1;", ParseErrorKind::ExpectedVariableName),
+        ("1 + a = 2;", ParseErrorKind::InvalidAssignmentTarget),
+        ("while true {}", ParseErrorKind::MissingLeftParenthesis),
+        (
+            "for (;;) { print 1;",
+            ParseErrorKind::UnexpectedEof {
+                expected: Box::new(ParseErrorKind::MissingRightBrace),
+            },
+        ),
+        ("if (a b", ParseErrorKind::MissingRightParenthesis),
+    ];
+
+    for (source, kind) in cases {
+        let errors = Parser::from(Lexer::new(source)).parse().unwrap_err();
+        assert_eq!(errors[0].kind(), &kind, "{}", source);
+    }
+}
+
+#[test]
+fn parse_error_span() {
+    let mut parser = Parser::from(Lexer::new("(1 + 2 ;"));
+    let error = parser.expression_rule().unwrap_err();
+    assert_eq!(error.kind(), &ParseErrorKind::MissingRightParenthesis);
+    assert_eq!(error.span(), Span::new(7, 8));
+
+    let errors = Parser::from(Lexer::new("1 + \"two")).parse().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span(), Span::new(4, 8));
+}
+
+#[test]
+fn adversarial_token_lists() {
+    let mut parser = Parser::new(Vec::new());
+    assert!(parser.is_at_end());
+    assert!(parser.expression_rule().unwrap_err().is_incomplete());
+
+    let nested = |depth| alloc::format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+    let source = nested(MAX_NESTING_DEPTH);
+    assert!(Parser::from(Lexer::new(&source)).expression_rule().is_ok());
+    let source = nested(MAX_NESTING_DEPTH + 1);
+    let mut parser = Parser::from(Lexer::new(&source));
+    assert_eq!(
+        parser.expression_rule().unwrap_err().kind(),
+        &ParseErrorKind::TooDeeplyNested
+    );
+
+    let chain = |length| alloc::format!("1{}", " + 1".repeat(length));
+    let source = chain(MAX_NESTING_DEPTH);
+    assert!(Parser::from(Lexer::new(&source)).expression_rule().is_ok());
+    let source = chain(MAX_NESTING_DEPTH + 1);
+    let mut parser = Parser::from(Lexer::new(&source));
+    assert_eq!(
+        parser.expression_rule().unwrap_err().kind(),
+        &ParseErrorKind::TooDeeplyNested
+    );
+}
+
+#[test]
+fn synchronize_skips_to_next_statement() {
+    let mut parser = Parser::from(Lexer::new("1 + + 2 * 3; print 4; var x = ) if"));
+
+    assert!(parser.declaration_rule().is_err());
+    parser.synchronize();
+    assert!(matches!(
+        parser.declaration_rule(),
+        Ok(Statement::Print { .. })
+    ));
+
+    assert!(parser.declaration_rule().is_err());
+    parser.synchronize();
+    assert!(parser.is_current_token(TokenKind::If));
+}
+
+#[test]
+fn custom_literal_parser() {
+    use crate::lexer::{CustomLiteral, LexerConfig};
+
+    fn parse_color(token: Token) -> Result<Box<Expression>, ParseErrorKind> {
+        let digits = &token.lexeme()["#rgb(".len()..token.lexeme().len() - 1];
+        let value =
+            u32::from_str_radix(digits, 16).map_err(|_| ParseErrorKind::ExpectedExpression)?;
+        let number = Token::new(TokenKind::Number, token.lexeme(), token.line_number())
+            .with_span(token.span())
+            .with_number_value(value as f64);
+        Ok(Box::new(Expression::Literal(number)))
+    }
+
+    let config = LexerConfig::new()
+        .custom_literal(CustomLiteral::new(0, "#rgb(", ")"))
+        .custom_literal(CustomLiteral::new(1, "@date\"", "\""));
+    let parse = |source| {
+        Parser::from(Lexer::with_config(source, config.clone()))
+            .with_custom_literal_parser(0, parse_color)
+            .expression_rule()
+    };
+
+    let Expression::Binary {
+        left_operand,
+        right_operand,
+        ..
+    } = *parse("#rgb(ff8000) == @date\"today\"").unwrap()
+    else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(
+        *left_operand,
+        Expression::Literal(
+            Token::new(TokenKind::Number, "#rgb(ff8000)", 1)
+                .with_span(Span::new(0, 12))
+                .with_number_value(0xff8000 as f64)
+        )
+    );
+    assert!(
+        matches!(*right_operand, Expression::Literal(token) if token.kind() == TokenKind::Custom(1))
+    );
+
+    let error = parse("#rgb(orange)").unwrap_err();
+    assert_eq!(error.kind(), &ParseErrorKind::ExpectedExpression);
+    assert_eq!(error.span(), Span::new(0, 12));
+}
+
+#[test]
+fn parse_reports_every_error() {
+    let errors = Parser::from(Lexer::new(
+        "print 1 +;\nvar = 2;\nprint 3;\n{ var x = (4; }\nprint 5",
+    ))
+    .parse()
+    .unwrap_err();
+
+    let errors = errors
+        .iter()
+        .map(|error| (error.kind().clone(), error.token().line_number()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            (ParseErrorKind::ExpectedExpression, 1),
+            (ParseErrorKind::ExpectedVariableName, 2),
+            (ParseErrorKind::MissingRightParenthesis, 4),
+            (
+                ParseErrorKind::UnexpectedEof {
+                    expected: Box::new(ParseErrorKind::MissingSemicolon)
+                },
+                5
+            ),
+        ]
+    );
+}
+
+#[test]
+fn custom_statements() {
+    use crate::{abstract_syntax_tree::program_to_canonical_string, dialect::Dialect};
+    use alloc::vec;
+
+    const REPEAT: u16 = 0;
+    const EXPECT: u16 = 1;
+
+    /// `expect <expression> because <string>;`
+    fn parse_expect<'a>(
+        parser: &mut Parser<'a>,
+        keyword: Token<'a>,
+    ) -> Result<Statement<'a>, ParseError<'a>> {
+        let condition = parser.expression_rule()?;
+        parser.consume_expected_token(TokenKind::Identifier, ParseErrorKind::ExpectedExpression)?;
+        let reason = parser.expression_rule()?;
+        parser.consume_expected_token(TokenKind::Semicolon, ParseErrorKind::MissingSemicolon)?;
+
+        Ok(Statement::Custom {
+            keyword,
+            expressions: vec![*condition, *reason],
+            statements: Vec::new(),
+            span: parser.span_since(keyword.span()),
+        })
     }
+
+    let dialect = Dialect::new("scripting")
+        .with_statement_keyword("repeat", REPEAT)
+        .with_statement_keyword("expect", EXPECT);
+
+    let program = Parser::from(Lexer::with_config(
+        "repeat 3 { print 1; } expect 1 < 2 because \"math\";",
+        dialect.lexer_config(),
+    ))
+    .with_custom_statement_parser(EXPECT, parse_expect)
+    .parse()
+    .unwrap();
+
+    assert_eq!(
+        program_to_canonical_string(&program),
+        "\
+lox-ast v1
+Custom \"repeat\"
+  Literal Number \"3\"
+  Print
+    Literal Number \"1\"
+Custom \"expect\"
+  Binary \"<\"
+    Literal Number \"1\"
+    Literal Number \"2\"
+  Literal String \"math\"
+"
+    );
+}
+
+#[test]
+fn precedence_climbing() {
+    use alloc::string::ToString;
+
+    let cases = [
+        (
+            "a = b or c and d == e < f + g * -h",
+            "(= a (or b (and c (== d (< e (+ f (* g (- h))))))))",
+        ),
+        ("a = b = c", "(= a (= b c))"),
+        ("1 - 2 - 3 * 4 / 5", "(- (- 1 2) (/ (* 3 4) 5))"),
+        ("a or b or c", "(or (or a b) c)"),
+    ];
+
+    for (source, expected) in cases {
+        let expression = Parser::from(Lexer::new(source)).expression_rule().unwrap();
+        assert_eq!(expression.to_string(), expected);
+    }
+}
+
+#[test]
+fn incomplete_input() {
+    let lines = ["if (ready) {", "  print \"go\";", "}"];
+
+    let mut parser = Parser::new(Lexer::new(lines[0]).map(Result::unwrap).collect());
+    for line in &lines[1..] {
+        let errors = parser.clone().parse().unwrap_err();
+        assert!(errors.iter().all(ParseError::is_incomplete));
+
+        let tokens = Lexer::new(line).map(Result::unwrap);
+        parser.feed_more_tokens(tokens).unwrap();
+    }
+    assert_eq!(parser.parse().unwrap().statements().len(), 1);
+
+    let mut streaming = Parser::from(Lexer::new(lines[0]));
+    assert!(streaming.clone().parse().is_err());
+    assert_eq!(
+        streaming.feed_more_tokens(Lexer::new(lines[2]).map(Result::unwrap)),
+        Err(FeedError::Streaming)
+    );
+    let errors = streaming.parse().unwrap_err();
+    assert!(errors.iter().all(ParseError::is_incomplete));
+
+    let errors = Parser::from(Lexer::new("print \"unclosed"))
+        .parse()
+        .unwrap_err();
+    assert!(errors.iter().all(ParseError::is_incomplete));
+
+    let errors = Parser::from(Lexer::new("print );")).parse().unwrap_err();
+    assert!(!errors[0].is_incomplete());
+}
+
+#[test]
+fn streaming_lexer_errors() {
+    use alloc::string::ToString;
+
+    let mut parser = Parser::from(Lexer::new("1 @ + 2"));
+    assert_eq!(parser.expression_rule().unwrap().to_string(), "(+ 1 2)");
+    assert_eq!(parser.errors().len(), 1);
+
+    let errors = Parser::from(Lexer::new("print 1 @ 2;\nprint # 3;\nprint 4 +;"))
+        .parse()
+        .unwrap_err();
+    let codes = errors
+        .iter()
+        .map(|error| (error.kind().code(), error.token().line_number()))
+        .collect::<Vec<_>>();
+    assert_eq!(codes, [("L0002", 1), ("L0002", 2), ("P0001", 3)]);
+
+    let source = format!("f({}0, @ );\nvar = 1;", "0, ".repeat(MAX_ARGUMENTS));
+    let errors = Parser::from(Lexer::new(&source)).parse().unwrap_err();
+    assert!(errors.is_sorted_by_key(|error| error.span().start));
+    assert_eq!(errors.len(), 3);
+}
+
+#[test]
+fn calls_and_properties() {
+    use alloc::string::ToString;
+
+    let cases = [
+        ("f()", "(call f)"),
+        (
+            "a.b.c(1, 2 + 3)(x)",
+            "(call (call (. (. a b) c) 1 (+ 2 3)) x)",
+        ),
+        ("-f(x).y", "(- (. (call f x) y))"),
+        ("(f)(g(h()))", "(call (group f) (call g (call h)))"),
+    ];
+    for (source, expected) in cases {
+        let expression = Parser::from(Lexer::new(source)).expression_rule().unwrap();
+        assert_eq!(expression.to_string(), expected);
+    }
+
+    let error = Parser::from(Lexer::new("f(1, 2;"))
+        .expression_rule()
+        .unwrap_err();
+    assert_eq!(
+        error.kind(),
+        &ParseErrorKind::MissingRightParenthesisAfterArguments
+    );
+    let error = Parser::from(Lexer::new("a.1"))
+        .expression_rule()
+        .unwrap_err();
+    assert_eq!(error.kind(), &ParseErrorKind::ExpectedPropertyName);
+
+    let source = format!("f({}0);", "0, ".repeat(MAX_ARGUMENTS));
+    let errors = Parser::from(Lexer::new(&source)).parse().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind(), &ParseErrorKind::TooManyArguments);
 }
@@ -0,0 +1,77 @@
+//! How tightly lox's operators bind. The parser is driven by this table,
+//! and tools that print or format expressions can use it to decide where parentheses are needed
+
+use crate::token::TokenKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a - b - c` groups as `(a - b) - c`
+    Left,
+    /// `a = b = c` groups as `a = (b = c)`
+    Right,
+}
+
+/// `=`. Binds looser than every other operator
+pub const ASSIGNMENT: u8 = 0;
+/// `or`
+pub const LOGIC_OR: u8 = 1;
+/// `and`
+pub const LOGIC_AND: u8 = 2;
+/// `==` and `!=`
+pub const EQUALITY: u8 = 3;
+/// `<`, `<=`, `>`, and `>=`
+pub const COMPARISON: u8 = 4;
+/// Binary `+` and `-`
+pub const TERM: u8 = 5;
+/// `*` and `/`
+pub const FACTOR: u8 = 6;
+/// Prefix `!` and `-`. Binds tighter than every binary operator
+pub const UNARY: u8 = 7;
+/// Calls `f(x)` and property access `a.b`. Binds tighter than every other operator
+pub const CALL: u8 = 8;
+
+/// Returns the precedence and associativity of `kind` as a binary operator. Higher precedence binds tighter.
+/// [None] when `kind` is not a binary operator
+pub const fn precedence(kind: TokenKind) -> Option<(u8, Associativity)> {
+    let precedence = match kind {
+        TokenKind::Equal => return Some((ASSIGNMENT, Associativity::Right)),
+        TokenKind::Or => LOGIC_OR,
+        TokenKind::And => LOGIC_AND,
+        TokenKind::EqualEqual | TokenKind::BangEqual => EQUALITY,
+        TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual => {
+            COMPARISON
+        }
+        TokenKind::Plus | TokenKind::Minus => TERM,
+        TokenKind::Star | TokenKind::Slash => FACTOR,
+        _ => return None,
+    };
+
+    Some((precedence, Associativity::Left))
+}
+
+#[test]
+fn precedence_table() {
+    assert_eq!(
+        precedence(TokenKind::Star),
+        Some((FACTOR, Associativity::Left))
+    );
+    assert_eq!(precedence(TokenKind::Bang), None);
+    assert_eq!(
+        precedence(TokenKind::Equal),
+        Some((ASSIGNMENT, Associativity::Right))
+    );
+
+    let groups = [
+        (LOGIC_OR, &[TokenKind::Or][..]),
+        (LOGIC_AND, &[TokenKind::And]),
+        (EQUALITY, TokenKind::EQUALITY_OPERATORS),
+        (COMPARISON, TokenKind::COMPARISON_OPERATORS),
+        (TERM, TokenKind::TERM_OPERATORS),
+        (FACTOR, TokenKind::FACTOR_OPERATORS),
+    ];
+    for (level, kinds) in groups {
+        for kind in kinds {
+            assert_eq!(precedence(*kind).map(|(level, _)| level), Some(level));
+        }
+    }
+}
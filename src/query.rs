@@ -0,0 +1,242 @@
+//! Iterator-based queries over a [Program] so analysis passes don't each write their own traversal
+
+use crate::{
+    abstract_syntax_tree::{Expression, Program, Statement},
+    token::Span,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// A statement or expression borrowed from a tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Node<'r, 'a> {
+    Statement(&'r Statement<'a>),
+    Expression(&'r Expression<'a>),
+}
+impl<'r, 'a> Node<'r, 'a> {
+    pub const fn span(&self) -> Span {
+        match self {
+            Node::Statement(statement) => statement.span(),
+            Node::Expression(expression) => expression.span(),
+        }
+    }
+    /// True when both are the same node in memory, not just equal trees
+    pub fn is(&self, other: Node) -> bool {
+        self.key() == other.key()
+    }
+    fn key(&self) -> (bool, usize) {
+        match self {
+            Node::Statement(statement) => (true, *statement as *const Statement as usize),
+            Node::Expression(expression) => (false, *expression as *const Expression as usize),
+        }
+    }
+    /// The statements and expressions directly inside this node, in source order
+    pub fn children(&self) -> Vec<Node<'r, 'a>> {
+        let mut children = Vec::new();
+
+        match *self {
+            Node::Statement(statement) => match statement {
+                Statement::Expression { expression, .. } | Statement::Print { expression, .. } => {
+                    children.push(Node::Expression(expression))
+                }
+                Statement::Var { initializer, .. } => {
+                    children.extend(initializer.as_deref().map(Node::Expression))
+                }
+                Statement::Block { statements, .. } => {
+                    children.extend(statements.iter().map(Node::Statement))
+                }
+                Statement::If {
+                    condition,
+                    then_branch,
+                    else_branch,
+                    ..
+                } => {
+                    children.push(Node::Expression(condition));
+                    children.push(Node::Statement(then_branch));
+                    children.extend(else_branch.as_deref().map(Node::Statement));
+                }
+                Statement::While {
+                    condition, body, ..
+                } => {
+                    children.push(Node::Expression(condition));
+                    children.push(Node::Statement(body));
+                }
+                Statement::For {
+                    initializer,
+                    condition,
+                    increment,
+                    body,
+                    ..
+                } => {
+                    children.extend(initializer.as_deref().map(Node::Statement));
+                    children.extend(condition.as_deref().map(Node::Expression));
+                    children.extend(increment.as_deref().map(Node::Expression));
+                    children.push(Node::Statement(body));
+                }
+                Statement::Custom {
+                    expressions,
+                    statements,
+                    ..
+                } => {
+                    children.extend(expressions.iter().map(Node::Expression));
+                    children.extend(statements.iter().map(Node::Statement));
+                }
+            },
+            Node::Expression(expression) => match expression {
+                Expression::Assign { value, .. } => children.push(Node::Expression(value)),
+                Expression::Binary {
+                    left_operand,
+                    right_operand,
+                    ..
+                }
+                | Expression::Logical {
+                    left_operand,
+                    right_operand,
+                    ..
+                } => {
+                    children.push(Node::Expression(left_operand));
+                    children.push(Node::Expression(right_operand));
+                }
+                Expression::Unary { right_operand, .. } => {
+                    children.push(Node::Expression(right_operand))
+                }
+                Expression::Call {
+                    callee, arguments, ..
+                } => {
+                    children.push(Node::Expression(callee));
+                    children.extend(arguments.iter().map(Node::Expression));
+                }
+                Expression::Get { object, .. } => children.push(Node::Expression(object)),
+                Expression::Grouping { expression, .. } => {
+                    children.push(Node::Expression(expression))
+                }
+                Expression::Literal(_) | Expression::Variable(_) => {}
+            },
+        }
+
+        children
+    }
+}
+
+/// Every node of a tree in pre-order: each node comes before its children, and children in source order
+pub struct Nodes<'r, 'a> {
+    /// the next nodes, the last one first
+    stack: Vec<Node<'r, 'a>>,
+}
+impl<'r, 'a> Iterator for Nodes<'r, 'a> {
+    type Item = Node<'r, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children().into_iter().rev());
+        Some(node)
+    }
+}
+
+impl<'a> Program<'a> {
+    /// Every statement and expression in the program. See [Nodes]
+    pub fn iter_nodes(&self) -> Nodes<'_, 'a> {
+        Nodes {
+            stack: self
+                .statements()
+                .iter()
+                .rev()
+                .map(Node::Statement)
+                .collect(),
+        }
+    }
+    /// Every expression for which `predicate` is true, in pre-order
+    pub fn find_all(&self, predicate: impl Fn(&Expression<'a>) -> bool) -> Vec<&Expression<'a>> {
+        self.iter_nodes()
+            .filter_map(|node| match node {
+                Node::Expression(expression) if predicate(expression) => Some(expression),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Every statement for which `predicate` is true, in pre-order
+    pub fn find_all_statements(
+        &self,
+        predicate: impl Fn(&Statement<'a>) -> bool,
+    ) -> Vec<&Statement<'a>> {
+        self.iter_nodes()
+            .filter_map(|node| match node {
+                Node::Statement(statement) if predicate(statement) => Some(statement),
+                _ => None,
+            })
+            .collect()
+    }
+    /// Builds a [Parents] to look up the parent of any node in the program
+    pub fn parents(&self) -> Parents<'_, 'a> {
+        let mut parents = BTreeMap::new();
+        for node in self.iter_nodes() {
+            for child in node.children() {
+                parents.insert(child.key(), node);
+            }
+        }
+
+        Parents { parents }
+    }
+}
+
+/// The parent of every node of a [Program], from [Program::parents]
+pub struct Parents<'r, 'a> {
+    parents: BTreeMap<(bool, usize), Node<'r, 'a>>,
+}
+impl<'r, 'a> Parents<'r, 'a> {
+    /// The node directly containing `node`. [None] for a top level statement or a node from another tree
+    pub fn parent(&self, node: Node) -> Option<Node<'r, 'a>> {
+        self.parents.get(&node.key()).copied()
+    }
+    /// The parent of `node`, its parent, and so on up to a top level statement
+    pub fn ancestors<'p>(&'p self, node: Node) -> impl Iterator<Item = Node<'r, 'a>> + 'p {
+        core::iter::successors(self.parent(node), |parent| self.parent(*parent))
+    }
+}
+
+#[test]
+fn program_queries() {
+    use crate::{lexer::Lexer, metrics, parser::Parser, token::TokenKind};
+    use alloc::string::ToString;
+
+    const SOURCE: &str = "var a = 1 + 2 * 3;\nif (a > 1 and a < 9 or !a) { while (a) a = a - 1; }";
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+
+    assert_eq!(
+        program.iter_nodes().count(),
+        metrics::metrics(&program).node_count()
+    );
+
+    let literals = program.find_all(|expression| matches!(expression, Expression::Literal(_)));
+    let literals = literals
+        .iter()
+        .map(|literal| literal.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(literals, ["1", "2", "3", "1", "9", "1"]);
+
+    let loops =
+        program.find_all_statements(|statement| matches!(statement, Statement::While { .. }));
+    assert_eq!(loops.len(), 1);
+
+    let parents = program.parents();
+    let star = program.find_all(|expression| {
+        matches!(expression, Expression::Binary { operator, .. } if operator.kind() == TokenKind::Star)
+    })[0];
+    let ancestors = parents
+        .ancestors(Node::Expression(star))
+        .map(|node| node.span())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ancestors,
+        [
+            program.find_all(|expression| matches!(expression, Expression::Binary { .. }))[0]
+                .span(),
+            program.statements()[0].span()
+        ]
+    );
+
+    let top_level = Node::Statement(&program.statements()[1]);
+    assert!(parents.parent(top_level).is_none());
+    assert!(parents
+        .parent(Node::Statement(loops[0]))
+        .is_some_and(|block| matches!(block, Node::Statement(Statement::Block { .. }))));
+}
@@ -0,0 +1,674 @@
+//! The interactive prompt: its settings, the [Session] that evaluates each input, and the `:` [Command]s.
+//! Each setting comes from the first of: a command line flag, an environment variable, the config file, or the default
+
+use crate::{
+    abstract_syntax_tree as enum_ast,
+    abstract_syntax_tree_visitor_pattern::{
+        interpreter::{Interpreter, RuntimeError},
+        Statement,
+    },
+    diagnostics::{Diagnostic, ToDiagnostic},
+    formatter,
+    lexer::{Lexer, LexerConfig},
+    lint::{self, LintConfig},
+    parser::{ParseError, Parser},
+    timing::{TimingOrder, TimingTrace},
+};
+use std::{cell::RefCell, env, fmt::Write, fs, path::PathBuf};
+
+pub const DEFAULT_PROMPT: &str = "> ";
+pub const DEFAULT_CONTINUATION_PROMPT: &str = ". ";
+pub const DEFAULT_BANNER: &str = concat!(
+    "lox ",
+    env!("CARGO_PKG_VERSION"),
+    "\nType :help for commands. Press Ctrl+D to exit"
+);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplConfig {
+    prompt: String,
+    continuation_prompt: String,
+    banner: Option<String>,
+}
+impl Default for ReplConfig {
+    fn default() -> Self {
+        Self {
+            prompt: DEFAULT_PROMPT.to_owned(),
+            continuation_prompt: DEFAULT_CONTINUATION_PROMPT.to_owned(),
+            banner: Some(DEFAULT_BANNER.to_owned()),
+        }
+    }
+}
+impl ReplConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Shown before each new input
+    pub fn prompt(self, prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            ..self
+        }
+    }
+    /// Shown before each line that continues an incomplete input, such as an unclosed block
+    pub fn continuation_prompt(self, continuation_prompt: impl Into<String>) -> Self {
+        Self {
+            continuation_prompt: continuation_prompt.into(),
+            ..self
+        }
+    }
+    /// Shown once at startup. [None] shows nothing
+    pub fn banner(self, banner: Option<String>) -> Self {
+        Self { banner, ..self }
+    }
+    pub fn get_prompt(&self) -> &str {
+        &self.prompt
+    }
+    pub fn get_continuation_prompt(&self) -> &str {
+        &self.continuation_prompt
+    }
+    pub fn get_banner(&self) -> Option<&str> {
+        self.banner.as_deref()
+    }
+
+    /// Applies each `key = value` line of a config file. Keys are `prompt`, `continuation_prompt`, and `banner`.
+    /// Values may be wrapped in `"` to keep surrounding spaces, `\n` in a value is a newline, and `banner = none` hides the banner.
+    /// Blank lines, lines starting with `#`, and unknown keys are ignored
+    pub fn apply_config_file(self, contents: &str) -> Self {
+        contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .fold(self, |config, (key, value)| {
+                config.apply(key.trim(), &parse_value(value))
+            })
+    }
+    /// Applies `LOX_PROMPT`, `LOX_CONTINUATION_PROMPT`, and `LOX_BANNER`, which take the same values as the config file
+    pub fn apply_environment(self) -> Self {
+        [
+            ("prompt", "LOX_PROMPT"),
+            ("continuation_prompt", "LOX_CONTINUATION_PROMPT"),
+            ("banner", "LOX_BANNER"),
+        ]
+        .into_iter()
+        .fold(self, |config, (key, variable)| match env::var(variable) {
+            Ok(value) => config.apply(key, &value.replace("\\n", "\n")),
+            Err(_) => config,
+        })
+    }
+    /// Reads settings from the file at `LOX_CONFIG`, or `.loxrc` in the home directory. A missing file changes nothing
+    pub fn apply_default_config_file(self) -> Self {
+        let path = env::var_os("LOX_CONFIG")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".loxrc")));
+
+        match path.and_then(|path| fs::read_to_string(path).ok()) {
+            Some(contents) => self.apply_config_file(&contents),
+            None => self,
+        }
+    }
+
+    fn apply(self, key: &str, value: &str) -> Self {
+        match key {
+            "prompt" => self.prompt(value),
+            "continuation_prompt" => self.continuation_prompt(value),
+            "banner" if value.eq_ignore_ascii_case("none") => self.banner(None),
+            "banner" => self.banner(Some(value.to_owned())),
+            _ => self,
+        }
+    }
+}
+
+fn parse_value(value: &str) -> String {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+
+    value.replace("\\n", "\n")
+}
+
+/// Evaluates prompt inputs one after another. Variables defined by one input stay defined for the next
+#[derive(Debug, Default)]
+pub struct Session {
+    interpreter: Interpreter,
+    lexer_config: LexerConfig,
+    /// the source of each top level declaration or assignment that ran without error, in order
+    history: RefCell<Vec<String>>,
+    /// the lexer warnings about the inputs evaluated since [Self::take_warnings] was last called
+    warnings: RefCell<Vec<Diagnostic>>,
+    /// the source last passed to [Self::evaluate], for commands given no code
+    last_input: RefCell<String>,
+}
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn lexer_config(self, lexer_config: LexerConfig) -> Self {
+        Self {
+            lexer_config,
+            ..self
+        }
+    }
+    /// Evaluates inputs with `interpreter`, such as one with [Interpreter::with_hook]
+    pub fn with_interpreter(self, interpreter: Interpreter) -> Self {
+        Self {
+            interpreter,
+            ..self
+        }
+    }
+    pub const fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+    /// The source of every top level `var` declaration and assignment statement evaluated without error, one per line, for `:save`.
+    /// Other statements are left out, so loading it does not print the values of old expressions again
+    pub fn history(&self) -> String {
+        self.history
+            .borrow()
+            .iter()
+            .map(|statement| format!("{}\n", statement))
+            .collect()
+    }
+    /// True when `source` only failed to parse because it ended early, so the prompt should read another line.
+    /// False when any error would still be there after more input
+    pub fn is_incomplete(&self, source: &str) -> bool {
+        Parser::from(Lexer::with_config(source, self.lexer_config.clone()))
+            .parse()
+            .is_err_and(|errors| {
+                !errors.is_empty() && errors.iter().all(|error| error.is_incomplete())
+            })
+    }
+    /// Returns the lexer warnings about the inputs evaluated since the last call, such as a number literal that loses precision
+    pub fn take_warnings(&self) -> Vec<Diagnostic> {
+        core::mem::take(&mut self.warnings.borrow_mut())
+    }
+    /// Runs `source`. The value of each expression statement is printed as if by `print`.
+    /// What was printed is in [Interpreter::take_output] and the warnings about `source` are in [Self::take_warnings],
+    /// even when an error stopped the input part way. Each input gets the whole [Interpreter::with_step_budget]
+    /// # Error
+    /// When `source` does not parse, or when running it fails. Nothing runs when it does not parse
+    pub fn evaluate<'a>(&self, source: &'a str) -> Result<(), SessionError<'a>> {
+        self.run(source, true)
+    }
+    /// [Self::evaluate], but expression statements only print their values when `echo` is set
+    fn run<'a>(&self, source: &'a str, echo: bool) -> Result<(), SessionError<'a>> {
+        source.clone_into(&mut self.last_input.borrow_mut());
+        let (result, warnings) =
+            Parser::from(Lexer::with_config(source, self.lexer_config.clone()))
+                .parse_with_warnings();
+        self.warnings
+            .borrow_mut()
+            .extend(warnings.iter().map(ToDiagnostic::to_diagnostic));
+        let (statements, interner) = result.map_err(SessionError::Parse)?.into_parts();
+
+        self.interpreter.reset_steps();
+        self.interpreter.with_symbols_of(&interner, || {
+            for statement in statements {
+                let recorded = match &statement {
+                    enum_ast::Statement::Var { .. } => true,
+                    enum_ast::Statement::Expression { expression, .. } => {
+                        matches!(**expression, enum_ast::Expression::Assign { .. })
+                    }
+                    _ => false,
+                }
+                .then(|| source[statement.span().range()].to_owned());
+
+                match Statement::from(statement) {
+                    Statement::Expression(expression) if echo => {
+                        let value = self.interpreter.evaluate(expression.expression())?;
+                        self.interpreter.print(&value);
+                    }
+                    statement => self.interpreter.execute(&statement)?,
+                }
+
+                self.history.borrow_mut().extend(recorded);
+            }
+
+            Ok(())
+        })
+    }
+    /// The keywords, and the globals defined so far, that start with the word at the end of `line`, in alphabetical order.
+    /// A line editor replaces that word with the chosen one when Tab is pressed
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        let word_start = line
+            .char_indices()
+            .rev()
+            .take_while(|(_, character)| character.is_alphanumeric() || *character == '_')
+            .last()
+            .map_or(line.len(), |(index, _)| index);
+        let word = &line[word_start..];
+
+        let mut candidates = self
+            .lexer_config
+            .keyword_table()
+            .all_spellings()
+            .map(str::to_owned)
+            .chain(self.interpreter.globals())
+            .filter(|candidate| candidate.starts_with(word))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.dedup();
+
+        candidates
+    }
+}
+
+/// A prompt input starting with `:`, run by [Session::run_command] instead of being evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command<'a> {
+    /// Prints the tokens of the code
+    Tokens(&'a str),
+    /// Prints the syntax tree of the code
+    Ast(&'a str),
+    /// Prints the [lint::lint] warnings about the code, or about the last evaluated input when there is none
+    Lint(&'a str),
+    /// Prints the code as [formatter::format] lays it out, or the last evaluated input when there is none
+    Fmt(&'a str),
+    /// Runs the code, or the last evaluated input when there is none, and prints its output and [TimingTrace::report].
+    /// It runs on a copy of the globals, so the session is left as it was
+    Profile(&'a str),
+    /// Prints every global variable and its value
+    Env,
+    /// Forgets every definition
+    Clear,
+    /// Evaluates the file at the path like a script, so the values of its expression statements are not printed
+    Load(&'a str),
+    /// Writes [Session::history] to the file at the path
+    Save(&'a str),
+    Help,
+}
+/// What each command is typed as and does, for `:help`
+pub const COMMANDS: &[(&str, &str)] = &[
+    (":tokens <code>", "Print the tokens of <code>"),
+    (":ast <code>", "Print the syntax tree of <code>"),
+    (
+        ":lint [code]",
+        "Print the warnings about [code], or the last input",
+    ),
+    (":fmt [code]", "Print [code] formatted, or the last input"),
+    (
+        ":profile [code]",
+        "Run [code], or the last input, on a copy of the globals and print how long each statement took",
+    ),
+    (":env", "Print every global variable and its value"),
+    (":clear", "Forget every definition"),
+    (":load <path>", "Evaluate the script at <path>"),
+    (
+        ":save <path>",
+        "Write the declarations and assignments that ran so far to <path>, to :load later",
+    ),
+    (":help", "Print this list"),
+];
+impl<'a> Command<'a> {
+    /// [None] when `input` is not a command.
+    /// # Error
+    /// The command name when it is not one of [COMMANDS] or is missing its argument
+    pub fn parse(input: &'a str) -> Option<Result<Self, &'a str>> {
+        let input = input.trim().strip_prefix(':')?;
+        let (name, argument) = input
+            .split_once(char::is_whitespace)
+            .map_or((input, ""), |(name, argument)| (name, argument.trim()));
+
+        let command = match (name, argument) {
+            ("tokens", code) => Command::Tokens(code),
+            ("ast", code) => Command::Ast(code),
+            ("lint", code) => Command::Lint(code),
+            ("fmt", code) => Command::Fmt(code),
+            ("profile", code) => Command::Profile(code),
+            ("env", "") => Command::Env,
+            ("clear", "") => Command::Clear,
+            ("load", path) if !path.is_empty() => Command::Load(path),
+            ("save", path) if !path.is_empty() => Command::Save(path),
+            ("help", "") => Command::Help,
+            _ => return Some(Err(name)),
+        };
+
+        Some(Ok(command))
+    }
+}
+
+impl Session {
+    /// Runs `command` and returns what it prints
+    /// # Error
+    /// The errors to report, already displayed with their source, when the code does not parse or the file cannot be run
+    pub fn run_command(&self, command: Command) -> Result<String, String> {
+        let mut output = String::new();
+
+        match command {
+            Command::Tokens(code) => {
+                for possible_token in Lexer::with_config(code, self.lexer_config.clone()) {
+                    let _ = match possible_token {
+                        Ok(token) => writeln!(output, "{}", token),
+                        Err(error) => {
+                            writeln!(output, "{}", error.to_diagnostic().render(code, None))
+                        }
+                    };
+                }
+            }
+            Command::Ast(code) => {
+                let program = Parser::from(Lexer::with_config(code, self.lexer_config.clone()))
+                    .parse()
+                    .map_err(|errors| display_errors(&errors, code))?;
+                let _ = writeln!(output, "{}", program);
+            }
+            Command::Lint(code) => {
+                let code = &self.or_last_input(code);
+                let program = Parser::from(Lexer::with_config(code, self.lexer_config.clone()))
+                    .parse()
+                    .map_err(|errors| display_errors(&errors, code))?;
+                for warning in lint::lint(&program, &LintConfig::new()) {
+                    let _ = writeln!(output, "{}", warning.to_diagnostic().render(code, None));
+                }
+            }
+            Command::Fmt(code) => {
+                let code = &self.or_last_input(code);
+                output = formatter::format(code).map_err(|errors| display_errors(&errors, code))?;
+            }
+            Command::Profile(code) => {
+                let code = &self.or_last_input(code);
+                let program = Parser::from(Lexer::with_config(code, self.lexer_config.clone()))
+                    .parse()
+                    .map_err(|errors| display_errors(&errors, code))?;
+
+                let trace = TimingTrace::new();
+                let interpreter = Interpreter::new().with_hook(trace.hook());
+                for name in self.interpreter.globals() {
+                    if let Some(value) = self.interpreter.get(&name) {
+                        interpreter.define(&name, value);
+                    }
+                }
+                let (statements, interner) = program.into_parts();
+                let result = interpreter.with_symbols_of(&interner, || {
+                    statements
+                        .into_iter()
+                        .try_for_each(|statement| interpreter.execute(&statement.into()))
+                });
+
+                output += &interpreter.take_output();
+                output += &trace.report(code, TimingOrder::Time);
+                if let Err(error) = result {
+                    return Err(format!(
+                        "{}{}\n",
+                        output,
+                        error.to_diagnostic().render(code, None)
+                    ));
+                }
+            }
+            Command::Env => {
+                for name in self.interpreter.globals() {
+                    if let Some(value) = self.interpreter.get(&name) {
+                        let _ = writeln!(output, "{} = {}", name, value);
+                    }
+                }
+            }
+            Command::Clear => {
+                self.interpreter.clear();
+                self.history.borrow_mut().clear();
+            }
+            Command::Load(path) => {
+                let source = fs::read_to_string(path)
+                    .map_err(|error| format!("Cannot read {}: {}\n", path, error))?;
+                let result = self.run(&source, false);
+                // rendered here since they are about the file rather than the input that loaded it
+                for warning in self.take_warnings() {
+                    let _ = writeln!(output, "{}", warning.render(&source, None));
+                }
+                output += &self.interpreter.take_output();
+                match result {
+                    Ok(()) => {}
+                    Err(SessionError::Parse(errors)) => {
+                        return Err(output + &display_errors(&errors, &source))
+                    }
+                    Err(SessionError::Runtime(error)) => {
+                        return Err(format!(
+                            "{}{}\n",
+                            output,
+                            error.to_diagnostic().render(&source, None)
+                        ))
+                    }
+                }
+            }
+            Command::Save(path) => {
+                fs::write(path, self.history())
+                    .map_err(|error| format!("Cannot write {}: {}\n", path, error))?;
+                let _ = writeln!(
+                    output,
+                    "Saved {} statements to {}",
+                    self.history.borrow().len(),
+                    path
+                );
+            }
+            Command::Help => {
+                let width = COMMANDS
+                    .iter()
+                    .map(|(usage, _)| usage.len())
+                    .max()
+                    .unwrap_or(0);
+                for (usage, help) in COMMANDS {
+                    let _ = writeln!(output, "{:width$}  {}", usage, help);
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+impl Session {
+    /// `code`, or the last evaluated input when it is empty
+    fn or_last_input(&self, code: &str) -> String {
+        match code {
+            "" => self.last_input.borrow().clone(),
+            code => code.to_owned(),
+        }
+    }
+}
+
+/// Each error displayed with `source`, one per line
+fn display_errors(errors: &[ParseError], source: &str) -> String {
+    errors
+        .iter()
+        .map(|error| format!("{}\n", error.to_diagnostic().render(source, None)))
+        .collect()
+}
+
+/// Why [Session::evaluate] stopped
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionError<'a> {
+    Parse(Vec<ParseError<'a>>),
+    Runtime(RuntimeError),
+}
+impl From<RuntimeError> for SessionError<'_> {
+    fn from(value: RuntimeError) -> Self {
+        Self::Runtime(value)
+    }
+}
+
+#[test]
+fn repl_config_file() {
+    let config = ReplConfig::new().apply_config_file(
+        "# lox settings\nprompt = \"lox> \"\ncontinuation_prompt=...\nbanner = none\ncolor = on\n",
+    );
+
+    assert_eq!(config.get_prompt(), "lox> ");
+    assert_eq!(config.get_continuation_prompt(), "...");
+    assert_eq!(config.get_banner(), None);
+
+    let config = ReplConfig::new().apply_config_file("banner = \"Welcome\\nto lox\"");
+    assert_eq!(config.get_banner(), Some("Welcome\nto lox"));
+    assert_eq!(config.get_prompt(), DEFAULT_PROMPT);
+
+    let session = Session::new();
+    assert!(session.is_incomplete("{ print 1;"));
+    assert!(!session.is_incomplete("print 1;"));
+    assert!(!session.is_incomplete("print );"));
+    assert!(!session.is_incomplete("print ); { print 1;"));
+
+    let session = Session::new().lexer_config(
+        LexerConfig::new().custom_literal(crate::lexer::CustomLiteral::new(0, "#[", "]")),
+    );
+    assert!(session.is_incomplete("print #[1, 2"));
+}
+
+#[test]
+fn session_keeps_definitions() {
+    let session = Session::new();
+
+    session.evaluate("var a = 1;").unwrap();
+    session.evaluate("a = a + 1; a * 10;").unwrap();
+    assert_eq!(session.interpreter().take_output(), "2\n20\n");
+
+    let error = session.evaluate("print a; print b;").unwrap_err();
+    assert!(matches!(error, SessionError::Runtime(_)));
+    assert_eq!(session.interpreter().take_output(), "2\n");
+
+    assert!(matches!(
+        session.evaluate("var = 1;"),
+        Err(SessionError::Parse(_))
+    ));
+    assert_eq!(session.interpreter().globals(), ["a"]);
+
+    let session = Session::new().lexer_config(LexerConfig::new().warn_on_precision_loss(true));
+    assert!(session.evaluate("print 9007199254740993 + nil;").is_err());
+    let warnings = session.take_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code(), "W0001");
+    assert!(session.take_warnings().is_empty());
+
+    // the step budget is for each input, not the whole session
+    let session = Session::new().with_interpreter(Interpreter::new().with_step_budget(2));
+    for _ in 0..3 {
+        session.evaluate("var b = 1; print b;").unwrap();
+    }
+    assert!(session.evaluate("print 1; print 2; print 3;").is_err());
+}
+
+#[test]
+fn session_completions() {
+    let session = Session::new();
+    session
+        .evaluate("var value = 1; var total = 2; { var void = 3; }")
+        .unwrap();
+
+    assert_eq!(session.complete("print v"), ["value", "var"]);
+    assert_eq!(session.complete("(t"), ["this", "total", "true"]);
+    assert_eq!(session.complete("print total"), ["total"]);
+    assert!(session.complete("1 +").len() > 16);
+}
+
+#[test]
+fn session_commands() {
+    use crate::abstract_syntax_tree_visitor_pattern::interpreter::Value;
+
+    let session = Session::new();
+    session.evaluate("var a = 1; var b = \"two\";").unwrap();
+    session.interpreter().take_output();
+
+    let run = |input| session.run_command(Command::parse(input).unwrap().unwrap());
+
+    assert_eq!(run(":env").unwrap(), "a = 1\nb = two\n");
+    assert_eq!(
+        run(":tokens a+1").unwrap(),
+        "1 Identifier a\n1 Plus +\n1 Number 1\n1 EndOfFile \n"
+    );
+    assert_eq!(run(" :ast print -a;").unwrap(), "(print (- a))\n");
+    assert!(run(":ast print").is_err());
+    assert!(run(":lint { var a = 1; }")
+        .unwrap()
+        .starts_with("warning[W0003]: Variable is never read\n"));
+    assert!(run(":lint { var _a = 1; }").unwrap().is_empty());
+    assert_eq!(
+        run(":fmt var a=1;{print a;}").unwrap(),
+        "var a = 1;\n{\n    print a;\n}\n"
+    );
+    assert!(run(":fmt print").is_err());
+    // without the times, which differ between runs
+    let profile = |input| {
+        run(input).map(|report| {
+            report
+                .lines()
+                .map(|line| {
+                    let columns = line.split_whitespace().collect::<Vec<_>>();
+                    match columns.len() {
+                        1 => line.to_owned(),
+                        _ => format!("{} {} {}", columns[0], columns[1], columns[4..].join(" ")),
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    assert_eq!(
+        profile(":profile a = a + 1; print a;").unwrap(),
+        [
+            "2",
+            "line:col hits statement",
+            "1:1 1 a = a + 1",
+            "1:12 1 print a",
+        ]
+    );
+    // the session's globals are not changed
+    assert_eq!(session.interpreter().get("a"), Some(Value::Number(1.0)));
+    assert!(run(":profile print nope;").is_err());
+    assert!(run(":profile print").is_err());
+    session.evaluate("var  c=a;").unwrap();
+    assert_eq!(run(":fmt").unwrap(), "var c = a;\n");
+    assert_eq!(
+        profile(":profile").unwrap(),
+        ["line:col hits statement", "1:1 1 var c=a"]
+    );
+    assert!(run(":help")
+        .unwrap()
+        .starts_with(":tokens <code>   Print the tokens"));
+
+    let path = env::temp_dir().join(format!("lox_session_commands_{}.lox", std::process::id()));
+    fs::write(&path, "print a + 1; var a = 5;").unwrap();
+    let load = format!(":load {}", path.display());
+    let loaded = run(&load);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), "2\n");
+    assert_eq!(session.interpreter().get("a"), Some(Value::Number(5.0)));
+
+    assert_eq!(run(":clear").unwrap(), "");
+    assert!(session.interpreter().globals().is_empty());
+    assert_eq!(session.history(), "");
+
+    assert_eq!(Command::parse("print 1;"), None);
+    assert_eq!(Command::parse(":env now"), Some(Err("env")));
+    assert_eq!(Command::parse(":load"), Some(Err("load")));
+    assert_eq!(Command::parse(":quit"), Some(Err("quit")));
+}
+
+#[test]
+fn session_save() {
+    let session = Session::new();
+    session
+        .evaluate("var a = 1; print a; var b = a + 1; a = b * 2;")
+        .unwrap();
+    // expressions are echoed rather than declared, so loading them would only print old values
+    session.evaluate("a * 10; a;").unwrap();
+    assert!(session.evaluate("var c = nope;").is_err());
+    assert!(session
+        .evaluate("var d = 4; print nope; var e = 5;")
+        .is_err());
+    assert_eq!(
+        session.history(),
+        "var a = 1;\nvar b = a + 1;\na = b * 2;\nvar d = 4;\n"
+    );
+
+    let path = env::temp_dir().join(format!("lox_session_save_{}.lox", std::process::id()));
+    let save = format!(":save {}", path.display());
+    let saved = session.run_command(Command::parse(&save).unwrap().unwrap());
+    assert_eq!(
+        saved.unwrap(),
+        format!("Saved 4 statements to {}\n", path.display())
+    );
+
+    let restored = Session::new();
+    let load = format!(":load {}", path.display());
+    let loaded = restored.run_command(Command::parse(&load).unwrap().unwrap());
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap(), "");
+    assert_eq!(restored.interpreter().globals(), ["a", "b", "d"]);
+    assert_eq!(restored.history(), session.history());
+}
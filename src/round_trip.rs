@@ -0,0 +1,182 @@
+//! Parser oracle: for every source that parses, `parse(print(ast)) == ast` (ignoring line numbers).
+//! Checked by the tests and the `round_trip` fuzz target, and by the `lox` binary in debug builds or when `LOX_ROUND_TRIP_CHECK` is set
+
+use crate::{
+    abstract_syntax_tree::{program_to_canonical_string, to_canonical_string, Expression, Program},
+    formatter,
+    lexer::Lexer,
+    parser::Parser,
+};
+use std::{
+    fmt::Display,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    path::PathBuf,
+};
+
+/// Parses `source` as a single expression. [None] when it is not exactly one valid expression
+fn parse_expression(source: &str) -> Option<Box<Expression<'_>>> {
+    let mut parser = Parser::from(Lexer::new(source));
+    let expression = parser.expression_rule().ok()?;
+    (parser.is_at_end() && parser.errors().is_empty()).then_some(expression)
+}
+
+/// Parses `source` as a program. [None] when it has any errors
+fn parse_program(source: &str) -> Option<Program<'_>> {
+    Parser::from(Lexer::new(source)).parse().ok()
+}
+
+/// Checks that printing the parsed form of `source` parses back to the same tree.
+/// A program is printed with [formatter::format] and compared with [Program::eq_ignoring_spans],
+/// and a source that is only a single expression, such as `1 + 2`, is printed with [Expression::to_source].
+/// Sources that parse as neither trivially pass
+pub fn check(source: &str) -> Result<(), RoundTripViolation> {
+    if let Some(program) = parse_program(source) {
+        return check_program(source, &program);
+    }
+    let Some(expression) = parse_expression(source) else {
+        return Ok(());
+    };
+
+    let printed = expression.to_source();
+    let expected = to_canonical_string(&expression);
+    let found = parse_expression(&printed).map(|reparsed| to_canonical_string(&reparsed));
+
+    if found.as_ref() == Some(&expected) {
+        return Ok(());
+    }
+
+    Err(RoundTripViolation {
+        source: source.to_owned(),
+        printed,
+        expected,
+        found,
+    })
+}
+
+fn check_program(source: &str, program: &Program) -> Result<(), RoundTripViolation> {
+    let printed = formatter::format(source).unwrap_or_default();
+    let reparsed = parse_program(&printed);
+
+    if reparsed
+        .as_ref()
+        .is_some_and(|reparsed| reparsed.eq_ignoring_spans(program))
+    {
+        return Ok(());
+    }
+
+    let found = reparsed.map(|reparsed| program_to_canonical_string(&reparsed));
+    Err(RoundTripViolation {
+        source: source.to_owned(),
+        printed,
+        expected: program_to_canonical_string(program),
+        found,
+    })
+}
+
+/// Shrinks `source` by repeatedly removing chunks of characters while `is_failing` still holds
+pub fn minimize(source: &str, is_failing: impl Fn(&str) -> bool) -> String {
+    let mut characters = source.chars().collect::<Vec<_>>();
+    let mut chunk_length = characters.len() / 2;
+
+    while chunk_length > 0 {
+        let mut start = 0;
+        let mut removed_any = false;
+
+        while start < characters.len() {
+            let end = (start + chunk_length).min(characters.len());
+            let candidate = characters[..start]
+                .iter()
+                .chain(&characters[end..])
+                .collect::<String>();
+
+            if is_failing(&candidate) {
+                characters.drain(start..end);
+                removed_any = true;
+            } else {
+                start += chunk_length;
+            }
+        }
+
+        if !removed_any {
+            chunk_length /= 2;
+        }
+    }
+
+    characters.into_iter().collect()
+}
+
+/// Minimizes the violating source and writes it to the reproduction directory, returning the file's path.
+/// The directory is `LOX_ROUND_TRIP_DIR` when set, otherwise `lox-round-trip` in [std::env::temp_dir]
+pub fn write_reproduction(violation: &RoundTripViolation) -> Result<PathBuf, io::Error> {
+    let minimized = minimize(&violation.source, |source| check(source).is_err());
+
+    let directory = std::env::var_os("LOX_ROUND_TRIP_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("lox-round-trip"));
+    std::fs::create_dir_all(&directory)?;
+
+    let mut hasher = DefaultHasher::new();
+    minimized.hash(&mut hasher);
+    let path = directory.join(format!("{:016x}.lox", hasher.finish()));
+
+    std::fs::write(&path, minimized)?;
+
+    Ok(path)
+}
+
+/// Runs [check] and panics on a violation after writing a minimized reproduction with [write_reproduction]
+pub fn assert_round_trips(source: &str) {
+    if let Err(violation) = check(source) {
+        match write_reproduction(&violation) {
+            Ok(path) => panic!("{}\nReproduction written to {}", violation, path.display()),
+            Err(error) => panic!("{}\nFailed to write reproduction: {}", violation, error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundTripViolation {
+    source: String,
+    printed: String,
+    expected: String,
+    found: Option<String>,
+}
+impl RoundTripViolation {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn printed(&self) -> &str {
+        &self.printed
+    }
+}
+impl Display for RoundTripViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Printed AST does not re-parse to itself\nsource: {:?}\nprinted: {:?}\nexpected:\n{}found:\n{}",
+            self.source,
+            self.printed,
+            self.expected,
+            self.found.as_deref().unwrap_or("parse error\n"),
+        )
+    }
+}
+impl std::error::Error for RoundTripViolation {}
+
+#[test]
+fn round_trip() {
+    const SOURCE: &str = include_str!("../simple_example.lox");
+
+    for line in SOURCE.lines() {
+        assert_round_trips(line);
+    }
+    assert_round_trips("(1 + 2) * -(3 / \"four\") >= !!(nil)");
+    assert_round_trips("(-f)(a = 1, (b)).c(\"d\")");
+    assert_round_trips(SOURCE);
+    assert_round_trips(
+        "var a = 1; // one\n{ if (a) print -(a); else { a = a.b(1); } }\nfor (;;) while (!a) a = nil;",
+    );
+
+    assert_eq!(minimize("1 + (2 * 3)", |source| source.contains('*')), "*");
+}
@@ -0,0 +1,165 @@
+//! [DiagnosticSink], which receives each [Diagnostic] as a stage finds it, and ways to run the lexer, parser,
+//! and interpreter reporting to one, so an embedder decides what happens to errors and warnings
+
+use crate::{
+    abstract_syntax_tree::Program,
+    abstract_syntax_tree_visitor_pattern::{interpreter::Interpreter, Statement},
+    diagnostics::Diagnostic,
+    lexer::{Lexer, TokenSource},
+    parser::Parser,
+    token::Token,
+};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// Where diagnostics go. Implemented for [Vec] to collect them, for closures to forward them,
+/// and by [RenderSink] to show them right away
+pub trait DiagnosticSink {
+    fn emit(&mut self, diagnostic: Diagnostic);
+}
+impl DiagnosticSink for Vec<Diagnostic> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self.push(diagnostic);
+    }
+}
+impl<F: FnMut(Diagnostic)> DiagnosticSink for F {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self(diagnostic)
+    }
+}
+
+/// Writes each diagnostic with [Diagnostic::render] as it is emitted, followed by a newline
+pub struct RenderSink<'s, W> {
+    source: &'s str,
+    path: Option<&'s str>,
+    writer: W,
+}
+impl<'s, W: Write> RenderSink<'s, W> {
+    /// Renders diagnostics about `source` to `writer`
+    pub const fn new(source: &'s str, writer: W) -> Self {
+        Self {
+            source,
+            path: None,
+            writer,
+        }
+    }
+    /// The path shown with each diagnostic. None by default
+    pub fn path(mut self, path: &'s str) -> Self {
+        self.path = Some(path);
+        self
+    }
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+impl<W: Write> DiagnosticSink for RenderSink<'_, W> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        let _ = writeln!(self.writer, "{}", diagnostic.render(self.source, self.path));
+    }
+}
+
+impl<'a> Lexer<'a> {
+    /// Like [Self::lex_all], but each error is emitted to `sink` as it is found, followed by the warnings
+    pub fn lex_to_sink(mut self, sink: &mut impl DiagnosticSink) -> Vec<Token<'a>> {
+        let mut tokens = Vec::new();
+
+        for possible_token in self.by_ref() {
+            match possible_token {
+                Ok(token) => tokens.push(token),
+                Err(error) => sink.emit(error.into()),
+            }
+        }
+        for warning in self.warnings() {
+            sink.emit(warning.clone().into());
+        }
+
+        tokens
+    }
+}
+
+impl<'a, S: TokenSource<'a>> Parser<'a, S> {
+    /// Like [Self::parse], but each error is emitted to `sink`, followed by the warnings. [None] when there were any errors
+    pub fn parse_to_sink(self, sink: &mut impl DiagnosticSink) -> Option<Program<'a>> {
+        let (result, warnings) = self.parse_with_warnings();
+        let program = match result {
+            Ok(program) => Some(program),
+            Err(errors) => {
+                for error in errors {
+                    sink.emit(error.into());
+                }
+                None
+            }
+        };
+        for warning in warnings {
+            sink.emit(warning.into());
+        }
+
+        program
+    }
+}
+
+impl Interpreter {
+    /// Executes `statements` in order until one fails, emitting its error to `sink`.
+    /// True when they all ran
+    pub fn execute_to_sink(
+        &self,
+        statements: &[Statement],
+        sink: &mut impl DiagnosticSink,
+    ) -> bool {
+        match statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement))
+        {
+            Ok(()) => true,
+            Err(error) => {
+                sink.emit(error.into());
+                false
+            }
+        }
+    }
+}
+
+#[test]
+fn diagnostic_sinks() {
+    use crate::lexer::LexerConfig;
+    use alloc::string::String;
+
+    let mut collected = Vec::new();
+    let tokens = Lexer::with_config(
+        "1 @ 9007199254740993",
+        LexerConfig::new().warn_on_precision_loss(true),
+    )
+    .lex_to_sink(&mut collected);
+    assert_eq!(tokens.len(), 3);
+    let codes = collected
+        .iter()
+        .map(|diagnostic| diagnostic.code())
+        .collect::<Vec<_>>();
+    assert_eq!(codes, ["L0002", "W0001"]);
+
+    let mut count = 0;
+    let program = Parser::from(Lexer::new("print (1; print 2")).parse_to_sink(&mut |_| count += 1);
+    assert!(program.is_none());
+    assert_eq!(count, 2);
+
+    let mut collected = Vec::new();
+    let program = Parser::from(Lexer::with_config(
+        "print 9007199254740993;",
+        LexerConfig::new().warn_on_precision_loss(true),
+    ))
+    .parse_to_sink(&mut collected);
+    assert!(program.is_some());
+    assert_eq!(collected[0].code(), "W0001");
+
+    const SOURCE: &str = "print 1; print -nil;";
+    let statements = Parser::from(Lexer::new(SOURCE))
+        .parse_into_visitor_pattern()
+        .unwrap();
+    let interpreter = Interpreter::new();
+    let mut sink = RenderSink::new(SOURCE, String::new()).path("a.lox");
+    assert!(!interpreter.execute_to_sink(&statements, &mut sink));
+    assert_eq!(interpreter.take_output(), "1\n");
+    assert!(sink
+        .into_writer()
+        .starts_with("error[R0001]: Operand must be a number\n --> a.lox:1:16\n"));
+}
@@ -0,0 +1,127 @@
+//! "Did you mean" notes for misspelled keywords and variable names, picked by [edit_distance]
+
+use crate::{diagnostics::Diagnostic, excerpt::floor_char_boundary, messages};
+use alloc::vec::Vec;
+
+/// How many characters must be inserted, removed, replaced, or swapped with their neighbor to turn `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    // the rows for the first i - 2, i - 1, and i characters of `a`
+    let mut before_previous = Vec::new();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for i in 1..=a.len() {
+        let mut current = Vec::with_capacity(b.len() + 1);
+        current.push(i);
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(before_previous[j - 2] + 1);
+            }
+            current.push(distance);
+        }
+        before_previous = core::mem::replace(&mut previous, current);
+    }
+
+    previous[b.len()]
+}
+
+/// The candidate nearest to `word` by [edit_distance], the first one on a tie.
+/// [None] when `word` is one of them, or none is within a third of its length
+pub fn closest<'c>(word: &str, candidates: impl IntoIterator<Item = &'c str>) -> Option<&'c str> {
+    let max_distance = word.chars().count() / 3;
+    let mut best: Option<(usize, &str)> = None;
+
+    for candidate in candidates {
+        let distance = edit_distance(word, candidate);
+        if distance == 0 {
+            return None;
+        }
+        if distance <= max_distance
+            && best.is_none_or(|(best_distance, _)| distance < best_distance)
+        {
+            best = Some((distance, candidate));
+        }
+    }
+
+    best.map(|(_, candidate)| candidate)
+}
+
+/// `diagnostic` with a "did you mean" note when it is about a misspelling:
+/// - an undefined variable (`R0004`), compared with `names` and `keywords`
+/// - a missing `;` (`P0005`) right after an identifier, which is likely a misspelled statement keyword, as in `prnt x;`
+pub fn did_you_mean(
+    diagnostic: Diagnostic,
+    source: &str,
+    keywords: &[&str],
+    names: &[&str],
+) -> Diagnostic {
+    let span = diagnostic.span();
+    let start = floor_char_boundary(source, span.start);
+
+    let suggestion = match diagnostic.code() {
+        "R0004" => {
+            let name = &source[start..floor_char_boundary(source, span.end).max(start)];
+            closest(name, names.iter().chain(keywords).copied())
+        }
+        "P0005" => {
+            let before = source[..start].trim_end();
+            let word_start = before
+                .char_indices()
+                .rev()
+                .take_while(|(_, character)| character.is_alphanumeric() || *character == '_')
+                .last()
+                .map_or(before.len(), |(index, _)| index);
+            closest(&before[word_start..], keywords.iter().copied())
+        }
+        _ => None,
+    };
+
+    match suggestion {
+        Some(suggestion) => diagnostic.note(messages::format_message(
+            messages::DID_YOU_MEAN_TEMPLATE,
+            &[("name", &suggestion)],
+        )),
+        None => diagnostic,
+    }
+}
+
+#[test]
+fn misspelling_suggestions() {
+    use crate::{
+        abstract_syntax_tree_visitor_pattern::interpreter::Interpreter, diagnostics::ToDiagnostic,
+        lexer::Lexer, parser::Parser, token::KeywordTable,
+    };
+
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("lenght", "length"), 1);
+    assert_eq!(edit_distance("", "abc"), 3);
+    assert_eq!(closest("prnt", ["var", "print", "printf"]), Some("print"));
+    assert_eq!(closest("nl", ["nil"]), None);
+    assert_eq!(closest("print", ["print", "prin"]), None);
+
+    let keywords = KeywordTable::default();
+    let keywords = keywords.all_spellings().collect::<Vec<_>>();
+
+    let source = "prnt x;";
+    let errors = Parser::from(Lexer::new(source)).parse().unwrap_err();
+    let diagnostic = did_you_mean(errors[0].to_diagnostic(), source, &keywords, &[]);
+    assert_eq!(diagnostic.notes(), ["did you mean 'print'?"]);
+
+    let source = "var length = 1; print lenght;";
+    let interpreter = Interpreter::new();
+    let error = Parser::from(Lexer::new(source))
+        .parse_into_visitor_pattern()
+        .unwrap()
+        .iter()
+        .try_for_each(|statement| interpreter.execute(statement))
+        .unwrap_err();
+    let names = interpreter.globals();
+    let names = names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+    let diagnostic = did_you_mean(error.to_diagnostic(), source, &keywords, &names);
+    assert_eq!(diagnostic.notes(), ["did you mean 'length'?"]);
+}
@@ -0,0 +1,323 @@
+//! Per-statement timing for `lox run --trace-timing`. A [TimingTrace] is fed the [Event::Execute] and [Event::Executed]
+//! events from an [Interpreter] hook, and counts how often each statement ran and how long it took.
+//! It can be printed as a table with [TimingTrace::report] or as folded stacks for flamegraph tools with [TimingTrace::folded_stacks]
+
+use crate::{
+    abstract_syntax_tree_visitor_pattern::interpreter::{Event, Interpreter},
+    line_index::LineIndex,
+    token::Span,
+};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::Write as _,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// How often one statement ran and how long that took
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StatementTiming {
+    span: Span,
+    hits: u64,
+    total: Duration,
+    /// `total` without the time spent in the statements inside it
+    self_time: Duration,
+}
+impl StatementTiming {
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+    /// How many times the statement ran
+    pub const fn hits(&self) -> u64 {
+        self.hits
+    }
+    /// How long it ran for altogether, including the statements inside it
+    pub const fn total(&self) -> Duration {
+        self.total
+    }
+    /// How long it ran for altogether, not counting the statements inside it
+    pub const fn self_time(&self) -> Duration {
+        self.self_time
+    }
+}
+
+/// The order [TimingTrace::statements] and [TimingTrace::report] list statements in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimingOrder {
+    /// the most total time first
+    #[default]
+    Time,
+    /// the most hits first
+    Hits,
+    /// in the order they appear in the source
+    Line,
+}
+impl TimingOrder {
+    pub const NAMES: &'static [&'static str] = &["time", "hits", "line"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "time" => Some(TimingOrder::Time),
+            "hits" => Some(TimingOrder::Hits),
+            "line" => Some(TimingOrder::Line),
+            _ => None,
+        }
+    }
+}
+
+/// A statement that started and has not finished
+struct Running {
+    span: Span,
+    started: Instant,
+    /// the time spent in the statements inside it that finished
+    inner: Duration,
+}
+
+#[derive(Default)]
+struct Timings {
+    /// keyed by the start and end of the statement's span
+    statements: BTreeMap<(usize, usize), StatementTiming>,
+    /// the self time of each chain of statements that ran, by the start and end of their spans, outermost first
+    stacks: BTreeMap<Vec<(usize, usize)>, Duration>,
+    /// the statements that are running, outermost first
+    running: Vec<Running>,
+}
+
+/// Collects [StatementTiming]s from the events passed to [Self::record]. Clones share what was collected,
+/// so one can be moved into the [Interpreter::with_hook] closure and another kept to read it afterwards
+#[derive(Clone, Default)]
+pub struct TimingTrace {
+    timings: Rc<RefCell<Timings>>,
+}
+impl core::fmt::Debug for TimingTrace {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TimingTrace")
+            .field("statements", &self.timings.borrow().statements.len())
+            .finish_non_exhaustive()
+    }
+}
+impl TimingTrace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// A hook for [Interpreter::with_hook] that records every event in this trace
+    pub fn hook(&self) -> impl FnMut(&Interpreter, &Event) + 'static {
+        let trace = self.clone();
+        move |_, event| trace.record(event)
+    }
+    /// Starts timing the statement of an [Event::Execute], and stops timing it at its [Event::Executed].
+    /// Other events are ignored
+    pub fn record(&self, event: &Event) {
+        let now = Instant::now();
+        let mut timings = self.timings.borrow_mut();
+        match *event {
+            Event::Execute { span } => timings.running.push(Running {
+                span,
+                started: now,
+                inner: Duration::ZERO,
+            }),
+            Event::Executed { span } => {
+                let stack = timings
+                    .running
+                    .iter()
+                    .map(|running| (running.span.start, running.span.end))
+                    .collect::<Vec<_>>();
+                let Some(running) = timings.running.pop() else {
+                    return;
+                };
+                debug_assert_eq!(running.span, span);
+                let elapsed = now - running.started;
+                let self_time = elapsed.saturating_sub(running.inner);
+
+                if let Some(outer) = timings.running.last_mut() {
+                    outer.inner += elapsed;
+                }
+                *timings.stacks.entry(stack).or_default() += self_time;
+                let timing =
+                    timings
+                        .statements
+                        .entry((span.start, span.end))
+                        .or_insert(StatementTiming {
+                            span,
+                            ..StatementTiming::default()
+                        });
+                timing.hits += 1;
+                timing.total += elapsed;
+                timing.self_time += self_time;
+            }
+            _ => {}
+        }
+    }
+    /// Forgets what was recorded, such as before running another script, whose spans would mix with these
+    pub fn clear(&self) {
+        *self.timings.borrow_mut() = Timings::default();
+    }
+    pub fn is_empty(&self) -> bool {
+        self.timings.borrow().statements.is_empty()
+    }
+    /// Every statement that finished running, in `order`. Ties are broken by position in the source
+    pub fn statements(&self, order: TimingOrder) -> Vec<StatementTiming> {
+        let mut statements = self
+            .timings
+            .borrow()
+            .statements
+            .values()
+            .copied()
+            .collect::<Vec<_>>();
+        match order {
+            TimingOrder::Time => statements.sort_by_key(|timing| core::cmp::Reverse(timing.total)),
+            TimingOrder::Hits => statements.sort_by_key(|timing| core::cmp::Reverse(timing.hits)),
+            TimingOrder::Line => {}
+        }
+        statements
+    }
+    /// A table of [Self::statements] in `order`, one line each with its position in `source`, hits, total time,
+    /// self time, and the start of its text
+    pub fn report(&self, source: &str, order: TimingOrder) -> String {
+        let index = LineIndex::new(source);
+        let mut report = format!(
+            "{:>9} {:>9} {:>12} {:>12}  statement\n",
+            "line:col", "hits", "total", "self"
+        );
+        for timing in self.statements(order) {
+            let _ = writeln!(
+                report,
+                "{:>9} {:>9} {:>12} {:>12}  {}",
+                position(&index, timing.span),
+                timing.hits,
+                milliseconds(timing.total),
+                milliseconds(timing.self_time),
+                statement_text(source, timing.span),
+            );
+        }
+        report
+    }
+    /// The self time of each chain of nested statements that ran, in the folded stack format that
+    /// `flamegraph.pl` and `inferno-flamegraph` read: one line per chain, with the frames outermost first
+    /// separated by `;`, then a space and the time in nanoseconds. Each frame is the statement's position in `source` and the start of its text
+    pub fn folded_stacks(&self, source: &str) -> String {
+        let index = LineIndex::new(source);
+        let frame = |&(start, end): &(usize, usize)| {
+            let span = Span::new(start, end);
+            format!(
+                "{} {}",
+                position(&index, span),
+                statement_text(source, span).replace(';', ",")
+            )
+        };
+
+        let mut folded = String::new();
+        for (stack, self_time) in &self.timings.borrow().stacks {
+            let frames = stack.iter().map(frame).collect::<Vec<_>>();
+            let _ = writeln!(folded, "{} {}", frames.join(";"), self_time.as_nanos());
+        }
+        folded
+    }
+}
+
+/// The 1-based `line:column` of the start of `span`
+fn position(index: &LineIndex, span: Span) -> String {
+    let position = index.position(span.start);
+    format!("{}:{}", position.line() + 1, position.character() + 1)
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// How many characters of a statement [statement_text] shows
+const STATEMENT_TEXT_LENGTH: usize = 40;
+
+/// The first line of the statement at `span`, without its final `;`, shortened to [STATEMENT_TEXT_LENGTH] characters
+fn statement_text(source: &str, span: Span) -> String {
+    let text = source.get(span.range()).unwrap_or_default().trim_end();
+    let text = text.strip_suffix(';').unwrap_or(text);
+    let first_line = text.lines().next().unwrap_or_default().trim_end();
+    if first_line.len() == text.len() && first_line.chars().count() <= STATEMENT_TEXT_LENGTH {
+        return first_line.to_owned();
+    }
+
+    let shortened = first_line
+        .chars()
+        .take(STATEMENT_TEXT_LENGTH)
+        .collect::<String>();
+    format!("{} ...", shortened.trim_end())
+}
+
+#[test]
+fn statement_timings() {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    const SOURCE: &str = "var a = 0;\nwhile (a < 3) {\n  a = a + 1;\n}\nprint a;";
+    let statements = Parser::from(Lexer::new(SOURCE))
+        .parse_into_visitor_pattern()
+        .unwrap();
+
+    let trace = TimingTrace::new();
+    let interpreter = Interpreter::new().with_hook(trace.hook());
+    for statement in &statements {
+        interpreter.execute(statement).unwrap();
+    }
+
+    let by_line = trace
+        .statements(TimingOrder::Line)
+        .iter()
+        .map(|timing| (&SOURCE[timing.span().range()], timing.hits()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        by_line,
+        [
+            ("var a = 0;", 1),
+            ("while (a < 3) {\n  a = a + 1;\n}", 1),
+            ("{\n  a = a + 1;\n}", 3),
+            ("a = a + 1;", 3),
+            ("print a;", 1),
+        ]
+    );
+    let most_hits = trace.statements(TimingOrder::Hits);
+    assert_eq!(most_hits[0].hits(), 3);
+    let while_loop = trace.statements(TimingOrder::Line)[1];
+    assert!(while_loop.total() >= while_loop.self_time());
+    assert!(trace.statements(TimingOrder::Time)[0].total() >= while_loop.total());
+
+    let report = trace.report(SOURCE, TimingOrder::Line);
+    // without the times, which differ between runs
+    let rows = report
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let columns = line.split_whitespace().collect::<Vec<_>>();
+            format!("{} {} {}", columns[0], columns[1], columns[4..].join(" "))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        [
+            "1:1 1 var a = 0",
+            "2:1 1 while (a < 3) { ...",
+            "2:15 3 { ...",
+            "3:3 3 a = a + 1",
+            "5:1 1 print a",
+        ]
+    );
+
+    let stacks = trace
+        .folded_stacks(SOURCE)
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0.to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        stacks,
+        [
+            "1:1 var a = 0",
+            "2:1 while (a < 3) { ...",
+            "2:1 while (a < 3) { ...;2:15 { ...",
+            "2:1 while (a < 3) { ...;2:15 { ...;3:3 a = a + 1",
+            "5:1 print a",
+        ]
+    );
+
+    trace.clear();
+    assert!(trace.is_empty());
+}
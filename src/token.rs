@@ -1,10 +1,43 @@
-use std::fmt::{Debug, Display};
+use crate::interner::Symbol;
+use alloc::{borrow::ToOwned, collections::BTreeMap, string::String};
+use core::fmt::{Debug, Display};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A range of bytes `start..end` in lox source code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+impl Span {
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+    pub const fn len(&self) -> usize {
+        self.end - self.start
+    }
+    pub const fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+    pub const fn range(&self) -> core::ops::Range<usize> {
+        self.start..self.end
+    }
+    /// The smallest span covering both `self` and `other`
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Token<'a> {
     kind: TokenKind,
     lexeme: &'a str,
     line_number: usize,
+    /// Where the whole token is in the source, including the quotes of a [TokenKind::String]
+    span: Span,
+    /// The parsed value of a [TokenKind::Number] so later stages don't re-parse the lexeme
+    number_value: Option<f64>,
+    /// The interned lexeme of an identifier or keyword, from the [crate::interner::Interner] of the lexer that produced the token
+    symbol: Option<Symbol>,
 }
 impl<'a> Token<'a> {
     pub const fn new(kind: TokenKind, lexeme: &'a str, line_number: usize) -> Self {
@@ -12,6 +45,9 @@ impl<'a> Token<'a> {
             kind,
             lexeme,
             line_number,
+            span: Span::new(0, 0),
+            number_value: None,
+            symbol: None,
         }
     }
     pub const fn end_of_file(line_number: usize) -> Token<'static> {
@@ -19,6 +55,24 @@ impl<'a> Token<'a> {
             kind: TokenKind::EndOfFile,
             lexeme: "",
             line_number,
+            span: Span::new(0, 0),
+            number_value: None,
+            symbol: None,
+        }
+    }
+    pub const fn with_symbol(self, symbol: Symbol) -> Self {
+        Self {
+            symbol: Some(symbol),
+            ..self
+        }
+    }
+    pub const fn with_span(self, span: Span) -> Self {
+        Self { span, ..self }
+    }
+    pub const fn with_number_value(self, number_value: f64) -> Self {
+        Self {
+            number_value: Some(number_value),
+            ..self
         }
     }
     pub const fn kind(&self) -> TokenKind {
@@ -33,17 +87,36 @@ impl<'a> Token<'a> {
     pub const fn line_number(&self) -> usize {
         self.line_number
     }
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+    pub const fn number_value(&self) -> Option<f64> {
+        self.number_value
+    }
+    pub const fn symbol(&self) -> Option<Symbol> {
+        self.symbol
+    }
+    /// True when the lexeme is the `&'static str` from [TokenKind::canonical_lexeme] rather than a slice of the source
+    pub fn has_canonical_lexeme(&self) -> bool {
+        self.kind
+            .canonical_lexeme()
+            .is_some_and(|canonical_lexeme| core::ptr::eq(canonical_lexeme, self.lexeme))
+    }
 }
 impl Display for Token<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{} {:?} {}", self.line_number, self.kind, self.lexeme)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TokenKind {
     Unrecognized,
     EndOfFile,
+    /// Only produced when [crate::lexer::LexerConfig::preserve_trivia] is set
+    Comment,
+    /// Only produced when [crate::lexer::LexerConfig::preserve_trivia] is set
+    Whitespace,
     LeftParentheses,
     RightParentheses,
     LeftBrace,
@@ -82,34 +155,97 @@ pub enum TokenKind {
     True,
     Var,
     While,
+    /// A host-defined literal registered with [crate::lexer::LexerConfig::custom_literal], identified by [crate::lexer::CustomLiteral::id]
+    Custom(u16),
+    /// A host-defined statement keyword registered with [crate::dialect::Dialect::with_statement_keyword]
+    CustomKeyword(u16),
 }
 impl TokenKind {
+    /// Returns the kind of keyword spelled by `identifier_lexeme` in [KEYWORDS], or [TokenKind::Identifier]
     pub fn parse_keyword(identifier_lexeme: &str) -> Self {
-        match identifier_lexeme {
-            "and" => TokenKind::And,
-            "class" => TokenKind::Class,
-            "else" => TokenKind::Else,
-            "false" => TokenKind::False,
-            "for" => TokenKind::For,
-            "fun" => TokenKind::Fun,
-            "if" => TokenKind::If,
-            "nil" => TokenKind::Nil,
-            "or" => TokenKind::Or,
-            "print" => TokenKind::Print,
-            "return" => TokenKind::Return,
-            "super" => TokenKind::Super,
-            "this" => TokenKind::This,
-            "true" => TokenKind::True,
-            "var" => TokenKind::Var,
-            "while" => TokenKind::While,
-            _ => TokenKind::Identifier,
-        }
+        KEYWORDS
+            .iter()
+            .find(|(spelling, _)| *spelling == identifier_lexeme)
+            .map_or(TokenKind::Identifier, |(_, kind)| *kind)
     }
     pub const fn is_end_of_file(&self) -> bool {
-        match self {
-            TokenKind::EndOfFile => true,
-            _ => false,
-        }
+        matches!(self, TokenKind::EndOfFile)
+    }
+    /// The kinds produced by [KEYWORDS]
+    pub const fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::And
+                | TokenKind::Class
+                | TokenKind::Else
+                | TokenKind::False
+                | TokenKind::Fun
+                | TokenKind::For
+                | TokenKind::If
+                | TokenKind::Nil
+                | TokenKind::Or
+                | TokenKind::Print
+                | TokenKind::Return
+                | TokenKind::Super
+                | TokenKind::This
+                | TokenKind::True
+                | TokenKind::Var
+                | TokenKind::While
+        )
+    }
+    /// Comments and whitespace, which carry no meaning for the parser
+    pub const fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::Comment | TokenKind::Whitespace)
+    }
+    /// The fixed spelling of punctuation, operators, and standard keywords
+    pub const fn canonical_lexeme(&self) -> Option<&'static str> {
+        let lexeme = match self {
+            TokenKind::LeftParentheses => "(",
+            TokenKind::RightParentheses => ")",
+            TokenKind::LeftBrace => "{",
+            TokenKind::RightBrace => "}",
+            TokenKind::Comma => ",",
+            TokenKind::Dot => ".",
+            TokenKind::Minus => "-",
+            TokenKind::Plus => "+",
+            TokenKind::Semicolon => ";",
+            TokenKind::Slash => "/",
+            TokenKind::Star => "*",
+            TokenKind::Bang => "!",
+            TokenKind::BangEqual => "!=",
+            TokenKind::Equal => "=",
+            TokenKind::EqualEqual => "==",
+            TokenKind::Greater => ">",
+            TokenKind::GreaterEqual => ">=",
+            TokenKind::Less => "<",
+            TokenKind::LessEqual => "<=",
+            TokenKind::And => "and",
+            TokenKind::Class => "class",
+            TokenKind::Else => "else",
+            TokenKind::False => "false",
+            TokenKind::Fun => "fun",
+            TokenKind::For => "for",
+            TokenKind::If => "if",
+            TokenKind::Nil => "nil",
+            TokenKind::Or => "or",
+            TokenKind::Print => "print",
+            TokenKind::Return => "return",
+            TokenKind::Super => "super",
+            TokenKind::This => "this",
+            TokenKind::True => "true",
+            TokenKind::Var => "var",
+            TokenKind::While => "while",
+            TokenKind::Unrecognized
+            | TokenKind::EndOfFile
+            | TokenKind::Comment
+            | TokenKind::Whitespace
+            | TokenKind::Identifier
+            | TokenKind::String
+            | TokenKind::Number
+            | TokenKind::Custom(_)
+            | TokenKind::CustomKeyword(_) => return None,
+        };
+        Some(lexeme)
     }
     pub fn is_any(&self, kinds: &[TokenKind]) -> bool {
         for kind in kinds {
@@ -131,3 +267,123 @@ impl TokenKind {
     pub const FACTOR_OPERATORS: &[Self] = &[TokenKind::Star, TokenKind::Slash];
     pub const UNARY_OPERATORS: &[Self] = &[TokenKind::Bang, TokenKind::Minus];
 }
+
+/// The standard lox keywords and the kind of token each produces
+pub const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("and", TokenKind::And),
+    ("class", TokenKind::Class),
+    ("else", TokenKind::Else),
+    ("false", TokenKind::False),
+    ("for", TokenKind::For),
+    ("fun", TokenKind::Fun),
+    ("if", TokenKind::If),
+    ("nil", TokenKind::Nil),
+    ("or", TokenKind::Or),
+    ("print", TokenKind::Print),
+    ("return", TokenKind::Return),
+    ("super", TokenKind::Super),
+    ("this", TokenKind::This),
+    ("true", TokenKind::True),
+    ("var", TokenKind::Var),
+    ("while", TokenKind::While),
+];
+
+/// The spellings a lexer recognizes as keywords. Starts as [KEYWORDS]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordTable {
+    keywords: BTreeMap<String, TokenKind>,
+    case_insensitive: bool,
+}
+impl Default for KeywordTable {
+    fn default() -> Self {
+        KEYWORDS
+            .iter()
+            .fold(Self::empty(), |table, (spelling, kind)| {
+                table.with_keyword(spelling, *kind)
+            })
+    }
+}
+impl KeywordTable {
+    /// A table without any keywords. Every word lexes as a [TokenKind::Identifier]
+    pub const fn empty() -> Self {
+        Self {
+            keywords: BTreeMap::new(),
+            case_insensitive: false,
+        }
+    }
+    pub fn with_keyword(mut self, spelling: &str, kind: TokenKind) -> Self {
+        self.insert(spelling, kind);
+        self
+    }
+    pub fn without_keyword(mut self, spelling: &str) -> Self {
+        self.remove(spelling);
+        self
+    }
+    /// When enabled `VAR`, `Var`, and `var` all match the keyword `var`
+    pub fn case_insensitive(self, case_insensitive: bool) -> Self {
+        Self {
+            case_insensitive,
+            ..self
+        }
+    }
+    pub fn insert(&mut self, spelling: &str, kind: TokenKind) {
+        self.keywords.insert(spelling.to_owned(), kind);
+    }
+    pub fn remove(&mut self, spelling: &str) -> Option<TokenKind> {
+        self.keywords.remove(spelling)
+    }
+    pub const fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+    /// Returns the kind of keyword spelled by `identifier_lexeme`, if any
+    pub fn get(&self, identifier_lexeme: &str) -> Option<TokenKind> {
+        if self.case_insensitive {
+            return self
+                .keywords
+                .iter()
+                .find(|(spelling, _)| spelling.eq_ignore_ascii_case(identifier_lexeme))
+                .map(|(_, kind)| *kind);
+        }
+
+        self.keywords.get(identifier_lexeme).copied()
+    }
+    /// Every spelling of `kind` in this table, in alphabetical order
+    pub fn spellings(&self, kind: TokenKind) -> impl Iterator<Item = &str> {
+        self.keywords
+            .iter()
+            .filter(move |(_, spelling_kind)| **spelling_kind == kind)
+            .map(|(spelling, _)| spelling.as_str())
+    }
+    /// Every spelling in this table, in alphabetical order
+    pub fn all_spellings(&self) -> impl Iterator<Item = &str> {
+        self.keywords.keys().map(String::as_str)
+    }
+    /// Like [TokenKind::parse_keyword] but using this table
+    pub fn parse_keyword(&self, identifier_lexeme: &str) -> TokenKind {
+        self.get(identifier_lexeme).unwrap_or(TokenKind::Identifier)
+    }
+}
+
+#[test]
+fn keyword_table() {
+    let table = KeywordTable::default()
+        .without_keyword("class")
+        .with_keyword("let", TokenKind::Var);
+
+    assert_eq!(table.parse_keyword("let"), TokenKind::Var);
+    assert_eq!(table.parse_keyword("var"), TokenKind::Var);
+    assert_eq!(table.parse_keyword("class"), TokenKind::Identifier);
+    assert_eq!(table.parse_keyword("WHILE"), TokenKind::Identifier);
+
+    let table = table.case_insensitive(true);
+    assert_eq!(table.parse_keyword("WHILE"), TokenKind::While);
+    assert_eq!(table.parse_keyword("Let"), TokenKind::Var);
+
+    assert!(table.spellings(TokenKind::Var).eq(["let", "var"]));
+
+    for (spelling, kind) in KEYWORDS {
+        assert_eq!(TokenKind::parse_keyword(spelling), *kind);
+        assert_eq!(kind.canonical_lexeme(), Some(*spelling));
+        assert!(kind.is_keyword());
+    }
+}
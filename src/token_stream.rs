@@ -0,0 +1,97 @@
+//! Lookahead over a [Lexer] that only lexes as far ahead as has been peeked
+
+use crate::{
+    interner::Interner,
+    lexer::{Lexer, LexerError, LexerWarning, TokenSource},
+    token::Token,
+};
+use alloc::collections::VecDeque;
+
+pub struct TokenStream<'a> {
+    lexer: Lexer<'a>,
+    /// tokens lexed by [Self::peek_nth] that have not been returned by [Iterator::next] yet
+    lookahead: VecDeque<Result<Token<'a>, LexerError<'a>>>,
+}
+impl<'a> Iterator for TokenStream<'a> {
+    type Item = Result<Token<'a>, LexerError<'a>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lookahead.pop_front().or_else(|| self.lexer.next())
+    }
+}
+impl<'a> From<Lexer<'a>> for TokenStream<'a> {
+    fn from(value: Lexer<'a>) -> Self {
+        Self::new(value)
+    }
+}
+/// Lets a parser read from a stream that was peeked into. Peeked tokens are not lost
+impl<'a> TokenSource<'a> for TokenStream<'a> {
+    fn into_interner(self) -> Interner<'a> {
+        self.lexer.into_interner()
+    }
+    fn warnings(&self) -> &[LexerWarning<'a>] {
+        self.lexer.warnings()
+    }
+}
+impl<'a> TokenStream<'a> {
+    pub const fn new(lexer: Lexer<'a>) -> Self {
+        Self {
+            lexer,
+            lookahead: VecDeque::new(),
+        }
+    }
+    /// Returns the item the next call to [Iterator::next] will return without consuming it
+    pub fn peek(&mut self) -> Option<&Result<Token<'a>, LexerError<'a>>> {
+        self.peek_nth(0)
+    }
+    /// Returns the item `n` places after the next one without consuming anything. `peek_nth(0)` is [Self::peek]
+    pub fn peek_nth(&mut self, n: usize) -> Option<&Result<Token<'a>, LexerError<'a>>> {
+        while self.lookahead.len() <= n {
+            self.lookahead.push_back(self.lexer.next()?);
+        }
+
+        self.lookahead.get(n)
+    }
+}
+
+#[test]
+fn lookahead() {
+    use crate::token::TokenKind;
+    use alloc::vec::Vec;
+
+    let mut tokens = TokenStream::new(Lexer::new("a = 1"));
+
+    let mut peek_kind = |n| {
+        tokens
+            .peek_nth(n)
+            .map(|token| token.as_ref().unwrap().kind())
+    };
+    assert_eq!(peek_kind(1), Some(TokenKind::Equal));
+    assert_eq!(peek_kind(3), Some(TokenKind::EndOfFile));
+    assert_eq!(peek_kind(4), None);
+
+    assert_eq!(tokens.peek().unwrap().as_ref().unwrap().lexeme(), "a");
+    let kinds = tokens
+        .map(|token| token.unwrap().kind())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Identifier,
+            TokenKind::Equal,
+            TokenKind::Number,
+            TokenKind::EndOfFile
+        ]
+    );
+}
+
+#[test]
+fn parse_peeked_stream() {
+    use crate::parser::Parser;
+
+    const SOURCE: &str = "var a = 1; print a;";
+    let mut tokens = TokenStream::new(Lexer::new(SOURCE));
+    assert!(tokens.peek_nth(2).is_some());
+
+    let program = Parser::from(tokens).parse().unwrap();
+    assert_eq!(program, Parser::from(Lexer::new(SOURCE)).parse().unwrap());
+}
@@ -0,0 +1,362 @@
+//! Traversals of [crate::abstract_syntax_tree] whose default methods recurse into every child,
+//! so a pass only overrides the nodes it cares about.
+//! An override can call the matching free function, such as [walk_expression], to keep recursing
+
+use crate::{
+    abstract_syntax_tree::{Expression, Program, Statement},
+    token::Token,
+};
+use alloc::{boxed::Box, vec::Vec};
+
+/// Visits every node of a tree by reference
+pub trait Walk<'a> {
+    fn walk_program(&mut self, program: &Program<'a>) {
+        for statement in program.statements() {
+            self.walk_statement(statement);
+        }
+    }
+    fn walk_statement(&mut self, statement: &Statement<'a>) {
+        walk_statement(self, statement);
+    }
+    fn walk_expression(&mut self, expression: &Expression<'a>) {
+        walk_expression(self, expression);
+    }
+    fn walk_literal(&mut self, _literal: &Token<'a>) {}
+    fn walk_variable(&mut self, _name: &Token<'a>) {}
+}
+
+/// Walks the children of `statement`
+pub fn walk_statement<'a, W: Walk<'a> + ?Sized>(walker: &mut W, statement: &Statement<'a>) {
+    match statement {
+        Statement::Expression { expression, .. } | Statement::Print { expression, .. } => {
+            walker.walk_expression(expression)
+        }
+        Statement::Var { initializer, .. } => {
+            if let Some(initializer) = initializer {
+                walker.walk_expression(initializer);
+            }
+        }
+        Statement::Block { statements, .. } => {
+            for statement in statements {
+                walker.walk_statement(statement);
+            }
+        }
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            walker.walk_expression(condition);
+            walker.walk_statement(then_branch);
+            if let Some(else_branch) = else_branch {
+                walker.walk_statement(else_branch);
+            }
+        }
+        Statement::While {
+            condition, body, ..
+        } => {
+            walker.walk_expression(condition);
+            walker.walk_statement(body);
+        }
+        Statement::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            if let Some(initializer) = initializer {
+                walker.walk_statement(initializer);
+            }
+            if let Some(condition) = condition {
+                walker.walk_expression(condition);
+            }
+            if let Some(increment) = increment {
+                walker.walk_expression(increment);
+            }
+            walker.walk_statement(body);
+        }
+        Statement::Custom {
+            expressions,
+            statements,
+            ..
+        } => {
+            for expression in expressions {
+                walker.walk_expression(expression);
+            }
+            for statement in statements {
+                walker.walk_statement(statement);
+            }
+        }
+    }
+}
+
+/// Walks the children of `expression`, or calls [Walk::walk_literal] or [Walk::walk_variable] for a leaf
+pub fn walk_expression<'a, W: Walk<'a> + ?Sized>(walker: &mut W, expression: &Expression<'a>) {
+    match expression {
+        Expression::Assign { value, .. } => walker.walk_expression(value),
+        Expression::Binary {
+            left_operand,
+            right_operand,
+            ..
+        }
+        | Expression::Logical {
+            left_operand,
+            right_operand,
+            ..
+        } => {
+            walker.walk_expression(left_operand);
+            walker.walk_expression(right_operand);
+        }
+        Expression::Unary { right_operand, .. } => walker.walk_expression(right_operand),
+        Expression::Call {
+            callee, arguments, ..
+        } => {
+            walker.walk_expression(callee);
+            for argument in arguments {
+                walker.walk_expression(argument);
+            }
+        }
+        Expression::Get { object, .. } => walker.walk_expression(object),
+        Expression::Grouping { expression, .. } => walker.walk_expression(expression),
+        Expression::Literal(literal) => walker.walk_literal(literal),
+        Expression::Variable(name) => walker.walk_variable(name),
+    }
+}
+
+/// Rebuilds a tree from the nodes each method returns. Spans are kept as they were
+pub trait Fold<'a> {
+    fn fold_program(&mut self, program: Program<'a>) -> Program<'a> {
+        let interner = program.interner().clone();
+        let statements = program
+            .into_statements()
+            .into_iter()
+            .map(|statement| self.fold_statement(statement))
+            .collect();
+
+        Program::new(statements, interner)
+    }
+    fn fold_statement(&mut self, statement: Statement<'a>) -> Statement<'a> {
+        fold_statement(self, statement)
+    }
+    fn fold_expression(&mut self, expression: Expression<'a>) -> Expression<'a> {
+        fold_expression(self, expression)
+    }
+    fn fold_literal(&mut self, literal: Token<'a>) -> Expression<'a> {
+        Expression::Literal(literal)
+    }
+    fn fold_variable(&mut self, name: Token<'a>) -> Expression<'a> {
+        Expression::Variable(name)
+    }
+}
+
+/// Reuses the allocation of `expression` for the folded expression
+fn fold_boxed_expression<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    mut expression: Box<Expression<'a>>,
+) -> Box<Expression<'a>> {
+    *expression = folder.fold_expression(*expression);
+    expression
+}
+fn fold_boxed_statement<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    mut statement: Box<Statement<'a>>,
+) -> Box<Statement<'a>> {
+    *statement = folder.fold_statement(*statement);
+    statement
+}
+fn fold_statements<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    statements: Vec<Statement<'a>>,
+) -> Vec<Statement<'a>> {
+    statements
+        .into_iter()
+        .map(|statement| folder.fold_statement(statement))
+        .collect()
+}
+
+/// Folds the children of `statement` and rebuilds it around them
+pub fn fold_statement<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    statement: Statement<'a>,
+) -> Statement<'a> {
+    match statement {
+        Statement::Expression { expression, span } => Statement::Expression {
+            expression: fold_boxed_expression(folder, expression),
+            span,
+        },
+        Statement::Print { expression, span } => Statement::Print {
+            expression: fold_boxed_expression(folder, expression),
+            span,
+        },
+        Statement::Var {
+            name,
+            initializer,
+            span,
+        } => Statement::Var {
+            name,
+            initializer: initializer.map(|initializer| fold_boxed_expression(folder, initializer)),
+            span,
+        },
+        Statement::Block { statements, span } => Statement::Block {
+            statements: fold_statements(folder, statements),
+            span,
+        },
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            span,
+        } => Statement::If {
+            condition: fold_boxed_expression(folder, condition),
+            then_branch: fold_boxed_statement(folder, then_branch),
+            else_branch: else_branch.map(|else_branch| fold_boxed_statement(folder, else_branch)),
+            span,
+        },
+        Statement::While {
+            condition,
+            body,
+            span,
+        } => Statement::While {
+            condition: fold_boxed_expression(folder, condition),
+            body: fold_boxed_statement(folder, body),
+            span,
+        },
+        Statement::For {
+            initializer,
+            condition,
+            increment,
+            body,
+            span,
+        } => Statement::For {
+            initializer: initializer.map(|initializer| fold_boxed_statement(folder, initializer)),
+            condition: condition.map(|condition| fold_boxed_expression(folder, condition)),
+            increment: increment.map(|increment| fold_boxed_expression(folder, increment)),
+            body: fold_boxed_statement(folder, body),
+            span,
+        },
+        Statement::Custom {
+            keyword,
+            expressions,
+            statements,
+            span,
+        } => Statement::Custom {
+            keyword,
+            expressions: expressions
+                .into_iter()
+                .map(|expression| folder.fold_expression(expression))
+                .collect(),
+            statements: fold_statements(folder, statements),
+            span,
+        },
+    }
+}
+
+/// Folds the children of `expression` and rebuilds it around them, or calls [Fold::fold_literal] or [Fold::fold_variable] for a leaf
+pub fn fold_expression<'a, F: Fold<'a> + ?Sized>(
+    folder: &mut F,
+    expression: Expression<'a>,
+) -> Expression<'a> {
+    match expression {
+        Expression::Assign { name, value, span } => Expression::Assign {
+            name,
+            value: fold_boxed_expression(folder, value),
+            span,
+        },
+        Expression::Binary {
+            left_operand,
+            operator,
+            right_operand,
+            span,
+        } => Expression::Binary {
+            left_operand: fold_boxed_expression(folder, left_operand),
+            operator,
+            right_operand: fold_boxed_expression(folder, right_operand),
+            span,
+        },
+        Expression::Unary {
+            operator,
+            right_operand,
+            span,
+        } => Expression::Unary {
+            operator,
+            right_operand: fold_boxed_expression(folder, right_operand),
+            span,
+        },
+        Expression::Call {
+            callee,
+            closing_parenthesis,
+            arguments,
+            span,
+        } => Expression::Call {
+            callee: fold_boxed_expression(folder, callee),
+            closing_parenthesis,
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect(),
+            span,
+        },
+        Expression::Get { object, name, span } => Expression::Get {
+            object: fold_boxed_expression(folder, object),
+            name,
+            span,
+        },
+        Expression::Logical {
+            left_operand,
+            operator,
+            right_operand,
+            span,
+        } => Expression::Logical {
+            left_operand: fold_boxed_expression(folder, left_operand),
+            operator,
+            right_operand: fold_boxed_expression(folder, right_operand),
+            span,
+        },
+        Expression::Grouping { expression, span } => Expression::Grouping {
+            expression: fold_boxed_expression(folder, expression),
+            span,
+        },
+        Expression::Literal(literal) => folder.fold_literal(literal),
+        Expression::Variable(name) => folder.fold_variable(name),
+    }
+}
+
+#[test]
+fn default_traversals() {
+    use crate::{lexer::Lexer, parser::Parser};
+    use alloc::string::ToString;
+
+    /// Collects the lexeme of every variable that is read
+    struct Reads<'a>(Vec<&'a str>);
+    impl<'a> Walk<'a> for Reads<'a> {
+        fn walk_variable(&mut self, name: &Token<'a>) {
+            self.0.push(name.lexeme());
+        }
+    }
+
+    /// Removes every grouping
+    struct Ungroup;
+    impl<'a> Fold<'a> for Ungroup {
+        fn fold_expression(&mut self, expression: Expression<'a>) -> Expression<'a> {
+            match expression {
+                Expression::Grouping { expression, .. } => self.fold_expression(*expression),
+                expression => fold_expression(self, expression),
+            }
+        }
+    }
+
+    const SOURCE: &str = "var a = (b); for (;c;) { print f((d), e.g); } if (h) i = j; else k;";
+    let program = Parser::from(Lexer::new(SOURCE)).parse().unwrap();
+
+    let mut reads = Reads(Vec::new());
+    reads.walk_program(&program);
+    assert_eq!(reads.0, ["b", "c", "f", "d", "e", "h", "j", "k"]);
+
+    let program = Ungroup.fold_program(program);
+    assert_eq!(
+        program.to_string(),
+        "(var a = b)\n(for _ c _ (block (print (call f d (. e g)))))\n(if-else h (; (= i j)) (; k))"
+    );
+}